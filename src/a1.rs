@@ -0,0 +1,74 @@
+//! Spreadsheet-style `A1` cell references: column letters then a 1-based
+//! row number, as in `B3` or `AA10`, and `A1:C5` for a block.
+
+use anyhow::{Result, anyhow};
+
+/// `A`..`Z`, then `AA`, `AB`, ... for 0-based column `col`.
+pub fn col_name(col: usize) -> String {
+    let mut n = col + 1;
+    let mut out = Vec::new();
+    while n > 0 {
+        let d = (n - 1) % 26;
+        out.push(b'A' + d as u8);
+        n = (n - 1) / 26;
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// The `A1` name of 0-based `(row, col)`.
+pub fn cell_name(row: usize, col: usize) -> String {
+    format!("{}{}", col_name(col), row + 1)
+}
+
+/// 0-based `(row, col)` of a reference like `b3` or `$B$3`.
+pub fn parse_cell(s: &str) -> Result<(usize, usize)> {
+    let bad = || anyhow!("bad cell reference {s:?}; use e.g. B3");
+    let t = s.trim().replace('$', "");
+    let split = t.find(|c: char| !c.is_ascii_alphabetic()).ok_or_else(bad)?;
+    let (letters, digits) = t.split_at(split);
+    if letters.is_empty() || letters.len() > 3 {
+        return Err(bad());
+    }
+    let col = letters.bytes().fold(0, |n, b| {
+        n * 26 + (b.to_ascii_uppercase() - b'A') as usize + 1
+    });
+    let row: usize = digits.parse().ok().filter(|&r| r >= 1).ok_or_else(bad)?;
+    Ok((row - 1, col - 1))
+}
+
+/// A cell or an `A1:C5` block, as its top-left and bottom-right corners.
+pub fn parse_range(s: &str) -> Result<((usize, usize), (usize, usize))> {
+    match s.split_once(':') {
+        Some((a, b)) => {
+            let (a, b) = (parse_cell(a)?, parse_cell(b)?);
+            Ok(((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1))))
+        }
+        None => {
+            let cell = parse_cell(s)?;
+            Ok((cell, cell))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_parses_references() -> Result<()> {
+        assert_eq!(col_name(0), "A");
+        assert_eq!(col_name(25), "Z");
+        assert_eq!(col_name(26), "AA");
+        assert_eq!(col_name(701), "ZZ");
+        assert_eq!(col_name(702), "AAA");
+        assert_eq!(cell_name(2, 1), "B3");
+        assert_eq!(parse_cell("b3")?, (2, 1));
+        assert_eq!(parse_cell("$AA$10")?, (9, 26));
+        assert_eq!(parse_range("C5:A1")?, ((0, 0), (4, 2)));
+        for bad in ["3", "B", "B0", "B3x", "ABCD1"] {
+            assert!(parse_cell(bad).is_err(), "{bad}");
+        }
+        Ok(())
+    }
+}
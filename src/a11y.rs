@@ -0,0 +1,83 @@
+use crate::escape;
+
+/// Longest cell value read out; the rest is summarized by its length.
+const MAX_SPOKEN: usize = 80;
+
+/// The cell under the cursor, as screen-reader mode reads it out after a
+/// move, e.g. `Row 3, column 2 (name): Alice, modified`.
+pub struct CellInfo<'a> {
+    pub row: usize,
+    pub col: usize,
+    pub header: Option<&'a str>,
+    pub value: &'a str,
+    pub modified: bool,
+    pub tagged: bool,
+}
+
+pub fn describe(cell: &CellInfo) -> String {
+    let mut out = format!("Row {}, column {}", cell.row + 1, cell.col + 1);
+    if let Some(h) = cell.header.filter(|h| !h.trim().is_empty()) {
+        out.push_str(&format!(" ({})", escape::display(h.trim())));
+    }
+    out.push_str(": ");
+    if cell.value.trim().is_empty() {
+        out.push_str("empty");
+    } else {
+        out.push_str(&escape::clipped(cell.value, MAX_SPOKEN));
+    }
+    if cell.modified {
+        out.push_str(", modified");
+    }
+    if cell.tagged {
+        out.push_str(", row tagged");
+    }
+    out
+}
+
+/// Text markers for states the table otherwise shows only as colors.
+pub fn decorate(text: &str, cursor: bool, modified: bool, tagged: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 4);
+    if tagged {
+        out.push('+');
+    }
+    if cursor {
+        out.push('[');
+    }
+    out.push_str(text);
+    if cursor {
+        out.push(']');
+    }
+    if modified {
+        out.push('*');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_cells_and_marks_states_in_text() {
+        let cell = CellInfo {
+            row: 2,
+            col: 1,
+            header: Some("name"),
+            value: "Alice",
+            modified: true,
+            tagged: false,
+        };
+        assert_eq!(describe(&cell), "Row 3, column 2 (name): Alice, modified");
+        let blank = CellInfo {
+            header: None,
+            value: " ",
+            modified: false,
+            tagged: true,
+            ..cell
+        };
+        assert_eq!(describe(&blank), "Row 3, column 2: empty, row tagged");
+
+        assert_eq!(decorate("x", true, true, true), "+[x]*");
+        assert_eq!(decorate("x", false, false, false), "x");
+    }
+}
@@ -0,0 +1,255 @@
+//! `:anonymize`: replace a column's values with salted hashes or numbered
+//! pseudonyms, the same value always getting the same replacement, so a
+//! file can be shared without the names or ids in it while still joining
+//! and grouping as before.
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+
+use crate::nulls::Nulls;
+
+/// What `:anonymize` puts in place of a column's values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// The first 16 hex digits of SHA-256 over the salt and the value. No
+    /// salt means a fresh one, shown afterwards so the run can be repeated.
+    Hash(Option<String>),
+    /// `<prefix>_01`, `<prefix>_02`, … in the order values first appear;
+    /// the prefix defaults to the column's name.
+    Sequence(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anonymize {
+    pub method: Method,
+    /// Where to write the `original,replacement` pairs, if anywhere.
+    pub map: Option<PathBuf>,
+}
+
+pub fn parse(s: &str) -> Result<Anonymize> {
+    let (spec, map) = match s.split_once('>') {
+        Some((spec, file)) if !file.trim().is_empty() => {
+            (spec.trim(), Some(PathBuf::from(file.trim())))
+        }
+        Some(_) => return Err(anyhow!("> needs a file for the mapping")),
+        None => (s.trim(), None),
+    };
+    let (how, arg) = spec
+        .split_once(char::is_whitespace)
+        .map_or((spec, ""), |(h, a)| (h, a.trim()));
+    let arg = (!arg.is_empty()).then(|| arg.to_string());
+    let method = match how {
+        "hash" => Method::Hash(arg),
+        "seq" | "pseudonym" => Method::Sequence(arg),
+        _ => {
+            return Err(anyhow!(
+                "usage: :anonymize hash [<salt>] | seq [<prefix>] [> <mapping.csv>]"
+            ));
+        }
+    };
+    Ok(Anonymize { method, map })
+}
+
+/// A salt for a run that wasn't given one, from the randomly keyed hasher
+/// std seeds from the operating system, so it can't be guessed from the
+/// time or the process id.
+pub fn fresh_salt() -> String {
+    let seed = RandomState::new();
+    let word = |i: u64| {
+        let mut h = seed.build_hasher();
+        h.write_u64(i);
+        h.finish()
+    };
+    format!("{:016x}{:016x}", word(0), word(1))
+}
+
+/// What [`plan`] works out: the cells to change and each distinct value's
+/// replacement, in the order the values first appear.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub cells: Vec<(usize, String)>,
+    pub mapping: Vec<(String, String)>,
+}
+
+/// The replacements for `col` from row `from` down. Missing cells (blank
+/// or a null token) are left as they are. `salt` is the hash's, already
+/// settled; `name` is the prefix a sequence falls back on.
+pub fn plan(
+    data: &[Vec<String>],
+    col: usize,
+    from: usize,
+    method: &Method,
+    salt: &str,
+    name: &str,
+    nulls: &Nulls,
+) -> Plan {
+    let values: Vec<(usize, &str)> = data
+        .iter()
+        .enumerate()
+        .skip(from)
+        .filter_map(|(r, row)| Some((r, row.get(col)?.as_str())))
+        .filter(|(_, v)| !v.trim().is_empty() && !nulls.is_null(v))
+        .collect();
+    let mut distinct: Vec<&str> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for &(_, v) in &values {
+        index.entry(v).or_insert_with(|| {
+            distinct.push(v);
+            distinct.len() - 1
+        });
+    }
+    let width = distinct.len().to_string().len().max(2);
+    let prefix = match method {
+        Method::Sequence(Some(p)) => p.as_str(),
+        _ => name,
+    };
+    let replacements: Vec<String> = distinct
+        .iter()
+        .enumerate()
+        .map(|(i, v)| match method {
+            Method::Hash(_) => {
+                let mut input = salt.as_bytes().to_vec();
+                input.push(0);
+                input.extend_from_slice(v.as_bytes());
+                hex(&sha256(&input))[..16].to_string()
+            }
+            Method::Sequence(_) => format!("{prefix}_{:0width$}", i + 1),
+        })
+        .collect();
+    Plan {
+        cells: values
+            .iter()
+            .map(|&(r, v)| (r, replacements[index[v]].clone()))
+            .collect(),
+        mapping: distinct
+            .iter()
+            .map(|v| v.to_string())
+            .zip(replacements)
+            .collect(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 (FIPS 180-4), which is all the hashing this needs.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in msg.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+    let mut out = [0u8; 32];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(cells: &[&str]) -> Vec<Vec<String>> {
+        cells.iter().map(|s| vec![s.to_string()]).collect()
+    }
+
+    #[test]
+    fn replaces_each_value_the_same_way_every_time() -> Result<()> {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            parse("seq customer > map.csv")?,
+            Anonymize {
+                method: Method::Sequence(Some("customer".into())),
+                map: Some(PathBuf::from("map.csv")),
+            }
+        );
+        assert_eq!(parse("hash")?.method, Method::Hash(None));
+        assert!(parse("shuffle").is_err());
+        assert!(parse("hash >").is_err());
+
+        let nulls = Nulls::default();
+        let data = column(&["name", "Alice", "Bob", "", "Alice"]);
+        let seq = plan(&data, 0, 1, &Method::Sequence(None), "", "name", &nulls);
+        assert_eq!(
+            seq.cells,
+            [
+                (1, "name_01".into()),
+                (2, "name_02".into()),
+                (4, "name_01".into())
+            ]
+        );
+        assert_eq!(seq.mapping[1], ("Bob".to_string(), "name_02".to_string()));
+
+        let hash = |salt| plan(&data, 0, 1, &Method::Hash(None), salt, "", &nulls).cells;
+        let (a, b) = (hash("pepper"), hash("salt"));
+        assert_eq!(a[0].1, a[2].1);
+        assert_ne!(a[0].1, a[1].1);
+        assert_ne!(a[0].1, b[0].1);
+        assert_eq!(a[0].1.len(), 16);
+        assert_ne!(fresh_salt(), "");
+        Ok(())
+    }
+}
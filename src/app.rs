@@ -0,0 +1,6302 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::a1;
+use crate::a11y;
+use crate::anonymize;
+use crate::clipboard::{self, PasteOptions};
+use crate::columns;
+use crate::command::{Command, GroupOp};
+use crate::complete;
+use crate::compress::Codec;
+use crate::config::Advance;
+use crate::csv_io::{self, load_csv, save_csv};
+use crate::escape;
+use crate::external;
+use crate::fill;
+use crate::filters::{self, SavedFilters};
+use crate::findings::Findings;
+use crate::fwf;
+use crate::header;
+use crate::history;
+use crate::log;
+use crate::mask::Mask;
+use crate::money;
+use crate::names::{self, Names};
+use crate::nulls::Nulls;
+use crate::outliers;
+use crate::palette;
+use crate::presets::{self, Preset, Presets};
+use crate::profile;
+use crate::prompt::{Prompt, PromptKind};
+use crate::remote::Remote;
+use crate::report;
+use crate::schema::{self, Schema};
+use crate::sci;
+use crate::search::{self, Area, Search};
+use crate::sort;
+use crate::split::Split;
+use crate::tags::{self, Tags};
+use crate::tutorial::Tutorial;
+use crate::typing;
+use crate::undo;
+use crate::view::{self, View};
+use crate::{
+    check, command, config, export, fuzzy, help, input, journal, lock, picker, prompt, recent,
+    replace, viz,
+};
+
+/// The last change, which `.` makes again at the cursor.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// An action with its count, e.g. `3dd` or `p`.
+    Action(input::Action, Option<usize>),
+    /// A committed edit: the value typed into the cell.
+    SetCell(String),
+    PasteSpecial(PasteOptions),
+    /// A cursor-relative `:` command, `:s` or `:fill`.
+    Command(Command),
+}
+
+#[derive(Default)]
+pub struct App {
+    pub file_path: PathBuf,
+    pub data: Vec<Vec<String>>, // rows x cols
+    pub row: usize,
+    pub col: usize,
+    pub editing: bool,
+    pub editor_buf: String,
+    pub dirty: bool,
+    pub message: Option<String>, // one-shot feedback shown in the info line
+    pub read_only: bool,
+    pub write_protected: bool, // the file can't be saved in place; `w` asks for another path
+    pub pending_replay: Vec<journal::Edit>, // unsaved edits found in the journal at startup
+    pub suspend_requested: bool, // Ctrl+Z; the event loop leaves the TUI
+    pub config: config::Config,
+    pub keymap: input::Keymap,
+    pub input: input::InputState, // pending count / chord in navigation mode
+    pub help: Option<help::HelpView>,
+    pub prompt: Option<Prompt>,
+    pub lock: Option<lock::FileLock>, // held while the file is open for editing
+    pub recent_list: Option<PathBuf>, // where opened files are remembered
+    pub recent: Option<picker::Picker>, // the `:recent` overlay
+    pub original: Vec<Vec<String>>,   // file contents as of the last load/save
+    pub origin: Vec<Option<usize>>,   // current row -> row in `original`; missing = new row
+    pub col_origin: Vec<Option<usize>>, // current column -> column in `original`
+    pub compare: bool,                // show the original-vs-current popup
+    pub findings: Option<Findings>,   // results panel of the last check
+    pub viz: viz::VizMode,            // data bars / heatmap for numeric columns
+    pub chart: Option<viz::Series>,   // the sparkline popup
+    pub anchor: Option<(usize, usize)>, // block selection from here to the cursor
+    pub live_search: Option<Search>,  // the `/` query being typed, for highlighting
+    pub last_search: Option<Search>,  // for `n` / `N`
+    pub fuzzy: Option<fuzzy::FuzzyView>, // the fuzzy-find overlay
+    pub history: undo::History,
+    pub show_empty: bool,                             // highlight blank cells
+    pub split: Option<Split>,                         // a second pane on the same table
+    pub clipboard: Option<clipboard::Block>,          // cells copied with `y`
+    pub paste_special: Option<PasteOptions>,          // the paste-special dialog
+    pub pending_paste: Option<(PasteOptions, usize)>, // a paste over N filled cells, awaiting y
+    pub pending_block: Option<clipboard::Block>,      // text pasted into the table, awaiting y
+    pub completion: Option<complete::Completion>,     // Ctrl+N/P cycling while editing
+    pub external: Option<external::Target>,           // the event loop runs $EDITOR on this
+    pub escaped: bool, // the file has control characters or invalid UTF-8
+    pub cell_view: Option<usize>, // full-value popup of the current cell, with its scroll
+    pub record_card: bool, // the cursor's row as `header: value` lines; `app.col` is the field
+    pub row_compare: Option<RowCompare>, // two rows side by side, from `gd`
+    pub header: bool,  // the first row names the columns
+    pub remote: Option<Remote>, // where the file was downloaded from
+    pub view: Option<View>, // display-only sort/filter; the data keeps file order
+    pub tags: Tags,    // rows marked with `t` for the `:tagged` commands
+    pub column_manager: Option<columns::Manager>, // the column manager overlay
+    pub columns: columns::Layout, // hidden and pinned columns
+    pub review: Option<replace::Review>, // a `:s///c` asking about each cell
+    pub last_change: Option<Change>, // what `.` repeats
+    pub names: Names,  // named ranges, kept in `<file>.names`
+    pub presets: Presets, // named views, kept in `<file>.views`
+    pub saved_filters: SavedFilters, // named filters, kept in `<file>.filters`
+    pub filter_panel: Option<filters::Panel>, // the `F` list of saved filters
+    pub spans: Option<csv_io::Spans>, // where `original`'s rows sit in the file, for quick saves
+    pub perf: Option<Perf>, // the `:perf` overlay's latest timings
+    pub tutorial: Option<Tutorial>, // `--tutorial`'s instructions, following along
+    pub scripts: Option<PathBuf>, // `<config dir>/scripts`, for `:script`
+    pub caps: palette::Caps, // what the terminal can draw
+    pub prompt_history: history::PromptHistory, // past `:` and `/` lines, for Up and Ctrl+R
+    pub export_dialog: Option<export::Dialog>, // the `gx` / `:export` dialog
+    pub export_settings: export::Settings, // what the dialog was last set to
+    pub export_file: Option<PathBuf>, // `<config dir>/export`, where they are kept
+    pub(crate) data_gen: u64, // bumped whenever `data` changes, so `measured` knows it is stale
+    pub(crate) measured: RefCell<Measured>, // figures taken from every row, as of a `data_gen`
+}
+
+/// What drawing the table needs from every row, kept until the rows change
+/// so a frame doesn't scan the whole file.
+#[derive(Default)]
+pub struct Measured {
+    max_cols: Option<(u64, usize)>,
+    ranges: Option<Ranges>,
+}
+
+/// `viz::column_ranges` and what it was worked out from.
+struct Ranges {
+    data_gen: u64,
+    nulls: Nulls,
+    decimal: viz::Decimal,
+    ranges: Vec<Option<(f64, f64)>>,
+}
+
+/// Two rows side by side, one line per column, for `gd`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowCompare {
+    pub rows: (usize, usize),
+    pub scroll: usize,
+    pub only_diff: bool, // hide the fields that match
+}
+
+/// Timings the event loop measures for the `:perf` overlay.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Perf {
+    /// Building and flushing the last frame.
+    pub frame: Duration,
+    /// From reading the last key to its frame being on screen.
+    pub latency: Duration,
+}
+
+impl App {
+    #[cfg(test)]
+    pub fn new(file_path: PathBuf, data: Vec<Vec<String>>) -> Self {
+        let mut app = Self {
+            file_path,
+            data,
+            ..Default::default()
+        };
+        app.mark_saved();
+        app
+    }
+
+    /// Take the current data as the on-disk baseline for change tracking.
+    pub fn mark_saved(&mut self) {
+        self.original = self.data.clone();
+        self.origin = (0..self.data.len()).map(Some).collect();
+        self.col_origin = (0..self.max_cols()).map(Some).collect();
+        self.spans = None;
+    }
+
+    /// For each row, the row of `original` it still matches exactly.
+    fn unchanged_rows(&self) -> Vec<Option<usize>> {
+        self.data
+            .iter()
+            .zip(&self.origin)
+            .map(|(row, o)| o.filter(|&o| self.original.get(o) == Some(row)))
+            .collect()
+    }
+
+    /// The value a cell had on disk, or `None` for a row added since then.
+    /// Cells of a column added since then were empty.
+    pub fn original_value(&self, r: usize, c: usize) -> Option<&str> {
+        let o = self.origin.get(r).copied().flatten()?;
+        let Some(Some(oc)) = self.col_origin.get(c) else {
+            return Some("");
+        };
+        Some(self.original[o].get(*oc).map_or("", String::as_str))
+    }
+
+    pub fn is_modified(&self, r: usize, c: usize) -> bool {
+        let current = self
+            .data
+            .get(r)
+            .and_then(|row| row.get(c))
+            .map_or("", String::as_str);
+        self.original_value(r, c) != Some(current)
+    }
+
+    /// False for a scratch buffer started without a file name.
+    pub fn has_path(&self) -> bool {
+        !self.file_path.as_os_str().is_empty()
+    }
+
+    /// Whether writing to `path` would overwrite the file being edited,
+    /// however the two paths are spelled.
+    pub fn is_open_file(&self, path: &Path) -> bool {
+        if !self.has_path() {
+            return false;
+        }
+        match (path.canonicalize(), self.file_path.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => path == self.file_path,
+        }
+    }
+
+    /// Load `path` into this session, replacing the current buffer. A missing
+    /// file starts a new grid that is created on the first write.
+    pub fn open(&mut self, path: PathBuf) -> Result<()> {
+        let exists = path.exists();
+        let start = Instant::now();
+        // Everything that can fail is read first, so a failure leaves the
+        // current buffer, its path and its lock as they were.
+        let loaded = if exists {
+            csv_io::load_with_spans(&path)
+                .with_context(|| "failed to load CSV")
+                .inspect_err(|e| log::error("open", e))?
+        } else {
+            csv_io::Loaded {
+                rows: Vec::new(),
+                spans: None,
+            }
+        };
+        let names = Names::load_for(&path)?;
+        let presets = Presets::load_for(&path)?;
+        let saved_filters = SavedFilters::load_for(&path)?;
+        let protected = exists && !csv_io::is_writable(&path);
+        // Reopening the file this session has locked keeps that lock.
+        let kept = !protected && self.lock.is_some() && self.is_open_file(&path);
+        let outcome = if protected || kept {
+            None
+        } else {
+            Some(lock::FileLock::acquire(&path)?)
+        };
+        let owned = kept || matches!(outcome, Some(lock::LockOutcome::Acquired(_)));
+        let pending_replay = if owned {
+            journal::read(&journal::journal_path_for(&path))?
+        } else {
+            Vec::new()
+        };
+
+        if self
+            .remote
+            .as_ref()
+            .is_some_and(|r| !r.local_path().is_ok_and(|l| l == path))
+        {
+            self.remote = None;
+        }
+        if !kept {
+            self.lock = None;
+        }
+        self.file_path = path;
+        self.data = loaded.rows;
+        self.data_gen += 1;
+        self.row = 0;
+        self.col = 0;
+        self.dirty = false;
+        self.read_only = false;
+        self.write_protected = false;
+        self.pending_replay.clear();
+        self.findings = None;
+        self.history = undo::History::default();
+        self.split = None;
+        self.view = None;
+        self.tags.clear();
+        self.columns = columns::Layout::default();
+        self.names = names;
+        self.presets = presets;
+        self.saved_filters = saved_filters;
+        self.filter_panel = None;
+        let hook = match self.config.post_load.clone() {
+            Some(cmd) if exists => Some(self.run_post_load(&cmd)),
+            _ => None,
+        };
+        self.mark_saved();
+        // The hook's output isn't the file, so its record offsets don't apply.
+        if hook.is_none() {
+            self.spans = loaded.spans;
+        }
+        log::event(
+            "open",
+            &[
+                ("path", &self.file_path.display()),
+                ("rows", &self.data.len()),
+                ("cols", &self.max_cols()),
+                ("ms", &start.elapsed().as_millis()),
+            ],
+        );
+        self.escaped = self.data.iter().flatten().any(|c| escape::is_unsafe(c));
+        let guessed = self.config.header.is_none()
+            && header::looks_like_header(&self.data, self.config.decimal);
+        self.header = self.config.header.unwrap_or(guessed);
+        self.apply_schema_masks();
+        self.message = if !exists {
+            Some("New file; it is created on the first write".to_string())
+        } else if guessed {
+            Some("Row 1 looks like a header, so header mode is on (:header off)".to_string())
+        } else if self.escaped {
+            Some(
+                "Control characters or invalid UTF-8 are shown escaped and saved unchanged"
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+        if let Some(Err(e)) = &hook {
+            log::error("post_load", e);
+            self.message = Some(format!(
+                "post_load hook failed; showing the file as is: {e:#}"
+            ));
+        }
+        if self.data.is_empty() {
+            self.prompt = Some(Prompt::new(PromptKind::NewDimensions));
+        }
+        match outcome {
+            Some(outcome) => self.take_lock(outcome),
+            None if protected => self.protect(),
+            None => {}
+        }
+        self.pending_replay = pending_replay;
+        self.check_sci();
+        self.remember_recent();
+        Ok(())
+    }
+
+    /// Warn about, or with `expand_sci` rewrite, cells that look like IDs a
+    /// spreadsheet turned into scientific notation. The table itself always
+    /// shows values as stored, long ones clipped rather than reformatted.
+    fn check_sci(&mut self) {
+        let found = sci::find(&self.data);
+        let Some(&(row, col)) = found.first() else {
+            return;
+        };
+        // Journal replay expects the file as saved, so leave it alone then.
+        if self.config.expand_sci && !self.read_only && self.pending_replay.is_empty() {
+            let edits = found
+                .iter()
+                .filter_map(|&(row, col)| {
+                    let value = sci::expand(&self.data[row][col])?;
+                    Some(journal::Edit::SetCell { row, col, value })
+                })
+                .collect();
+            self.record_all(edits);
+            self.message = Some(format!(
+                "Expanded {} cell(s) from scientific notation (u to undo)",
+                found.len()
+            ));
+        } else {
+            self.message = Some(format!(
+                "{} cell(s) look like numbers mangled into scientific notation, e.g. {} at row {}, column {} (expand_sci = on rewrites them)",
+                found.len(),
+                self.data[row][col],
+                row + 1,
+                col + 1
+            ));
+        }
+    }
+
+    fn remember_recent(&mut self) {
+        // A downloaded copy lives in a temp dir; it's no use to reopen.
+        if self.remote.is_some() {
+            return;
+        }
+        if let Some(list) = &self.recent_list
+            && let Err(e) = recent::record(list, &self.file_path)
+        {
+            self.message = Some(format!("Could not update recent files: {e:#}"));
+        }
+    }
+
+    /// Take the advisory lock for `file_path`, falling back to read-only when
+    /// another live session holds it.
+    pub fn acquire_lock(&mut self) -> Result<()> {
+        let outcome = lock::FileLock::acquire(&self.file_path)?;
+        self.take_lock(outcome);
+        Ok(())
+    }
+
+    fn take_lock(&mut self, outcome: lock::LockOutcome) {
+        match outcome {
+            lock::LockOutcome::Acquired(l) => self.lock = Some(l),
+            lock::LockOutcome::HeldBy(pid) => {
+                let owner = pid.map_or("another session".to_string(), |p| format!("pid {p}"));
+                self.read_only = true;
+                self.message = Some(format!("Locked by {owner}; opened read-only"));
+            }
+            lock::LockOutcome::Unwritable => self.protect(),
+        }
+    }
+
+    /// Fall back to read-only for a file that can't be saved in place.
+    fn protect(&mut self) {
+        self.read_only = true;
+        self.write_protected = true;
+        self.message =
+            Some("File is write-protected; opened read-only (w saves a copy)".to_string());
+    }
+
+    /// Replace the grid with an empty `rows` x `cols` one.
+    pub fn reset_grid(&mut self, rows: usize, cols: usize) {
+        self.data = vec![vec![String::new(); cols]; rows];
+        self.data_gen += 1;
+        self.mark_saved();
+        self.row = 0;
+        self.col = 0;
+    }
+
+    /// Next (or previous) modified cell in row-major order from the cursor,
+    /// wrapping around the end of the grid.
+    pub fn find_modified(&self, forward: bool) -> Option<(usize, usize)> {
+        let cols = self.max_cols().max(1);
+        let total = self.data.len() * cols;
+        let here = self.row * cols + self.col;
+        (1..=total)
+            .map(|step| {
+                let i = if forward {
+                    (here + step) % total
+                } else {
+                    (here + total - step % total) % total
+                };
+                (i / cols, i % cols)
+            })
+            .find(|&(r, c)| self.is_modified(r, c))
+    }
+
+    /// Check the data against the file's schema, or with `spec` one column
+    /// against a format, and list the problems in the results panel.
+    fn validate(&mut self, spec: Option<(String, String)>) -> Result<()> {
+        let schema = match spec {
+            Some((column, format)) => Schema {
+                columns: vec![schema::ColumnRule {
+                    column,
+                    format: schema::Format::parse(&format)?,
+                    warning: false,
+                }],
+                ..Schema::default()
+            },
+            None => Schema::load_for(&self.file_path)?.ok_or_else(|| {
+                anyhow!(
+                    "no schema at {}; add `column = format` lines there or use :validate <col> <format>",
+                    schema::schema_path_for(&self.file_path).display()
+                )
+            })?,
+        };
+        let items = schema.validate(
+            &self.data,
+            self.header,
+            &self.config.nulls,
+            self.config.decimal,
+        )?;
+        if items.is_empty() {
+            self.findings = None;
+            self.message = Some(format!(
+                "Valid: {} rule(s) pass on every row",
+                schema.columns.len() + schema.rows.len()
+            ));
+        } else {
+            let warnings = items.iter().filter(|f| f.warning).count();
+            let title = format!(
+                "validate: {} error(s), {warnings} warning(s)",
+                items.len() - warnings
+            );
+            self.findings = Some(Findings::new(title, items));
+        }
+        Ok(())
+    }
+
+    /// Write the results panel's findings, all of them whatever the rule
+    /// filter, to `path`. With no panel open, validate against the schema
+    /// first.
+    fn write_report(&mut self, path: &Path) -> Result<()> {
+        if self.is_open_file(path) {
+            bail!("That's the file being edited; pick another name");
+        }
+        if self.findings.is_none() {
+            self.validate(None)?;
+        }
+        let items = self.findings.as_ref().map_or(&[][..], |f| &f.items[..]);
+        report::write(path, items, &self.data, self.header)?;
+        self.message = Some(format!(
+            "Wrote {} finding(s) to {}",
+            items.len(),
+            path.display()
+        ));
+        Ok(())
+    }
+
+    /// The columns' layout and the view's sort and filter, as a preset.
+    fn current_preset(&self) -> Preset {
+        let view = self.view.as_ref();
+        Preset {
+            columns: self.columns.clone(),
+            sort: view.map(|v| v.sort.clone()).unwrap_or_default(),
+            filter: view.and_then(|v| v.filter.as_ref()).map(|f| f.rule.clone()),
+        }
+    }
+
+    /// Run a `:view` command; presets are saved next to the file as soon
+    /// as they change.
+    fn view_preset(&mut self, op: presets::Op) -> Result<()> {
+        use presets::Op;
+        let changed = match op {
+            Op::List => {
+                let all = self.presets.names();
+                self.message = Some(if all.is_empty() {
+                    "No saved views; :view save <name> saves this one".to_string()
+                } else {
+                    format!("Views: {}", all.join(", "))
+                });
+                false
+            }
+            Op::Save(name) => {
+                self.presets.insert(name.clone(), self.current_preset());
+                self.message = Some(format!("Saved the view as {name}"));
+                true
+            }
+            Op::Delete(name) => {
+                if !self.presets.remove(&name) {
+                    return Err(anyhow!("No view named {name}"));
+                }
+                self.message = Some(format!("Dropped the view {name}"));
+                true
+            }
+            Op::Apply(name) => {
+                let preset = self
+                    .presets
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No view named {name}"))?;
+                let cols = self.max_cols();
+                if preset.columns.order(cols).is_empty() {
+                    return Err(anyhow!("View {name} hides every column"));
+                }
+                let filter = match &preset.filter {
+                    Some(rule) => Some(view::Filter::with_rule(rule.clone(), self.headers())?),
+                    None => None,
+                };
+                if let Some(k) = preset.sort.iter().find(|k| k.col >= cols) {
+                    return Err(anyhow!(
+                        "View {name} sorts by column {}, which is gone",
+                        k.col + 1
+                    ));
+                }
+                // Masks stay on whatever the view shows.
+                self.columns = columns::Layout {
+                    masked: std::mem::take(&mut self.columns.masked),
+                    masked_last4: std::mem::take(&mut self.columns.masked_last4),
+                    ..preset.columns
+                };
+                self.update_view(|v| {
+                    v.sort = preset.sort;
+                    v.filter = filter;
+                });
+                if !self.column_order().contains(&self.col) {
+                    self.col = self.col_at(0);
+                }
+                self.message = Some(format!("View {name}"));
+                false
+            }
+        };
+        if changed && self.has_path() {
+            self.presets.save_for(&self.file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Data rows the view shows, leaving out the header.
+    fn shown_rows(&self) -> usize {
+        self.visible_rows() - usize::from(self.header).min(self.visible_rows())
+    }
+
+    /// Run a `:filters` command; saved filters are written next to the file
+    /// as soon as they change.
+    fn saved_filter(&mut self, op: presets::Op) -> Result<()> {
+        use presets::Op;
+        let changed = match op {
+            Op::List => {
+                if self.saved_filters.names().is_empty() {
+                    self.message =
+                        Some("No saved filters; :filters save <name> saves this one".to_string());
+                } else {
+                    self.filter_panel = Some(filters::Panel::default());
+                }
+                false
+            }
+            Op::Apply(name) => {
+                self.toggle_filter(&name)?;
+                false
+            }
+            Op::Save(name) => {
+                let filter = self
+                    .view
+                    .as_ref()
+                    .and_then(|v| v.filter.clone())
+                    .ok_or_else(|| anyhow!("No filter to save; :filter or * sets one"))?;
+                self.saved_filters.insert(name.clone(), filter.rule.clone());
+                // It stays on, now under its name.
+                self.update_view(|v| {
+                    v.filter = None;
+                    v.saved.retain(|(n, _)| *n != name);
+                    v.saved.push((name.clone(), filter));
+                });
+                self.message = Some(format!("Saved the filter as {name}"));
+                true
+            }
+            Op::Delete(name) => {
+                if !self.saved_filters.remove(&name) {
+                    return Err(anyhow!("No filter named {name}"));
+                }
+                self.update_view(|v| v.saved.retain(|(n, _)| *n != name));
+                self.message = Some(format!("Dropped the filter {name}"));
+                true
+            }
+        };
+        if changed && self.has_path() {
+            self.saved_filters.save_for(&self.file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Mask the columns the schema's `mask` lines name. A schema that
+    /// doesn't load masks nothing (`:validate` says why), and neither does
+    /// a line naming a column that isn't there.
+    fn apply_schema_masks(&mut self) {
+        let Ok(Some(schema)) = Schema::load_for(&self.file_path) else {
+            return;
+        };
+        let headers = if self.header { self.headers() } else { &[] };
+        let masks: Vec<(usize, Mask)> = schema
+            .masks
+            .iter()
+            .filter_map(|(c, m)| Some((header::resolve_column(headers, c).ok()?, *m)))
+            .collect();
+        for (col, mask) in masks {
+            self.columns.set_mask(col, Some(mask));
+        }
+    }
+
+    /// `value`, from column `col`, as it is drawn: masked if the column is.
+    pub fn masked<'a>(&self, col: usize, value: &'a str) -> Cow<'a, str> {
+        match self.columns.mask(col) {
+            Some(mask) => Cow::Owned(mask.apply(value)),
+            None => Cow::Borrowed(value),
+        }
+    }
+
+    /// The cells an export with `settings` writes, with the header row
+    /// first when it asks for one and the file has one; says whether it does.
+    pub fn export_table(&self, settings: &export::Settings) -> Result<(Vec<Vec<String>>, bool)> {
+        let (scope, header) = (settings.scope, settings.header);
+        let body = |r: &usize| !self.header || *r != 0;
+        let all_cols = (0, self.max_cols().saturating_sub(1));
+        let (rows, (left, right)): (Vec<usize>, _) = match scope {
+            export::Scope::All => ((0..self.data.len()).filter(body).collect(), all_cols),
+            export::Scope::Shown => (
+                (0..self.visible_rows())
+                    .map(|p| self.row_at(p))
+                    .filter(body)
+                    .collect(),
+                all_cols,
+            ),
+            export::Scope::Selection => {
+                let (top, left, bottom, right) = self
+                    .selection()
+                    .ok_or_else(|| anyhow!("No selection; v starts one"))?;
+                ((top..=bottom).filter(body).collect(), (left, right))
+            }
+            export::Scope::Tagged if self.tags.is_empty() => bail!("No tagged rows; t tags one"),
+            export::Scope::Tagged => (self.tags.rows().filter(body).collect(), all_cols),
+        };
+        let header = header && self.header && !self.data.is_empty();
+        let cells = |r: usize| -> Vec<String> {
+            (left..=right)
+                .map(|c| {
+                    let v = self.data[r].get(c).map_or("", String::as_str);
+                    let header_row = header && r == 0;
+                    if settings.masked && !header_row {
+                        self.masked(c, v).into_owned()
+                    } else {
+                        v.to_string()
+                    }
+                })
+                .collect()
+        };
+        let table = header
+            .then_some(0)
+            .into_iter()
+            .chain(rows.into_iter().filter(|&r| r < self.data.len()))
+            .map(cells)
+            .collect();
+        Ok((table, header))
+    }
+
+    /// Write an export with `settings` to `dest` and remember them for the
+    /// next one.
+    pub fn export_to(&mut self, settings: export::Settings, dest: &Path) -> Result<()> {
+        if dest.as_os_str().is_empty() {
+            bail!("Type where to save the export");
+        }
+        if self.is_open_file(dest) {
+            bail!("That's the file being edited; pick another name");
+        }
+        let (table, header) = self.export_table(&settings)?;
+        let title = self.file_path.display().to_string();
+        let bytes = export::render(&settings, &title, &table, header)?;
+        std::fs::write(dest, bytes).with_context(|| format!("write {dest:?}"))?;
+        self.export_settings = settings;
+        if let Some(file) = &self.export_file {
+            settings.save(file)?;
+        }
+        self.message = Some(format!(
+            "Exported {} row(s) as {} to {}",
+            table.len() - usize::from(header),
+            settings.format.label(),
+            dest.display()
+        ));
+        Ok(())
+    }
+
+    /// The columns, hidden ones too, where rows `a` and `b` hold different
+    /// values; a missing cell counts as empty.
+    pub fn row_differences(&self, a: usize, b: usize) -> Vec<usize> {
+        let cell = |r: usize, c: usize| {
+            self.data
+                .get(r)
+                .and_then(|row| row.get(c))
+                .map_or("", String::as_str)
+        };
+        (0..self.max_cols())
+            .filter(|&c| cell(a, c) != cell(b, c))
+            .collect()
+    }
+
+    /// Compare the two tagged rows, or the one tagged row with the cursor's.
+    pub fn open_row_compare(&mut self) -> Result<()> {
+        let tagged: Vec<usize> = self.tags.rows().collect();
+        let rows = match tagged[..] {
+            [a, b] => (a, b),
+            [a] if a != self.row => (a.min(self.row), a.max(self.row)),
+            _ => bail!(
+                "Tag two rows with t (or one and put the cursor on the other) to compare them"
+            ),
+        };
+        if rows.1 >= self.data.len() {
+            bail!("Row {} is past the end of the file", rows.1 + 1);
+        }
+        self.row_compare = Some(RowCompare {
+            rows,
+            ..RowCompare::default()
+        });
+        Ok(())
+    }
+
+    /// Collapse or expand the group the cursor is in, leaving the cursor on
+    /// its first row.
+    pub fn toggle_group(&mut self) -> Result<()> {
+        let (v, g) = self
+            .view
+            .as_ref()
+            .and_then(|v| Some((v, v.group.as_ref()?)))
+            .ok_or_else(|| anyhow!("Not grouped; :group <col> groups the rows"))?;
+        let run = v
+            .position(self.row)
+            .and_then(|pos| g.run_at(pos))
+            .map(|k| g.runs[k].clone())
+            .ok_or_else(|| anyhow!("The header row isn't in a group"))?;
+        let first = v.rows[run.pos];
+        let collapse = !g.collapsed.contains(&run.value);
+        self.update_view(|v| {
+            if let Some(g) = &mut v.group {
+                if collapse {
+                    g.collapsed.insert(run.value.clone());
+                } else {
+                    g.collapsed.remove(&run.value);
+                }
+            }
+        });
+        self.row = first;
+        self.message = Some(format!(
+            "{} {} ({} row(s))",
+            if collapse { "Collapsed" } else { "Expanded" },
+            escape::display(&run.value),
+            run.len
+        ));
+        Ok(())
+    }
+
+    /// Switch the saved filter `name` on or off.
+    pub fn toggle_filter(&mut self, name: &str) -> Result<()> {
+        let on = self
+            .view
+            .as_ref()
+            .is_some_and(|v| v.saved.iter().any(|(n, _)| n == name));
+        if on {
+            self.update_view(|v| v.saved.retain(|(n, _)| n != name));
+        } else {
+            let rule = self
+                .saved_filters
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("No filter named {name}"))?;
+            let filter = view::Filter::with_rule(rule, self.headers())
+                .with_context(|| format!("filter {name}"))?;
+            self.update_view(|v| v.saved.push((name.to_string(), filter)));
+        }
+        self.message = Some(format!(
+            "Filter {name} {}: {} row(s) shown",
+            if on { "off" } else { "on" },
+            self.shown_rows()
+        ));
+        Ok(())
+    }
+
+    /// Run a `:name` command; names are saved next to the file as soon as
+    /// they change.
+    fn named_range(&mut self, op: names::Op) -> Result<()> {
+        use names::Op;
+        let changed = match op {
+            Op::List => {
+                let all = self.names.describe();
+                self.message = Some(if all.is_empty() {
+                    "No named ranges; :name range <name> names the selection".to_string()
+                } else {
+                    format!("Names: {}", all.join(", "))
+                });
+                false
+            }
+            Op::Define(name) => {
+                let (top, left, bottom, right) = self
+                    .selection()
+                    .unwrap_or((self.row, self.col, self.row, self.col));
+                let range = ((top, left), (bottom, right));
+                self.names.insert(name.clone(), range);
+                self.message = Some(format!("Named {} {name}", names::range_text(&range)));
+                true
+            }
+            Op::Delete(name) => {
+                if !self.names.remove(&name) {
+                    return Err(anyhow!("No range named {name}"));
+                }
+                self.message = Some(format!("Dropped the name {name}"));
+                true
+            }
+            Op::Export(name, path) => {
+                if self.is_open_file(&path) {
+                    bail!("That's the file being edited; pick another name");
+                }
+                let ((top, left), (bottom, right)) = self
+                    .names
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("No range named {name}"))?;
+                let cell = |r: usize, c: usize| {
+                    self.data
+                        .get(r)
+                        .and_then(|row| row.get(c))
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                let with_header = self.header && top > 0;
+                let rows: Vec<Vec<String>> = with_header
+                    .then_some(0)
+                    .into_iter()
+                    .chain(top..=bottom)
+                    .map(|r| (left..=right).map(|c| cell(r, c)).collect())
+                    .collect();
+                save_csv(&path, &rows)?;
+                self.message = Some(format!(
+                    "Exported {name} ({}) to {}",
+                    names::range_text(&((top, left), (bottom, right))),
+                    path.display()
+                ));
+                false
+            }
+        };
+        if changed && self.has_path() {
+            self.names.save_for(&self.file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Move to the next (or previous) cell the results panel lists, in
+    /// row-major order from the cursor and wrapping around, and select it in
+    /// the panel. False when there is nothing listed.
+    pub fn step_finding(&mut self, forward: bool) -> bool {
+        let Some(panel) = self.findings.as_mut() else {
+            return false;
+        };
+        let here = (self.row, self.col);
+        let cells: Vec<(usize, usize)> = panel.shown().map(|f| (f.row, f.col)).collect();
+        if cells.is_empty() {
+            return false;
+        }
+        panel.selected = if forward {
+            cells.iter().position(|&c| c > here).unwrap_or(0)
+        } else {
+            cells
+                .iter()
+                .rposition(|&c| c < here)
+                .unwrap_or(cells.len() - 1)
+        };
+        let (row, col) = cells[panel.selected];
+        self.row = row.min(self.data.len().saturating_sub(1));
+        self.col = col;
+        self.clamp_col();
+        true
+    }
+
+    pub fn max_cols(&self) -> usize {
+        let mut measured = self.measured.borrow_mut();
+        if let Some((gen_at, cols)) = measured.max_cols
+            && gen_at == self.data_gen
+        {
+            return cols;
+        }
+        let cols = self.data.iter().map(|r| r.len()).max().unwrap_or(0);
+        measured.max_cols = Some((self.data_gen, cols));
+        cols
+    }
+
+    /// `viz::column_ranges` for the table, worked out again only when the
+    /// rows or the null and decimal settings have changed.
+    pub fn column_ranges(&self) -> Vec<Option<(f64, f64)>> {
+        let cols = self.max_cols().max(1);
+        let (nulls, decimal) = (&self.config.nulls, self.config.decimal);
+        let mut measured = self.measured.borrow_mut();
+        if let Some(kept) = &measured.ranges
+            && kept.data_gen == self.data_gen
+            && kept.nulls == *nulls
+            && kept.decimal == decimal
+        {
+            return kept.ranges.clone();
+        }
+        let ranges = viz::column_ranges(&self.data, cols, nulls, decimal);
+        measured.ranges = Some(Ranges {
+            data_gen: self.data_gen,
+            nulls: nulls.clone(),
+            decimal,
+            ranges: ranges.clone(),
+        });
+        ranges
+    }
+
+    pub fn ensure_cell_exists(&mut self, r: usize, c: usize) {
+        self.data_gen += 1;
+        if r >= self.data.len() {
+            self.data.resize(r + 1, Vec::new());
+        }
+        if c >= self.data[r].len() {
+            self.data[r].resize(c + 1, String::new());
+        }
+    }
+
+    pub fn journal_path(&self) -> PathBuf {
+        journal::journal_path_for(&self.file_path)
+    }
+
+    pub fn set_cell(&mut self, r: usize, c: usize, value: String) {
+        self.record(journal::Edit::SetCell {
+            row: r,
+            col: c,
+            value,
+        });
+    }
+
+    /// Copy the selection, or the current cell, and end the selection.
+    pub fn yank(&mut self) {
+        let (top, left, bottom, right) = self
+            .selection()
+            .unwrap_or((self.row, self.col, self.row, self.col));
+        let block: clipboard::Block = (top..=bottom)
+            .map(|r| {
+                (left..=right)
+                    .map(|c| {
+                        self.data
+                            .get(r)
+                            .and_then(|row| row.get(c))
+                            .cloned()
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect();
+        self.message = Some(format!(
+            "Copied {}x{} cell(s)",
+            bottom - top + 1,
+            right - left + 1
+        ));
+        self.clipboard = Some(block);
+        self.anchor = None;
+    }
+
+    /// Paste the copied cells with the cursor at their top-left corner, as
+    /// one undoable step; with `confirm_paste` on, a paste that would
+    /// replace non-empty cells waits for `y` first.
+    pub fn paste(&mut self, opts: PasteOptions) {
+        let Some(edits) = self.paste_edits(opts) else {
+            return;
+        };
+        let overwrites = edits
+            .iter()
+            .filter(|e| match e {
+                journal::Edit::SetCell { row, col, value } => self
+                    .data
+                    .get(*row)
+                    .and_then(|r| r.get(*col))
+                    .is_some_and(|old| !old.trim().is_empty() && old != value),
+                _ => false,
+            })
+            .count();
+        if self.config.confirm_paste && overwrites > 0 {
+            self.pending_paste = Some((opts, overwrites));
+            return;
+        }
+        self.paste_now(opts);
+    }
+
+    fn paste_edits(&mut self, opts: PasteOptions) -> Option<Vec<journal::Edit>> {
+        let Some(block) = &self.clipboard else {
+            self.message = Some("Nothing copied; y copies the cell or selection".to_string());
+            return None;
+        };
+        let edits = clipboard::arrange(block, opts)
+            .into_iter()
+            .map(|(dr, dc, value)| journal::Edit::SetCell {
+                row: self.row + dr,
+                col: self.col + dc,
+                value,
+            })
+            .collect();
+        Some(edits)
+    }
+
+    fn paste_now(&mut self, opts: PasteOptions) {
+        let Some(edits) = self.paste_edits(opts) else {
+            return;
+        };
+        let n = edits.len();
+        self.record_all(edits);
+        self.message = Some(format!("Pasted {n} cell(s)"));
+    }
+
+    /// Write `block` with the cursor at its top-left corner, as one undoable
+    /// step; the clipboard is left alone.
+    pub fn paste_block(&mut self, block: &clipboard::Block) {
+        let edits: Vec<journal::Edit> = clipboard::arrange(block, PasteOptions::default())
+            .into_iter()
+            .map(|(dr, dc, value)| journal::Edit::SetCell {
+                row: self.row + dr,
+                col: self.col + dc,
+                value,
+            })
+            .collect();
+        let n = edits.len();
+        self.record_all(edits);
+        self.message = Some(format!("Pasted {n} cell(s)"));
+    }
+
+    /// Run a `:tagged` command over the tagged rows.
+    pub fn bulk(&mut self, op: tags::Bulk) -> Result<()> {
+        use tags::Bulk;
+        let n = self.tags.len();
+        if n == 0 && op != Bulk::Clear {
+            self.message = Some("No tagged rows; t tags the current row".to_string());
+            return Ok(());
+        }
+        let rows = |app: &Self| -> Vec<Vec<String>> {
+            app.tags.rows().map(|r| app.data[r].clone()).collect()
+        };
+        match op {
+            Bulk::Delete => {
+                let edits = self
+                    .tags
+                    .rows()
+                    .rev()
+                    .map(|row| journal::Edit::DeleteRows { row, count: 1 })
+                    .collect();
+                self.record_all(edits);
+                self.clamp_col();
+                self.message = Some(format!("Deleted {n} tagged row(s); u to undo"));
+            }
+            Bulk::Yank => {
+                let cols = self.max_cols();
+                let mut block = rows(self);
+                for row in &mut block {
+                    row.resize(cols, String::new());
+                }
+                self.clipboard = Some(block);
+                self.message = Some(format!("Copied {n} tagged row(s); p pastes them"));
+            }
+            Bulk::Export(path) => {
+                if self.is_open_file(&path) {
+                    self.message =
+                        Some("That's the file being edited; pick another name".to_string());
+                    return Ok(());
+                }
+                let mut out = rows(self);
+                if self.header && !self.tags.contains(0) && !self.data.is_empty() {
+                    out.insert(0, self.data[0].clone());
+                }
+                self.message = Some(match save_csv(&path, &out) {
+                    Ok(()) => format!("Exported {n} tagged row(s) to {}", path.display()),
+                    Err(e) => format!("Could not export: {e:#}"),
+                });
+            }
+            Bulk::Empty => {
+                let edits: Vec<_> = self
+                    .tags
+                    .rows()
+                    .flat_map(|row| {
+                        let cells = self.data.get(row).map_or(&[][..], Vec::as_slice);
+                        cells.iter().enumerate().filter(|(_, v)| !v.is_empty()).map(
+                            move |(col, _)| journal::Edit::SetCell {
+                                row,
+                                col,
+                                value: String::new(),
+                            },
+                        )
+                    })
+                    .collect();
+                let cells = edits.len();
+                self.record_all(edits);
+                self.message = Some(format!(
+                    "Emptied {cells} cell(s) in {n} tagged row(s); the rows are kept"
+                ));
+            }
+            Bulk::Clear => {
+                self.tags.clear();
+                self.message = Some(format!("Untagged {n} row(s)"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an edit, append it to the journal, and make it undoable.
+    pub fn record(&mut self, edit: journal::Edit) {
+        self.record_all(vec![edit]);
+    }
+
+    /// Several edits that undo as one step, e.g. a replace over a column.
+    pub fn record_all(&mut self, edits: Vec<journal::Edit>) {
+        if edits.is_empty() {
+            return;
+        }
+        let step = self.apply_logged(edits);
+        self.history.push(step);
+        self.refresh_view();
+    }
+
+    /// Re-sort and re-filter the view after the data changed.
+    fn refresh_view(&mut self) {
+        let pinned = usize::from(self.header);
+        if let Some(v) = &mut self.view {
+            v.rebuild(
+                &self.data,
+                pinned,
+                Some(self.row),
+                &self.config.nulls,
+                self.config.decimal,
+            );
+        }
+    }
+
+    /// Change the view with `f`, dropping it once it neither sorts nor filters.
+    fn update_view(&mut self, f: impl FnOnce(&mut View)) {
+        let mut v = self.view.take().unwrap_or_default();
+        f(&mut v);
+        if !v.is_empty() {
+            v.rebuild(
+                &self.data,
+                usize::from(self.header),
+                None,
+                &self.config.nulls,
+                self.config.decimal,
+            );
+            // Land on the first shown row when the cursor's row was hidden.
+            if v.position(self.row).is_none() {
+                self.row = v
+                    .rows
+                    .get(usize::from(self.header))
+                    .or(v.rows.first())
+                    .copied()
+                    .unwrap_or(0);
+                self.clamp_col();
+            }
+            self.view = Some(v);
+        }
+    }
+
+    /// Data columns in screen order, without the hidden ones.
+    pub fn column_order(&self) -> Vec<usize> {
+        self.columns.order(self.max_cols().max(1))
+    }
+
+    /// Screen position of the cursor's column.
+    pub fn col_pos(&self) -> usize {
+        self.column_order()
+            .iter()
+            .position(|&c| c == self.col)
+            .unwrap_or(0)
+    }
+
+    /// Data column shown at screen position `pos`, clamped to the last one.
+    pub fn col_at(&self, pos: usize) -> usize {
+        let order = self.column_order();
+        order
+            .get(pos.min(order.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Apply the cells taken during a `:s///c` review as one undoable step.
+    fn finish_review(&mut self) {
+        let Some(review) = self.review.take() else {
+            return;
+        };
+        let edits: Vec<_> = review
+            .accepted()
+            .map(|c| journal::Edit::SetCell {
+                row: c.row,
+                col: c.col,
+                value: c.after.clone(),
+            })
+            .collect();
+        let n = edits.len();
+        self.record_all(edits);
+        self.message = Some(format!(
+            "Replaced in {n} of {} cell(s)",
+            review.changes.len()
+        ));
+    }
+
+    /// The cursor's cell as screen-reader mode reads it out.
+    pub fn describe_cursor(&self) -> String {
+        let (row, col) = (self.row, self.col);
+        let header = self
+            .header
+            .then(|| self.headers().get(col))
+            .flatten()
+            .filter(|_| row > 0);
+        a11y::describe(&a11y::CellInfo {
+            row,
+            col,
+            header: header.map(String::as_str),
+            value: self
+                .data
+                .get(row)
+                .and_then(|r| r.get(col))
+                .map_or("", String::as_str),
+            modified: self.is_modified(row, col),
+            tagged: self.tags.contains(row),
+        })
+    }
+
+    /// Open the column manager on the cursor's column.
+    pub fn open_column_manager(&mut self) {
+        let cols = self.max_cols();
+        let names = (0..cols)
+            .map(|c| match self.headers().get(c) {
+                Some(h) if self.header => h.clone(),
+                _ => format!("Column {}", c + 1),
+            })
+            .collect();
+        self.column_manager = Some(columns::Manager::new(names, &self.columns, self.col));
+    }
+
+    /// Apply the column manager's order and names as one undoable step, and
+    /// take its visibility and pins. On error the manager stays open.
+    pub fn apply_column_manager(&mut self, m: &columns::Manager) -> Result<()> {
+        let layout = m.layout();
+        if layout.order(m.entries.len()).is_empty() {
+            return Err(anyhow!("leave at least one column visible"));
+        }
+        let order = m.order();
+        let mut edits = Vec::new();
+        if order.iter().enumerate().any(|(i, &o)| i != o) {
+            if self.read_only {
+                return Err(anyhow!(
+                    "file is open read-only; only visibility and pins can change"
+                ));
+            }
+            edits.push(journal::Edit::ReorderCols { order });
+        }
+        if self.header {
+            let names: Vec<String> = m
+                .entries
+                .iter()
+                .map(|e| e.name.trim().to_string())
+                .collect();
+            for (i, e) in m.entries.iter().enumerate() {
+                if self.headers().get(e.col).map(|h| h.trim()) == Some(names[i].as_str()) {
+                    continue;
+                }
+                if self.read_only {
+                    return Err(anyhow!(
+                        "file is open read-only; only visibility and pins can change"
+                    ));
+                }
+                header::check_name(&names, i, &names[i])?;
+                edits.push(journal::Edit::SetCell {
+                    row: 0,
+                    col: i,
+                    value: names[i].clone(),
+                });
+            }
+        }
+        let changed = edits.len();
+        self.record_all(edits);
+        // The manager has no say in alignment or masks; the reorder above
+        // moved them.
+        self.columns = columns::Layout {
+            rtl: std::mem::take(&mut self.columns.rtl),
+            masked: std::mem::take(&mut self.columns.masked),
+            masked_last4: std::mem::take(&mut self.columns.masked_last4),
+            ..layout
+        };
+        if !self.column_order().contains(&self.col) {
+            self.col = self.col_at(0);
+        }
+        self.message = Some(if changed == 0 {
+            "Column layout updated".to_string()
+        } else {
+            "Columns updated; u undoes the reorder and renames".to_string()
+        });
+        Ok(())
+    }
+
+    /// Number of rows on screen: all of them, or those the view shows.
+    pub fn visible_rows(&self) -> usize {
+        self.view.as_ref().map_or(self.data.len(), |v| v.rows.len())
+    }
+
+    /// Screen position of the cursor's row.
+    pub fn view_pos(&self) -> usize {
+        match &self.view {
+            Some(v) => v.position(self.row).unwrap_or(0),
+            None => self.row,
+        }
+    }
+
+    /// Data row shown at screen position `pos`, clamped to the last one.
+    pub fn row_at(&self, pos: usize) -> usize {
+        let pos = pos.min(self.visible_rows().saturating_sub(1));
+        self.view
+            .as_ref()
+            .map_or(pos, |v| v.rows.get(pos).copied().unwrap_or(0))
+    }
+
+    /// Apply and journal `edits`, returning the step that reverts them.
+    fn apply_logged(&mut self, edits: Vec<journal::Edit>) -> undo::Step {
+        use journal::Edit;
+        let rows_change = edits
+            .iter()
+            .any(|e| matches!(e, Edit::DeleteRows { .. } | Edit::InsertRow { .. }));
+        let cols_change = edits.iter().any(|e| {
+            matches!(
+                e,
+                Edit::InsertCol { .. } | Edit::DeleteCol { .. } | Edit::ReorderCols { .. }
+            )
+        });
+        let mut step = undo::Step {
+            edits: Vec::new(),
+            origin: rows_change.then(|| self.origin.clone()),
+            col_origin: cols_change.then(|| self.col_origin.clone()),
+            cursor: (self.row, self.col),
+        };
+        let mut inverses = Vec::new();
+        for edit in &edits {
+            inverses.push(self.inverse(edit));
+            self.apply(edit.clone());
+        }
+        if self.has_path()
+            && let Err(e) = journal::append(&self.journal_path(), &edits)
+        {
+            // A failing journal must not cost the edit itself; just report it.
+            self.message = Some(format!("Journal write failed: {e:#}"));
+        }
+        step.edits = inverses.into_iter().rev().flatten().collect();
+        step
+    }
+
+    /// Edits that take `edit` back, computed before it is applied.
+    fn inverse(&self, edit: &journal::Edit) -> Vec<journal::Edit> {
+        use journal::Edit;
+        match edit {
+            Edit::SetCell { row, col, .. } => vec![Edit::SetCell {
+                row: *row,
+                col: *col,
+                value: self
+                    .data
+                    .get(*row)
+                    .and_then(|r| r.get(*col))
+                    .cloned()
+                    .unwrap_or_default(),
+            }],
+            Edit::DeleteRows { row, count } => {
+                let end = row.saturating_add(*count).min(self.data.len());
+                (*row..end)
+                    .map(|r| Edit::InsertRow {
+                        row: r,
+                        cells: self.data[r].clone(),
+                    })
+                    .collect()
+            }
+            Edit::InsertRow { row, .. } => vec![Edit::DeleteRows {
+                row: *row,
+                count: 1,
+            }],
+            Edit::Reorder { order } => {
+                let mut back = vec![0; order.len()];
+                for (new, &old) in order.iter().enumerate() {
+                    back[old] = new;
+                }
+                vec![Edit::Reorder { order: back }]
+            }
+            Edit::InsertCol { col, .. } => vec![Edit::DeleteCol { col: *col }],
+            Edit::DeleteCol { col } => vec![Edit::InsertCol {
+                col: *col,
+                cells: self
+                    .data
+                    .iter()
+                    .map(|r| r.get(*col).cloned().unwrap_or_default())
+                    .collect(),
+            }],
+            Edit::ReorderCols { order } => {
+                let mut back = vec![0; order.len()];
+                for (new, &old) in order.iter().enumerate() {
+                    back[old] = new;
+                }
+                vec![Edit::ReorderCols { order: back }]
+            }
+        }
+    }
+
+    /// Revert the last change; `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.history.pop_undo() else {
+            return false;
+        };
+        let redo = self.revert(step);
+        self.history.push_redo(redo);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.history.pop_redo() else {
+            return false;
+        };
+        let undo = self.revert(step);
+        self.history.push_undo(undo);
+        true
+    }
+
+    fn revert(&mut self, step: undo::Step) -> undo::Step {
+        let back = self.apply_logged(step.edits);
+        if let Some(origin) = step.origin {
+            self.origin = origin;
+        }
+        if let Some(col_origin) = step.col_origin {
+            self.col_origin = col_origin;
+        }
+        (self.row, self.col) = step.cursor;
+        self.row = self.row.min(self.data.len().saturating_sub(1));
+        self.clamp_col();
+        self.refresh_view();
+        back
+    }
+
+    pub fn apply(&mut self, edit: journal::Edit) {
+        self.data_gen += 1;
+        match edit {
+            journal::Edit::SetCell { row, col, value } => {
+                self.ensure_cell_exists(row, col);
+                self.data[row][col] = value;
+            }
+            journal::Edit::DeleteRows { row, count } => {
+                let end = row.saturating_add(count).min(self.data.len());
+                if row < end {
+                    self.data.drain(row..end);
+                    self.tags.delete(row, end - row);
+                    let o_end = end.min(self.origin.len());
+                    if row < o_end {
+                        self.origin.drain(row..o_end);
+                    }
+                }
+                self.row = self.row.min(self.data.len().saturating_sub(1));
+            }
+            journal::Edit::InsertRow { row, cells } => {
+                let row = row.min(self.data.len());
+                self.data.insert(row, cells);
+                self.tags.insert(row);
+                if row <= self.origin.len() {
+                    self.origin.insert(row, None);
+                }
+            }
+            journal::Edit::Reorder { order } => {
+                if order.len() != self.data.len() {
+                    return;
+                }
+                // Rows past the end of `origin` are new; keep them so.
+                self.origin.resize(self.data.len(), None);
+                let mut old: Vec<Option<Vec<String>>> = std::mem::take(&mut self.data)
+                    .into_iter()
+                    .map(Some)
+                    .collect();
+                self.tags.reorder(&order);
+                let old_origin = std::mem::take(&mut self.origin);
+                for &i in &order {
+                    self.data.push(old[i].take().unwrap_or_default());
+                    self.origin.push(old_origin[i]);
+                }
+            }
+            journal::Edit::InsertCol { col, cells } => {
+                for (r, value) in cells.into_iter().enumerate() {
+                    self.ensure_cell_exists(r, col.saturating_sub(1));
+                    let row = &mut self.data[r];
+                    row.insert(col.min(row.len()), value);
+                }
+                let at = col.min(self.col_origin.len());
+                self.col_origin.insert(at, None);
+                self.columns.insert(col);
+            }
+            journal::Edit::DeleteCol { col } => {
+                for row in &mut self.data {
+                    if col < row.len() {
+                        row.remove(col);
+                    }
+                }
+                if col < self.col_origin.len() {
+                    self.col_origin.remove(col);
+                }
+                self.columns.delete(col);
+                self.clamp_col();
+            }
+            journal::Edit::ReorderCols { order } => {
+                for row in &mut self.data {
+                    // Keep short rows short: stop after the last cell that exists.
+                    let len = order
+                        .iter()
+                        .rposition(|&o| o < row.len())
+                        .map_or(0, |i| i + 1);
+                    *row = order[..len]
+                        .iter()
+                        .map(|&o| row.get(o).cloned().unwrap_or_default())
+                        .collect();
+                }
+                self.col_origin.resize(order.len(), None);
+                self.col_origin = order.iter().map(|&o| self.col_origin[o]).collect();
+                self.columns.reorder(&order);
+                self.col = order.iter().position(|&o| o == self.col).unwrap_or(0);
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// The selected block as `(top, left, bottom, right)`, inclusive.
+    pub fn selection(&self) -> Option<(usize, usize, usize, usize)> {
+        let (r, c) = self.anchor?;
+        Some((
+            r.min(self.row),
+            c.min(self.col),
+            r.max(self.row),
+            c.max(self.col),
+        ))
+    }
+
+    pub fn is_selected(&self, r: usize, c: usize) -> bool {
+        self.selection().is_some_and(|(top, left, bottom, right)| {
+            (top..=bottom).contains(&r) && (left..=right).contains(&c)
+        })
+    }
+
+    /// Cells covered by `scope`, row by row. Cells past the end of a short
+    /// row are included for the cell, column and selection scopes.
+    pub fn cells_in(&self, scope: replace::Scope) -> Result<Vec<(usize, usize)>> {
+        use replace::Scope;
+        Ok(match scope {
+            Scope::Cell => vec![(self.row, self.col)],
+            Scope::Column => (0..self.data.len()).map(|r| (r, self.col)).collect(),
+            Scope::Selection => {
+                let (top, left, bottom, right) = self
+                    .selection()
+                    .ok_or_else(|| anyhow::anyhow!("no selection; press v to start one"))?;
+                (top..=bottom)
+                    .flat_map(|r| (left..=right).map(move |c| (r, c)))
+                    .collect()
+            }
+            Scope::Tagged if self.tags.is_empty() => {
+                return Err(anyhow!("no tagged rows; t tags the current row"));
+            }
+            Scope::Tagged => {
+                let cols = self.max_cols();
+                self.tags
+                    .rows()
+                    .flat_map(|r| (0..cols).map(move |c| (r, c)))
+                    .collect()
+            }
+            Scope::File => self
+                .data
+                .iter()
+                .enumerate()
+                .flat_map(|(r, row)| (0..row.len()).map(move |c| (r, c)))
+                .collect(),
+        })
+    }
+
+    /// Cells a `/` search in `area` visits, in row-major order.
+    pub fn search_cells(&self, area: Area) -> Vec<(usize, usize)> {
+        match area {
+            Area::File => self.cells_in(replace::Scope::File).unwrap_or_default(),
+            Area::Column(c) => (0..self.data.len()).map(|r| (r, c)).collect(),
+            Area::Block(top, left, bottom, right) => (top..=bottom)
+                .flat_map(|r| (left..=right).map(move |c| (r, c)))
+                .collect(),
+        }
+    }
+
+    /// Every cell up to the widest row, including the gaps of short rows.
+    pub fn grid_area(&self) -> Area {
+        let bottom = self.data.len().saturating_sub(1);
+        Area::Block(0, 0, bottom, self.max_cols().saturating_sub(1))
+    }
+
+    /// The next (or previous) blank cell after the cursor, in the current
+    /// column or, with `whole_file`, anywhere up to the widest row.
+    pub fn find_empty(&self, forward: bool, whole_file: bool) -> Option<((usize, usize), bool)> {
+        let area = if whole_file {
+            self.grid_area()
+        } else {
+            Area::Column(self.col)
+        };
+        let cells = self.search_cells(area);
+        let blank = |t: &str| t.trim().is_empty();
+        search::next_cell(
+            &self.data,
+            &cells,
+            blank,
+            (self.row, self.col),
+            forward,
+            false,
+        )
+    }
+
+    /// The area a new search starts with: the selection if there is one.
+    fn default_search_area(&self) -> Area {
+        match self.selection() {
+            Some((top, left, bottom, right)) => Area::Block(top, left, bottom, right),
+            None => Area::File,
+        }
+    }
+
+    /// Tab in the search prompt: file → column → selection (if any) → file.
+    fn next_search_area(&self, area: Area, origin: (usize, usize)) -> Area {
+        match (area, self.selection()) {
+            (Area::File, _) => Area::Column(origin.1),
+            (Area::Column(_), Some((top, left, bottom, right))) => {
+                Area::Block(top, left, bottom, right)
+            }
+            _ => Area::File,
+        }
+    }
+
+    /// Re-run the search being typed from where it started, moving the
+    /// cursor to the nearest match at or after that position.
+    fn update_live_search(&mut self) {
+        let Some(Prompt {
+            kind: PromptKind::Search { origin, area },
+            buf,
+            ..
+        }) = &self.prompt
+        else {
+            return;
+        };
+        let (origin, area) = (*origin, *area);
+        (self.row, self.col) = origin;
+        // An unfinished pattern like `(ab` simply has no matches yet.
+        self.live_search = Search::new(buf, area).ok().filter(|_| !buf.is_empty());
+        if let Some(s) = &self.live_search
+            && let Some((pos, _)) =
+                search::next_match(&self.data, &self.search_cells(area), s, origin, true, true)
+        {
+            (self.row, self.col) = pos;
+        }
+    }
+
+    /// Hand the cell, row or file to `$EDITOR` and apply what comes back as
+    /// one undoable step. The caller suspends the TUI around this.
+    pub fn edit_externally(&mut self, target: external::Target) -> Result<()> {
+        let (first, old) = match target {
+            external::Target::Cell => {
+                let value = self
+                    .data
+                    .get(self.row)
+                    .and_then(|r| r.get(self.col))
+                    .cloned()
+                    .unwrap_or_default();
+                let edited = external::edit_text(&value)?;
+                if edited != value {
+                    self.set_cell(self.row, self.col, edited);
+                    self.message = Some("Cell updated from the editor".to_string());
+                } else {
+                    self.message = Some("No changes from the editor".to_string());
+                }
+                return Ok(());
+            }
+            external::Target::Row => (self.row, self.data.get(self.row..=self.row).unwrap_or(&[])),
+            external::Target::File => (0, &self.data[..]),
+        };
+        let ext = self
+            .file_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("csv");
+        let path = external::temp_path(ext)?;
+        let result = save_csv(&path, old)
+            .and_then(|()| external::run_editor(&path))
+            .and_then(|()| load_csv(&path));
+        let _ = std::fs::remove_file(&path);
+        let new = result?;
+        // An emptied buffer is more likely a slip than a request to delete.
+        if new.is_empty() {
+            self.message = Some("The editor returned nothing; no changes made".to_string());
+            return Ok(());
+        }
+        let edits = external::diff(old, &new, first);
+        let n = edits.len();
+        self.record_all(edits);
+        self.clamp_col();
+        self.message = Some(if n == 0 {
+            "No changes from the editor".to_string()
+        } else {
+            format!("Applied {n} change(s) from the editor")
+        });
+        Ok(())
+    }
+
+    /// Download `remote` and edit the local copy. Saving uploads it back to
+    /// S3; an HTTP source asks for a local path instead.
+    pub fn open_remote(&mut self, remote: Remote) -> Result<()> {
+        let local = remote.local_path()?;
+        remote.download(&local)?;
+        let url = remote.url().to_string();
+        self.remote = Some(remote);
+        self.open(local)?;
+        if self.message.is_none() {
+            self.message = Some(format!("Downloaded {url}"));
+        }
+        Ok(())
+    }
+
+    /// Whether `w` has to ask where to save: a new buffer, or one read over
+    /// HTTP that can't be written back.
+    pub fn needs_save_path(&self) -> bool {
+        !self.has_path() || matches!(self.remote, Some(Remote::Http(_)))
+    }
+
+    /// Load a fixed-width file as a new, unsaved CSV next to it, so `w`
+    /// writes `<name>.csv` and never touches the original.
+    pub fn import_fwf(&mut self, path: &Path, spec: &fwf::Spec) -> Result<()> {
+        let data = fwf::load(path, spec)?;
+        if data.is_empty() {
+            return Err(anyhow!("no columns found in {}", path.display()));
+        }
+        let csv = path.with_extension("csv");
+        if csv.exists() {
+            return Err(anyhow!(
+                "{} already exists; not importing over it",
+                csv.display()
+            ));
+        }
+        self.open(csv.clone())?;
+        self.prompt = None;
+        self.data = data;
+        self.data_gen += 1;
+        self.header = self
+            .config
+            .header
+            .unwrap_or(header::looks_like_header(&self.data, self.config.decimal));
+        self.dirty = true;
+        self.message = Some(format!(
+            "Imported {} row(s) from {}; w writes {}",
+            self.data.len(),
+            path.display(),
+            csv.display()
+        ));
+        Ok(())
+    }
+
+    /// The header row's cells; empty when there is no data.
+    pub fn headers(&self) -> &[String] {
+        self.data.first().map_or(&[], Vec::as_slice)
+    }
+
+    /// Give column `col` a new header name, refusing blank or duplicate names.
+    pub fn rename_column(&mut self, col: usize, name: &str) -> Result<()> {
+        if !self.header {
+            return Err(anyhow!("header mode is off; :header turns it on"));
+        }
+        if col >= self.max_cols() {
+            return Err(anyhow!("no column {}", col + 1));
+        }
+        header::check_name(self.headers(), col, name)?;
+        let name = name.trim().to_string();
+        self.message = Some(format!("Renamed column {} to {name:?}", col + 1));
+        self.set_cell(0, col, name);
+        Ok(())
+    }
+
+    /// The cell after (or before) the cursor in screen order: along the row
+    /// and on to the next one for `Right`, or straight down.
+    fn neighbour(&self, advance: Advance, forward: bool) -> Option<(usize, usize)> {
+        let (pos, col_pos) = (self.view_pos(), self.col_pos());
+        let last_col = self.column_order().len().saturating_sub(1);
+        let below = (pos + 1 < self.visible_rows()).then(|| self.row_at(pos + 1));
+        let above = pos.checked_sub(1).map(|p| self.row_at(p));
+        match (advance, forward) {
+            (Advance::Off, _) => None,
+            (Advance::Right, true) if col_pos < last_col => {
+                Some((self.row, self.col_at(col_pos + 1)))
+            }
+            (Advance::Right, true) => below.map(|r| (r, self.col_at(0))),
+            (Advance::Right, false) if col_pos > 0 => Some((self.row, self.col_at(col_pos - 1))),
+            (Advance::Right, false) => above.map(|r| (r, self.col_at(last_col))),
+            (Advance::Down, true) => below.map(|r| (r, self.col)),
+            (Advance::Down, false) => above.map(|r| (r, self.col)),
+        }
+    }
+
+    /// After a commit, start editing the neighbouring cell, for data entry
+    /// and Tab; stops at either end of the table.
+    fn edit_next(&mut self, advance: Advance, forward: bool) {
+        let Some((row, col)) = self.neighbour(advance, forward) else {
+            let end = if forward { "End" } else { "Start" };
+            self.message = Some(format!("{end} of the table; editing stopped here"));
+            return;
+        };
+        (self.row, self.col) = (row, col);
+        self.ensure_cell_exists(row, col);
+        self.editor_buf = self.data[row][col].clone();
+        self.editing = true;
+    }
+
+    pub fn clamp_col(&mut self) {
+        let len = self.data.get(self.row).map_or(0, Vec::len);
+        self.col = self.col.min(len.saturating_sub(1));
+    }
+
+    /// Replace the loaded rows with what the `post_load` command prints for
+    /// them; on failure they stay as read.
+    fn run_post_load(&mut self, command: &str) -> Result<()> {
+        let delimiter = csv_io::delimiter_for(&self.file_path);
+        let input = csv_io::to_bytes(&self.data, delimiter)?;
+        let out = external::pipe(external::shell(command), command, &input)?;
+        self.data = csv_io::from_bytes(&out, delimiter)?;
+        self.data_gen += 1;
+        Ok(())
+    }
+
+    /// Pipe the rows `first..end` (all of them for `None`) through `cmd` as
+    /// CSV and replace them with what it prints, as one undo step. `name`
+    /// is what ran, for messages. Returns the number of changes.
+    pub fn pipe_rows(
+        &mut self,
+        cmd: std::process::Command,
+        name: &str,
+        rows: Option<(usize, usize)>,
+    ) -> Result<usize> {
+        let (first, end) = rows.unwrap_or((0, self.data.len()));
+        let delimiter = csv_io::delimiter_for(&self.file_path);
+        let old = &self.data[first..end];
+        let input = csv_io::to_bytes(old, delimiter)?;
+        let out = external::pipe(cmd, name, &input)?;
+        let new = csv_io::from_bytes(&out, delimiter)?;
+        // Most likely a command that failed quietly, not a wish to delete.
+        if new.is_empty() && !old.is_empty() {
+            bail!("{name} printed nothing; no changes made");
+        }
+        let edits = external::diff(old, &new, first);
+        let n = edits.len();
+        self.record_all(edits);
+        self.clamp_col();
+        Ok(n)
+    }
+
+    /// Run the script `name` from the scripts folder on the selected rows,
+    /// or all of them, like `:pipe`. It gets `args` split on spaces and
+    /// learns where it is from `CSV_*` environment variables.
+    pub fn run_script(&mut self, name: &str, args: &str) -> Result<usize> {
+        let dir = self
+            .scripts
+            .clone()
+            .ok_or_else(|| anyhow!("no config directory to find scripts in"))?;
+        let path = external::script_path(&dir, name)?;
+        let selection = self.selection();
+        let rows = selection.map(|(top, _, bottom, _)| (top, bottom + 1));
+        let mut cmd = std::process::Command::new(&path);
+        cmd.args(args.split_whitespace())
+            .env("CSV_FILE", &self.file_path)
+            .env("CSV_ROW", (self.row + 1).to_string())
+            .env("CSV_COL", (self.col + 1).to_string())
+            .env("CSV_HEADER", if self.header { "1" } else { "0" })
+            .env("CSV_FIRST_ROW", (rows.map_or(0, |r| r.0) + 1).to_string())
+            .env(
+                "CSV_SELECTION",
+                selection.map_or(String::new(), |(t, l, b, r)| {
+                    names::range_text(&((t, l), (b, r)))
+                }),
+            );
+        self.pipe_rows(cmd, name, rows)
+    }
+
+    /// Write the file. A `pre_save` hook runs first; when it fails nothing
+    /// is written, the buffer stays dirty and the message says why.
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(cmd) = self.config.pre_save.clone()
+            && let Err(e) = self.pipe_rows(external::shell(&cmd), &cmd, None)
+        {
+            log::error("pre_save", &e);
+            self.message = Some(format!("pre_save hook failed; not saved: {e:#}"));
+            return Ok(());
+        }
+        let start = Instant::now();
+        self.write_file()
+            .inspect(|()| {
+                log::event(
+                    "save",
+                    &[
+                        ("path", &self.file_path.display()),
+                        ("rows", &self.data.len()),
+                        ("ms", &start.elapsed().as_millis()),
+                    ],
+                );
+            })
+            .inspect_err(|e| log::error("save", e))
+    }
+
+    fn write_file(&mut self) -> Result<()> {
+        self.normalize_nulls();
+        self.normalize_decimals();
+        // A plain file keeps its untouched rows as they were on disk.
+        let spans = if Codec::for_path(&self.file_path).is_none() {
+            let reuse = self.unchanged_rows();
+            Some(csv_io::save_reusing(
+                &self.file_path,
+                &self.data,
+                &reuse,
+                self.spans.as_ref(),
+            )?)
+        } else {
+            save_csv(&self.file_path, &self.data)?;
+            None
+        };
+        self.mark_saved();
+        self.spans = spans;
+        if let Some(remote) = &self.remote {
+            if let Err(e) = remote.upload(&self.file_path) {
+                // The local copy is written but the object isn't; staying
+                // dirty keeps :q from dropping the edits.
+                log::error("upload", &e);
+                self.message = Some(format!("Saved locally, but the upload failed: {e:#}"));
+                return Ok(());
+            }
+            self.message = Some(format!("Uploaded to {}", remote.url()));
+        }
+        journal::clear(&self.journal_path())?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// With `null_as` set, rewrite every null cell below the header that way,
+    /// as one undo step, so the file gets a single spelling of "no value".
+    fn normalize_nulls(&mut self) {
+        let Some(repr) = self.config.null_as.clone() else {
+            return;
+        };
+        let nulls = &self.config.nulls;
+        let edits: Vec<journal::Edit> = self
+            .data
+            .iter()
+            .enumerate()
+            .skip(usize::from(self.header))
+            .flat_map(|(row, cells)| {
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| nulls.is_null(c) && **c != repr)
+                    .map(move |(col, _)| (row, col))
+            })
+            .map(|(row, col)| journal::Edit::SetCell {
+                row,
+                col,
+                value: repr.clone(),
+            })
+            .collect();
+        if !edits.is_empty() {
+            self.message = Some(format!("Wrote {} null cell(s) as \"{repr}\"", edits.len()));
+        }
+        self.record_all(edits);
+    }
+
+    /// With `normalize_decimal` and comma decimals, rewrite every number
+    /// below the header as `1234.56`, as one undo step.
+    fn normalize_decimals(&mut self) {
+        let decimal = self.config.decimal;
+        if !self.config.normalize_decimal || decimal == viz::Decimal::Dot {
+            return;
+        }
+        let edits: Vec<journal::Edit> = self
+            .data
+            .iter()
+            .enumerate()
+            .skip(usize::from(self.header))
+            .flat_map(|(row, cells)| {
+                cells.iter().enumerate().filter_map(move |(col, c)| {
+                    decimal
+                        .normalize(c)
+                        .filter(|plain| plain != c)
+                        .map(|value| journal::Edit::SetCell { row, col, value })
+                })
+            })
+            .collect();
+        if !edits.is_empty() {
+            self.message = Some(format!(
+                "Wrote {} number(s) with a dot decimal",
+                edits.len()
+            ));
+        }
+        self.record_all(edits);
+    }
+
+    /// Whether moves past the edge should grow the grid.
+    fn extends(&self) -> bool {
+        self.config.auto_extend && !self.read_only
+    }
+
+    /// Insert `count` rows at `at` as one undo step, each filled with the
+    /// schema's `default` values, and put the cursor on the first.
+    fn insert_rows(&mut self, at: usize, count: usize) -> Result<()> {
+        let schema = Schema::load_for(&self.file_path)?.unwrap_or_default();
+        let today = picker::format_time(SystemTime::now());
+        let rows = schema.new_rows(
+            &self.data,
+            self.header,
+            self.max_cols(),
+            &today[..10],
+            count,
+        )?;
+        let edits = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, cells)| journal::Edit::InsertRow { row: at + i, cells })
+            .collect();
+        self.record_all(edits);
+        self.row = at.min(self.data.len().saturating_sub(1));
+        self.clamp_col();
+        self.message = Some(if schema.defaults.is_empty() {
+            format!("Inserted {count} row(s)")
+        } else {
+            format!("Inserted {count} row(s) with the schema's defaults")
+        });
+        Ok(())
+    }
+
+    /// First save of an unnamed buffer: adopt the path, lock it, then write.
+    pub fn save_as(&mut self, path: PathBuf) -> Result<()> {
+        self.file_path = path;
+        // From here on this is an ordinary local file.
+        self.remote = None;
+        self.lock = None;
+        self.read_only = false;
+        self.write_protected = false;
+        self.acquire_lock()?;
+        if self.read_only {
+            return Ok(());
+        }
+        self.save()?;
+        if self.dirty {
+            return Ok(());
+        }
+        self.message = Some(format!("Saved {}", self.file_path.display()));
+        self.remember_recent();
+        Ok(())
+    }
+
+    pub fn replay_journal(&mut self) {
+        for e in std::mem::take(&mut self.pending_replay) {
+            self.apply(e);
+        }
+        self.clamp_col();
+    }
+}
+
+pub fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
+    // Returns Ok(true) to request exit
+    let before = (app.row, app.col);
+    let quit = dispatch_key(app, key)?;
+    if let Some(mut t) = app.tutorial.take()
+        && t.update(app)
+    {
+        app.tutorial = Some(t);
+    }
+    if app.config.screen_reader && (app.row, app.col) != before && app.message.is_none() {
+        app.message = Some(app.describe_cursor());
+    }
+    Ok(quit)
+}
+
+/// Text pasted into the terminal (bracketed paste), or committed by an input
+/// method in one piece: it goes into the cell being edited, line breaks and
+/// all, or onto the prompt's line as if typed.
+pub fn handle_paste(app: &mut App, text: &str) {
+    if app.editing {
+        app.completion = None;
+        app.editor_buf.push_str(&text.replace("\r\n", "\n"));
+    } else if let Some(p) = app.prompt.as_mut() {
+        p.recall = None;
+        p.reverse = None;
+        p.buf.push_str(&typing::one_line(text));
+        app.update_live_search();
+    } else if app.read_only {
+        app.message = Some("File is open read-only; paste not applied".to_string());
+    } else {
+        match clipboard::parse_text(text) {
+            Ok(block) if !block.is_empty() => app.pending_block = Some(block),
+            Ok(_) => {}
+            Err(e) => app.message = Some(format!("Pasted text is not CSV: {e}")),
+        }
+    }
+}
+
+fn dispatch_key(app: &mut App, key: KeyEvent) -> Result<bool> {
+    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.suspend_requested = true;
+        return Ok(false);
+    }
+
+    if app.editing {
+        let ctrl = key.modifiers == KeyModifiers::CONTROL;
+        if !(ctrl && matches!(key.code, KeyCode::Char('n' | 'p'))) {
+            app.completion = None;
+        }
+        match key.code {
+            KeyCode::Char(c @ ('n' | 'p')) if ctrl => {
+                let forward = c == 'n';
+                match &mut app.completion {
+                    Some(c) => c.step(forward),
+                    None => {
+                        let found =
+                            complete::candidates(&app.data, app.row, app.col, &app.editor_buf);
+                        if found.is_empty() {
+                            return Ok(false);
+                        }
+                        let mut c = complete::Completion::new(app.editor_buf.clone(), found);
+                        if !forward {
+                            c.step(false);
+                            c.step(false);
+                        }
+                        app.completion = Some(c);
+                    }
+                }
+                if let Some(c) = &app.completion {
+                    app.editor_buf = c.current().to_string();
+                }
+            }
+            KeyCode::Enter => {
+                let value = std::mem::take(&mut app.editor_buf);
+                app.last_change = Some(Change::SetCell(value.clone()));
+                app.set_cell(app.row, app.col, value);
+                app.editing = false;
+                if app.config.entry != Advance::Off {
+                    app.edit_next(app.config.entry, true);
+                }
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                let value = std::mem::take(&mut app.editor_buf);
+                app.last_change = Some(Change::SetCell(value.clone()));
+                app.set_cell(app.row, app.col, value);
+                app.editing = false;
+                app.edit_next(Advance::Right, key.code == KeyCode::Tab);
+            }
+            KeyCode::Esc => {
+                app.editor_buf.clear();
+                app.editing = false;
+            }
+            KeyCode::Backspace => {
+                typing::pop_grapheme(&mut app.editor_buf);
+            }
+            KeyCode::Char(c) if typing::is_typed(&key) => {
+                app.editor_buf.push(c);
+            }
+            KeyCode::Left => {}
+            KeyCode::Right => {}
+            KeyCode::Up => {}
+            KeyCode::Down => {}
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if !app.pending_replay.is_empty() {
+        // The journal is the only copy of those edits, so nothing but an
+        // explicit answer disposes of it.
+        match key.code {
+            KeyCode::Char('y') => {
+                app.replay_journal();
+                app.message = Some("Replayed journaled edits".to_string());
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                app.pending_replay.clear();
+                journal::clear(&app.journal_path())?;
+                app.message = Some("Discarded journaled edits".to_string());
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(block) = app.pending_block.take() {
+        if key.code == KeyCode::Char('y') {
+            app.paste_block(&block);
+        } else {
+            app.message = Some("Paste cancelled".to_string());
+        }
+        return Ok(false);
+    }
+
+    if let Some((opts, _)) = app.pending_paste.take() {
+        if key.code == KeyCode::Char('y') {
+            app.paste_now(opts);
+        } else {
+            app.message = Some("Paste cancelled".to_string());
+        }
+        return Ok(false);
+    }
+
+    if let Some(p) = app.prompt.as_mut() {
+        if p.recall_key(&app.prompt_history, key) {
+            app.update_live_search();
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Enter => {
+                let Some(p) = app.prompt.take() else {
+                    return Ok(false);
+                };
+                return submit_prompt(app, p);
+            }
+            KeyCode::Esc => {
+                // A new grid still needs a size; cancelling means 1x1.
+                match app.prompt.take().map(|p| p.kind) {
+                    Some(PromptKind::NewDimensions) => app.reset_grid(1, 1),
+                    Some(PromptKind::Search { origin, .. }) => {
+                        (app.row, app.col) = origin;
+                        app.live_search = None;
+                    }
+                    _ => {}
+                }
+            }
+            KeyCode::Tab => {
+                if let PromptKind::Search { origin, area } = p.kind {
+                    let area = app.next_search_area(area, origin);
+                    if let Some(p) = app.prompt.as_mut() {
+                        p.kind = PromptKind::Search { origin, area };
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                typing::pop_grapheme(&mut p.buf);
+            }
+            KeyCode::Char(c) if typing::is_typed(&key) => {
+                p.buf.push(c);
+            }
+            _ => {}
+        }
+        app.update_live_search();
+        return Ok(false);
+    }
+
+    if app.chart.take().is_some() {
+        return Ok(false);
+    }
+
+    if app.record_card {
+        let pos = app.col_pos();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => app.col = app.col_at(pos.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => app.col = app.col_at(pos + 1),
+            KeyCode::Home | KeyCode::Char('g') => app.col = app.col_at(0),
+            KeyCode::End | KeyCode::Char('G') => app.col = app.col_at(usize::MAX),
+            KeyCode::Char('K') => app.row = app.row_at(app.view_pos().saturating_sub(1)),
+            KeyCode::Char('J') => app.row = app.row_at(app.view_pos() + 1),
+            KeyCode::Enter | KeyCode::Char('e') if app.read_only => {
+                app.message = Some("File is open read-only".to_string());
+            }
+            KeyCode::Enter | KeyCode::Char('e') => {
+                app.ensure_cell_exists(app.row, app.col);
+                app.editor_buf = app.data[app.row][app.col].clone();
+                app.editing = true;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => app.record_card = false,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(d) = app.export_dialog.as_mut() {
+        let on_dest = d.current() == export::Field::Destination;
+        match key.code {
+            KeyCode::Esc => app.export_dialog = None,
+            KeyCode::Enter => {
+                let (settings, dest) = (d.settings, PathBuf::from(&d.dest));
+                match app.export_to(settings, &dest) {
+                    Ok(()) => app.export_dialog = None,
+                    Err(e) => app.message = Some(format!("{e:#}")),
+                }
+            }
+            KeyCode::Up | KeyCode::BackTab => d.step(false),
+            KeyCode::Down | KeyCode::Tab => d.step(true),
+            KeyCode::Char('k') if !on_dest => d.step(false),
+            KeyCode::Char('j') if !on_dest => d.step(true),
+            KeyCode::Left | KeyCode::Char('h') if !on_dest => d.change(false, &app.file_path),
+            KeyCode::Right | KeyCode::Char('l' | ' ') if !on_dest => d.change(true, &app.file_path),
+            KeyCode::Backspace if on_dest => {
+                typing::pop_grapheme(&mut d.dest);
+                d.dest_typed = true;
+            }
+            KeyCode::Char(c) if on_dest && typing::is_typed(&key) => {
+                d.dest.push(c);
+                d.dest_typed = true;
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(cmp) = app.row_compare.as_mut() {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => cmp.scroll = cmp.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => cmp.scroll += 1,
+            KeyCode::Home | KeyCode::Char('g') => cmp.scroll = 0,
+            KeyCode::Char('d') => {
+                cmp.only_diff = !cmp.only_diff;
+                cmp.scroll = 0;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => app.row_compare = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(scroll) = app.cell_view.as_mut() {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *scroll += 1,
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(20),
+            KeyCode::PageDown => *scroll += 20,
+            KeyCode::Home | KeyCode::Char('g') => *scroll = 0,
+            _ => app.cell_view = None,
+        }
+        return Ok(false);
+    }
+
+    if app.compare {
+        app.compare = false;
+        if key.code == KeyCode::Char('r')
+            && let Some(orig) = app.original_value(app.row, app.col).map(str::to_string)
+        {
+            app.set_cell(app.row, app.col, orig);
+            app.message = Some("Reverted cell to its on-disk value".to_string());
+        }
+        return Ok(false);
+    }
+
+    if let Some(panel) = app.findings.as_mut() {
+        match key.code {
+            KeyCode::Esc => {
+                app.findings = None;
+                return Ok(false);
+            }
+            KeyCode::Tab => {
+                panel.focused = !panel.focused;
+                return Ok(false);
+            }
+            KeyCode::Up if panel.focused => {
+                panel.select_prev();
+                return Ok(false);
+            }
+            KeyCode::Down if panel.focused => {
+                panel.select_next();
+                return Ok(false);
+            }
+            // Not as the end of a chord like ]f.
+            KeyCode::Char('f') if panel.focused && !app.input.is_pending() => {
+                panel.cycle_filter();
+                return Ok(false);
+            }
+            KeyCode::Enter if panel.focused => {
+                // Rows may have been deleted since the check ran.
+                if let Some(f) = panel.current() {
+                    app.row = f.row.min(app.data.len().saturating_sub(1));
+                    app.col = f.col;
+                    app.clamp_col();
+                }
+                return Ok(false);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(picker) = app.recent.as_mut() {
+        if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+            app.recent = None;
+        } else if let Some(picker::Pick::File(path)) = picker.handle_key(key)? {
+            app.recent = None;
+            // Loading fails before the buffer is touched, so it stays open.
+            if let Err(e) = app.open(path) {
+                app.message = Some(format!("Could not open: {e:#}"));
+            }
+        }
+        return Ok(false);
+    }
+
+    if let Some(view) = app.help.as_mut() {
+        match key.code {
+            KeyCode::Esc => app.help = None,
+            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll += 1,
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+            KeyCode::PageDown => view.scroll += 10,
+            KeyCode::Backspace => {
+                typing::pop_grapheme(&mut view.query);
+                view.scroll = 0;
+            }
+            KeyCode::Char(c) if typing::is_typed(&key) => {
+                view.query.push(c);
+                view.scroll = 0;
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(view) = app.fuzzy.as_mut() {
+        match key.code {
+            KeyCode::Esc => app.fuzzy = None,
+            KeyCode::Enter => {
+                if let Some(hit) = view.current() {
+                    (app.row, app.col) = (hit.row, hit.col);
+                }
+                app.fuzzy = None;
+            }
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => {
+                view.selected = (view.selected + 1).min(view.hits.len().saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                typing::pop_grapheme(&mut view.query);
+                view.refresh(&app.data);
+            }
+            KeyCode::Char(c) if typing::is_typed(&key) => {
+                view.query.push(c);
+                view.refresh(&app.data);
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(review) = app.review.as_mut() {
+        match key.code {
+            KeyCode::Char('y') => review.decide(true),
+            KeyCode::Char('n') => review.decide(false),
+            KeyCode::Char('a') => review.accept_rest(),
+            KeyCode::Char('q') | KeyCode::Esc => review.index = review.changes.len(),
+            _ => {}
+        }
+        if review.is_done() {
+            app.finish_review();
+        } else if let Some(c) = review.current() {
+            (app.row, app.col) = (c.row, c.col);
+        }
+        return Ok(false);
+    }
+
+    if let Some(panel) = app.filter_panel.as_mut() {
+        let names: Vec<String> = app
+            .saved_filters
+            .names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let selected = names.get(panel.cursor).cloned();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => panel.step(false, names.len()),
+            KeyCode::Down | KeyCode::Char('j') => panel.step(true, names.len()),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some(name) = selected
+                    && let Err(e) = app.toggle_filter(&name)
+                {
+                    app.message = Some(format!("{e:#}"));
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(name) = selected {
+                    if let Err(e) = app.saved_filter(presets::Op::Delete(name)) {
+                        app.message = Some(format!("{e:#}"));
+                    }
+                    let left = app.saved_filters.names().len();
+                    match app.filter_panel.as_mut() {
+                        Some(_) if left == 0 => app.filter_panel = None,
+                        Some(p) => p.cursor = p.cursor.min(left - 1),
+                        None => {}
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => app.filter_panel = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(m) = app.column_manager.as_mut() {
+        if let Some(buf) = m.renaming.as_mut() {
+            match key.code {
+                KeyCode::Char(c) if typing::is_typed(&key) => buf.push(c),
+                KeyCode::Backspace => {
+                    typing::pop_grapheme(buf);
+                }
+                KeyCode::Enter => {
+                    let name = std::mem::take(buf);
+                    m.renaming = None;
+                    if let Some(e) = m.selected() {
+                        e.name = name;
+                    }
+                }
+                KeyCode::Esc => m.renaming = None,
+                _ => {}
+            }
+            return Ok(false);
+        }
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Up if shift => m.shift(false),
+            KeyCode::Down if shift => m.shift(true),
+            KeyCode::Char('K') => m.shift(false),
+            KeyCode::Char('J') => m.shift(true),
+            KeyCode::Up | KeyCode::Char('k') => m.step(false),
+            KeyCode::Down | KeyCode::Char('j') => m.step(true),
+            KeyCode::Char(' ') => {
+                if let Some(e) = m.selected() {
+                    e.hidden = !e.hidden;
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(e) = m.selected() {
+                    e.pinned = !e.pinned;
+                }
+            }
+            KeyCode::Char('r') if !app.header => {
+                app.message = Some("Header mode is off; :header turns it on to rename".to_string());
+            }
+            KeyCode::Char('r') => {
+                let name = m.selected().map(|e| e.name.clone()).unwrap_or_default();
+                m.renaming = Some(name);
+            }
+            KeyCode::Enter => {
+                let m = m.clone();
+                match app.apply_column_manager(&m) {
+                    Ok(()) => app.column_manager = None,
+                    Err(e) => app.message = Some(format!("{e:#}")),
+                }
+            }
+            KeyCode::Esc => app.column_manager = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if let Some(opts) = app.paste_special.as_mut() {
+        match key.code {
+            KeyCode::Char('t') => opts.transpose = !opts.transpose,
+            KeyCode::Char('b') => opts.skip_blanks = !opts.skip_blanks,
+            KeyCode::Char('v') => opts.values_only = !opts.values_only,
+            KeyCode::Enter => {
+                let opts = *opts;
+                app.paste_special = None;
+                app.last_change = Some(Change::PasteSpecial(opts));
+                app.paste(opts);
+            }
+            KeyCode::Esc => app.paste_special = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    if key.code == KeyCode::Esc && !app.input.is_pending() && app.anchor.take().is_some() {
+        return Ok(false);
+    }
+
+    app.message = None;
+    match app.input.feed(&app.keymap, key) {
+        input::Resolved::Action { action, count } => apply_action(app, action, count),
+        input::Resolved::Pending | input::Resolved::Unbound => Ok(false),
+    }
+}
+
+fn submit_prompt(app: &mut App, p: Prompt) -> Result<bool> {
+    if let Some(kind) = p.history_kind()
+        && let Err(e) = app.prompt_history.add(kind, &p.buf)
+    {
+        log::error("history", &e);
+    }
+    match p.kind {
+        PromptKind::NewDimensions => match prompt::parse_dimensions(&p.buf) {
+            Ok((rows, cols)) => app.reset_grid(rows, cols),
+            Err(e) => {
+                app.message = Some(format!("{e}"));
+                app.prompt = Some(p);
+            }
+        },
+        PromptKind::Command => match command::parse(&p.buf) {
+            Ok(cmd) => {
+                // Only the name: arguments can quote cell values.
+                let name: String = p
+                    .buf
+                    .trim_start()
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '!')
+                    .collect();
+                log::event("command", &[("name", &name)]);
+                if matches!(
+                    cmd,
+                    Command::Substitute(_) | Command::Fill(_) | Command::StripCurrency
+                ) && !app.read_only
+                {
+                    app.last_change = Some(Change::Command(cmd.clone()));
+                }
+                return run_command(app, cmd);
+            }
+            Err(e) => {
+                log::error("command", &e);
+                app.message = Some(format!("{e}"));
+            }
+        },
+        PromptKind::Search { origin, area } => match app.live_search.take() {
+            Some(s) => {
+                let here = app
+                    .data
+                    .get(app.row)
+                    .and_then(|r| r.get(app.col))
+                    .map_or("", String::as_str);
+                if (app.row, app.col) == origin && !s.matches(here) {
+                    app.message = Some(format!("Pattern not found: {}", s.query));
+                }
+                app.last_search = Some(s);
+            }
+            None if p.buf.is_empty() => {}
+            None => {
+                if let Err(e) = Search::new(&p.buf, area) {
+                    app.message = Some(format!("{e:#}"));
+                }
+            }
+        },
+        PromptKind::Rename { col } => {
+            if let Err(e) = app.rename_column(col, &p.buf) {
+                app.message = Some(format!("{e:#}"));
+                app.prompt = Some(p);
+            }
+        }
+        PromptKind::SavePath { then_quit } => {
+            let path = p.buf.trim();
+            if path.is_empty() {
+                app.message = Some("No file name given; not saved".to_string());
+                return Ok(false);
+            }
+            match app.save_as(PathBuf::from(path)) {
+                Err(e) if permission_denied(&e) => {
+                    app.message = Some(format!("{e:#}; try another path"));
+                    app.prompt = Some(p);
+                    return Ok(false);
+                }
+                Err(e) => {
+                    app.message = Some(format!("Could not save: {e:#}"));
+                    return Ok(false);
+                }
+                Ok(()) => {}
+            }
+            return Ok(then_quit && !app.dirty);
+        }
+    }
+    Ok(false)
+}
+
+pub fn run_command(app: &mut App, cmd: Command) -> Result<bool> {
+    use input::Action;
+    match cmd {
+        Command::Write => apply_action(app, Action::Write, None),
+        Command::Quit => apply_action(app, Action::Quit, None),
+        Command::ForceQuit => Ok(true),
+        Command::Reload(_) if !app.has_path() || app.remote.is_some() => {
+            app.message = Some("Nothing on disk to reload".to_string());
+            Ok(false)
+        }
+        Command::Reload(false) if app.dirty => {
+            app.message = Some("Unsaved changes; :reload! discards them".to_string());
+            Ok(false)
+        }
+        Command::Reload(_) => {
+            let (row, col) = (app.row, app.col);
+            if let Err(e) = app.open(app.file_path.clone()) {
+                app.message = Some(format!("Could not reload; kept the buffer: {e:#}"));
+                return Ok(false);
+            }
+            app.row = row.min(app.data.len().saturating_sub(1));
+            app.col = col;
+            app.clamp_col();
+            // A session that fell back to read-only doesn't own the journal.
+            if app.lock.is_none() {
+                return Ok(false);
+            }
+            app.pending_replay.clear();
+            app.message = Some(match journal::clear(&app.journal_path()) {
+                Ok(()) => format!("Reloaded {}", app.file_path.display()),
+                Err(e) => format!("Reloaded, but could not clear the journal: {e:#}"),
+            });
+            Ok(false)
+        }
+        Command::WriteQuit => {
+            apply_action(app, Action::Write, None)?;
+            // An unnamed buffer is now prompting for a path; quit after it.
+            if let Some(p) = app.prompt.as_mut() {
+                p.kind = PromptKind::SavePath { then_quit: true };
+                return Ok(false);
+            }
+            Ok(!app.dirty)
+        }
+        Command::Help => apply_action(app, Action::Help, None),
+        Command::Export(export::Format::Html) => apply_action(app, Action::ExportHtml, None),
+        Command::Export(export::Format::Aligned) if !app.has_path() => {
+            app.message = Some("Write the file first to name the export".to_string());
+            Ok(false)
+        }
+        Command::Export(export::Format::Aligned) => {
+            let out = export::aligned_path_for(&app.file_path);
+            app.message = Some(
+                match export::export_aligned(&out, &app.file_path, &app.data) {
+                    Ok(()) => format!("Exported aligned text to {}", out.display()),
+                    Err(e) => format!("Could not export: {e:#}"),
+                },
+            );
+            Ok(false)
+        }
+        Command::Export(_) if !app.has_path() => {
+            app.message = Some("Write the file first to name the export".to_string());
+            Ok(false)
+        }
+        Command::Export(format) => {
+            let settings = export::Settings {
+                format,
+                ..app.export_settings
+            };
+            if let Err(e) = app.export_to(settings, &settings.path_for(&app.file_path)) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::ExportDialog => apply_action(app, Action::ExportDialog, None),
+        Command::Header(on) => {
+            app.header = on.unwrap_or(!app.header);
+            app.refresh_view();
+            app.message = Some(
+                if app.header {
+                    "Header mode on: row 1 names the columns"
+                } else {
+                    "Header mode off"
+                }
+                .to_string(),
+            );
+            Ok(false)
+        }
+        Command::Filter(None) => {
+            app.update_view(|v| v.filter = None);
+            app.message = Some("Filter cleared".to_string());
+            Ok(false)
+        }
+        Command::FilterExpr(text) => {
+            match view::Filter::with_rule(view::Rule::Expr(text), app.headers()) {
+                Ok(f) => {
+                    app.update_view(|v| v.filter = Some(f));
+                    app.message = Some(format!(
+                        "{} row(s) match; :filter shows all",
+                        app.shown_rows()
+                    ));
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Filter(Some((col, query))) => {
+            let filter = header::resolve_column(app.headers(), &col)
+                .and_then(|c| view::Filter::new(c, &query));
+            match filter {
+                Ok(f) => {
+                    app.update_view(|v| v.filter = Some(f));
+                    app.message = Some(format!(
+                        "{} row(s) match; :filter shows all",
+                        app.shown_rows()
+                    ));
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Entry(advance) => {
+            app.config.entry = advance.unwrap_or(match app.config.entry {
+                Advance::Off => Advance::Right,
+                _ => Advance::Off,
+            });
+            app.message = Some(
+                match app.config.entry {
+                    Advance::Off => "Data entry off",
+                    Advance::Right => "Data entry: Enter commits and edits the next cell right",
+                    Advance::Down => "Data entry: Enter commits and edits the cell below",
+                }
+                .to_string(),
+            );
+            Ok(false)
+        }
+        Command::ScreenReader(on) => {
+            app.config.screen_reader = on.unwrap_or(!app.config.screen_reader);
+            app.message = Some(if app.config.screen_reader {
+                format!("Screen-reader mode on. {}", app.describe_cursor())
+            } else {
+                "Screen-reader mode off".to_string()
+            });
+            Ok(false)
+        }
+        Command::Rtl(on) => {
+            let was = app.columns.rtl.contains(app.col);
+            let on = on.unwrap_or(!was);
+            if on != was {
+                app.columns.rtl.toggle(app.col);
+            }
+            app.message = Some(format!(
+                "Column {} is {}",
+                a1::col_name(app.col),
+                if on {
+                    "right-aligned, right to left"
+                } else {
+                    "left-aligned"
+                }
+            ));
+            Ok(false)
+        }
+        Command::Mask(mask) => {
+            app.columns.set_mask(app.col, mask);
+            app.message = Some(match mask {
+                Some(Mask::Full) => format!("Column {} is masked", a1::col_name(app.col)),
+                Some(Mask::Last4) => format!(
+                    "Column {} shows only the last 4 characters",
+                    a1::col_name(app.col)
+                ),
+                None => format!("Column {} is shown unmasked", a1::col_name(app.col)),
+            });
+            Ok(false)
+        }
+        Command::Perf(on) => {
+            app.perf = on
+                .unwrap_or(app.perf.is_none())
+                .then(|| app.perf.unwrap_or_default());
+            Ok(false)
+        }
+        Command::AutoExtend(on) => {
+            app.config.auto_extend = on.unwrap_or(!app.config.auto_extend);
+            app.message = Some(
+                if app.config.auto_extend {
+                    "Moving past the last row or column adds one"
+                } else {
+                    "Moves stop at the last row and column"
+                }
+                .to_string(),
+            );
+            Ok(false)
+        }
+        Command::ViewOnly(on) => {
+            app.config.view_only = on.unwrap_or(!app.config.view_only);
+            app.message = Some(
+                if app.config.view_only {
+                    "View-only mode: :sort and :filter change the display until :commit-view"
+                } else {
+                    "View-only mode off: :sort reorders the data"
+                }
+                .to_string(),
+            );
+            Ok(false)
+        }
+        Command::ResetView => {
+            app.view = None;
+            app.message = Some("Showing all rows in file order".to_string());
+            Ok(false)
+        }
+        Command::CommitView if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::CommitView
+            if app
+                .view
+                .as_ref()
+                .and_then(|v| v.group.as_ref())
+                .is_some_and(|g| !g.collapsed.is_empty()) =>
+        {
+            app.message =
+                Some("Collapsed rows would be deleted; :group expand shows them first".to_string());
+            Ok(false)
+        }
+        Command::CommitView => {
+            let Some(v) = app.view.take() else {
+                app.message = Some("No sort or filter to commit".to_string());
+                return Ok(false);
+            };
+            let hidden = app.data.len() - v.rows.len();
+            let row = v.position(app.row).unwrap_or(0);
+            app.record_all(v.commit_edits(app.data.len()));
+            app.row = row.min(app.data.len().saturating_sub(1));
+            app.clamp_col();
+            app.message = Some(format!(
+                "Applied the view to the data ({hidden} hidden row(s) deleted); u to undo"
+            ));
+            Ok(false)
+        }
+        Command::Tagged(tags::Bulk::Delete | tags::Bulk::Empty) if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Tagged(op) => {
+            app.bulk(op)?;
+            Ok(false)
+        }
+        Command::Fill(_) if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Fill(how) => {
+            let from = usize::from(app.header);
+            match fill::plan(
+                &app.data,
+                app.col,
+                from,
+                &how,
+                &app.config.nulls,
+                app.config.decimal,
+            ) {
+                Ok(cells) => {
+                    app.message = Some(format!(
+                        "Filled {} missing cell(s) in column {}",
+                        cells.len(),
+                        app.col + 1
+                    ));
+                    let col = app.col;
+                    app.record_all(
+                        cells
+                            .into_iter()
+                            .map(|(row, value)| journal::Edit::SetCell { row, col, value })
+                            .collect(),
+                    );
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Anonymize(_) if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Anonymize(how) => {
+            use anonymize::Method;
+            let salt = match &how.method {
+                Method::Hash(Some(salt)) => salt.clone(),
+                Method::Hash(None) => anonymize::fresh_salt(),
+                Method::Sequence(_) => String::new(),
+            };
+            let name = match app.headers().get(app.col) {
+                Some(h) if app.header && !h.trim().is_empty() => h.trim().to_string(),
+                _ => a1::col_name(app.col),
+            };
+            let plan = anonymize::plan(
+                &app.data,
+                app.col,
+                usize::from(app.header),
+                &how.method,
+                &salt,
+                &name,
+                &app.config.nulls,
+            );
+            if plan.cells.is_empty() {
+                app.message = Some(format!(
+                    "Column {} has no values to replace",
+                    a1::col_name(app.col)
+                ));
+                return Ok(false);
+            }
+            // The mapping goes first, so a file that can't be written leaves
+            // the data alone.
+            if let Some(path) = &how.map {
+                if app.is_open_file(path) {
+                    app.message = Some(
+                        "The mapping would overwrite the file being edited; pick another name"
+                            .to_string(),
+                    );
+                    return Ok(false);
+                }
+                let mut rows = vec![vec!["original".to_string(), "replacement".to_string()]];
+                rows.extend(plan.mapping.iter().map(|(a, b)| vec![a.clone(), b.clone()]));
+                if let Err(e) = save_csv(path, &rows) {
+                    app.message = Some(format!("Could not write the mapping: {e:#}"));
+                    return Ok(false);
+                }
+            }
+            let mut text = format!(
+                "Replaced {} cell(s), {} distinct value(s), in column {}",
+                plan.cells.len(),
+                plan.mapping.len(),
+                a1::col_name(app.col)
+            );
+            if let Method::Hash(None) = how.method {
+                text.push_str(&format!(" (salt {salt})"));
+            }
+            if let Some(path) = &how.map {
+                text.push_str(&format!("; mapping in {}", path.display()));
+            }
+            app.message = Some(text + "; u undoes it");
+            let col = app.col;
+            app.record_all(
+                plan.cells
+                    .into_iter()
+                    .map(|(row, value)| journal::Edit::SetCell { row, col, value })
+                    .collect(),
+            );
+            Ok(false)
+        }
+        Command::Script(None) => {
+            let dir = app.scripts.clone().unwrap_or_default();
+            let names = external::scripts(&dir);
+            app.message = Some(if names.is_empty() {
+                format!("No scripts in {}", dir.display())
+            } else {
+                format!("Scripts: {}", names.join(", "))
+            });
+            Ok(false)
+        }
+        Command::Pipe(_) | Command::Script(_) if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Pipe(command) => {
+            let rows = app.selection().map(|(top, _, bottom, _)| (top, bottom + 1));
+            app.message = Some(
+                match app.pipe_rows(external::shell(&command), &command, rows) {
+                    Ok(0) => format!("No changes from {command}"),
+                    Ok(n) => format!("Applied {n} change(s) from {command}"),
+                    Err(e) => format!("{e:#}"),
+                },
+            );
+            app.anchor = None;
+            Ok(false)
+        }
+        Command::Script(Some((name, args))) => {
+            app.message = Some(match app.run_script(&name, &args) {
+                Ok(0) => format!("No changes from {name}"),
+                Ok(n) => format!("Applied {n} change(s) from {name}"),
+                Err(e) => format!("{e:#}"),
+            });
+            app.anchor = None;
+            Ok(false)
+        }
+        Command::StripCurrency if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::StripCurrency => {
+            let (changes, failed) = money::plan(
+                &app.data,
+                app.col,
+                usize::from(app.header),
+                &app.config.nulls,
+                app.config.decimal,
+            );
+            let mut message = format!(
+                "Cleaned {} cell(s) in column {}",
+                changes.len(),
+                app.col + 1
+            );
+            if failed.is_empty() {
+                app.findings = None;
+            } else {
+                message.push_str(&format!("; {} couldn't be read as numbers", failed.len()));
+                let title = format!(
+                    "strip-currency {}: {} cell(s) left as they were, ]f/[f to step",
+                    app.col + 1,
+                    failed.len()
+                );
+                app.findings = Some(Findings::new(title, failed));
+            }
+            app.record_all(
+                changes
+                    .into_iter()
+                    .map(|c| journal::Edit::SetCell {
+                        row: c.row,
+                        col: c.col,
+                        value: c.after,
+                    })
+                    .collect(),
+            );
+            app.message = Some(message);
+            Ok(false)
+        }
+        Command::Rename { .. } if app.read_only => {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Rename { col, name } => {
+            let result = header::resolve_column(app.headers(), &col)
+                .and_then(|c| app.rename_column(c, &name));
+            if let Err(e) = result {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::Outliers(rule) => {
+            let from = usize::from(app.header);
+            match outliers::find(
+                &app.data,
+                app.col,
+                from,
+                rule,
+                &app.config.nulls,
+                app.config.decimal,
+            ) {
+                Ok(items) if items.is_empty() => {
+                    app.findings = None;
+                    app.message = Some(format!(
+                        "No outliers in column {} ({})",
+                        app.col + 1,
+                        rule.label()
+                    ));
+                }
+                Ok(items) => {
+                    let title = format!(
+                        "outliers {} ({}): {} cell(s), ]f/[f to step",
+                        app.col + 1,
+                        rule.label(),
+                        items.len()
+                    );
+                    app.findings = Some(Findings::new(title, items));
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Report(path) => {
+            if let Err(e) = app.write_report(&path) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::Profile(path) if app.is_open_file(&path) => {
+            app.message = Some("That's the file being edited; pick another name".to_string());
+            Ok(false)
+        }
+        Command::Profile(path) => {
+            let profiles =
+                profile::profile(&app.data, app.header, &app.config.nulls, app.config.decimal);
+            match profile::write(&path, &profiles) {
+                Ok(()) => {
+                    app.message = Some(format!(
+                        "Wrote a profile of {} column(s) to {}",
+                        profiles.len(),
+                        path.display()
+                    ))
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Validate(spec) => {
+            if let Err(e) = app.validate(spec) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::Unique(col) => {
+            if col >= app.max_cols() {
+                app.message = Some(format!("No column {}", col + 1));
+                return Ok(false);
+            }
+            let items = check::unique_violations(&app.data, col);
+            if items.is_empty() {
+                app.findings = None;
+                app.message = Some(format!(
+                    "Column {} is a valid key: all values unique",
+                    col + 1
+                ));
+            } else {
+                let title = format!("unique {}: {} problem(s)", col + 1, items.len());
+                app.findings = Some(Findings::new(title, items));
+            }
+            Ok(false)
+        }
+        Command::Substitute(_) | Command::Sort(_) | Command::Seq(_) | Command::Duplicate
+            if app.read_only =>
+        {
+            app.message = Some("File is open read-only".to_string());
+            Ok(false)
+        }
+        Command::Seq(spec) => {
+            let col = if spec.at_end { app.max_cols() } else { 0 };
+            // The header row gets a name for the column, not the first value.
+            let skip = usize::from(app.header).min(app.data.len());
+            let mut cells = vec!["id".to_string(); skip];
+            cells.extend(spec.seq.values(app.data.len() - skip));
+            app.record(journal::Edit::InsertCol { col, cells });
+            app.col = col;
+            app.message = Some(format!(
+                "Inserted generated column {} ({} row(s)); u to undo",
+                col + 1,
+                app.data.len() - skip
+            ));
+            Ok(false)
+        }
+        Command::Goto(from, to) => {
+            let (rows, cols) = (app.data.len(), app.max_cols());
+            if to.0 >= rows || to.1 >= cols {
+                app.message = Some(format!(
+                    "{} is outside the table ({rows} row(s), {cols} column(s))",
+                    a1::cell_name(to.0, to.1)
+                ));
+            } else if let Some(v) = &app.view
+                && (v.position(from.0).is_none() || v.position(to.0).is_none())
+            {
+                app.message = Some("That row is hidden by the view's filter".to_string());
+            } else {
+                app.anchor = (from != to).then_some(from);
+                (app.row, app.col) = to;
+            }
+            Ok(false)
+        }
+        Command::GotoName(name) => match app.names.get(&name) {
+            Some((from, to)) => run_command(app, Command::Goto(from, to)),
+            None => {
+                app.message = Some(format!("No range named {name}"));
+                Ok(false)
+            }
+        },
+        Command::Name(op) => {
+            if let Err(e) = app.named_range(op) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::View(op) => {
+            if let Err(e) = app.view_preset(op) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::Group(GroupOp::By(col)) => {
+            match header::resolve_column(app.headers(), &col) {
+                Ok(c) => {
+                    app.update_view(|v| v.group = Some(view::Grouping::new(c)));
+                    let runs = app
+                        .view
+                        .as_ref()
+                        .and_then(|v| v.group.as_ref())
+                        .map_or(0, |g| g.runs.len());
+                    app.message = Some(format!(
+                        "{runs} group(s) by column {}; za collapses or expands one",
+                        a1::col_name(c)
+                    ));
+                }
+                Err(e) => app.message = Some(format!("{e:#}")),
+            }
+            Ok(false)
+        }
+        Command::Group(GroupOp::Off) => {
+            app.update_view(|v| v.group = None);
+            app.message = Some("Grouping off".to_string());
+            Ok(false)
+        }
+        Command::Group(_) if app.view.as_ref().is_none_or(|v| v.group.is_none()) => {
+            app.message = Some("Not grouped; :group <col> groups the rows".to_string());
+            Ok(false)
+        }
+        Command::Group(op) => {
+            let collapse = op == GroupOp::CollapseAll;
+            app.update_view(|v| {
+                if let Some(g) = &mut v.group {
+                    g.collapsed = if collapse {
+                        g.runs.iter().map(|r| r.value.clone()).collect()
+                    } else {
+                        Default::default()
+                    };
+                }
+            });
+            app.message = Some(
+                if collapse {
+                    "Collapsed every group; za expands one"
+                } else {
+                    "Expanded every group"
+                }
+                .to_string(),
+            );
+            Ok(false)
+        }
+        Command::Filters(op) => {
+            if let Err(e) = app.saved_filter(op) {
+                app.message = Some(format!("{e:#}"));
+            }
+            Ok(false)
+        }
+        Command::Duplicate if app.data.is_empty() => Ok(false),
+        Command::Duplicate => {
+            let col = app.col;
+            let mut cells: Vec<String> = app
+                .data
+                .iter()
+                .map(|row| row.get(col).cloned().unwrap_or_default())
+                .collect();
+            if app.header {
+                let headers = app.headers();
+                let base = format!("{}_copy", cells[0]);
+                cells[0] = (1..)
+                    .map(|i| {
+                        if i == 1 {
+                            base.clone()
+                        } else {
+                            format!("{base}{i}")
+                        }
+                    })
+                    .find(|name| !headers.contains(name))
+                    .unwrap_or(base);
+            }
+            app.record(journal::Edit::InsertCol {
+                col: col + 1,
+                cells,
+            });
+            app.col = col + 1;
+            app.message = Some(format!(
+                "Duplicated column {} as column {}; u to undo",
+                col + 1,
+                col + 2
+            ));
+            Ok(false)
+        }
+        Command::Sort(keys) if app.config.view_only => {
+            if let Some(k) = keys.iter().find(|k| k.col >= app.max_cols()) {
+                app.message = Some(format!("No column {}", k.col + 1));
+                return Ok(false);
+            }
+            app.update_view(|v| v.sort = keys);
+            app.message = Some("Sorted the view; :commit-view applies it to the data".to_string());
+            Ok(false)
+        }
+        Command::Sort(keys) => {
+            if let Some(k) = keys.iter().find(|k| k.col >= app.max_cols()) {
+                app.message = Some(format!("No column {}", k.col + 1));
+                return Ok(false);
+            }
+            // The header row stays on top.
+            let skip = usize::from(app.header).min(app.data.len());
+            let order: Vec<usize> = (0..skip)
+                .chain(
+                    sort::order(&app.data[skip..], &keys, app.config.decimal)
+                        .into_iter()
+                        .map(|o| o + skip),
+                )
+                .collect();
+            if order.iter().enumerate().all(|(i, &o)| i == o) {
+                app.message = Some("Already sorted".to_string());
+                return Ok(false);
+            }
+            // Keep the cursor on the same record.
+            let row = order.iter().position(|&o| o == app.row).unwrap_or(0);
+            app.record(journal::Edit::Reorder { order });
+            app.row = row;
+            app.message = Some(format!(
+                "Sorted {} row(s); u to undo",
+                app.data.len() - skip
+            ));
+            Ok(false)
+        }
+        Command::Substitute(sub) => {
+            let cells = match app.cells_in(sub.scope) {
+                Ok(cells) => cells,
+                Err(e) => {
+                    app.message = Some(format!("{e:#}"));
+                    return Ok(false);
+                }
+            };
+            let changes = replace::changes(&app.data, cells.into_iter(), &sub, usize::MAX)?;
+            if sub.confirm && !changes.is_empty() {
+                (app.row, app.col) = (changes[0].row, changes[0].col);
+                app.review = Some(replace::Review::new(changes));
+                return Ok(false);
+            }
+            app.record_all(
+                changes
+                    .iter()
+                    .map(|c| journal::Edit::SetCell {
+                        row: c.row,
+                        col: c.col,
+                        value: c.after.clone(),
+                    })
+                    .collect(),
+            );
+            app.message = Some(if changes.is_empty() {
+                format!(
+                    "Pattern not found in {}: {}",
+                    sub.scope.label(),
+                    sub.pattern
+                )
+            } else {
+                format!("Replaced in {} cell(s)", changes.len())
+            });
+            Ok(false)
+        }
+        Command::Recent => {
+            if app.dirty {
+                app.message =
+                    Some("Unsaved changes; write them before opening another file".into());
+                return Ok(false);
+            }
+            let paths = match app.recent_list.as_deref().map(recent::load) {
+                Some(Ok(paths)) => paths,
+                Some(Err(e)) => {
+                    app.message = Some(format!("Could not read recent files: {e:#}"));
+                    return Ok(false);
+                }
+                None => Vec::new(),
+            };
+            if paths.is_empty() {
+                app.message = Some("No recent files".to_string());
+            } else {
+                app.recent = Some(picker::Picker::recent(&paths));
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Whether a failed save was refused by permissions or a read-only mount.
+fn permission_denied(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|c| c.downcast_ref().is_some_and(csv_io::is_permission_error))
+}
+
+/// Say why a save failed, keeping the edits; when permissions refused it,
+/// ask for another path to save (and then quit, for `then_quit`) to.
+fn save_failed(app: &mut App, e: anyhow::Error, then_quit: bool) {
+    if permission_denied(&e) {
+        app.message = Some(format!("{e:#}; save to another path"));
+        app.prompt = Some(Prompt::new(PromptKind::SavePath { then_quit }));
+    } else {
+        // E.g. a compression tool that isn't installed.
+        app.message = Some(format!("Could not save: {e:#}"));
+    }
+}
+
+fn apply_action(app: &mut App, action: input::Action, count: Option<usize>) -> Result<bool> {
+    use input::Action;
+    // Returns Ok(true) to request exit
+    let n = count.unwrap_or(1).max(1);
+    log::event("action", &[("name", &action.name()), ("count", &n)]);
+    if matches!(
+        action,
+        Action::DeleteRow
+            | Action::DeleteColumn
+            | Action::ClearCells
+            | Action::Paste
+            | Action::InsertRowBelow
+            | Action::InsertRowAbove
+    ) && !app.read_only
+    {
+        app.last_change = Some(Change::Action(action, count));
+    }
+    match action {
+        Action::Quit if app.dirty && app.needs_save_path() => {
+            app.prompt = Some(Prompt::new(PromptKind::SavePath { then_quit: true }));
+        }
+        Action::Quit => {
+            // Auto-save on quit if dirty
+            if app.dirty
+                && !app.read_only
+                && let Err(e) = app.save()
+            {
+                save_failed(app, e, true);
+                return Ok(false);
+            }
+            // Still dirty when a pre_save hook stopped the save.
+            return Ok(!app.dirty || app.read_only);
+        }
+        Action::Write if app.write_protected => {
+            app.prompt = Some(Prompt::new(PromptKind::SavePath { then_quit: false }));
+        }
+        Action::Write
+        | Action::Edit
+        | Action::ChangeCell
+        | Action::DeleteRow
+        | Action::InsertRowBelow
+        | Action::InsertRowAbove
+        | Action::Undo
+        | Action::Redo
+        | Action::Paste
+        | Action::PasteSpecial
+        | Action::ExternalCell
+        | Action::ExternalRow
+        | Action::ExternalFile
+        | Action::RenameColumn
+        | Action::Repeat
+        | Action::ClearCells
+        | Action::DeleteColumn
+            if app.read_only =>
+        {
+            app.message = Some("File is open read-only".to_string());
+        }
+        Action::Write if app.needs_save_path() => {
+            app.prompt = Some(Prompt::new(PromptKind::SavePath { then_quit: false }));
+        }
+        Action::Write => {
+            if let Err(e) = app.save() {
+                save_failed(app, e, false);
+            }
+        }
+        Action::ExportHtml if !app.has_path() => {
+            app.message = Some("Write the file first to name the export".to_string());
+        }
+        Action::ExportHtml => {
+            let out = export::html_path_for(&app.file_path);
+            let title = app.file_path.display().to_string();
+            app.message = Some(match export::export_html(&out, &title, &app.data) {
+                Ok(()) => format!("Exported HTML to {}", out.display()),
+                Err(e) => format!("Could not export: {e:#}"),
+            });
+        }
+        Action::Edit => {
+            app.ensure_cell_exists(app.row, app.col);
+            app.editor_buf = app.data[app.row][app.col].clone();
+            app.editing = true;
+        }
+        Action::ChangeCell => {
+            app.ensure_cell_exists(app.row, app.col);
+            app.editor_buf.clear();
+            app.editing = true;
+        }
+        // In a view the rows on screen needn't be next to each other in the file.
+        Action::DeleteRow if !app.data.is_empty() && app.view.is_some() => {
+            let pos = app.view_pos();
+            let mut rows: Vec<usize> = (pos..pos + n)
+                .take_while(|&p| p < app.visible_rows())
+                .map(|p| app.row_at(p))
+                .collect();
+            // The cursor moves on to the next shown row, or the one before.
+            let next = (pos + rows.len() < app.visible_rows())
+                .then(|| app.row_at(pos + rows.len()))
+                .or_else(|| pos.checked_sub(1).map(|p| app.row_at(p)));
+            rows.sort_unstable_by(|a, b| b.cmp(a));
+            let gone = rows.clone();
+            app.record_all(
+                rows.into_iter()
+                    .map(|row| journal::Edit::DeleteRows { row, count: 1 })
+                    .collect(),
+            );
+            app.row = next.map_or(0, |r| r - gone.iter().filter(|&&d| d < r).count());
+            app.refresh_view();
+            app.clamp_col();
+        }
+        Action::DeleteRow
+            if let Some((top, _, bottom, _)) = app.selection()
+                && !app.data.is_empty() =>
+        {
+            app.anchor = None;
+            let count = (bottom + 1).min(app.data.len()).saturating_sub(top);
+            app.record(journal::Edit::DeleteRows { row: top, count });
+            app.row = top.min(app.data.len().saturating_sub(1));
+            app.clamp_col();
+            app.message = Some(format!("Deleted {count} row(s)"));
+        }
+        Action::DeleteRow if !app.data.is_empty() => {
+            let count = n.min(app.data.len() - app.row);
+            app.record(journal::Edit::DeleteRows {
+                row: app.row,
+                count: n,
+            });
+            app.clamp_col();
+            app.message = Some(format!("Deleted {count} row(s)"));
+        }
+        Action::DeleteRow => {}
+        Action::InsertRowBelow | Action::InsertRowAbove => {
+            let below = action == Action::InsertRowBelow && !app.data.is_empty();
+            // The header stays the first row.
+            let at = (app.row + usize::from(below)).max(usize::from(app.header));
+            if let Err(e) = app.insert_rows(at, n) {
+                app.message = Some(format!("{e:#}"));
+            }
+        }
+        Action::DeleteColumn if app.max_cols() == 0 => {}
+        Action::DeleteColumn => {
+            let (left, right) = match app.anchor.take() {
+                Some((_, c)) => (c.min(app.col), c.max(app.col)),
+                None => (app.col, app.col),
+            };
+            let right = right.min(app.max_cols() - 1);
+            let edits = (left..=right)
+                .rev()
+                .map(|col| journal::Edit::DeleteCol { col })
+                .collect();
+            app.record_all(edits);
+            app.col = left;
+            app.clamp_col();
+            app.message = Some(format!("Deleted {} column(s)", right + 1 - left));
+        }
+        Action::ClearCells => {
+            let (top, left, bottom, right) = app
+                .selection()
+                .unwrap_or((app.row, app.col, app.row, app.col));
+            app.anchor = None;
+            let edits: Vec<_> = (top..=bottom)
+                .flat_map(|r| (left..=right).map(move |c| (r, c)))
+                .filter(|&(r, c)| {
+                    app.data
+                        .get(r)
+                        .and_then(|row| row.get(c))
+                        .is_some_and(|v| !v.is_empty())
+                })
+                .map(|(row, col)| journal::Edit::SetCell {
+                    row,
+                    col,
+                    value: String::new(),
+                })
+                .collect();
+            let n = edits.len();
+            app.record_all(edits);
+            app.message = Some(format!("Cleared {n} cell(s); rows and columns are kept"));
+        }
+        Action::MoveLeft => {
+            app.col = app.col_at(app.col_pos().saturating_sub(n));
+        }
+        Action::MoveRight
+            if app.extends()
+                && !app.data.is_empty()
+                && app.col_pos() + n >= app.column_order().len() =>
+        {
+            let first = app.max_cols();
+            let extra = app.col_pos() + n + 1 - app.column_order().len();
+            let edits = (first..first + extra)
+                .map(|col| journal::Edit::InsertCol {
+                    col,
+                    cells: vec![String::new(); app.data.len()],
+                })
+                .collect();
+            app.record_all(edits);
+            app.col = first + extra - 1;
+            app.message = Some(format!("Added {extra} column(s)"));
+        }
+        Action::MoveRight => {
+            app.col = app.col_at(app.col_pos() + n);
+        }
+        Action::MoveUp => {
+            app.row = app.row_at(app.view_pos().saturating_sub(n));
+            app.clamp_col();
+        }
+        // A filtered or sorted view has no last row to grow from.
+        Action::MoveDown
+            if app.extends()
+                && app.view.is_none()
+                && !app.data.is_empty()
+                && app.row + n >= app.data.len() =>
+        {
+            let (col, len) = (app.col, app.data.len());
+            if let Err(e) = app.insert_rows(len, app.row + n + 1 - len) {
+                app.message = Some(format!("{e:#}"));
+            }
+            app.row = app.data.len().saturating_sub(1);
+            app.col = col;
+            app.clamp_col();
+        }
+        Action::MoveDown => {
+            app.row = app.row_at(app.view_pos() + n);
+            app.clamp_col();
+        }
+        // With a count, `gg`/`G` jump to that (1-based) row like vim.
+        Action::FirstRow | Action::LastRow if count.is_some() => {
+            app.row = app.row_at(n - 1);
+            app.clamp_col();
+        }
+        Action::FirstRow => {
+            app.row = app.row_at(0);
+            app.clamp_col();
+        }
+        Action::LastRow => {
+            app.row = app.row_at(usize::MAX);
+            app.clamp_col();
+        }
+        Action::Undo | Action::Redo => {
+            let mut done = 0;
+            for _ in 0..n {
+                let ok = if action == Action::Undo {
+                    app.undo()
+                } else {
+                    app.redo()
+                };
+                if !ok {
+                    break;
+                }
+                done += 1;
+            }
+            app.message = Some(match (action, done) {
+                (Action::Undo, 0) => "Already at oldest change".to_string(),
+                (_, 0) => "Already at newest change".to_string(),
+                (Action::Undo, n) => format!("Undid {n} change(s)"),
+                (_, n) => format!("Redid {n} change(s)"),
+            });
+        }
+        Action::NextEmpty
+        | Action::PrevEmpty
+        | Action::NextEmptyAnywhere
+        | Action::PrevEmptyAnywhere => {
+            let forward = matches!(action, Action::NextEmpty | Action::NextEmptyAnywhere);
+            let whole_file = matches!(
+                action,
+                Action::NextEmptyAnywhere | Action::PrevEmptyAnywhere
+            );
+            for _ in 0..n {
+                match app.find_empty(forward, whole_file) {
+                    Some((pos, wrapped)) => {
+                        (app.row, app.col) = pos;
+                        if wrapped {
+                            app.message = Some("Search wrapped around".to_string());
+                        }
+                    }
+                    None => {
+                        let place = if whole_file {
+                            "the file"
+                        } else {
+                            "this column"
+                        };
+                        app.message = Some(format!("No empty cells in {place}"));
+                        break;
+                    }
+                }
+            }
+        }
+        Action::ToggleEmpty => {
+            app.show_empty = !app.show_empty;
+            let cell = |r: usize, c: usize| app.data.get(r).and_then(|row| row.get(c));
+            let count = app
+                .search_cells(app.grid_area())
+                .into_iter()
+                .filter(|&(r, c)| cell(r, c).is_none_or(|v| v.trim().is_empty()))
+                .count();
+            app.message = Some(if app.show_empty {
+                format!("Highlighting {count} empty cell(s)")
+            } else {
+                "Empty-cell highlighting off".to_string()
+            });
+        }
+        Action::SplitView if app.split.take().is_some() => {
+            app.message = Some("Split closed".to_string());
+        }
+        Action::SplitView => {
+            app.split = Some(Split::new((app.row, app.col)));
+            app.message = Some("Split view; Ctrl+W w switches panes".to_string());
+        }
+        Action::SwitchPane | Action::ScrollLock if app.split.is_none() => {
+            app.message = Some("No split view; Ctrl+W v opens one".to_string());
+        }
+        Action::SwitchPane => {
+            let rows = app.data.len();
+            let mut cursor = (app.row, app.col);
+            if let Some(split) = &mut app.split {
+                split.switch(&mut cursor, rows);
+            }
+            (app.row, app.col) = cursor;
+            app.clamp_col();
+        }
+        Action::ScrollLock => {
+            let rows = app.data.len();
+            let cursor = (app.row, app.col);
+            if let Some(split) = &mut app.split {
+                app.message = Some(if split.toggle_lock(cursor, rows) {
+                    format!("Scroll lock on ({:+} rows)", split.lock.unwrap_or_default())
+                } else {
+                    "Scroll lock off".to_string()
+                });
+            }
+        }
+        Action::ExternalCell => app.external = Some(external::Target::Cell),
+        Action::ExternalRow => app.external = Some(external::Target::Row),
+        Action::ExternalFile => app.external = Some(external::Target::File),
+        Action::Yank => app.yank(),
+        Action::Paste => {
+            for _ in 0..n {
+                app.paste(PasteOptions::default());
+            }
+        }
+        Action::PasteSpecial if app.clipboard.is_none() => {
+            app.message = Some("Nothing copied; y copies the cell or selection".to_string());
+        }
+        Action::PasteSpecial => app.paste_special = Some(PasteOptions::default()),
+        Action::Help => app.help = Some(help::HelpView::default()),
+        Action::FuzzyFind => app.fuzzy = Some(fuzzy::FuzzyView::default()),
+        Action::NextModified | Action::PrevModified => {
+            match app.find_modified(action == Action::NextModified) {
+                Some((r, c)) => (app.row, app.col) = (r, c),
+                None => app.message = Some("No modified cells".to_string()),
+            }
+        }
+        // A count replaces the repeated change's own, as in vim.
+        Action::Repeat => match app.last_change.clone() {
+            None => app.message = Some("Nothing to repeat yet".to_string()),
+            Some(Change::Action(action, c)) => return apply_action(app, action, count.or(c)),
+            Some(Change::SetCell(value)) => {
+                app.ensure_cell_exists(app.row, app.col);
+                app.set_cell(app.row, app.col, value);
+            }
+            Some(Change::PasteSpecial(opts)) => app.paste(opts),
+            Some(Change::Command(cmd)) => return run_command(app, cmd),
+        },
+        Action::Issues if app.findings.is_some() => app.findings = None,
+        Action::Issues => {
+            if let Err(e) = app.validate(None) {
+                app.message = Some(format!("{e:#}"));
+            }
+        }
+        Action::NextFinding | Action::PrevFinding => {
+            if !app.step_finding(action == Action::NextFinding) {
+                app.message = Some("No findings; run a check like :outliers first".to_string());
+            }
+        }
+        Action::CompareCell if !app.is_modified(app.row, app.col) => {
+            app.message = Some("Cell unchanged since the last save".to_string());
+        }
+        Action::CompareCell => app.compare = true,
+        Action::ViewCell => app.cell_view = Some(0),
+        Action::RecordCard if app.data.is_empty() => {
+            app.message = Some("No rows to show".to_string());
+        }
+        Action::RecordCard => app.record_card = true,
+        Action::ExportDialog => {
+            app.export_dialog = Some(export::Dialog::new(app.export_settings, &app.file_path));
+        }
+        Action::CompareRows => {
+            if let Err(e) = app.open_row_compare() {
+                app.message = Some(format!("{e:#}"));
+            }
+        }
+        Action::RenameColumn if !app.header || app.row != 0 => {
+            app.message = Some("r renames a column from the header row (:header on)".to_string());
+        }
+        Action::RenameColumn => {
+            let mut p = Prompt::new(PromptKind::Rename { col: app.col });
+            p.buf = app.headers().get(app.col).cloned().unwrap_or_default();
+            app.prompt = Some(p);
+        }
+        Action::ToggleRainbow => {
+            app.config.rainbow = !app.config.rainbow;
+            app.message = Some(
+                if app.config.rainbow {
+                    "Rainbow columns on"
+                } else {
+                    "Rainbow columns off"
+                }
+                .to_string(),
+            );
+        }
+        Action::FilterEqual | Action::FilterNotEqual => {
+            let value = app
+                .data
+                .get(app.row)
+                .and_then(|r| r.get(app.col))
+                .cloned()
+                .unwrap_or_default();
+            let rule = if action == Action::FilterEqual {
+                view::Rule::Is(app.col, value)
+            } else {
+                view::Rule::IsNot(app.col, value)
+            };
+            let filter = view::Filter::with_rule(rule, &[])?;
+            let describe = filter.describe();
+            app.update_view(|v| v.filter = Some(filter));
+            app.message = Some(format!(
+                "{describe}: {} row(s); :filter shows all",
+                app.shown_rows()
+            ));
+        }
+        Action::ToggleBadges => {
+            app.config.badges = !app.config.badges;
+            app.message = Some(
+                if app.config.badges {
+                    "Cell badges on"
+                } else {
+                    "Cell badges off"
+                }
+                .to_string(),
+            );
+        }
+        Action::CycleViz => {
+            app.viz = app.viz.next();
+            app.message = Some(format!("Numeric columns: {}", app.viz.label()));
+        }
+        Action::ChartColumn | Action::ChartRow => {
+            let series = if action == Action::ChartColumn {
+                viz::Series::column(&app.data, app.col, &app.config.nulls, app.config.decimal)
+            } else {
+                viz::Series::row(&app.data, app.row, &app.config.nulls, app.config.decimal)
+            };
+            if series.values.is_empty() {
+                app.message = Some(format!("{} has no numbers to chart", series.title));
+            } else {
+                app.chart = Some(series);
+            }
+        }
+        Action::CommandLine => {
+            let mut p = Prompt::new(PromptKind::Command);
+            // As in vim, a command typed over a selection applies to it.
+            if app.anchor.is_some() {
+                p.buf = "'<,'>".to_string();
+            }
+            app.prompt = Some(p);
+        }
+        Action::Search => {
+            app.prompt = Some(Prompt::new(PromptKind::Search {
+                origin: (app.row, app.col),
+                area: app.default_search_area(),
+            }));
+        }
+        Action::SearchNext | Action::SearchPrev => {
+            let Some(s) = &app.last_search else {
+                app.message = Some("No previous search".to_string());
+                return Ok(false);
+            };
+            let cells = app.search_cells(s.area);
+            let forward = action == Action::SearchNext;
+            for _ in 0..n {
+                match search::next_match(&app.data, &cells, s, (app.row, app.col), forward, false) {
+                    Some((pos, wrapped)) => {
+                        (app.row, app.col) = pos;
+                        if wrapped {
+                            app.message = Some("Search wrapped around".to_string());
+                        }
+                    }
+                    None => {
+                        app.message = Some(format!("Pattern not found: {}", s.query));
+                        break;
+                    }
+                }
+            }
+        }
+        Action::ToggleTag => {
+            for _ in 0..n {
+                app.tags.toggle(app.row);
+                let next = app.row_at(app.view_pos() + 1);
+                if next == app.row {
+                    break;
+                }
+                app.row = next;
+            }
+            app.clamp_col();
+            app.message = Some(format!(
+                "{} tagged row(s); :tagged delete|yank|export <file>, :ts/re/rep/",
+                app.tags.len()
+            ));
+        }
+        Action::Select if app.view.is_some() && app.anchor.is_none() => {
+            app.message =
+                Some("Selections follow file order; :commit-view or :reset-view first".to_string());
+        }
+        Action::Select => {
+            app.anchor = match app.anchor {
+                Some(_) => None,
+                None => Some((app.row, app.col)),
+            };
+        }
+        Action::FirstCol => app.col = app.col_at(0),
+        Action::LastCol => {
+            let len = app.data.get(app.row).map_or(0, Vec::len);
+            let order = app.column_order();
+            app.col = order.iter().rev().find(|&&c| c < len).copied().unwrap_or(0);
+        }
+        Action::ColumnManager => app.open_column_manager(),
+        Action::SavedFilters => app.saved_filter(presets::Op::List)?,
+        Action::ToggleGroup => {
+            if let Err(e) = app.toggle_group() {
+                app.message = Some(format!("{e:#}"));
+            }
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_io::load_csv;
+    use std::env;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_edit_flow_and_write_key() -> Result<()> {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("tui_csv_viewer_flow_{}.csv", std::process::id()));
+        std::fs::write(&path, b"a,b\nc,d\n")?;
+
+        let data = load_csv(&path)?;
+        let mut app = App::new(path.clone(), data);
+
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        assert!(app.editing);
+        assert_eq!(app.editor_buf, "a");
+
+        handle_key(&mut app, key(KeyCode::Char('X')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(!app.editing);
+        assert_eq!(app.data[0][0], "aX");
+        assert!(app.dirty);
+
+        handle_key(&mut app, key(KeyCode::Char('w')))?;
+        assert!(!app.dirty);
+        let reread = std::fs::read_to_string(&app.file_path)?;
+        assert!(reread.contains("aX,b"));
+        let _ = std::fs::remove_file(&app.file_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_html_key_writes_file() -> Result<()> {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("tui_csv_viewer_html_{}.csv", std::process::id()));
+        let mut app = App::new(path.clone(), vec![vec!["<x>".into(), "y".into()]]);
+
+        handle_key(&mut app, key(KeyCode::Char('H')))?;
+        let out = export::html_path_for(&path);
+        let html = std::fs::read_to_string(&out)?;
+        assert!(html.contains("<td>&lt;x&gt;</td><td>y</td>"));
+        assert!(app.message.is_some());
+        let _ = std::fs::remove_file(&out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_save_keeps_the_edits_and_says_why() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_viewer_nosave_{}", std::process::id()));
+        let mut app = App::new(dir.join("a.csv.gz"), vec![vec!["x".into()]]);
+        app.dirty = true;
+        assert!(!handle_key(&mut app, key(KeyCode::Char('w')))?);
+        assert!(app.dirty);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not save"))
+        );
+        // Quitting tries the same save, and stays open when it fails.
+        app.message = None;
+        assert!(!handle_key(&mut app, key(KeyCode::Char('q')))?);
+        assert!(app.dirty);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not save"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aligned_and_html_exports_report_a_failed_write() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_gone_{}", std::process::id()));
+        let mut app = App::new(path.join("a.csv"), vec![vec!["x".into()]]);
+        for line in ["export aligned", "export html"] {
+            app.message = None;
+            assert!(!run_command(&mut app, command::parse(line)?)?);
+            assert!(
+                app.message
+                    .as_deref()
+                    .is_some_and(|m| m.starts_with("Could not export"))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_blocks_edit_and_write() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_ro_{}.csv", std::process::id()));
+        let mut app = App::new(path.clone(), vec![vec!["a".into()]]);
+        app.read_only = true;
+        app.dirty = true;
+
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        assert!(!app.editing);
+        handle_key(&mut app, key(KeyCode::Char('w')))?;
+        assert!(app.dirty);
+        assert!(handle_key(&mut app, key(KeyCode::Char('q')))?);
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_records_edits_until_save_and_replays() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_jr_{}.csv", std::process::id()));
+        std::fs::write(&path, b"a,b\n")?;
+        let mut app = App::new(path.clone(), load_csv(&path)?);
+
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        handle_key(&mut app, key(KeyCode::Char('!')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        let recorded = journal::read(&app.journal_path())?;
+        assert_eq!(recorded.len(), 1);
+        assert!(matches!(&recorded[0], journal::Edit::SetCell { value, .. } if value == "a!"));
+
+        // Simulate a crash: a fresh session picks the edit up from the journal.
+        let mut recovered = App::new(path.clone(), load_csv(&path)?);
+        recovered.pending_replay = recorded;
+        recovered
+            .pending_replay
+            .push(journal::Edit::DeleteRows { row: 1, count: 1 });
+        // A stray key neither replays nor throws the journal away.
+        handle_key(&mut recovered, key(KeyCode::Down))?;
+        handle_key(&mut recovered, key(KeyCode::Char('x')))?;
+        assert_eq!(recovered.pending_replay.len(), 2);
+        assert!(recovered.journal_path().exists());
+        assert_eq!(recovered.data[0][0], "a");
+        handle_key(&mut recovered, key(KeyCode::Char('y')))?;
+        assert_eq!(recovered.data[0][0], "a!");
+        assert!(recovered.dirty);
+
+        handle_key(&mut recovered, key(KeyCode::Char('w')))?;
+        assert!(!recovered.journal_path().exists());
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ctrl_z_requests_suspend_even_while_editing() -> Result<()> {
+        let mut app = App::new(PathBuf::from("unused.csv"), vec![vec!["a".into()]]);
+        app.editing = true;
+        app.editor_buf = "a".into();
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL),
+        )?;
+        assert!(app.suspend_requested);
+        assert_eq!(app.editor_buf, "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_counts_and_chords_drive_navigation_and_row_deletion() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_chord_{}.csv", std::process::id()));
+        let data = (0..5).map(|i| vec![i.to_string(), "x".into()]).collect();
+        let mut app = App::new(path.clone(), data);
+
+        for c in "3j".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.row, 3);
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT),
+        )?;
+        assert_eq!(app.row, 4);
+        for c in "gg$".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!((app.row, app.col), (0, 1));
+
+        for c in "2dd".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.data.len(), 3);
+        assert_eq!(app.data[0][0], "2");
+        assert!(app.dirty);
+        let _ = journal::clear(&app.journal_path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_help_overlay_captures_typing_until_esc() -> Result<()> {
+        let mut app = App::new(PathBuf::from("unused.csv"), vec![vec!["a".into()]]);
+        handle_key(&mut app, key(KeyCode::Char('?')))?;
+        assert!(app.help.is_some());
+        // `q` goes to the search box instead of quitting.
+        assert!(!handle_key(&mut app, key(KeyCode::Char('q')))?);
+        assert_eq!(app.help.as_ref().map(|h| h.query.as_str()), Some("q"));
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert!(app.help.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unnamed_buffer_prompts_for_size_and_save_path() -> Result<()> {
+        let mut app = App::new(PathBuf::new(), Vec::new());
+        app.prompt = Some(Prompt::new(PromptKind::NewDimensions));
+        for c in "2x3".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!((app.data.len(), app.max_cols()), (2, 3));
+
+        app.set_cell(1, 2, "z".into());
+        // Quitting a dirty unnamed buffer asks where to save instead of exiting.
+        assert!(!handle_key(&mut app, key(KeyCode::Char('q')))?);
+        assert!(matches!(
+            app.prompt.as_ref().map(|p| p.kind),
+            Some(PromptKind::SavePath { then_quit: true })
+        ));
+        let path = env::temp_dir().join(format!("tui_csv_viewer_new_{}.csv", std::process::id()));
+        for c in path.display().to_string().chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert!(handle_key(&mut app, key(KeyCode::Enter))?);
+        assert_eq!(std::fs::read_to_string(&path)?, ",,\n,,z\n");
+        drop(app);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recent_command_reopens_a_remembered_file() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_viewer_recent_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let (a, b) = (dir.join("a.csv"), dir.join("b.csv"));
+        std::fs::write(&a, "from,a\n")?;
+        std::fs::write(&b, "from,b\n")?;
+
+        let mut app = App {
+            recent_list: Some(dir.join("recent")),
+            ..App::default()
+        };
+        app.open(a.clone())?;
+        app.open(b.clone())?;
+        assert_eq!(app.data[0][1], "b");
+
+        for c in ":recent".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.recent.is_some());
+        // The list is most-recent first: b, then a.
+        handle_key(&mut app, key(KeyCode::Down))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.recent.is_none());
+        assert_eq!(app.data[0][1], "a");
+
+        // A remembered file that no longer loads leaves this one open.
+        std::fs::remove_file(&b)?;
+        std::fs::create_dir(&b)?;
+        for c in ":recent".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        handle_key(&mut app, key(KeyCode::Down))?;
+        assert!(!handle_key(&mut app, key(KeyCode::Enter))?);
+        assert_eq!(app.data[0][1], "a");
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not open"))
+        );
+        drop(app);
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_and_revert_tracks_rows_across_deletes() -> Result<()> {
+        let data = vec![vec!["a".into()], vec!["b".into()], vec!["c".into()]];
+        let mut app = App::new(PathBuf::new(), data);
+        // Delete the first row, then edit what is now row 0 (originally "b").
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        app.set_cell(0, 0, "B!".into());
+        assert!(app.is_modified(0, 0));
+        assert!(!app.is_modified(1, 0));
+        assert_eq!(app.original_value(0, 0), Some("b"));
+
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('K'), KeyModifiers::SHIFT),
+        )?;
+        assert!(app.compare);
+        handle_key(&mut app, key(KeyCode::Char('r')))?;
+        assert!(!app.compare);
+        assert_eq!(app.data[0][0], "b");
+        assert!(!app.is_modified(0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jumps_between_modified_cells_with_wraparound() -> Result<()> {
+        let data = vec![vec!["a".into(), "b".into()], vec!["c".into(), "d".into()]];
+        let mut app = App::new(PathBuf::new(), data);
+        app.set_cell(0, 1, "B".into());
+        app.set_cell(1, 0, "C".into());
+        let jump = |app: &mut App, keys: &str| -> Result<(usize, usize)> {
+            for c in keys.chars() {
+                handle_key(app, key(KeyCode::Char(c)))?;
+            }
+            Ok((app.row, app.col))
+        };
+        assert_eq!(jump(&mut app, "]c")?, (0, 1));
+        assert_eq!(jump(&mut app, "]c")?, (1, 0));
+        assert_eq!(jump(&mut app, "]c")?, (0, 1));
+        assert_eq!(jump(&mut app, "[c")?, (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_check_opens_a_jumpable_panel() -> Result<()> {
+        let data = vec![
+            vec!["1".into()],
+            vec!["2".into()],
+            vec!["".into()],
+            vec!["2".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, Command::Unique(0))?;
+        let rows: Vec<_> = app
+            .findings
+            .as_ref()
+            .map(|f| f.items.iter().map(|i| i.row).collect())
+            .unwrap_or_default();
+        assert_eq!(rows, [1, 2, 3]);
+
+        handle_key(&mut app, key(KeyCode::Down))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.row, 2);
+        // With focus back on the table, arrows move the cursor again.
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        handle_key(&mut app, key(KeyCode::Down))?;
+        assert_eq!(app.row, 3);
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert!(app.findings.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chart_popup_needs_numbers_and_closes_on_any_key() -> Result<()> {
+        let data = vec![
+            vec!["name".into(), "qty".into()],
+            vec!["a".into(), "4".into()],
+            vec!["b".into(), "7".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        handle_key(&mut app, key(KeyCode::Char('z')))?;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        assert!(app.chart.is_none());
+        assert!(app.message.is_some());
+
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('z')))?;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        assert_eq!(app.chart.as_ref().map(|c| c.values.len()), Some(2));
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        assert!(app.chart.is_none());
+        assert_eq!(app.row, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_over_scopes() -> Result<()> {
+        let data = vec![
+            vec!["a1".into(), "b1".into()],
+            vec!["a2".into(), "b2".into()],
+            vec!["a3".into(), "b3".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        let run = |app: &mut App, line: &str| -> Result<bool> {
+            let cmd = command::parse(line)?;
+            run_command(app, cmd)
+        };
+
+        run(&mut app, r"s/(\w)(\d)/$2$1/")?;
+        assert_eq!(app.data[0], ["1a", "b1"]);
+        run(&mut app, "cs/^a/A/")?;
+        assert_eq!(app.data[2][0], "A3");
+
+        // Select the lower-right 2x1 block; `:` prefills the selection range.
+        app.row = 1;
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('v')))?;
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char(':')))?;
+        assert_eq!(app.prompt.as_ref().map(|p| p.buf.as_str()), Some("'<,'>"));
+        app.prompt = None;
+        run(&mut app, "'<,'>s/b/B/")?;
+        assert_eq!(app.data[1][1], "B2");
+        assert_eq!(app.data[2][1], "B3");
+        assert_eq!(app.data[0][1], "b1");
+
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert!(app.selection().is_none());
+        run(&mut app, "'<,'>s/b/B/")?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.contains("no selection"))
+        );
+
+        run(&mut app, "%s/\\d/#/g")?;
+        assert_eq!(app.data, [["#a", "b#"], ["A#", "B#"], ["A#", "B#"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_search_moves_live_and_esc_restores() -> Result<()> {
+        let data = vec![
+            vec!["alpha".into(), "beta".into()],
+            vec!["gamma".into(), "zeta".into()],
+            vec!["beta2".into(), "eta".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('/')))?;
+        handle_key(&mut app, key(KeyCode::Char('b')))?;
+        // Nearest match at or after the start, wrapping past the end.
+        assert_eq!((app.row, app.col), (2, 0));
+        handle_key(&mut app, key(KeyCode::Char('x')))?;
+        assert_eq!((app.row, app.col), (1, 0));
+        handle_key(&mut app, key(KeyCode::Backspace))?;
+        assert!(app.live_search.is_some());
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert_eq!((app.row, app.col), (1, 0));
+        assert!(app.live_search.is_none());
+
+        handle_key(&mut app, key(KeyCode::Char('/')))?;
+        for c in "eta".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!((app.row, app.col), (1, 1));
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!((app.row, app.col), (2, 0));
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!((app.row, app.col), (2, 1));
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!((app.row, app.col), (0, 1));
+        assert_eq!(app.message.as_deref(), Some("Search wrapped around"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_scoped_to_column_or_selection() -> Result<()> {
+        let data = vec![
+            vec!["12".into(), "x".into()],
+            vec!["3".into(), "a12".into()],
+            vec!["124".into(), "b12".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('/')))?;
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        assert_eq!(
+            app.prompt.as_ref().map(|p| p.label()).as_deref(),
+            Some("[col 2] /")
+        );
+        handle_key(&mut app, key(KeyCode::Char('1')))?;
+        handle_key(&mut app, key(KeyCode::Char('2')))?;
+        assert_eq!((app.row, app.col), (1, 1));
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!((app.row, app.col), (2, 1));
+
+        // With a selection, `/` starts out limited to it.
+        (app.row, app.col) = (1, 0);
+        handle_key(&mut app, key(KeyCode::Char('v')))?;
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char('/')))?;
+        for c in "12".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!((app.row, app.col), (2, 0));
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!((app.row, app.col), (2, 0));
+        assert_eq!(app.message.as_deref(), Some("Search wrapped around"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuzzy_find_jumps_to_the_best_cell() -> Result<()> {
+        let data = vec![
+            vec!["id".into(), "city".into()],
+            vec!["1".into(), "Sunnyvale Cat Cafe".into()],
+            vec!["2".into(), "New York City".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL),
+        )?;
+        for c in "nyc".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.fuzzy.as_ref().map(|v| v.hits.len()), Some(2));
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.fuzzy.is_none());
+        assert_eq!((app.row, app.col), (2, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_count_and_ranges_follow_edits() {
+        let data = vec![vec!["1".into(), "x".into()], vec!["5".into(), "y".into()]];
+        let mut app = App::new(PathBuf::new(), data);
+        assert_eq!(app.max_cols(), 2);
+        assert_eq!(app.column_ranges(), [Some((1.0, 5.0)), None]);
+        app.set_cell(1, 0, "9".into());
+        assert_eq!(app.column_ranges()[0], Some((1.0, 9.0)));
+        app.set_cell(0, 3, "7".into());
+        assert_eq!(app.max_cols(), 4);
+        assert_eq!(app.column_ranges()[3], Some((7.0, 7.0)));
+        assert!(app.undo() && app.undo());
+        assert_eq!(app.column_ranges()[0], Some((1.0, 5.0)));
+        app.config.nulls = Nulls::parse("5");
+        assert_eq!(app.column_ranges()[0], Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_sort_is_undoable_and_keeps_the_cursor_record() -> Result<()> {
+        let data = vec![
+            vec!["b".into(), "2".into()],
+            vec!["a".into(), "10".into()],
+            vec!["c".into(), "1".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        app.row = 1;
+        run_command(&mut app, command::parse("sort 2 desc")?)?;
+        let col0: Vec<_> = app.data.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(col0, ["a", "b", "c"]);
+        assert_eq!(app.row, 0);
+        // Rows keep their identity, so nothing shows as modified.
+        assert!(app.find_modified(true).is_none());
+
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        )?;
+        assert_eq!(app.data[0][0], "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_restores_deleted_rows_and_groups_replacements() -> Result<()> {
+        let data = vec![vec!["x1".into()], vec!["x2".into()], vec!["x3".into()]];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        run_command(&mut app, command::parse("%s/x/y/")?)?;
+        handle_key(&mut app, key(KeyCode::Char('2')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        assert_eq!(app.data, [["y3"]]);
+
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, [["y1"], ["y2"], ["y3"]]);
+        assert_eq!(app.original_value(1, 0), Some("x2"));
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.message.as_deref(), Some("Already at oldest change"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jumps_to_empty_cells_in_column_and_file() -> Result<()> {
+        let data = vec![
+            vec!["1".into(), "".into()],
+            vec!["".into(), "x".into()],
+            vec!["3".into()],
+            vec![" ".into(), "y".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        let feed = |app: &mut App, keys: &str| -> Result<()> {
+            for c in keys.chars() {
+                handle_key(app, key(KeyCode::Char(c)))?;
+            }
+            Ok(())
+        };
+        feed(&mut app, "]e")?;
+        assert_eq!((app.row, app.col), (1, 0));
+        feed(&mut app, "]e")?;
+        assert_eq!((app.row, app.col), (3, 0));
+        // Across the file, the missing cell of the short row counts too.
+        feed(&mut app, "[E")?;
+        assert_eq!((app.row, app.col), (2, 1));
+        feed(&mut app, "[E")?;
+        assert_eq!((app.row, app.col), (1, 0));
+        feed(&mut app, "ze")?;
+        assert!(app.show_empty);
+        assert_eq!(app.message.as_deref(), Some("Highlighting 4 empty cell(s)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_seq_inserts_an_undoable_id_column() -> Result<()> {
+        let data = vec![vec!["a".into(), "b".into()], vec!["c".into()]];
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("seq 10 5")?)?;
+        assert_eq!(app.data, [vec!["10", "a", "b"], vec!["15", "c"]]);
+        // Existing values moved with their column, so only the new one differs.
+        assert!(app.is_modified(0, 0));
+        assert!(!app.is_modified(0, 1) && !app.is_modified(1, 1));
+
+        run_command(&mut app, command::parse("seq last")?)?;
+        assert_eq!(app.data[1], ["15", "c", "", "2"]);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        // The short row keeps the padding it got, as after undoing an edit
+        // past the end of a row.
+        assert_eq!(app.data, [vec!["a", "b"], vec!["c", ""]]);
+        assert!(app.find_modified(true).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seq_leaves_the_header_row_out_of_the_numbering() -> Result<()> {
+        let data = [["name"], ["amy"], ["bob"]]
+            .map(|r| r.map(String::from).to_vec())
+            .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("seq")?)?;
+        assert_eq!(app.data, [["id", "name"], ["1", "amy"], ["2", "bob"]]);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Inserted generated column 1 (2 row(s)); u to undo")
+        );
+        run_command(&mut app, command::parse("seq uuid last")?)?;
+        assert_eq!(app.data[0][2], "id");
+        assert_eq!(app.data[1][2].len(), 36);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_scroll_lock_follows_the_focused_pane() -> Result<()> {
+        let data = (0..50).map(|i| vec![i.to_string()]).collect();
+        let mut app = App::new(PathBuf::new(), data);
+        let ctrl_w = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        let keys = |app: &mut App, s: &str| -> Result<()> {
+            for c in s.chars() {
+                handle_key(app, key(KeyCode::Char(c)))?;
+            }
+            Ok(())
+        };
+        handle_key(&mut app, ctrl_w)?;
+        keys(&mut app, "v")?;
+        keys(&mut app, "30j")?;
+        handle_key(&mut app, ctrl_w)?;
+        keys(&mut app, "b")?;
+        assert_eq!(app.message.as_deref(), Some("Scroll lock on (-30 rows)"));
+        keys(&mut app, "5j")?;
+        let split = app
+            .split
+            .as_ref()
+            .map(|s| s.other_cursor((app.row, app.col), 50));
+        assert_eq!((app.row, split), (35, Some((5, 0))));
+
+        handle_key(&mut app, ctrl_w)?;
+        keys(&mut app, "w")?;
+        assert_eq!(app.row, 5);
+        keys(&mut app, "k")?;
+        let split = app
+            .split
+            .as_ref()
+            .map(|s| s.other_cursor((app.row, app.col), 50));
+        assert_eq!(split, Some((34, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_paste_special_transposes_and_skips_blanks() -> Result<()> {
+        let data = vec![
+            vec!["a".into(), "".into()],
+            vec!["x".into(), "y".into()],
+            vec!["1".into(), "2".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        for c in "vly".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.message.as_deref(), Some("Copied 1x2 cell(s)"));
+        assert!(app.anchor.is_none());
+
+        // Transposed without blanks: "a" goes down the column, "" is skipped.
+        (app.row, app.col) = (1, 0);
+        for c in "Ptb".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.data[1..], [vec!["a", "y"], vec!["1", "2"]]);
+        assert_eq!(app.message.as_deref(), Some("Pasted 1 cell(s)"));
+
+        app.row = 2;
+        handle_key(&mut app, key(KeyCode::Char('p')))?;
+        assert_eq!(app.data[2], ["a", ""]);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[2], ["1", "2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ctrl_n_completes_from_the_column() -> Result<()> {
+        let data = ["fruit", "banana", "apple", "banana", "apricot", ""]
+            .iter()
+            .map(|v| vec![v.to_string()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        app.row = 5;
+        for c in "ea".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        handle_key(&mut app, ctrl('n'))?;
+        assert_eq!(app.editor_buf, "apple");
+        handle_key(&mut app, ctrl('n'))?;
+        assert_eq!(app.editor_buf, "apricot");
+        handle_key(&mut app, ctrl('n'))?;
+        assert_eq!(app.editor_buf, "a");
+        handle_key(&mut app, ctrl('p'))?;
+        handle_key(&mut app, key(KeyCode::Char('!')))?;
+        assert_eq!(app.editor_buf, "apricot!");
+        assert!(app.completion.is_none());
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.data[5], ["apricot!"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_in_header_mode_rejects_duplicates() -> Result<()> {
+        let data = vec![
+            vec!["id".into(), "name".into()],
+            vec!["2".into(), "b".into()],
+            vec!["1".into(), "a".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("rename name full name")?)?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.contains("header mode is off"))
+        );
+
+        run_command(&mut app, command::parse("header")?)?;
+        run_command(&mut app, command::parse("rename name full name")?)?;
+        assert_eq!(app.data[0], ["id", "full name"]);
+        run_command(&mut app, command::parse("rename 2 id")?)?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("\"id\" is already the name of column 1")
+        );
+
+        // `r` on the header row prompts with the current name.
+        handle_key(&mut app, key(KeyCode::Char('r')))?;
+        assert_eq!(app.prompt.as_ref().map(|p| p.buf.as_str()), Some("id"));
+        handle_key(&mut app, key(KeyCode::Char('x')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.data[0], ["idx", "full name"]);
+
+        // Sorting keeps the header in place.
+        run_command(&mut app, command::parse("sort 1")?)?;
+        assert_eq!(app.data[0][0], "idx");
+        assert_eq!(app.data[1][0], "1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_source_asks_for_a_local_path_on_write() -> Result<()> {
+        let mut app = App::new(PathBuf::from("/tmp/copy.csv"), vec![vec!["a".into()]]);
+        app.remote = Remote::parse("https://example.com/a.csv");
+        handle_key(&mut app, key(KeyCode::Char('w')))?;
+        assert!(matches!(
+            app.prompt.as_ref().map(|p| &p.kind),
+            Some(PromptKind::SavePath { then_quit: false })
+        ));
+        app.remote = Remote::parse("s3://bucket/a.csv");
+        assert!(!app.needs_save_path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_upload_keeps_the_buffer_dirty() -> Result<()> {
+        let path = crate::scratch::path("upload.csv")?;
+        let mut app = App::new(path.clone(), vec![vec!["a".into()]]);
+        app.remote = Remote::parse("https://example.com/a.csv");
+        app.dirty = true;
+        app.save()?;
+        assert!(app.dirty);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Saved locally, but the upload failed"))
+        );
+        assert_eq!(std::fs::read_to_string(&path)?, "a\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_protected_file_opens_read_only_and_saves_elsewhere() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_viewer_wp_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let (src, copy) = (dir.join("ro.csv"), dir.join("copy.csv"));
+        std::fs::write(&src, "a,b\n")?;
+        let mut perms = std::fs::metadata(&src)?.permissions();
+        perms.set_readonly(true);
+        std::fs::set_permissions(&src, perms)?;
+
+        let mut app = App::default();
+        app.open(src.clone())?;
+        assert!(app.read_only && app.write_protected);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.contains("write-protected"))
+        );
+
+        handle_key(&mut app, key(KeyCode::Char('w')))?;
+        assert!(matches!(
+            app.prompt.as_ref().map(|p| &p.kind),
+            Some(PromptKind::SavePath { then_quit: false })
+        ));
+        for c in copy.to_string_lossy().chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(!app.read_only && !app.write_protected);
+        assert_eq!(std::fs::read_to_string(&copy)?, "a,b\n");
+        drop(app);
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_only_sort_and_filter_until_commit() -> Result<()> {
+        let data = vec![
+            vec!["name".into(), "n".into()],
+            vec!["b".into(), "2".into()],
+            vec!["a".into(), "3".into()],
+            vec!["c".into(), "1".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        app.header = true;
+        app.config.view_only = true;
+        run_command(&mut app, command::parse("sort 2")?)?;
+        assert_eq!(app.data, data);
+        assert_eq!(
+            app.view.as_ref().map(|v| v.rows.clone()),
+            Some(vec![0, 3, 1, 2])
+        );
+
+        // j walks the view: header, then c, b, a.
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        assert_eq!(app.row, 3);
+        handle_key(&mut app, key(KeyCode::Char('G')))?;
+        assert_eq!(app.row, 2);
+
+        run_command(&mut app, command::parse("filter name ^[ab]$")?)?;
+        assert_eq!(
+            app.view.as_ref().map(|v| v.rows.clone()),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(
+            app.message.as_deref(),
+            Some("2 row(s) match; :filter shows all")
+        );
+
+        run_command(&mut app, command::parse("commit-view")?)?;
+        assert!(app.view.is_none());
+        assert_eq!(
+            app.data,
+            [["name", "n"], ["b", "2"], ["a", "3"]].map(|r| r.map(String::from).to_vec())
+        );
+        // One undo brings back the file order and the hidden row.
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_in_a_filtered_view_removes_only_shown_rows() -> Result<()> {
+        let data: Vec<Vec<String>> = ["x1", "y", "x2", "x3"]
+            .iter()
+            .map(|s| vec![s.to_string()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("filter 1 x")?)?;
+        assert_eq!(app.row, 0);
+        for c in "2dd".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.data, [vec!["y".to_string()], vec!["x3".to_string()]]);
+        assert_eq!(app.view.as_ref().map(|v| v.rows.clone()), Some(vec![1]));
+        assert_eq!(app.row, 1);
+        run_command(&mut app, command::parse("reset-view")?)?;
+        assert!(app.view.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tagged_rows_take_bulk_actions() -> Result<()> {
+        let data: Vec<Vec<String>> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|s| vec![s.to_string(), "x".to_string()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data.clone());
+        // t tags and moves down; 2j skips b and c.
+        handle_key(&mut app, key(KeyCode::Char('t')))?;
+        for c in "2jt".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.tags.rows().collect::<Vec<_>>(), [0, 3]);
+
+        run_command(&mut app, command::parse("ts/x/y/")?)?;
+        assert_eq!(app.data[0][1], "y");
+        assert_eq!(app.data[1][1], "x");
+        assert_eq!(app.data[3][1], "y");
+
+        run_command(&mut app, command::parse("tagged yank")?)?;
+        assert_eq!(app.clipboard.as_ref().map(Vec::len), Some(2));
+
+        let out = env::temp_dir().join(format!("tui_csv_viewer_tagged_{}.csv", std::process::id()));
+        run_command(
+            &mut app,
+            command::parse(&format!("tagged export {}", out.display()))?,
+        )?;
+        assert_eq!(std::fs::read_to_string(&out)?, "a,y\nd,y\n");
+
+        // Neither a bad path nor the open file itself is written to.
+        let bad = out.join("nested.csv");
+        run_command(
+            &mut app,
+            command::parse(&format!("tagged export {}", bad.display()))?,
+        )?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not export"))
+        );
+        app.file_path = out.clone();
+        run_command(
+            &mut app,
+            command::parse(&format!("tagged export {}", out.display()))?,
+        )?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("That's the file being edited; pick another name")
+        );
+        app.file_path = PathBuf::new();
+        let _ = std::fs::remove_file(&out);
+
+        run_command(&mut app, command::parse("tagged delete")?)?;
+        assert_eq!(app.data, data[1..3]);
+        assert!(app.tags.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tagged_edits_are_refused_read_only() -> Result<()> {
+        let data = vec![vec!["a".to_string(), "x".to_string()]];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        app.read_only = true;
+        app.tags.toggle(0);
+        for op in ["tagged empty", "tagged delete"] {
+            run_command(&mut app, command::parse(op)?)?;
+            assert_eq!(app.message.as_deref(), Some("File is open read-only"));
+        }
+        assert_eq!(app.data, data);
+        assert!(!app.dirty);
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_manager_reorders_hides_and_renames() -> Result<()> {
+        let data = vec![
+            vec!["id".into(), "name".into(), "qty".into()],
+            vec!["1".into(), "a".into(), "5".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        app.header = true;
+        app.col = 2;
+        for c in "gcKK".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        // qty is now first; hide name and rename id.
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char('r')))?;
+        handle_key(&mut app, key(KeyCode::Backspace))?;
+        handle_key(&mut app, key(KeyCode::Backspace))?;
+        for c in "key".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        // A Ctrl shortcut types nothing into the name.
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+        )?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char(' ')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.column_manager.is_none());
+        assert_eq!(app.data[0], ["qty", "key", "name"]);
+        assert_eq!(app.data[1], ["5", "1", "a"]);
+        assert_eq!(app.column_order(), [0, 1]);
+
+        // l stops at the last shown column.
+        app.col = 0;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        assert_eq!(app.col, 1);
+
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirmed_substitution_asks_cell_by_cell() -> Result<()> {
+        let data: Vec<Vec<String>> = ["a1", "a2", "a3"]
+            .iter()
+            .map(|s| vec![s.to_string()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data.clone());
+        run_command(&mut app, command::parse("%s/a/b/c")?)?;
+        assert_eq!(app.review.as_ref().map(|r| r.changes.len()), Some(3));
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!(app.row, 1);
+        handle_key(&mut app, key(KeyCode::Char('y')))?;
+        handle_key(&mut app, key(KeyCode::Char('q')))?;
+        assert!(app.review.is_none());
+        assert_eq!(
+            app.data,
+            [["a1"], ["b2"], ["a3"]].map(|r| r.map(String::from).to_vec())
+        );
+        assert_eq!(app.message.as_deref(), Some("Replaced in 1 of 3 cell(s)"));
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_screen_reader_mode_reads_out_moves() -> Result<()> {
+        let data = vec![
+            vec!["id".into(), "name".into()],
+            vec!["1".into(), "Alice".into()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("screen-reader")?)?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Screen-reader mode on. Row 1, column 1: id")
+        );
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Row 2, column 2 (name): Alice")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mangled_scientific_ids_warn_or_expand_on_open() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_sci_{}.csv", std::process::id()));
+        std::fs::write(&path, b"id,n\n1.23457E+11,2.5E-3\n")?;
+        let mut app = App::default();
+        app.open(path.clone())?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("1 cell(s) look"))
+        );
+        assert_eq!(app.data[1][0], "1.23457E+11");
+
+        app.config.expand_sci = true;
+        app.open(path.clone())?;
+        assert_eq!(app.data[1], ["123457000000", "2.5E-3"]);
+        assert!(app.dirty);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[1][0], "1.23457E+11");
+        let _ = std::fs::remove_file(app.journal_path());
+        drop(app);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_cells_are_normalized_on_save() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_null_{}.csv", std::process::id()));
+        let data = [["id", "v"], ["1", "NA"], ["2", ""], ["3", "NULL"]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let mut app = App::new(path.clone(), data);
+        app.header = true;
+        app.config.null_as = Some(r"\N".to_string());
+        app.save()?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "id,v\n1,\\N\n2,\\N\n3,\\N\n"
+        );
+        assert_eq!(
+            app.message.as_deref(),
+            Some(r#"Wrote 3 null cell(s) as "\N""#)
+        );
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_command_fills_the_cursor_column_as_one_step() -> Result<()> {
+        let data = [
+            ["name", "qty"],
+            ["a", "2"],
+            ["b", ""],
+            ["c", "NA"],
+            ["d", "6"],
+        ]
+        .iter()
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        app.col = 1;
+        run_command(&mut app, command::parse("fill mean")?)?;
+        assert_eq!(app.data[2][1], "4");
+        assert_eq!(app.data[3][1], "4");
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Filled 2 missing cell(s) in column 2")
+        );
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[3][1], "NA");
+        app.col = 0;
+        run_command(&mut app, command::parse("fill mean")?)?;
+        assert_eq!(app.message.as_deref(), Some("column 1 has no numbers"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_outliers_are_listed_and_stepped_through() -> Result<()> {
+        let data = ["5", "900", "6", "5", "4", "-800", "5", "6"]
+            .iter()
+            .map(|s| vec![s.to_string()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("outliers iqr")?)?;
+        let rows: Vec<_> = app
+            .findings
+            .as_ref()
+            .map(|f| f.items.iter().map(|i| i.row).collect())
+            .unwrap_or_default();
+        assert_eq!(rows, [1, 5]);
+        // ]f and [f work from the table, wrapping around at either end.
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        for expected in [1, 5, 1] {
+            handle_key(&mut app, key(KeyCode::Char(']')))?;
+            handle_key(&mut app, key(KeyCode::Char('f')))?;
+            assert_eq!(app.row, expected);
+        }
+        handle_key(&mut app, key(KeyCode::Char('[')))?;
+        handle_key(&mut app, key(KeyCode::Char('f')))?;
+        assert_eq!(app.row, 5);
+        assert_eq!(app.findings.as_ref().map(|f| f.selected), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_uses_the_sidecar_schema_or_an_inline_format() -> Result<()> {
+        let path =
+            env::temp_dir().join(format!("tui_csv_viewer_schema_{}.csv", std::process::id()));
+        let data = [
+            ["ip", "phone"],
+            ["10.0.0.1", "+81312345678"],
+            ["10.0.0", "0312345678"],
+        ]
+        .iter()
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect();
+        let mut app = App::new(path.clone(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("validate")?)?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("no schema at"))
+        );
+
+        std::fs::write(schema::schema_path_for(&path), "ip = ipv4\nphone = phone\n")?;
+        run_command(&mut app, command::parse("validate")?)?;
+        let cells: Vec<_> = app
+            .findings
+            .as_ref()
+            .map(|f| f.items.iter().map(|i| (i.row, i.col)).collect())
+            .unwrap_or_default();
+        assert_eq!(cells, [(2, 0), (2, 1)]);
+
+        run_command(&mut app, command::parse("validate 1 ip")?)?;
+        assert_eq!(app.findings.as_ref().map(|f| f.items.len()), Some(1));
+        let _ = std::fs::remove_file(schema::schema_path_for(&path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_issues_panel_lists_schema_problems_and_filters_by_rule() -> Result<()> {
+        let path =
+            env::temp_dir().join(format!("tui_csv_viewer_issues_{}.csv", std::process::id()));
+        std::fs::write(
+            schema::schema_path_for(&path),
+            "mail = email\nwarn site = url\n",
+        )?;
+        let data = [["mail", "site"], ["x", "y"], ["a@b.io", "z"]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let mut app = App::new(path.clone(), data);
+        app.header = true;
+        handle_key(&mut app, key(KeyCode::Char('g')))?;
+        handle_key(&mut app, key(KeyCode::Char('i')))?;
+        let panel = app.findings.as_ref().map(|f| f.title.clone());
+        assert_eq!(panel.as_deref(), Some("validate: 1 error(s), 2 warning(s)"));
+
+        handle_key(&mut app, key(KeyCode::Char('f')))?;
+        handle_key(&mut app, key(KeyCode::Char('f')))?;
+        handle_key(&mut app, key(KeyCode::Down))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!((app.row, app.col), (2, 1));
+        // ]f still works while the panel has focus.
+        handle_key(&mut app, key(KeyCode::Char(']')))?;
+        handle_key(&mut app, key(KeyCode::Char('f')))?;
+        assert_eq!((app.row, app.col), (1, 1));
+
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        handle_key(&mut app, key(KeyCode::Char('g')))?;
+        handle_key(&mut app, key(KeyCode::Char('i')))?;
+        assert!(app.findings.is_none());
+        let _ = std::fs::remove_file(schema::schema_path_for(&path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_writes_the_findings_as_json() -> Result<()> {
+        let out =
+            env::temp_dir().join(format!("tui_csv_viewer_report_{}.json", std::process::id()));
+        let data = [["id"], ["1"], ["1"]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, Command::Unique(0))?;
+        run_command(&mut app, Command::Report(out.clone()))?;
+        let json = std::fs::read_to_string(&out)?;
+        assert_eq!(json.matches("\"rule\": \"unique\"").count(), 2);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Wrote 2 finding(s)"))
+        );
+        // Not over the file being edited.
+        app.file_path = out.clone();
+        run_command(&mut app, Command::Report(out.clone()))?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("That's the file being edited; pick another name")
+        );
+        assert_eq!(std::fs::read_to_string(&out)?, json);
+        let _ = std::fs::remove_file(&out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_writes_column_stats_as_csv() -> Result<()> {
+        let out =
+            env::temp_dir().join(format!("tui_csv_viewer_profile_{}.csv", std::process::id()));
+        let data = [["id", "name"], ["1", "a"], ["2", ""]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, Command::Profile(out.clone()))?;
+        let csv = std::fs::read_to_string(&out)?;
+        assert!(csv.contains("\n2,name,text,1,1,0.5,1,a,a\n"));
+        assert_eq!(
+            app.message.as_deref(),
+            Some(format!("Wrote a profile of 2 column(s) to {}", out.display()).as_str())
+        );
+        // Not over the file being edited.
+        let written = std::fs::read_to_string(&out)?;
+        app.file_path = out.clone();
+        run_command(&mut app, Command::Profile(out.clone()))?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("That's the file being edited; pick another name")
+        );
+        assert_eq!(std::fs::read_to_string(&out)?, written);
+        let _ = std::fs::remove_file(&out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_mode_moves_right_then_wraps_to_the_next_row() -> Result<()> {
+        let data = vec![vec![String::new(); 2]; 2];
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("entry")?)?;
+        assert_eq!(app.config.entry, Advance::Right);
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        for v in ["a", "b", "c", "d"] {
+            handle_key(&mut app, key(KeyCode::Char(v.chars().next().unwrap())))?;
+            handle_key(&mut app, key(KeyCode::Enter))?;
+        }
+        assert_eq!(app.data, [["a", "b"], ["c", "d"]]);
+        assert!(!app.editing);
+        assert!(app.message.as_deref().is_some_and(|m| m.starts_with("End")));
+
+        run_command(&mut app, command::parse("entry down")?)?;
+        (app.row, app.col) = (0, 1);
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.editing);
+        assert_eq!((app.row, app.col, app.editor_buf.as_str()), (1, 1, "d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tab_commits_and_edits_the_next_or_previous_cell() -> Result<()> {
+        let data = vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["c".to_string()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        handle_key(&mut app, key(KeyCode::Char('e')))?;
+        handle_key(&mut app, key(KeyCode::Char('1')))?;
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        assert_eq!((app.col, app.editor_buf.as_str()), (1, "b"));
+        handle_key(&mut app, key(KeyCode::Tab))?;
+        assert_eq!((app.row, app.col, app.editor_buf.as_str()), (1, 0, "c"));
+        handle_key(&mut app, key(KeyCode::Char('2')))?;
+        handle_key(&mut app, key(KeyCode::BackTab))?;
+        assert_eq!((app.row, app.col), (0, 1));
+        assert!(app.editing);
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert_eq!(app.data, [vec!["a1", "b"], vec!["c2"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot_repeats_the_last_change_at_the_cursor() -> Result<()> {
+        let data = (1..=5)
+            .map(|i| vec![i.to_string(), String::new()])
+            .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        handle_key(&mut app, key(KeyCode::Char('.')))?;
+        assert_eq!(app.message.as_deref(), Some("Nothing to repeat yet"));
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        handle_key(&mut app, key(KeyCode::Char('x')))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        app.row = 2;
+        handle_key(&mut app, key(KeyCode::Char('.')))?;
+        assert_eq!(app.data[2], ["3", "x"]);
+
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('2')))?;
+        handle_key(&mut app, key(KeyCode::Char('.')))?;
+        let firsts: Vec<&str> = app.data.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(firsts, ["1", "2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_paste_over_filled_cells_waits_for_confirmation() -> Result<()> {
+        let data = vec![vec!["a".to_string(), String::new()], vec!["b".to_string()]];
+        let mut app = App::new(PathBuf::new(), data);
+        app.config.confirm_paste = true;
+        handle_key(&mut app, key(KeyCode::Char('y')))?;
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('p')))?;
+        assert_eq!(app.data[0], ["a", "a"]);
+        app.col = 0;
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('p')))?;
+        assert!(matches!(app.pending_paste, Some((_, 1))));
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!(
+            (app.data[1][0].as_str(), app.message.as_deref()),
+            ("b", Some("Paste cancelled"))
+        );
+        handle_key(&mut app, key(KeyCode::Char('p')))?;
+        handle_key(&mut app, key(KeyCode::Char('y')))?;
+        assert_eq!(app.data[1][0], "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearing_cells_keeps_rows_but_deleting_removes_them() -> Result<()> {
+        let data = vec![
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec!["d".to_string(), "e".to_string(), "f".to_string()],
+            vec!["g".to_string(), "h".to_string(), "i".to_string()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        handle_key(&mut app, key(KeyCode::Char('v')))?;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        handle_key(&mut app, key(KeyCode::Char('x')))?;
+        assert_eq!(app.data[0], ["", "", "c"]);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Cleared 2 cell(s); rows and columns are kept")
+        );
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        assert_eq!(app.data[1], ["d", "f"]);
+        assert_eq!(app.message.as_deref(), Some("Deleted 1 column(s)"));
+
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('v')))?;
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        assert_eq!(app.data, [["", "c"]]);
+        assert_eq!(app.message.as_deref(), Some("Deleted 2 row(s)"));
+
+        app.tags.toggle(0);
+        app.bulk(tags::Bulk::Empty)?;
+        assert_eq!(app.data, [["", ""]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dup_copies_the_column_beside_it_with_a_fresh_header() -> Result<()> {
+        let data = vec![
+            vec!["id".to_string(), "id_copy".to_string()],
+            vec!["1".to_string()],
+        ];
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, Command::Duplicate)?;
+        assert_eq!(app.data[0], ["id", "id_copy2", "id_copy"]);
+        assert_eq!(app.data[1], ["1", "1"]);
+        assert_eq!(app.col, 1);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[0], ["id", "id_copy"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_goto_moves_to_a1_cells_and_selects_ranges() -> Result<()> {
+        let data = vec![vec![String::new(); 3]; 4];
+        let mut app = App::new(PathBuf::new(), data);
+        run_command(&mut app, command::parse("goto C2")?)?;
+        assert_eq!((app.row, app.col, app.anchor), (1, 2, None));
+        run_command(&mut app, command::parse("goto A1:B4")?)?;
+        assert_eq!(app.selection(), Some((0, 0, 3, 1)));
+        run_command(&mut app, command::parse("goto D1")?)?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("D1 is outside the table (4 row(s), 3 column(s))")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_ranges_persist_and_can_be_selected_and_exported() -> Result<()> {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("tui_csv_viewer_names_{}.csv", std::process::id()));
+        let out = dir.join(format!(
+            "tui_csv_viewer_names_out_{}.csv",
+            std::process::id()
+        ));
+        std::fs::write(&path, "q,n\na,1\nb,2\nc,3\n")?;
+        let mut app = App::default();
+        app.open(path.clone())?;
+        run_command(&mut app, command::parse("goto A2:B3")?)?;
+        run_command(&mut app, command::parse("name range first")?)?;
+        assert_eq!(app.message.as_deref(), Some("Named A2:B3 first"));
+        assert_eq!(
+            std::fs::read_to_string(names::names_path_for(&path))?,
+            "first = A2:B3\n"
+        );
+
+        let mut again = App::default();
+        again.open(path.clone())?;
+        run_command(&mut again, command::parse("goto first")?)?;
+        assert_eq!(again.selection(), Some((1, 0, 2, 1)));
+        again.header = true;
+        let export = format!("name export first {}", out.display());
+        run_command(&mut again, command::parse(&export)?)?;
+        assert_eq!(std::fs::read_to_string(&out)?, "q,n\na,1\nb,2\n");
+        let over = format!("name export first {}", path.display());
+        run_command(&mut again, command::parse(&over)?)?;
+        assert_eq!(
+            again.message.as_deref(),
+            Some("That's the file being edited; pick another name")
+        );
+        assert_eq!(std::fs::read_to_string(&path)?, "q,n\na,1\nb,2\nc,3\n");
+        run_command(&mut again, command::parse("name delete first")?)?;
+        assert!(!names::names_path_for(&path).exists());
+        let _ = std::fs::remove_file(&out);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_comma_sorts_numbers_and_can_normalize_on_save() -> Result<()> {
+        let path =
+            env::temp_dir().join(format!("tui_csv_viewer_decimal_{}.csv", std::process::id()));
+        let data = [
+            ["item", "price"],
+            ["a", "1.234,5"],
+            ["b", "99,9"],
+            ["c", "5"],
+        ]
+        .iter()
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect();
+        let mut app = App::new(path.clone(), data);
+        app.header = true;
+        app.config.decimal = viz::Decimal::Comma;
+        run_command(&mut app, command::parse("sort 2")?)?;
+        let items: Vec<_> = app.data.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(items, ["item", "c", "b", "a"]);
+        app.config.normalize_decimal = true;
+        app.save()?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "item,price\nc,5\nb,99.9\na,1234.5\n"
+        );
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_currency_cleans_the_column_and_lists_what_it_left() -> Result<()> {
+        let data = [
+            ["item", "price"],
+            ["a", "$1,200.00"],
+            ["b", "TBD"],
+            ["c", "€5"],
+        ]
+        .iter()
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        app.col = 1;
+        run_command(&mut app, command::parse("strip-currency")?)?;
+        let prices: Vec<_> = app.data.iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(prices, ["price", "1200.00", "TBD", "5"]);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Cleaned 2 cell(s) in column 2; 1 couldn't be read as numbers")
+        );
+        assert_eq!(app.findings.as_ref().map(|f| f.items.len()), Some(1));
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[1][1], "$1,200.00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_inserted_rows_take_the_schema_defaults() -> Result<()> {
+        let path = env::temp_dir().join(format!(
+            "tui_csv_viewer_defaults_{}.csv",
+            std::process::id()
+        ));
+        std::fs::write(
+            schema::schema_path_for(&path),
+            "default id = {next}\ndefault status = open\n",
+        )?;
+        let data = [["id", "status"], ["4", "done"]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let mut app = App::new(path.clone(), data);
+        app.header = true;
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('o')))?;
+        assert_eq!(app.data[2], ["5", "open"]);
+        assert_eq!(app.row, 2);
+        handle_key(&mut app, key(KeyCode::Char('g')))?;
+        handle_key(&mut app, key(KeyCode::Char('g')))?;
+        handle_key(&mut app, key(KeyCode::Char('O')))?;
+        // Above the header means just below it.
+        assert_eq!((app.row, app.data[1][0].as_str()), (1, "6"));
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data.len(), 3);
+        let _ = std::fs::remove_file(schema::schema_path_for(&path));
+        let _ = std::fs::remove_file(app.journal_path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_extend_adds_rows_and_columns_at_the_edges() -> Result<()> {
+        let data = vec![vec!["a".into(), "b".into()], vec!["c".into(), "d".into()]];
+        let mut app = App::new(PathBuf::new(), data.clone());
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        assert_eq!((app.col, app.max_cols()), (1, 2));
+        run_command(&mut app, command::parse("auto-extend on")?)?;
+        handle_key(&mut app, key(KeyCode::Char('l')))?;
+        assert_eq!((app.col, app.data[0].len()), (2, 3));
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        assert_eq!((app.row, app.col), (2, 2));
+        assert_eq!(app.data[2], ["", "", ""]);
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn perf_command_toggles_the_overlay() -> Result<()> {
+        let mut app = App::new(PathBuf::new(), vec![vec!["a".into()]]);
+        run_command(&mut app, command::parse("perf")?)?;
+        assert_eq!(app.perf, Some(Perf::default()));
+        run_command(&mut app, command::parse("perf on")?)?;
+        assert!(app.perf.is_some());
+        run_command(&mut app, command::parse("perf")?)?;
+        assert_eq!(app.perf, None);
+        Ok(())
+    }
+
+    #[test]
+    fn pipes_rows_and_runs_load_and_save_hooks() -> Result<()> {
+        let grid = |rows: &[&[&str]]| -> Vec<Vec<String>> {
+            rows.iter()
+                .map(|r| r.iter().map(|s| s.to_string()).collect())
+                .collect()
+        };
+        let mut app = App::new(
+            PathBuf::new(),
+            grid(&[&["name", "n"], &["b", "2"], &["a", "1"]]),
+        );
+        app.header = true;
+        app.row = 1;
+        app.anchor = Some((2, 1));
+        run_command(&mut app, command::parse("pipe tr ab AB")?)?;
+        assert_eq!(app.data, grid(&[&["name", "n"], &["B", "2"], &["A", "1"]]));
+        run_command(&mut app, command::parse("pipe sed 1d")?)?;
+        assert_eq!(app.data, grid(&[&["B", "2"], &["A", "1"]]));
+        apply_action(&mut app, input::Action::Undo, None)?;
+        assert_eq!(app.data.len(), 3);
+        run_command(&mut app, command::parse("pipe exit 3")?)?;
+        assert_eq!(app.data.len(), 3);
+        assert!(app.message.as_deref().is_some_and(|m| m.contains("failed")));
+
+        let path = env::temp_dir().join(format!("tui_csv_hooks_{}.csv", std::process::id()));
+        std::fs::write(&path, "name,n\nb,2\na,1\n")?;
+        let mut app = App::default();
+        app.config.post_load = Some("sort".into());
+        app.open(path.clone())?;
+        assert_eq!(app.data[0], ["a", "1"]);
+        assert!(!app.dirty);
+        app.config.pre_save = Some("false".into());
+        app.set_cell(0, 0, "z".into());
+        app.save()?;
+        assert!(app.dirty);
+        assert_eq!(std::fs::read_to_string(&path)?, "name,n\nb,2\na,1\n");
+        app.config.pre_save = Some("tr z Z".into());
+        app.save()?;
+        assert!(!app.dirty);
+        assert_eq!(std::fs::read_to_string(&path)?, "Z,1\nb,2\nname,n\n");
+        app.lock = None;
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scripts_run_from_the_config_folder_with_the_cursor_in_env() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = env::temp_dir().join(format!("tui_csv_scripts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let script = dir.join("stamp");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nsed \"s/$/,$1 $CSV_ROW $CSV_SELECTION/\"\n",
+        )?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+        std::fs::write(dir.join("notes.txt"), "not a script")?;
+        let mut app = App::new(PathBuf::new(), vec![vec!["a".into()], vec!["b".into()]]);
+        app.scripts = Some(dir.clone());
+        run_command(&mut app, command::parse("script")?)?;
+        assert_eq!(app.message.as_deref(), Some("Scripts: stamp"));
+        app.row = 1;
+        app.anchor = Some((1, 0));
+        run_command(&mut app, command::parse("script stamp x")?)?;
+        assert_eq!(app.data[0], ["a"]);
+        assert_eq!(app.data[1], ["b", "x 2 A2"]);
+        run_command(&mut app, command::parse("script ../stamp")?)?;
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.contains("bad script name"))
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_keeps_edits_unless_forced() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_rl_{}.csv", std::process::id()));
+        std::fs::write(&path, b"a,b\n1,2\n")?;
+        let mut app = App::new(path.clone(), load_csv(&path)?);
+        app.row = 1;
+        app.set_cell(1, 0, "x".into());
+        std::fs::write(&path, b"a,b\n1,2\n3,4\n")?;
+
+        run_command(&mut app, Command::Reload(false))?;
+        assert_eq!(app.data[1][0], "x");
+        run_command(&mut app, Command::Reload(true))?;
+        assert_eq!(app.data.len(), 3);
+        assert!(!app.dirty);
+        assert_eq!(app.row, 1);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_keeps_the_buffer_and_another_sessions_journal() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_rl2_{}.csv", std::process::id()));
+        std::fs::write(&path, b"a,b\n")?;
+        let mut app = App::new(path.clone(), load_csv(&path)?);
+
+        // Another live session (pid 1) holds the lock and has a journal.
+        let lock = lock::lock_path_for(&path);
+        std::fs::write(&lock, b"1\n")?;
+        std::fs::write(app.journal_path(), b"pending\n")?;
+        run_command(&mut app, Command::Reload(true))?;
+        assert!(app.read_only);
+        assert!(app.journal_path().exists());
+        let _ = std::fs::remove_file(app.journal_path());
+        std::fs::remove_file(&lock)?;
+
+        // An unreadable file leaves the current buffer in place.
+        std::fs::remove_file(&path)?;
+        std::fs::create_dir(&path)?;
+        assert!(!run_command(&mut app, Command::Reload(true))?);
+        assert_eq!(app.data, [["a", "b"]]);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not reload"))
+        );
+        std::fs::remove_dir(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_a_failed_open_leaves_the_open_file_and_its_lock_alone() -> Result<()> {
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let a = dir.join(format!("tui_csv_viewer_keep_a_{id}.csv"));
+        let b = dir.join(format!("tui_csv_viewer_keep_b_{id}.csv"));
+        std::fs::write(&a, b"x,y\n")?;
+        std::fs::write(&b, b"1,2\n")?;
+        let mut app = App::new(PathBuf::new(), Vec::new());
+        app.open(a.clone())?;
+        app.set_cell(0, 0, "edited".into());
+
+        // A broken sidecar of the new file.
+        let names = names::names_path_for(&b);
+        std::fs::write(&names, b"not a name line\n")?;
+        assert!(app.open(b.clone()).is_err());
+        std::fs::remove_file(&names)?;
+        // A journal that can't be read (a link to itself), after the new
+        // lock was taken.
+        #[cfg(unix)]
+        {
+            let journal = journal::journal_path_for(&b);
+            std::os::unix::fs::symlink(&journal, &journal)?;
+            assert!(app.open(b.clone()).is_err());
+            std::fs::remove_file(&journal)?;
+        }
+
+        assert_eq!(app.file_path, a);
+        assert_eq!(app.data, [["edited", "y"]]);
+        assert!(app.dirty && !app.read_only && app.lock.is_some());
+        assert!(lock::lock_path_for(&a).exists());
+        assert!(!lock::lock_path_for(&b).exists());
+
+        // Reopening the same file keeps its lock rather than tripping on it.
+        app.open(a.clone())?;
+        assert!(!app.read_only && app.lock.is_some());
+        app.lock = None;
+        for p in [&a, &b] {
+            std::fs::remove_file(p)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompt_recalls_history_with_up_and_ctrl_r() -> Result<()> {
+        let mut app = App::new(PathBuf::new(), vec![vec!["a".into(), "b".into()]]);
+        let typed = |app: &mut App, text: &str| -> Result<()> {
+            handle_key(app, key(KeyCode::Char(':')))?;
+            for c in text.chars() {
+                handle_key(app, key(KeyCode::Char(c)))?;
+            }
+            handle_key(app, key(KeyCode::Enter))?;
+            Ok(())
+        };
+        typed(&mut app, "goto B1")?;
+        typed(&mut app, "goto A1")?;
+        typed(&mut app, "set sep ;")?;
+        let buf = |app: &App| app.prompt.as_ref().map(|p| p.buf.clone());
+
+        handle_key(&mut app, key(KeyCode::Char(':')))?;
+        handle_key(&mut app, key(KeyCode::Up))?;
+        assert_eq!(buf(&app).as_deref(), Some("set sep ;"));
+        handle_key(&mut app, key(KeyCode::Up))?;
+        handle_key(&mut app, key(KeyCode::Up))?;
+        assert_eq!(buf(&app).as_deref(), Some("goto B1"));
+        handle_key(&mut app, key(KeyCode::Down))?;
+        handle_key(&mut app, key(KeyCode::Down))?;
+        handle_key(&mut app, key(KeyCode::Down))?;
+        assert_eq!(buf(&app).as_deref(), Some(""));
+
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        handle_key(&mut app, ctrl_r)?;
+        handle_key(&mut app, key(KeyCode::Char('g')))?;
+        assert_eq!(buf(&app).as_deref(), Some("goto A1"));
+        handle_key(&mut app, ctrl_r)?;
+        assert_eq!(buf(&app).as_deref(), Some("goto B1"));
+        handle_key(&mut app, ctrl_r)?;
+        assert!(
+            app.prompt
+                .as_ref()
+                .is_some_and(|p| p.label().starts_with("(failed"))
+        );
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.col, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_paste_goes_into_the_editor_and_backspace_takes_whole_graphemes() -> Result<()> {
+        let mut app = App::new(PathBuf::new(), vec![vec!["x".to_string()]]);
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        handle_key(&mut app, key(KeyCode::Char('c')))?;
+        handle_paste(&mut app, "caf\u{e9} \u{1f44d}\u{1f3fd}\r\nline 2");
+        assert_eq!(app.editor_buf, "caf\u{e9} \u{1f44d}\u{1f3fd}\nline 2");
+        for _ in 0..8 {
+            handle_key(&mut app, key(KeyCode::Backspace))?;
+        }
+        assert_eq!(app.editor_buf, "caf\u{e9} ");
+        let altgr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        handle_key(&mut app, KeyEvent::new(KeyCode::Char('\u{20ac}'), altgr))?;
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.data[0][0], "caf\u{e9} \u{20ac}");
+
+        handle_key(&mut app, key(KeyCode::Char(':')))?;
+        handle_paste(&mut app, "goto\nA1");
+        assert_eq!(app.prompt.as_ref().map(|p| p.buf.as_str()), Some("goto A1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_paste_in_the_table_asks_then_writes_a_block() -> Result<()> {
+        let mut app = App::new(PathBuf::new(), vec![vec!["x".to_string()]]);
+        handle_paste(&mut app, "a\tb\n\"two\nlines\"\tc\n");
+        assert_eq!(app.pending_block.as_ref().map(Vec::len), Some(2));
+        handle_key(&mut app, key(KeyCode::Char('n')))?;
+        assert_eq!(app.data, [["x"]]);
+        handle_paste(&mut app, "1,2\n3,4");
+        handle_key(&mut app, key(KeyCode::Char('y')))?;
+        assert_eq!(app.data, [["1", "2"], ["3", "4"]]);
+        assert_eq!(app.message.as_deref(), Some("Pasted 4 cell(s)"));
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[0][0], "x");
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_presets_bring_back_columns_sort_and_filter() -> Result<()> {
+        let data = [["b", "1", "x"], ["a", "2", "y"], ["c", "3", "x"]]
+            .map(|r| r.map(String::from).to_vec())
+            .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.config.view_only = true;
+        app.columns.hidden.toggle(1);
+        for cmd in ["sort 1", "filter 3 x", "view save qa"] {
+            run_command(&mut app, command::parse(cmd)?)?;
+        }
+        app.columns = columns::Layout::default();
+        run_command(&mut app, command::parse("reset-view")?)?;
+        assert_eq!(app.visible_rows(), 3);
+
+        run_command(&mut app, command::parse("view qa")?)?;
+        assert_eq!(app.message.as_deref(), Some("View qa"));
+        assert_eq!(app.column_order(), [0, 2]);
+        assert_eq!(app.view.as_ref().map(|v| v.rows.clone()), Some(vec![0, 2]));
+        run_command(&mut app, command::parse("view nope")?)?;
+        assert_eq!(app.message.as_deref(), Some("No view named nope"));
+        run_command(&mut app, command::parse("view")?)?;
+        assert_eq!(app.message.as_deref(), Some("Views: qa"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_star_and_hash_filter_by_the_cell_under_the_cursor() -> Result<()> {
+        let data = [["1", "OK"], ["2", "FAIL"], ["3", "OK"], ["4", "fail"]]
+            .map(|r| r.map(String::from).to_vec())
+            .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.col = 1;
+        handle_key(&mut app, key(KeyCode::Char('*')))?;
+        assert_eq!(app.view.as_ref().map(|v| v.rows.clone()), Some(vec![0, 2]));
+        assert_eq!(
+            app.message.as_deref(),
+            Some("col 2 == OK: 2 row(s); :filter shows all")
+        );
+
+        run_command(&mut app, command::parse("filter")?)?;
+        app.row = 1;
+        handle_key(&mut app, key(KeyCode::Char('#')))?;
+        // Exact and case-sensitive, unlike a `/` query.
+        assert_eq!(
+            app.view.as_ref().map(|v| v.rows.clone()),
+            Some(vec![0, 2, 3])
+        );
+        assert_eq!(app.row, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_expressions_combine_columns() -> Result<()> {
+        let data = [
+            ["id", "status", "latency"],
+            ["1", "FAILED", "750"],
+            ["2", "FAILED", "90"],
+            ["3", "OK", "900"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        let slow = r#"filter col("status")=="FAILED" && num("latency")>500"#;
+        run_command(&mut app, command::parse(slow)?)?;
+        assert_eq!(app.view.as_ref().map(|v| v.rows.clone()), Some(vec![0, 1]));
+        assert_eq!(
+            app.message.as_deref(),
+            Some("1 row(s) match; :filter shows all")
+        );
+        run_command(
+            &mut app,
+            command::parse("filter latency < 100 or not $2 == \"FAILED\"")?,
+        )?;
+        assert_eq!(
+            app.view.as_ref().map(|v| v.rows.clone()),
+            Some(vec![0, 2, 3])
+        );
+        run_command(&mut app, command::parse("filter num(speed) > 1")?)?;
+        assert_eq!(app.message.as_deref(), Some("no column named \"speed\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_saved_filters_toggle_from_the_list_and_show_by_name() -> Result<()> {
+        let data = [
+            ["id", "status", "ms"],
+            ["1", "FAILED", "750"],
+            ["2", "FAILED", "90"],
+            ["3", "OK", "900"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("filters save failed")?)?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("No filter to save; :filter or * sets one")
+        );
+        (app.row, app.col) = (1, 1);
+        handle_key(&mut app, key(KeyCode::Char('*')))?;
+        run_command(&mut app, command::parse("filters save failed")?)?;
+        run_command(&mut app, command::parse("filter ms > 500")?)?;
+        run_command(&mut app, command::parse("filters save slow")?)?;
+        let describe = |app: &App| app.view.as_ref().map(|v| v.describe(app.data.len()));
+        assert_eq!(describe(&app).as_deref(), Some("failed & slow: 2/4 rows"));
+
+        handle_key(&mut app, key(KeyCode::Char('F')))?;
+        assert!(app.filter_panel.is_some());
+        handle_key(&mut app, key(KeyCode::Char(' ')))?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Filter failed off: 2 row(s) shown")
+        );
+        assert_eq!(describe(&app).as_deref(), Some("slow: 3/4 rows"));
+        handle_key(&mut app, key(KeyCode::Char('j')))?;
+        handle_key(&mut app, key(KeyCode::Char('d')))?;
+        assert_eq!(app.saved_filters.names(), ["failed"]);
+        assert!(app.view.is_none());
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        run_command(&mut app, command::parse("filters failed")?)?;
+        assert_eq!(
+            app.view.as_ref().map(|v| v.rows.clone()),
+            Some(vec![0, 1, 2])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_a_column_and_collapse_with_za() -> Result<()> {
+        let data = [
+            ["level", "msg"],
+            ["info", "started"],
+            ["info", "ready"],
+            ["warn", "slow disk"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("group level")?)?;
+        assert_eq!(
+            app.message.as_deref(),
+            Some("2 group(s) by column A; za collapses or expands one")
+        );
+        app.row = 2;
+        handle_key(&mut app, key(KeyCode::Char('z')))?;
+        handle_key(&mut app, key(KeyCode::Char('a')))?;
+        assert_eq!(app.message.as_deref(), Some("Collapsed info (2 row(s))"));
+        assert_eq!((app.row, app.visible_rows()), (1, 3));
+        run_command(&mut app, command::parse("commit-view")?)?;
+        assert_eq!(app.data.len(), 4);
+        run_command(&mut app, command::parse("group expand")?)?;
+        assert_eq!(app.visible_rows(), 4);
+        run_command(&mut app, command::parse("group off")?)?;
+        assert!(app.view.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_card_moves_by_field_and_edits_in_place() -> Result<()> {
+        let data = [
+            ["id", "name", "city"],
+            ["1", "Alice", "Tokyo"],
+            ["2", "Bob", "Osaka"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        app.row = 1;
+        for c in "grjj".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert!(app.record_card);
+        assert_eq!(app.col, 2);
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert!(app.editing);
+        for _ in 0..5 {
+            handle_key(&mut app, key(KeyCode::Backspace))?;
+        }
+        for c in "Kyoto".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.data[1][2], "Kyoto");
+        assert!(app.record_card && !app.editing);
+        handle_key(&mut app, key(KeyCode::Char('J')))?;
+        assert_eq!((app.row, app.col), (2, 2));
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert!(!app.record_card);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_rows_lists_the_fields_that_differ() -> Result<()> {
+        let data = [
+            ["id", "vendor", "amount"],
+            ["1", "ACME", "10"],
+            ["2", "ACME ", "10"],
+            ["3", "Other", "12"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        app.row = 1;
+        for c in "gd".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.row_compare, None);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Tag two rows"))
+        );
+        handle_key(&mut app, key(KeyCode::Char('t')))?;
+        app.row = 2;
+        for c in "gdd".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        let cmp = app.row_compare.unwrap_or_default();
+        assert_eq!(cmp.rows, (1, 2));
+        assert!(cmp.only_diff);
+        assert_eq!(app.row_differences(1, 2), [0, 1]);
+        handle_key(&mut app, key(KeyCode::Esc))?;
+        assert_eq!(app.row_compare, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_dialog_writes_the_shown_rows_and_remembers_the_settings() -> Result<()> {
+        let out =
+            env::temp_dir().join(format!("tui_csv_viewer_export_{}.json", std::process::id()));
+        let data = [["id", "status"], ["1", "OK"], ["2", "FAILED"], ["3", "OK"]]
+            .map(|r| r.map(String::from).to_vec())
+            .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        run_command(&mut app, command::parse("filter status OK")?)?;
+        run_command(&mut app, command::parse("export")?)?;
+        // Format: CSV -> TSV -> JSON; then down to the rows and on to "shown".
+        for c in "lljjjjl".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        if let Some(d) = app.export_dialog.as_mut() {
+            d.dest = out.display().to_string();
+        }
+        handle_key(&mut app, key(KeyCode::Enter))?;
+        assert_eq!(app.export_dialog, None);
+        let json = std::fs::read_to_string(&out)?;
+        assert_eq!(
+            json,
+            "[\n  {\"id\": \"1\", \"status\": \"OK\"},\n  {\"id\": \"3\", \"status\": \"OK\"}\n]\n"
+        );
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Exported 2 row(s) as JSON"))
+        );
+        for c in "gx".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        let d = app.export_dialog.clone();
+        assert_eq!(
+            d.map(|d| (d.settings.format, d.settings.scope)),
+            Some((export::Format::Json, export::Scope::Shown))
+        );
+        let _ = std::fs::remove_file(&out);
+
+        // A remembered scope that no longer applies is reported, not fatal.
+        app.file_path = out.with_extension("csv");
+        app.export_settings.scope = export::Scope::Selection;
+        assert!(!run_command(&mut app, command::parse("export json")?)?);
+        assert!(!out.exists());
+        assert_eq!(app.message.as_deref(), Some("No selection; v starts one"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_masks_come_from_the_schema_and_the_command_and_follow_their_column() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_viewer_mask_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("cards.csv");
+        std::fs::write(&path, "name,card\nAlice,4111111111111111\n")?;
+        std::fs::write(schema::schema_path_for(&path), "mask card = last4\n")?;
+        let mut app = App {
+            config: config::Config {
+                header: Some(true),
+                ..config::Config::default()
+            },
+            ..App::default()
+        };
+        app.open(path.clone())?;
+        assert_eq!(app.columns.mask(1), Some(Mask::Last4));
+        run_command(&mut app, command::parse("mask")?)?;
+        assert_eq!(app.message.as_deref(), Some("Column A is masked"));
+        let mut settings = export::Settings::default();
+        assert_eq!(
+            app.export_table(&settings)?.0[1],
+            ["Alice", "4111111111111111"]
+        );
+        settings.masked = true;
+        let (table, _) = app.export_table(&settings)?;
+        assert_eq!(table, [["name", "card"], ["•••", "•••1111"]]);
+        for c in "dc".chars() {
+            handle_key(&mut app, key(KeyCode::Char(c)))?;
+        }
+        assert_eq!(app.columns.mask(0), Some(Mask::Last4));
+        run_command(&mut app, command::parse("mask off")?)?;
+        assert_eq!(app.columns.mask(0), None);
+        drop(app);
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymize_replaces_a_column_consistently_and_writes_the_mapping() -> Result<()> {
+        let map = env::temp_dir().join(format!(
+            "tui_csv_viewer_pseudonyms_{}.csv",
+            std::process::id()
+        ));
+        let data = [
+            ["customer", "total"],
+            ["Alice", "10"],
+            ["Bob", "5"],
+            ["Alice", "7"],
+        ]
+        .map(|r| r.map(String::from).to_vec())
+        .to_vec();
+        let mut app = App::new(PathBuf::new(), data);
+        app.header = true;
+        let line = format!("anonymize seq > {}", map.display());
+        run_command(&mut app, command::parse(&line)?)?;
+        let column: Vec<&str> = app.data.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(
+            column,
+            ["customer", "customer_01", "customer_02", "customer_01"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&map)?,
+            "original,replacement\nAlice,customer_01\nBob,customer_02\n"
+        );
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        assert_eq!(app.data[1][0], "Alice");
+
+        run_command(&mut app, command::parse("anonymize hash pepper")?)?;
+        assert_eq!(app.data[1][0], app.data[3][0]);
+        assert_ne!(app.data[1][0], app.data[2][0]);
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Replaced 3 cell(s), 2 distinct value(s)"))
+        );
+        let _ = std::fs::remove_file(&map);
+
+        // A mapping that can't be written, or would land on the file being
+        // edited, is reported and changes nothing.
+        handle_key(&mut app, key(KeyCode::Char('u')))?;
+        let unwritable = map.join("no-such-dir").join("map.csv");
+        run_command(
+            &mut app,
+            command::parse(&format!("anonymize seq > {}", unwritable.display()))?,
+        )?;
+        assert_eq!(app.data[1][0], "Alice");
+        assert!(
+            app.message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Could not write the mapping"))
+        );
+        app.file_path = map.clone();
+        std::fs::write(&map, "customer,total\n")?;
+        run_command(
+            &mut app,
+            command::parse(&format!("anonymize seq > {}", map.display()))?,
+        )?;
+        assert_eq!(std::fs::read_to_string(&map)?, "customer,total\n");
+        assert_eq!(app.data[1][0], "Alice");
+        let _ = std::fs::remove_file(&map);
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::escape;
+
+/// Cells longer than this many characters get the long-cell badge, well
+/// before `max_cell_len` clips them.
+pub const LONG: usize = 200;
+
+/// Something about a cell's content worth seeing at a glance, because it
+/// tends to get mangled on the way into or out of other tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    Newline,
+    Long,
+    RawBytes,
+    LeadingZeros,
+    Scientific,
+}
+
+impl Badge {
+    /// The small mark drawn after the value in the table.
+    pub fn mark(self) -> char {
+        match self {
+            Badge::Newline => '↵',
+            Badge::Long => '…',
+            Badge::RawBytes => '¿',
+            Badge::LeadingZeros => '⁰',
+            Badge::Scientific => 'ᴱ',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Badge::Newline => "line break",
+            Badge::Long => "very long",
+            Badge::RawBytes => "invalid UTF-8 kept as bytes",
+            Badge::LeadingZeros => "leading zeros, maybe an ID",
+            Badge::Scientific => "scientific notation",
+        }
+    }
+}
+
+/// The badges of `s`, in the order of [`Badge`].
+pub fn of(s: &str) -> Vec<Badge> {
+    let t = s.trim();
+    let digits = |t: &str| !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit());
+    let scientific = t.split_once(['e', 'E']).is_some_and(|(m, e)| {
+        let m = m.strip_prefix(['-', '+']).unwrap_or(m);
+        let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+        digits(&m.replacen('.', "", 1)) && digits(e)
+    });
+    [
+        (Badge::Newline, s.contains(['\n', '\r'])),
+        (Badge::Long, s.chars().count() > LONG),
+        (Badge::RawBytes, escape::has_raw_bytes(s)),
+        (
+            Badge::LeadingZeros,
+            t.len() > 1 && t.starts_with('0') && digits(t),
+        ),
+        (Badge::Scientific, scientific),
+    ]
+    .into_iter()
+    .filter_map(|(b, on)| on.then_some(b))
+    .collect()
+}
+
+/// The marks of `s` run together, or empty for an unremarkable cell.
+pub fn marks(s: &str) -> String {
+    of(s).into_iter().map(Badge::mark).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spots_notable_content() {
+        assert_eq!(of("00123"), [Badge::LeadingZeros]);
+        assert_eq!(of("1.23E+11"), [Badge::Scientific]);
+        assert_eq!(of("-5e-3"), [Badge::Scientific]);
+        assert_eq!(of("a\nb"), [Badge::Newline]);
+        assert_eq!(of(&escape::decode(b"caf\xe9")), [Badge::RawBytes]);
+        assert_eq!(marks(&"x".repeat(LONG + 1)), "…");
+        for plain in ["0", "0.5", "120", "Eve", "e5", "1e", "hello"] {
+            assert!(of(plain).is_empty(), "{plain}");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Timings for the paths whose cost grows with the file: loading, saving,
+//! sorting, filtering and drawing a frame. They are ignored tests, so
+//! `cargo test --release -- --ignored --nocapture bench_` runs them and
+//! prints each timing, for comparing before and after a change.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+
+use crate::app::App;
+use crate::csv_io;
+use crate::nulls::Nulls;
+use crate::sort;
+use crate::ui;
+use crate::view::{Filter, View};
+use crate::viz::Decimal;
+
+const ROWS: usize = 500_000;
+
+/// A header and `ROWS` rows of mixed numbers, text and dates.
+fn table() -> Vec<Vec<String>> {
+    let mut data = vec![vec![
+        "id".into(),
+        "name".into(),
+        "score".into(),
+        "day".into(),
+    ]];
+    data.extend((0..ROWS).map(|i| {
+        vec![
+            ((i * 7919) % ROWS).to_string(),
+            format!("name {}", i % 997),
+            format!("{}.{}", i % 1000, i % 10),
+            format!("2024-{:02}-{:02}", i % 12 + 1, i % 28 + 1),
+        ]
+    }));
+    data
+}
+
+fn report(what: &str, took: Duration) {
+    println!("{what}: {took:?} ({ROWS} rows)");
+}
+
+fn time<T>(what: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = Instant::now();
+    let out = f()?;
+    report(what, start.elapsed());
+    Ok(out)
+}
+
+fn temp(name: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "tui_csv_viewer_bench_{name}_{}.csv",
+        std::process::id()
+    ))
+}
+
+#[test]
+#[ignore]
+fn bench_load_and_save() -> Result<()> {
+    let path = temp("save");
+    let data = table();
+    csv_io::save_csv(&path, &data)?;
+    let loaded = time("load", || csv_io::load_with_spans(&path))?;
+    time("save everything", || csv_io::save_csv(&path, &data))?;
+    let spans = csv_io::load_with_spans(&path)?.spans;
+    let mut edited = data.clone();
+    edited[ROWS / 2][1] = "edited".into();
+    let reuse: Vec<Option<usize>> = (0..edited.len())
+        .map(|r| (r != ROWS / 2).then_some(r))
+        .collect();
+    time("save one edit", || {
+        csv_io::save_reusing(&path, &edited, &reuse, spans.as_ref())
+    })?;
+    assert_eq!(loaded.rows.len(), data.len());
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn bench_sort_and_filter() -> Result<()> {
+    let data = table();
+    let keys = sort::parse_keys("3 desc, 2")?;
+    let order = time("sort by number then text", || {
+        Ok(sort::order(&data[1..], &keys, Decimal::Dot))
+    })?;
+    assert_eq!(order.len(), ROWS);
+    let mut view = View {
+        filter: Some(Filter::new(1, "name 99")?),
+        ..View::default()
+    };
+    time("filter", || {
+        view.rebuild(&data, 1, None, &Nulls::default(), Decimal::Dot);
+        Ok(())
+    })?;
+    assert!(view.rows.len() > 1);
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn bench_render() -> Result<()> {
+    let mut app = App::new(PathBuf::new(), table());
+    app.header = true;
+    app.row = ROWS / 2;
+    let mut terminal = Terminal::new(TestBackend::new(200, 60))?;
+    let frames = 100;
+    time(&format!("draw {frames} frames"), || {
+        for _ in 0..frames {
+            app.row += 1;
+            ui::draw_ui(&mut terminal, &app)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
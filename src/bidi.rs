@@ -0,0 +1,145 @@
+//! Right-to-left text for terminals that draw characters in the order they
+//! are sent, which is most of them: Arabic or Hebrew cells come out with
+//! their letters backwards unless the runs are turned around first. This is
+//! a simplified take on the Unicode bidi algorithm, for one line at a time:
+//! letters are strong in their script's direction, digits count as
+//! left-to-right, and anything else takes the direction around it.
+
+/// Whether `c` belongs to a right-to-left script. Arabic-Indic digits sit
+/// in the Arabic block but count as digits, and U+FEFF at the end of it is
+/// the byte-order mark.
+pub fn is_rtl(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFE | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    ) && !matches!(c, '\u{660}'..='\u{669}' | '\u{6f0}'..='\u{6f9}')
+}
+
+pub fn has_rtl(s: &str) -> bool {
+    s.chars().any(is_rtl)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Ltr,
+    Rtl,
+}
+
+fn strong(c: char) -> Option<Dir> {
+    if is_rtl(c) {
+        Some(Dir::Rtl)
+    } else if c.is_alphanumeric() {
+        Some(Dir::Ltr)
+    } else {
+        None
+    }
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        c => c,
+    }
+}
+
+/// `s` in the order to draw it, and how many characters into that the
+/// logical end of `s` falls, which is where typing goes on. Text without
+/// right-to-left letters comes back as it is.
+pub fn visual(s: &str) -> (String, usize) {
+    let chars: Vec<char> = s.chars().collect();
+    if !chars.iter().copied().any(is_rtl) {
+        return (s.to_string(), chars.len());
+    }
+    // The first strong letter sets the direction of the whole line.
+    let base = chars.iter().find_map(|&c| strong(c)).unwrap_or(Dir::Ltr);
+    let mut dirs: Vec<Option<Dir>> = chars.iter().map(|&c| strong(c)).collect();
+    let mut i = 0;
+    while i < dirs.len() {
+        if dirs[i].is_some() {
+            i += 1;
+            continue;
+        }
+        let end = (i..dirs.len())
+            .find(|&j| dirs[j].is_some())
+            .unwrap_or(dirs.len());
+        let before = if i == 0 {
+            base
+        } else {
+            dirs[i - 1].unwrap_or(base)
+        };
+        let after = dirs.get(end).copied().flatten().unwrap_or(base);
+        let dir = if before == after { before } else { base };
+        dirs[i..end].fill(Some(dir));
+        i = end;
+    }
+    // Runs of one direction, in logical order.
+    let mut runs: Vec<(Dir, Vec<char>)> = Vec::new();
+    for (&c, dir) in chars.iter().zip(dirs) {
+        let dir = dir.unwrap_or(base);
+        match runs.last_mut() {
+            Some((d, run)) if *d == dir => run.push(c),
+            _ => runs.push((dir, vec![c])),
+        }
+    }
+    let last = runs.len() - 1;
+    let mut out = String::new();
+    let mut end = 0;
+    let order: Vec<usize> = match base {
+        Dir::Ltr => (0..runs.len()).collect(),
+        Dir::Rtl => (0..runs.len()).rev().collect(),
+    };
+    for i in order {
+        let (dir, run) = &runs[i];
+        let drawn = out.chars().count();
+        match dir {
+            Dir::Ltr => out.extend(run.iter()),
+            Dir::Rtl => out.extend(run.iter().rev().map(|&c| mirror(c))),
+        }
+        if i == last {
+            end = match dir {
+                Dir::Ltr => drawn + run.len(),
+                Dir::Rtl => drawn,
+            };
+        }
+    }
+    (out, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reorders_right_to_left_runs_for_drawing() {
+        assert_eq!(visual("plain (text)"), ("plain (text)".to_string(), 12));
+        // Hebrew "shalom": drawn last letter first, typing goes on at the left.
+        assert_eq!(visual("שלום"), ("םולש".to_string(), 0));
+        // Numbers and Latin words inside keep their own order.
+        assert_eq!(visual("שלום 123"), ("123 םולש".to_string(), 3));
+        assert_eq!(visual("id: שלום (x)"), ("id: םולש (x)".to_string(), 12));
+        assert_eq!(visual("(שלום)"), ("(םולש)".to_string(), 0));
+        assert!(has_rtl("مرحبا") && !has_rtl("hello"));
+    }
+
+    #[test]
+    fn keeps_arabic_indic_digits_in_order() {
+        assert!(!has_rtl("١٢٣") && !has_rtl("۴۵۶"));
+        assert_eq!(visual("١٢٣"), ("١٢٣".to_string(), 3));
+        assert_eq!(visual("سعر ١٢٣"), ("١٢٣ رعس".to_string(), 3));
+        assert_eq!(visual("قیمت ۴۵۶"), ("۴۵۶ تمیق".to_string(), 3));
+    }
+
+    #[test]
+    fn ignores_the_byte_order_mark() {
+        assert!(!is_rtl('\u{feff}'));
+        assert_eq!(visual("\u{feff}id"), ("\u{feff}id".to_string(), 3));
+        assert!(is_rtl('\u{fefc}'));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use crate::findings::Finding;
+
+/// Cells in column `col` that would break a primary key: blanks and values
+/// occurring more than once. Every occurrence of a duplicate is reported so
+/// all offending rows can be visited.
+pub fn unique_violations(data: &[Vec<String>], col: usize) -> Vec<Finding> {
+    let value = |r: usize| data[r].get(col).map_or("", String::as_str);
+    let mut seen: HashMap<&str, Vec<usize>> = HashMap::new();
+    for r in 0..data.len() {
+        let v = value(r);
+        if !v.trim().is_empty() {
+            seen.entry(v).or_default().push(r);
+        }
+    }
+    let mut out = Vec::new();
+    for r in 0..data.len() {
+        let v = value(r);
+        if v.trim().is_empty() {
+            out.push(Finding {
+                row: r,
+                col,
+                rule: "unique".to_string(),
+                text: "blank key".to_string(),
+                warning: false,
+                whole_row: false,
+            });
+            continue;
+        }
+        let rows = &seen[v];
+        if rows.len() > 1 {
+            let others: Vec<String> = rows
+                .iter()
+                .filter(|&&o| o != r)
+                .map(|o| (o + 1).to_string())
+                .collect();
+            out.push(Finding {
+                row: r,
+                col,
+                rule: "unique".to_string(),
+                text: format!("{v:?} also in row {}", others.join(", ")),
+                warning: false,
+                whole_row: false,
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn reports_blanks_and_every_duplicate_occurrence() {
+        let data = grid(&[&["1", "a"], &["2", "b"], &["1", "c"], &[" ", "d"], &["3"]]);
+        let found = unique_violations(&data, 0);
+        let rows: Vec<_> = found.iter().map(|f| f.row).collect();
+        assert_eq!(rows, [0, 2, 3]);
+        assert_eq!(found[0].text, "\"1\" also in row 3");
+        assert_eq!(found[2].text, "blank key");
+        // Short rows count as blank in that column.
+        assert_eq!(unique_violations(&data, 1).len(), 1);
+    }
+}
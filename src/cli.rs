@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::fwf;
+
+/// Parsed command-line arguments. Options override the config file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub path: Option<PathBuf>,
+    pub tick_rate: Option<Duration>,
+    /// `--fwf <widths|auto>`: read the file as fixed-width text.
+    pub fwf: Option<fwf::Spec>,
+    /// `--expand-sci`: turn mangled scientific-notation IDs back into digits.
+    pub expand_sci: bool,
+    /// `--decimal-comma`: read numbers written like `1.234,56`.
+    pub decimal_comma: bool,
+    /// `--validate`: check the file against its schema and exit, no TUI.
+    pub validate: bool,
+    /// `--new --template <schema>`: create the file from a schema's columns
+    /// and example rows before opening it.
+    pub new: bool,
+    pub template: Option<PathBuf>,
+    /// `--log-file <path>`: append a line per action, file operation and
+    /// error there.
+    pub log_file: Option<PathBuf>,
+    /// `--control <socket>`: take requests from other programs on a Unix
+    /// socket there.
+    pub control: Option<PathBuf>,
+    /// `--record <session.json>`: save the session's keys there on exit.
+    pub record: Option<PathBuf>,
+    /// `--replay <session.json>`: type a recorded session's keys again.
+    pub replay: Option<PathBuf>,
+    /// `--tutorial`: open a sample file with step-by-step instructions.
+    pub tutorial: bool,
+}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args> {
+    let mut out = Args::default();
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--tick-rate" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--tick-rate needs a value in ms"))?;
+                out.tick_rate = Some(config::parse_tick_rate(&v)?);
+            }
+            "--fwf" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--fwf needs field widths (e.g. 5,10,8) or auto"))?;
+                out.fwf = Some(fwf::parse_spec(&v)?);
+            }
+            "--expand-sci" => out.expand_sci = true,
+            "--tutorial" => out.tutorial = true,
+            "--decimal-comma" => out.decimal_comma = true,
+            "--log-file" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--log-file needs a file to write to"))?;
+                out.log_file = Some(PathBuf::from(v));
+            }
+            "--control" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--control needs the path of a socket to create"))?;
+                out.control = Some(PathBuf::from(v));
+            }
+            "--record" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--record needs a file to save the session to"))?;
+                out.record = Some(PathBuf::from(v));
+            }
+            "--replay" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--replay needs a recorded session file"))?;
+                out.replay = Some(PathBuf::from(v));
+            }
+            "--validate" => out.validate = true,
+            "--new" => out.new = true,
+            "--template" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| anyhow!("--template needs a schema file"))?;
+                out.template = Some(PathBuf::from(v));
+            }
+            s if s.starts_with("--") => return Err(anyhow!("unknown option {s}")),
+            _ if out.path.is_some() => return Err(anyhow!("unexpected argument {arg:?}")),
+            _ => out.path = Some(PathBuf::from(arg)),
+        }
+    }
+    if out.new != out.template.is_some() {
+        return Err(anyhow!("--new and --template <schema> go together"));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Args> {
+        parse(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parses_path_and_tick_rate_in_any_order() -> Result<()> {
+        let a = args(&["--tick-rate", "50", "data.csv"])?;
+        assert_eq!(a.path, Some(PathBuf::from("data.csv")));
+        assert_eq!(a.tick_rate, Some(Duration::from_millis(50)));
+        let a = args(&["old.dat", "--fwf", "auto"])?;
+        assert_eq!(a.fwf, Some(fwf::Spec::Auto));
+        assert!(args(&["--expand-sci", "ids.csv"])?.expand_sci);
+        assert!(args(&["--decimal-comma", "eu.csv"])?.decimal_comma);
+        assert!(args(&["--validate", "ids.csv"])?.validate);
+        assert!(args(&["--tutorial"])?.tutorial);
+        assert_eq!(
+            args(&["--log-file", "debug.log"])?.log_file,
+            Some(PathBuf::from("debug.log"))
+        );
+        assert_eq!(
+            args(&["--control", "/tmp/csv.sock"])?.control,
+            Some(PathBuf::from("/tmp/csv.sock"))
+        );
+        let a = args(&["--replay", "bug.json", "--record", "again.json"])?;
+        assert_eq!(a.replay, Some(PathBuf::from("bug.json")));
+        assert_eq!(a.record, Some(PathBuf::from("again.json")));
+        let a = args(&["--new", "up.csv", "--template", "upload.schema"])?;
+        assert_eq!(a.template, Some(PathBuf::from("upload.schema")));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_options_and_extra_paths() {
+        assert!(args(&["--nope"]).is_err());
+        assert!(args(&["a.csv", "b.csv"]).is_err());
+        assert!(args(&["--tick-rate"]).is_err());
+        assert!(args(&["--fwf", "3,x", "a.txt"]).is_err());
+        assert!(args(&["--new", "a.csv"]).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+/// Options of the paste-special dialog.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PasteOptions {
+    /// Rows become columns.
+    pub transpose: bool,
+    /// Blank copied cells leave the target cell alone.
+    pub skip_blanks: bool,
+    /// Formula cells (starting with `=`) are pasted as blanks, since the
+    /// editor can't compute their values.
+    pub values_only: bool,
+}
+
+/// Copied cells, row by row.
+pub type Block = Vec<Vec<String>>;
+
+/// Text pasted into the table: tab-separated, as spreadsheets copy it, if
+/// it has a tab, otherwise CSV. Quoted fields may hold line breaks.
+pub fn parse_text(text: &str) -> anyhow::Result<Block> {
+    let delimiter = if text.contains('\t') { b'\t' } else { b',' };
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+    rdr.records()
+        .map(|r| Ok(r?.iter().map(str::to_string).collect()))
+        .collect()
+}
+
+pub fn is_formula(cell: &str) -> bool {
+    cell.trim_start().starts_with('=')
+}
+
+/// What to write at each `(row, col)` offset from the paste position.
+/// Skipped cells are left out.
+pub fn arrange(block: &Block, opts: PasteOptions) -> Vec<(usize, usize, String)> {
+    let mut out = Vec::new();
+    for (r, row) in block.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            let value = if opts.values_only && is_formula(cell) {
+                ""
+            } else {
+                cell.as_str()
+            };
+            if opts.skip_blanks && value.trim().is_empty() {
+                continue;
+            }
+            let (dr, dc) = if opts.transpose { (c, r) } else { (r, c) };
+            out.push((dr, dc, value.to_string()));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arranges_with_each_option() {
+        let block: Block = vec![
+            vec!["a".into(), "".into()],
+            vec!["=A1*2".into(), "d".into()],
+        ];
+        assert_eq!(arrange(&block, PasteOptions::default()).len(), 4);
+
+        let transposed = arrange(
+            &block,
+            PasteOptions {
+                transpose: true,
+                ..Default::default()
+            },
+        );
+        assert!(transposed.contains(&(0, 1, "=A1*2".into())));
+        assert!(transposed.contains(&(1, 0, "".into())));
+
+        let opts = PasteOptions {
+            skip_blanks: true,
+            values_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            arrange(&block, opts),
+            [(0, 0, "a".into()), (1, 1, "d".into())]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::mask::Mask;
+use crate::tags::Tags;
+
+/// How columns appear in the table: hidden ones are left out and pinned ones
+/// come first, whatever their place in the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub hidden: Tags,
+    pub pinned: Tags,
+    /// Columns of right-to-left text, drawn against the cell's right edge.
+    pub rtl: Tags,
+    /// Columns drawn as `•••`, and ones showing only the last four characters.
+    pub masked: Tags,
+    pub masked_last4: Tags,
+}
+
+impl Layout {
+    pub fn is_default(&self) -> bool {
+        self.hidden.is_empty() && self.pinned.is_empty()
+    }
+
+    /// Data columns in display order, out of `cols`.
+    pub fn order(&self, cols: usize) -> Vec<usize> {
+        let shown = |c: &usize| !self.hidden.contains(*c);
+        let pinned = (0..cols).filter(|&c| self.pinned.contains(c));
+        let rest = (0..cols).filter(|&c| !self.pinned.contains(c));
+        pinned.chain(rest).filter(shown).collect()
+    }
+
+    pub fn insert(&mut self, col: usize) {
+        for tags in self.all_mut() {
+            tags.insert(col);
+        }
+    }
+
+    pub fn delete(&mut self, col: usize) {
+        for tags in self.all_mut() {
+            tags.delete(col, 1);
+        }
+    }
+
+    pub fn reorder(&mut self, order: &[usize]) {
+        for tags in self.all_mut() {
+            tags.reorder(order);
+        }
+    }
+
+    fn all_mut(&mut self) -> [&mut Tags; 5] {
+        [
+            &mut self.hidden,
+            &mut self.pinned,
+            &mut self.rtl,
+            &mut self.masked,
+            &mut self.masked_last4,
+        ]
+    }
+
+    pub fn mask(&self, col: usize) -> Option<Mask> {
+        if self.masked.contains(col) {
+            Some(Mask::Full)
+        } else if self.masked_last4.contains(col) {
+            Some(Mask::Last4)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_mask(&mut self, col: usize, mask: Option<Mask>) {
+        for (tags, m) in [
+            (&mut self.masked, Mask::Full),
+            (&mut self.masked_last4, Mask::Last4),
+        ] {
+            if tags.contains(col) != (mask == Some(m)) {
+                tags.toggle(col);
+            }
+        }
+    }
+}
+
+/// One line of the column manager.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The column's index before the manager opened.
+    pub col: usize,
+    pub name: String,
+    pub hidden: bool,
+    pub pinned: bool,
+}
+
+/// The column manager overlay: a working copy of the columns' order,
+/// names, visibility and pins that only takes effect on confirm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manager {
+    pub entries: Vec<Entry>,
+    pub cursor: usize,
+    /// The new name being typed for the selected column.
+    pub renaming: Option<String>,
+}
+
+impl Manager {
+    pub fn new(names: Vec<String>, layout: &Layout, cursor: usize) -> Self {
+        let entries: Vec<Entry> = names
+            .into_iter()
+            .enumerate()
+            .map(|(col, name)| Entry {
+                col,
+                name,
+                hidden: layout.hidden.contains(col),
+                pinned: layout.pinned.contains(col),
+            })
+            .collect();
+        let cursor = cursor.min(entries.len().saturating_sub(1));
+        Self {
+            entries,
+            cursor,
+            renaming: None,
+        }
+    }
+
+    pub fn step(&mut self, down: bool) {
+        let last = self.entries.len().saturating_sub(1);
+        self.cursor = if down {
+            (self.cursor + 1).min(last)
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+    }
+
+    /// Move the selected column one place up or down, taking the cursor along.
+    pub fn shift(&mut self, down: bool) {
+        let to = if down {
+            self.cursor + 1
+        } else {
+            self.cursor.wrapping_sub(1)
+        };
+        if to < self.entries.len() {
+            self.entries.swap(self.cursor, to);
+            self.cursor = to;
+        }
+    }
+
+    pub fn selected(&mut self) -> Option<&mut Entry> {
+        self.entries.get_mut(self.cursor)
+    }
+
+    /// New column `i` is old column `order[i]`.
+    pub fn order(&self) -> Vec<usize> {
+        self.entries.iter().map(|e| e.col).collect()
+    }
+
+    /// The layout after the reorder, by new column index.
+    pub fn layout(&self) -> Layout {
+        let mut layout = Layout::default();
+        for (i, e) in self.entries.iter().enumerate() {
+            if e.hidden {
+                layout.hidden.toggle(i);
+            }
+            if e.pinned {
+                layout.pinned.toggle(i);
+            }
+        }
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_order_and_manager_edits() {
+        let mut layout = Layout::default();
+        layout.pinned.toggle(2);
+        layout.hidden.toggle(1);
+        assert_eq!(layout.order(4), [2, 0, 3]);
+        layout.reorder(&[2, 0, 1, 3]);
+        assert_eq!(layout.order(4), [0, 1, 3]);
+
+        let names = ["id", "name", "qty"].map(String::from).to_vec();
+        let mut m = Manager::new(names, &Layout::default(), 2);
+        m.shift(false);
+        m.shift(false);
+        m.shift(false);
+        assert_eq!(m.order(), [2, 0, 1]);
+        assert_eq!(m.cursor, 0);
+        m.step(true);
+        if let Some(e) = m.selected() {
+            e.hidden = true;
+        }
+        assert_eq!(m.layout().order(3), [0, 2]);
+    }
+}
@@ -0,0 +1,479 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+
+use crate::a1;
+use crate::anonymize::{self, Anonymize};
+use crate::config::Advance;
+use crate::export;
+use crate::expr;
+use crate::fill::{self, Fill};
+use crate::generate::{self, SeqSpec};
+use crate::mask::Mask;
+use crate::names;
+use crate::outliers::{self, Rule};
+use crate::presets;
+use crate::replace::{self, Substitute};
+use crate::sort::{self, SortKey};
+use crate::tags;
+
+/// A `:` command-line command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Write,
+    Quit,
+    /// `:q!` — leave without the auto-save on quit.
+    ForceQuit,
+    WriteQuit,
+    /// `:reload` reads the file again; `:reload!` even with unsaved edits.
+    Reload(bool),
+    Recent,
+    Help,
+    /// `:unique <col>` — check a key column for blanks and duplicates.
+    Unique(usize),
+    /// `:[range]s/pattern/replacement/[g]`.
+    Substitute(Substitute),
+    /// `:sort 2 asc, 5 desc`.
+    Sort(Vec<SortKey>),
+    /// `:seq [first|last] [uuid | start [step]]` — insert a generated column.
+    Seq(SeqSpec),
+    /// `:header [on|off]`; no argument toggles.
+    Header(Option<bool>),
+    /// `:rename <col> <new name>`, the column as a number or header name.
+    Rename {
+        col: String,
+        name: String,
+    },
+    /// `:export csv|tsv|json|md|html|txt`, straight to the file beside it.
+    Export(export::Format),
+    /// `:export` on its own: the dialog that asks for each setting.
+    ExportDialog,
+    /// `:filter <col> <regex>` hides other rows; no argument shows them all.
+    Filter(Option<(String, String)>),
+    /// `:filter <expression>`, such as `col("status") == "FAILED"`.
+    FilterExpr(String),
+    /// `:group <col> | off | collapse | expand`.
+    Group(GroupOp),
+    /// `:filters [<name> | save <name> | delete <name>]`: switch a saved
+    /// filter on or off, save the current one, or list them.
+    Filters(presets::Op),
+    /// `:view-only [on|off]` — whether `:sort` only changes the display.
+    ViewOnly(Option<bool>),
+    /// `:screen-reader [on|off]` — read out cells and mark states in text.
+    ScreenReader(Option<bool>),
+    /// `:auto-extend [on|off]` — whether moving past the last row or column
+    /// adds a new one.
+    AutoExtend(Option<bool>),
+    /// `:pipe <shell command>` — feed the selected rows, or the whole file,
+    /// to the command as CSV and replace them with what it prints.
+    Pipe(String),
+    /// `:script <name> [args]` — run an executable from the scripts folder
+    /// of the config directory like `:pipe`; `:script` lists them.
+    Script(Option<(String, String)>),
+    /// `:perf [on|off]` — an overlay with the last frame's draw time and
+    /// how long the last key took to reach the screen.
+    Perf(Option<bool>),
+    /// `:rtl [on|off]` — draw the current column's cells from the right, for
+    /// Arabic or Hebrew text.
+    Rtl(Option<bool>),
+    /// `:mask [full|last4|off]` — draw the current column's cells as `•••`,
+    /// or with only their last four characters; `off` shows them again.
+    Mask(Option<Mask>),
+    /// `:entry [right|down|off]` — data-entry mode; no argument toggles it
+    /// with rightward moves.
+    Entry(Option<Advance>),
+    /// `:goto B3` moves to a cell; `:goto A1:C5` selects a block.
+    Goto((usize, usize), (usize, usize)),
+    /// `:goto <name>` selects a named range.
+    GotoName(String),
+    /// `:name ...` — define, drop, list or export named ranges.
+    Name(names::Op),
+    /// `:view ...` — switch to, save, drop or list named view presets.
+    View(presets::Op),
+    /// `:dup` — copy the cursor column into a new one beside it.
+    Duplicate,
+    /// `:commit-view` — apply the view's sort and filter to the data.
+    CommitView,
+    /// `:reset-view` — drop the view's sort and filter.
+    ResetView,
+    /// `:tagged delete|yank|clear|export <file>` — act on the tagged rows.
+    Tagged(tags::Bulk),
+    /// `:fill down|mean|median|value <text>` — fill the cursor column's
+    /// missing cells.
+    Fill(Fill),
+    /// `:anonymize hash [<salt>] | seq [<prefix>] [> <mapping.csv>]` —
+    /// replace the cursor column's values with hashes or pseudonyms.
+    Anonymize(Anonymize),
+    /// `:strip-currency` — turn the cursor column's amounts like `$1,234.50`
+    /// into plain numbers.
+    StripCurrency,
+    /// `:outliers [sd <n> | iqr [k]]` — list the cursor column's outliers.
+    Outliers(Rule),
+    /// `:validate` checks the file against its `.schema`; `:validate <col>
+    /// <format>` checks one column against a built-in format.
+    Validate(Option<(String, String)>),
+    /// `:report <file.csv|file.json>` — write the findings to a file.
+    Report(PathBuf),
+    /// `:profile <file.csv|file.json>` — write per-column stats to a file.
+    Profile(PathBuf),
+}
+
+pub fn parse(line: &str) -> Result<Command> {
+    if let Some(sub) = replace::parse(line)? {
+        return Ok(Command::Substitute(sub));
+    }
+    let line = line.trim();
+    let (name, rest) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(n, r)| (n, r.trim()));
+    let no_args = |cmd: Command| {
+        if rest.is_empty() {
+            Ok(cmd)
+        } else {
+            Err(anyhow!(":{name} takes no arguments"))
+        }
+    };
+    match name {
+        "w" | "write" => no_args(Command::Write),
+        "q" | "quit" => no_args(Command::Quit),
+        "q!" | "quit!" => no_args(Command::ForceQuit),
+        "wq" | "x" => no_args(Command::WriteQuit),
+        "reload" | "e!" => no_args(Command::Reload(name == "e!")),
+        "reload!" => no_args(Command::Reload(true)),
+        "recent" => no_args(Command::Recent),
+        "help" | "h" => no_args(Command::Help),
+        "unique" => Ok(Command::Unique(parse_column(rest)?)),
+        "sort" => Ok(Command::Sort(sort::parse_keys(rest)?)),
+        "seq" => Ok(Command::Seq(generate::parse(rest)?)),
+        "header" => Ok(Command::Header(parse_switch(name, rest)?)),
+        "view-only" => Ok(Command::ViewOnly(parse_switch(name, rest)?)),
+        "screen-reader" => Ok(Command::ScreenReader(parse_switch(name, rest)?)),
+        "auto-extend" => Ok(Command::AutoExtend(parse_switch(name, rest)?)),
+        "perf" => Ok(Command::Perf(parse_switch(name, rest)?)),
+        "rtl" => Ok(Command::Rtl(parse_switch(name, rest)?)),
+        "mask" if rest == "off" => Ok(Command::Mask(None)),
+        "mask" => Ok(Command::Mask(Some(Mask::parse(rest)?))),
+        "pipe" if rest.is_empty() => Err(anyhow!("usage: :pipe <shell command>")),
+        "pipe" => Ok(Command::Pipe(rest.to_string())),
+        "script" if rest.is_empty() => Ok(Command::Script(None)),
+        "script" => {
+            let (script, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            Ok(Command::Script(Some((
+                script.to_string(),
+                args.trim().to_string(),
+            ))))
+        }
+        "entry" if rest.is_empty() => Ok(Command::Entry(None)),
+        "entry" => Ok(Command::Entry(Some(Advance::parse(rest)?))),
+        "dup" | "duplicate" => no_args(Command::Duplicate),
+        "goto" | "go" if names::is_name(rest) => Ok(Command::GotoName(rest.to_string())),
+        "goto" | "go" if !rest.is_empty() => {
+            let (from, to) = a1::parse_range(rest)?;
+            Ok(Command::Goto(from, to))
+        }
+        "name" => Ok(Command::Name(names::parse_op(rest)?)),
+        "view" => Ok(Command::View(presets::parse_op("view", rest)?)),
+        "filters" => Ok(Command::Filters(presets::parse_op("filters", rest)?)),
+        "group" => Ok(Command::Group(match rest {
+            "" => return Err(anyhow!("usage: :group <col> | off | collapse | expand")),
+            "off" => GroupOp::Off,
+            "collapse" => GroupOp::CollapseAll,
+            "expand" => GroupOp::ExpandAll,
+            col => GroupOp::By(col.to_string()),
+        })),
+        "goto" | "go" => Err(anyhow!(
+            "usage: :goto <cell> or :goto <cell>:<cell>, e.g. B3"
+        )),
+        "commit-view" => no_args(Command::CommitView),
+        "reset-view" => no_args(Command::ResetView),
+        "tagged" => Ok(Command::Tagged(tags::parse_bulk(rest)?)),
+        "fill" => Ok(Command::Fill(fill::parse(rest)?)),
+        "anonymize" => Ok(Command::Anonymize(anonymize::parse(rest)?)),
+        "strip-currency" => no_args(Command::StripCurrency),
+        "outliers" => Ok(Command::Outliers(outliers::parse(rest)?)),
+        "report" if !rest.is_empty() => Ok(Command::Report(PathBuf::from(rest))),
+        "report" => Err(anyhow!("usage: :report <file.csv|file.json>")),
+        "profile" if !rest.is_empty() => Ok(Command::Profile(PathBuf::from(rest))),
+        "profile" => Err(anyhow!("usage: :profile <file.csv|file.json>")),
+        "validate" => match rest.split_once(char::is_whitespace) {
+            _ if rest.is_empty() => Ok(Command::Validate(None)),
+            Some((col, format)) => Ok(Command::Validate(Some((
+                col.to_string(),
+                format.trim().to_string(),
+            )))),
+            None => Err(anyhow!("usage: :validate [<col> <format>]")),
+        },
+        "filter" => match rest.split_once(char::is_whitespace) {
+            _ if rest.is_empty() => Ok(Command::Filter(None)),
+            _ if filter_expression(line).is_some() => {
+                expr::Condition::parse(rest)?;
+                Ok(Command::FilterExpr(rest.to_string()))
+            }
+            Some((col, query)) if !query.trim().is_empty() => Ok(Command::Filter(Some((
+                col.to_string(),
+                query.trim().to_string(),
+            )))),
+            _ => Err(anyhow!(
+                "usage: :filter <col> <regex> or :filter <expression>"
+            )),
+        },
+        "export" if rest.is_empty() => Ok(Command::ExportDialog),
+        "export" => export::Format::parse(rest)
+            .map(Command::Export)
+            .ok_or_else(|| anyhow!(":export takes csv, tsv, json, md, html or txt")),
+        "rename" => match rest.split_once(char::is_whitespace) {
+            Some((col, name)) if !name.trim().is_empty() => Ok(Command::Rename {
+                col: col.to_string(),
+                name: name.trim().to_string(),
+            }),
+            _ => Err(anyhow!("usage: :rename <col> <new name>")),
+        },
+        "" => Err(anyhow!("empty command")),
+        other => Err(anyhow!("unknown command: {other}")),
+    }
+}
+
+/// `on`/`off`, or nothing to toggle.
+fn parse_switch(name: &str, rest: &str) -> Result<Option<bool>> {
+    match rest {
+        "" => Ok(None),
+        "on" => Ok(Some(true)),
+        "off" => Ok(Some(false)),
+        _ => Err(anyhow!(":{name} takes on or off")),
+    }
+}
+
+/// What `:group` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupOp {
+    /// Group by this column, a number or header name.
+    By(String),
+    Off,
+    CollapseAll,
+    ExpandAll,
+}
+
+/// The expression of a `:filter` line that gives one rather than a column
+/// and regex: one that parses, or that starts like one (`col(`, `num(`,
+/// `(`, `!` or `not`) so its mistakes can be pointed out as it is typed.
+pub fn filter_expression(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("filter")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim();
+    let lower = rest.to_ascii_lowercase();
+    let starts = ["col(", "num(", "(", "!", "not "]
+        .iter()
+        .any(|p| lower.starts_with(p));
+    (!rest.is_empty() && (starts || expr::Condition::parse(rest).is_ok())).then_some(rest)
+}
+
+/// A 1-based column number as typed by the user, returned 0-based.
+pub fn parse_column(s: &str) -> Result<usize> {
+    match s.trim().parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(n - 1),
+        _ if s.trim().is_empty() => Err(anyhow!("missing column number")),
+        _ => Err(anyhow!("bad column {s:?}; use a 1-based number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_commands_and_aliases() -> Result<()> {
+        assert_eq!(parse("w")?, Command::Write);
+        assert_eq!(parse(" wq ")?, Command::WriteQuit);
+        assert_eq!(parse("reload!")?, Command::Reload(true));
+        assert_eq!(parse("q!")?, Command::ForceQuit);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        assert!(parse("nope").is_err());
+    }
+
+    #[test]
+    fn parses_recent_without_arguments() -> Result<()> {
+        assert_eq!(parse("recent")?, Command::Recent);
+        assert!(parse("recent extra").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_unique_with_a_column_number() -> Result<()> {
+        assert_eq!(parse("unique 2")?, Command::Unique(1));
+        assert!(parse("unique").is_err());
+        assert!(parse("unique 0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_substitute_and_sort() -> Result<()> {
+        assert!(matches!(parse("%s/a/b/")?, Command::Substitute(_)));
+        assert!(matches!(parse("sort 1, 2 desc")?, Command::Sort(k) if k.len() == 2));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_header() -> Result<()> {
+        assert_eq!(parse("header off")?, Command::Header(Some(false)));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_rename_with_spaces_in_the_name() -> Result<()> {
+        assert!(
+            matches!(parse("rename 2  unit price")?, Command::Rename { name, .. } if name == "unit price")
+        );
+        assert!(parse("rename 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_export_formats_and_the_dialog() -> Result<()> {
+        assert_eq!(
+            parse("export txt")?,
+            Command::Export(export::Format::Aligned)
+        );
+        assert_eq!(
+            parse("export md")?,
+            Command::Export(export::Format::Markdown)
+        );
+        assert_eq!(parse("export")?, Command::ExportDialog);
+        assert!(parse("export xls").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_column_filters() -> Result<()> {
+        assert_eq!(
+            parse("filter status ^FAIL")?,
+            Command::Filter(Some(("status".into(), "^FAIL".into())))
+        );
+        assert_eq!(parse("filter")?, Command::Filter(None));
+        assert!(parse("filter 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_filter_expressions() -> Result<()> {
+        assert_eq!(
+            parse(r#"filter col("status")=="FAILED" && num(ms) > 500"#)?,
+            Command::FilterExpr(r#"col("status")=="FAILED" && num(ms) > 500"#.into())
+        );
+        assert!(parse(r#"filter col("status") = "x""#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_on_off_toggles() -> Result<()> {
+        assert_eq!(parse("view-only on")?, Command::ViewOnly(Some(true)));
+        assert_eq!(parse("auto-extend")?, Command::AutoExtend(None));
+        assert_eq!(parse("perf off")?, Command::Perf(Some(false)));
+        assert_eq!(parse("rtl")?, Command::Rtl(None));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_mask_kinds() -> Result<()> {
+        assert_eq!(parse("mask")?, Command::Mask(Some(Mask::Full)));
+        assert_eq!(parse("mask last4")?, Command::Mask(Some(Mask::Last4)));
+        assert_eq!(parse("mask off")?, Command::Mask(None));
+        assert!(parse("mask half").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_view_group_and_filter_presets() -> Result<()> {
+        assert_eq!(
+            parse("view save qa")?,
+            Command::View(presets::Op::Save("qa".into()))
+        );
+        assert_eq!(
+            parse("group status")?,
+            Command::Group(GroupOp::By("status".into()))
+        );
+        assert_eq!(parse("group off")?, Command::Group(GroupOp::Off));
+        assert_eq!(
+            parse("filters failed")?,
+            Command::Filters(presets::Op::Apply("failed".into()))
+        );
+        assert!(parse("commit-view now").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_pipe_with_the_whole_shell_command() -> Result<()> {
+        assert_eq!(
+            parse("pipe mlr --csv sort -nr n | head")?,
+            Command::Pipe("mlr --csv sort -nr n | head".into())
+        );
+        assert!(parse("pipe").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_script_with_its_arguments() -> Result<()> {
+        assert_eq!(parse("script")?, Command::Script(None));
+        assert_eq!(
+            parse("script dedupe  email  lower")?,
+            Command::Script(Some(("dedupe".into(), "email  lower".into())))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_tagged_bulk_actions() -> Result<()> {
+        assert_eq!(parse("tagged yank")?, Command::Tagged(tags::Bulk::Yank));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_column_cleanups() -> Result<()> {
+        assert_eq!(parse("fill median")?, Command::Fill(Fill::Median));
+        assert!(matches!(
+            parse("anonymize hash s3cret")?,
+            Command::Anonymize(Anonymize { map: None, .. })
+        ));
+        assert_eq!(parse("strip-currency")?, Command::StripCurrency);
+        assert_eq!(parse("outliers iqr 3")?, Command::Outliers(Rule::Iqr(3.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_validate_report_and_profile() -> Result<()> {
+        assert_eq!(
+            parse("validate mail email")?,
+            Command::Validate(Some(("mail".into(), "email".into())))
+        );
+        assert!(parse("validate mail").is_err());
+        assert_eq!(
+            parse("report qa/issues.json")?,
+            Command::Report(PathBuf::from("qa/issues.json"))
+        );
+        assert!(parse("profile").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_entry_and_duplicate() -> Result<()> {
+        assert_eq!(parse("entry down")?, Command::Entry(Some(Advance::Down)));
+        assert!(parse("entry sideways").is_err());
+        assert_eq!(parse("duplicate")?, Command::Duplicate);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_goto_ranges_and_names() -> Result<()> {
+        assert_eq!(parse("goto b2:a1")?, Command::Goto((0, 0), (1, 1)));
+        assert_eq!(
+            parse("goto sales_q1")?,
+            Command::GotoName("sales_q1".into())
+        );
+        Ok(())
+    }
+}
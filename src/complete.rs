@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+/// Distinct values of column `col` that start with `prefix` (ignoring
+/// case), most frequent first. The cell being edited, `(row, col)`, doesn't
+/// count, and neither do blanks or an exact match of the prefix.
+pub fn candidates(data: &[Vec<String>], row: usize, col: usize, prefix: &str) -> Vec<String> {
+    let lower = prefix.to_lowercase();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (r, cells) in data.iter().enumerate() {
+        if let Some(v) = cells.get(col)
+            && r != row
+            && !v.trim().is_empty()
+            && v != prefix
+            && v.to_lowercase().starts_with(&lower)
+        {
+            *counts.entry(v).or_default() += 1;
+        }
+    }
+    let mut out: Vec<(&str, usize)> = counts.into_iter().collect();
+    out.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    out.into_iter().map(|(v, _)| v.to_string()).collect()
+}
+
+/// Tab completion in progress: the typed text and what Tab cycles through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub typed: String,
+    pub candidates: Vec<String>,
+    /// Index into `candidates`; `candidates.len()` is the typed text again.
+    pub index: usize,
+}
+
+impl Completion {
+    pub fn new(typed: String, candidates: Vec<String>) -> Self {
+        Self {
+            typed,
+            candidates,
+            index: 0,
+        }
+    }
+
+    /// Step forward or back, wrapping through the typed text.
+    pub fn step(&mut self, forward: bool) {
+        let n = self.candidates.len() + 1;
+        self.index = if forward {
+            (self.index + 1) % n
+        } else {
+            (self.index + n - 1) % n
+        };
+    }
+
+    pub fn current(&self) -> &str {
+        self.candidates.get(self.index).unwrap_or(&self.typed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_frequent_values_and_cycles_back() {
+        let data: Vec<Vec<String>> = ["Tokyo", "Osaka", "tokushima", "Tokyo", "", "To", "Toyama"]
+            .iter()
+            .map(|v| vec![v.to_string()])
+            .collect();
+        let found = candidates(&data, 5, 0, "to");
+        assert_eq!(found, ["Tokyo", "Toyama", "tokushima"]);
+
+        let mut c = Completion::new("to".into(), found);
+        assert_eq!(c.current(), "Tokyo");
+        c.step(false);
+        assert_eq!(c.current(), "to");
+        c.step(false);
+        assert_eq!(c.current(), "tokushima");
+        c.step(true);
+        c.step(true);
+        assert_eq!(c.current(), "Tokyo");
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+
+use crate::external;
+
+/// Compression picked from the file extension. The system's `gzip`, `zstd`
+/// and `bzip2` tools do the work, so no codec is linked in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Codec {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gz" => Some(Codec::Gzip),
+            "zst" => Some(Codec::Zstd),
+            "bz2" => Some(Codec::Bzip2),
+            _ => None,
+        }
+    }
+
+    pub fn program(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+            Codec::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Pipe `input` through the tool, compressing or with `-d` decompressing.
+    fn run(self, input: &[u8], decompress: bool) -> Result<Vec<u8>> {
+        let program = self.program();
+        let mut cmd = Command::new(program);
+        cmd.arg("-c").arg("-q");
+        if decompress {
+            cmd.arg("-d");
+        }
+        external::pipe(cmd, program, input)
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        self.run(data, false)
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        self.run(data, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_codecs_and_round_trips_gzip() -> Result<()> {
+        assert_eq!(Codec::for_path(Path::new("a.csv.gz")), Some(Codec::Gzip));
+        assert_eq!(Codec::for_path(Path::new("a.tsv.ZST")), Some(Codec::Zstd));
+        assert_eq!(Codec::for_path(Path::new("a.csv")), None);
+        let data = b"a,b\n1,2\n";
+        let packed = Codec::Gzip.compress(data)?;
+        assert_ne!(packed, data);
+        assert_eq!(Codec::Gzip.decompress(&packed)?, data);
+        Ok(())
+    }
+}
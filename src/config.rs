@@ -0,0 +1,418 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::input::Keyboard;
+use crate::nulls::{self, Nulls};
+use crate::palette::{Caps, Depth};
+use crate::statusline;
+use crate::viz::Decimal;
+
+/// User settings read from `<config dir>/config`, a plain `key = value` file
+/// where `#` starts a comment. Unknown keys are ignored so older builds keep
+/// working with newer config files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// How long the event loop waits for input before waking up.
+    pub tick_rate: Duration,
+    /// Key remaps from `bind <keys> = <action>` lines, in file order.
+    pub bindings: Vec<(String, String)>,
+    /// The keyboard the default bindings are laid out for.
+    pub keyboard: Keyboard,
+    /// Longest cell text drawn in the table, in bytes; the rest is cut with
+    /// a marker so one huge cell can't stall rendering.
+    pub max_cell_len: usize,
+    /// Header mode on load: `Some` forces it, `None` guesses from the data.
+    pub header: Option<bool>,
+    /// `:sort` changes only the display until `:commit-view`.
+    pub view_only: bool,
+    /// Read out the cell after each move and mark states in text, not color.
+    pub screen_reader: bool,
+    /// Rewrite cells like `1.23E+11` as plain digits on load.
+    pub expand_sci: bool,
+    /// Values that mean "no value" (`null_tokens = "", NA, NULL, \N`).
+    pub nulls: Nulls,
+    /// Write every null cell this way on save; `None` keeps them as typed.
+    pub null_as: Option<String>,
+    /// Data-entry mode: where Enter moves after committing an edit.
+    pub entry: Advance,
+    /// Ask before a paste replaces non-empty cells.
+    pub confirm_paste: bool,
+    /// Tint each column with its own background hue.
+    pub rainbow: bool,
+    /// The terminal's background, so tints stay readable on it.
+    pub theme: Theme,
+    /// Show the selected cell's stored value on a line above the table.
+    pub formula_bar: bool,
+    /// Mark cells with line breaks, leading zeros and the like.
+    pub badges: bool,
+    /// Turn right-to-left runs around before drawing; off for terminals
+    /// that reorder text themselves.
+    pub bidi: bool,
+    /// How numbers write their decimal point, for sorting, stats and checks.
+    pub decimal: Decimal,
+    /// On save, rewrite comma-decimal numbers as plain `1234.56`.
+    pub normalize_decimal: bool,
+    /// Moving right or down past the edge adds a column or row.
+    pub auto_extend: bool,
+    /// Shell command each loaded file is piped through (as CSV on stdin);
+    /// its output is what gets shown and edited.
+    pub post_load: Option<String>,
+    /// Shell command the data is piped through before each save; the save
+    /// writes its output, or nothing if it fails.
+    pub pre_save: Option<String>,
+    /// What the status line shows, with `{file}`-style placeholders.
+    pub status_format: statusline::Template,
+    /// Set the terminal's window title to the file name.
+    pub title: bool,
+    /// Tell the terminal the file's directory with OSC 7.
+    pub osc7: bool,
+    /// The terminal's colors (`colors = 256`); `None` guesses from `TERM`.
+    pub colors: Option<Depth>,
+    /// Box-drawing borders; `None` uses them with a UTF-8 locale.
+    pub unicode_borders: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// Where data-entry mode goes after a cell is committed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Advance {
+    /// Enter just commits, as usual.
+    #[default]
+    Off,
+    /// The next column, wrapping to the start of the next row.
+    Right,
+    Down,
+}
+
+impl Advance {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(Advance::Off),
+            "right" => Ok(Advance::Right),
+            "down" => Ok(Advance::Down),
+            _ => Err(anyhow!("entry must be right, down or off")),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tick_rate: Duration::from_millis(250),
+            bindings: Vec::new(),
+            keyboard: Keyboard::Qwerty,
+            max_cell_len: 1000,
+            header: None,
+            view_only: false,
+            screen_reader: false,
+            expand_sci: false,
+            nulls: Nulls::default(),
+            null_as: None,
+            entry: Advance::Off,
+            confirm_paste: false,
+            rainbow: false,
+            theme: Theme::Dark,
+            formula_bar: true,
+            badges: true,
+            bidi: true,
+            decimal: Decimal::Dot,
+            normalize_decimal: false,
+            auto_extend: false,
+            post_load: None,
+            pre_save: None,
+            status_format: statusline::Template::default(),
+            title: true,
+            osc7: false,
+            colors: None,
+            unicode_borders: None,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/tui-csv-editor`, falling back to `~/.config/tui-csv-editor`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("tui-csv-editor"))
+}
+
+impl Config {
+    /// Load the user config, or defaults when there is none.
+    pub fn load() -> Result<Self> {
+        match config_dir() {
+            Some(dir) => Self::load_from(&dir.join("config")),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// What the terminal can draw: detected, unless set here.
+    pub fn caps(&self) -> Caps {
+        let mut caps = Caps::detect(|k| env::var(k).ok());
+        if let Some(depth) = self.colors {
+            caps.depth = depth;
+        }
+        if let Some(unicode) = self.unicode_borders {
+            caps.unicode = unicode;
+        }
+        caps
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("parse {path:?}")),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read {path:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut cfg = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            // Only whole lines are comments: values such as commands and
+            // bindings may contain `#`.
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `key = value`", idx + 1))?;
+            cfg.set(key.trim(), value.trim())
+                .with_context(|| format!("line {}", idx + 1))?;
+        }
+        Ok(cfg)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        if let Some(keys) = key.strip_prefix("bind ") {
+            self.bindings
+                .push((keys.trim().to_string(), value.to_string()));
+            return Ok(());
+        }
+        match key {
+            "tick_rate_ms" => self.tick_rate = parse_tick_rate(value)?,
+            "keyboard" => self.keyboard = Keyboard::parse(value)?,
+            "max_cell_len" => {
+                self.max_cell_len = value
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| anyhow!("invalid max_cell_len {value:?}"))?;
+            }
+            "header" => {
+                self.header = match value {
+                    "auto" => None,
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => return Err(anyhow!("header must be auto, on or off")),
+                };
+            }
+            "view_only" => self.view_only = parse_on_off(key, value)?,
+            "screen_reader" => self.screen_reader = parse_on_off(key, value)?,
+            "expand_sci" => self.expand_sci = parse_on_off(key, value)?,
+            "null_tokens" => self.nulls = Nulls::parse(value),
+            "null_as" => self.null_as = Some(nulls::token(value)),
+            "entry" => self.entry = Advance::parse(value)?,
+            "confirm_paste" => self.confirm_paste = parse_on_off(key, value)?,
+            "rainbow" => self.rainbow = parse_on_off(key, value)?,
+            "decimal" => {
+                self.decimal = match value {
+                    "dot" => Decimal::Dot,
+                    "comma" => Decimal::Comma,
+                    _ => return Err(anyhow!("decimal must be dot or comma")),
+                };
+            }
+            "normalize_decimal" => self.normalize_decimal = parse_on_off(key, value)?,
+            "post_load" => self.post_load = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "pre_save" => self.pre_save = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "status_format" => self.status_format = statusline::Template::parse(value)?,
+            "colors" => self.colors = Depth::parse(value)?,
+            "borders" => {
+                self.unicode_borders = match value {
+                    "auto" => None,
+                    "unicode" => Some(true),
+                    "ascii" => Some(false),
+                    _ => return Err(anyhow!("borders must be auto, unicode or ascii")),
+                };
+            }
+            "title" => self.title = parse_on_off(key, value)?,
+            "osc7" => self.osc7 = parse_on_off(key, value)?,
+            "auto_extend" => self.auto_extend = parse_on_off(key, value)?,
+            "badges" => self.badges = parse_on_off(key, value)?,
+            "bidi" => self.bidi = parse_on_off(key, value)?,
+            "formula_bar" => self.formula_bar = parse_on_off(key, value)?,
+            "theme" => {
+                self.theme = match value {
+                    "dark" => Theme::Dark,
+                    "light" => Theme::Light,
+                    _ => return Err(anyhow!("theme must be dark or light")),
+                };
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_on_off(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(anyhow!("{key} must be on or off")),
+    }
+}
+
+pub fn parse_tick_rate(value: &str) -> Result<Duration> {
+    let ms: u64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid tick rate {value:?}"))?;
+    if ms == 0 {
+        return Err(anyhow!("tick rate must be at least 1ms"));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_comment_lines_and_unknown_keys() -> Result<()> {
+        let cfg = Config::parse("# settings\n  # indented\n\nfuture_key = x\n")?;
+        assert_eq!(cfg, Config::default());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_hash_signs_inside_values() -> Result<()> {
+        let cfg = Config::parse("pre_save = sed 's/#//'\nbind gn = s/#/No. /g")?;
+        assert_eq!(cfg.pre_save.as_deref(), Some("sed 's/#//'"));
+        assert_eq!(cfg.bindings, [("gn".to_string(), "s/#/No. /g".to_string())]);
+        assert!(Config::parse("tick_rate_ms = 1000 # slow").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_tick_rate() -> Result<()> {
+        let cfg = Config::parse("tick_rate_ms = 1000")?;
+        assert_eq!(cfg.tick_rate, Duration::from_millis(1000));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_max_cell_len() -> Result<()> {
+        assert_eq!(Config::parse("max_cell_len = 80")?.max_cell_len, 80);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_header_and_view_modes() -> Result<()> {
+        assert_eq!(Config::parse("header = off")?.header, Some(false));
+        assert!(Config::parse("view_only = on")?.view_only);
+        assert!(Config::parse("screen_reader = on")?.screen_reader);
+        assert!(Config::parse("expand_sci = on")?.expand_sci);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_null_tokens_and_what_nulls_save_as() -> Result<()> {
+        let cfg = Config::parse("null_tokens = n/a, -\nnull_as = \"\"")?;
+        assert!(cfg.nulls.is_null("-") && !cfg.nulls.is_null("NA"));
+        assert_eq!(cfg.null_as.as_deref(), Some(""));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_entry_paste_and_auto_extend() -> Result<()> {
+        assert_eq!(Config::parse("entry = down")?.entry, Advance::Down);
+        assert!(Config::parse("confirm_paste = on")?.confirm_paste);
+        assert!(Config::parse("auto_extend = on")?.auto_extend);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_theme_and_display_toggles() -> Result<()> {
+        let cfg = Config::parse("rainbow = on\ntheme = light")?;
+        assert!(cfg.rainbow && cfg.theme == Theme::Light);
+        assert!(!Config::parse("formula_bar = off")?.formula_bar);
+        assert!(!Config::parse("badges = off")?.badges);
+        assert!(!Config::parse("bidi = off")?.bidi);
+        let cfg = Config::parse("title = off\nosc7 = on")?;
+        assert!(!cfg.title && cfg.osc7);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_decimal_settings() -> Result<()> {
+        let cfg = Config::parse("decimal = comma\nnormalize_decimal = on")?;
+        assert!(cfg.decimal == Decimal::Comma && cfg.normalize_decimal);
+        assert!(Config::parse("decimal = point").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_hooks_with_an_empty_one_unset() -> Result<()> {
+        let cfg = Config::parse("pre_save = mlr --csv sort -f name\npost_load =")?;
+        assert_eq!(cfg.pre_save.as_deref(), Some("mlr --csv sort -f name"));
+        assert_eq!(cfg.post_load, None);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_status_format() -> Result<()> {
+        let cfg = Config::parse("status_format = {file} {dims}[ {filter}]")?;
+        assert_eq!(cfg.status_format.render(|_| "x".into()), "x x x");
+        assert!(Config::parse("status_format = {size}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_colors_and_borders() -> Result<()> {
+        let cfg = Config::parse("colors = 256\nborders = ascii")?;
+        assert_eq!(
+            cfg.caps(),
+            Caps {
+                depth: Depth::Indexed,
+                unicode: false
+            }
+        );
+        assert!(Config::parse("colors = many").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn collects_key_bindings_in_order() -> Result<()> {
+        let cfg = Config::parse("bind s = write\nbind <C-s> = write\nkeyboard = azerty\n")?;
+        assert_eq!(cfg.keyboard, Keyboard::Azerty);
+        assert_eq!(
+            cfg.bindings,
+            vec![
+                ("s".to_string(), "write".to_string()),
+                ("<C-s>".to_string(), "write".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(Config::parse("tick_rate_ms 100").is_err());
+        assert!(Config::parse("tick_rate_ms = 0").is_err());
+        assert!(Config::parse("max_cell_len = -1").is_err());
+        assert!(Config::parse("header = maybe").is_err());
+        assert!(Config::parse("entry = up").is_err());
+    }
+}
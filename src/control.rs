@@ -0,0 +1,300 @@
+//! `--control <socket>`: a Unix socket other programs and test scripts can
+//! drive the running editor through, one request per line and one reply
+//! line each (`ok ...` or `err ...`):
+//!
+//! - `status` — file, size, cursor and state, as a JSON object
+//! - `get B3` / `get A1:C5` — a cell as a JSON string, a block as arrays
+//! - `set B3 <text>` — set a cell, as one undoable edit
+//! - anything else runs as a `:` command (`goto B3`, `w`, `reload`,
+//!   `sort 2 desc`, ...) and replies with the message it left
+//!
+//! Connections are served on their own threads; the requests themselves
+//! are run by the event loop, between keys, so they never race an edit.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::{fs, thread};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::a1;
+use crate::app::{self, App};
+use crate::command;
+use crate::log;
+use crate::report::json_string;
+use crate::scratch;
+
+/// One request line and where its reply goes.
+pub struct Request {
+    pub line: String,
+    reply: Sender<String>,
+}
+
+impl Request {
+    pub fn respond(self, reply: String) {
+        let _ = self.reply.send(reply);
+    }
+}
+
+/// The listening socket; dropping it removes the socket file.
+pub struct Server {
+    path: PathBuf,
+    requests: Receiver<Request>,
+}
+
+impl Server {
+    /// Listen on `path`, readable and writable by this user only. A stale
+    /// socket left by a crashed run is replaced; anything else already at
+    /// `path` is refused rather than removed.
+    pub fn bind(path: &Path) -> Result<Self> {
+        if let Ok(meta) = fs::symlink_metadata(path) {
+            if !meta.file_type().is_socket() {
+                bail!("{path:?} exists and is not a socket");
+            }
+            if UnixStream::connect(path).is_ok() {
+                bail!("{path:?} is already in use by another session");
+            }
+            fs::remove_file(path).with_context(|| format!("remove stale {path:?}"))?;
+        }
+        let listener = bind_private(path)?;
+        let (tx, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || serve(stream, tx));
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            requests,
+        })
+    }
+
+    /// The next waiting request, without blocking.
+    pub fn try_next(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+}
+
+/// Bind in a directory only this user can enter, tighten the socket's
+/// mode there, then link it in at `path`, so it is never reachable under
+/// the umask's looser mode. Linking fails rather than replace anything
+/// that turned up at `path` meanwhile.
+fn bind_private(path: &Path) -> Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = scratch::private_dir(parent, ".tui-csv-editor-control")?;
+    let inner = dir.join("socket");
+    let result = (|| {
+        let listener = UnixListener::bind(&inner).with_context(|| format!("listen on {path:?}"))?;
+        fs::set_permissions(&inner, fs::Permissions::from_mode(0o600))?;
+        fs::hard_link(&inner, path).with_context(|| format!("listen on {path:?}"))?;
+        Ok(listener)
+    })();
+    let _ = fs::remove_file(&inner);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Pass each line of one connection to the event loop and write back its
+/// reply, until the client hangs up or the editor exits.
+fn serve(stream: UnixStream, requests: Sender<Request>) {
+    let Ok(mut out) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let (reply, answer) = mpsc::channel();
+        if requests.send(Request { line, reply }).is_err() {
+            return;
+        }
+        let Ok(answer) = answer.recv() else {
+            return;
+        };
+        if writeln!(out, "{answer}").is_err() {
+            return;
+        }
+    }
+}
+
+/// Run one request against the editor: the reply line, and whether the
+/// editor should now exit.
+pub fn handle(app: &mut App, line: &str) -> (String, bool) {
+    match run(app, line.trim()) {
+        Ok((reply, quit)) => (reply, quit),
+        Err(e) => (
+            format!("err {}", format!("{e:#}").replace('\n', " ")),
+            false,
+        ),
+    }
+}
+
+fn run(app: &mut App, line: &str) -> Result<(String, bool)> {
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    log::event("control", &[("request", &word)]);
+    match word {
+        "status" => Ok((format!("ok {}", status(app)), false)),
+        "get" => Ok((format!("ok {}", get(app, rest)?), false)),
+        "set" => {
+            let (cell, value) = rest.split_once(' ').unwrap_or((rest, ""));
+            let (row, col) = a1::parse_cell(cell)?;
+            if app.read_only {
+                bail!("file is open read-only");
+            }
+            let (rows, cols) = (app.data.len(), app.max_cols());
+            if row >= rows || col >= cols {
+                bail!(
+                    "{} is outside the table ({rows} row(s), {cols} column(s))",
+                    a1::cell_name(row, col)
+                );
+            }
+            app.set_cell(row, col, value.to_string());
+            Ok(("ok".to_string(), false))
+        }
+        "" => bail!("empty request"),
+        _ => {
+            let cmd = command::parse(line)?;
+            app.message = None;
+            let quit = app::run_command(app, cmd)?;
+            let reply = match app.message.take() {
+                Some(m) => format!("ok {}", m.replace('\n', " ")),
+                None => "ok".to_string(),
+            };
+            Ok((reply, quit))
+        }
+    }
+}
+
+fn status(app: &App) -> String {
+    format!(
+        "{{\"file\": {}, \"rows\": {}, \"cols\": {}, \"cell\": \"{}\", \"row\": {}, \"col\": {}, \"dirty\": {}, \"read_only\": {}}}",
+        json_string(&app.file_path.display().to_string()),
+        app.data.len(),
+        app.max_cols(),
+        a1::cell_name(app.row, app.col),
+        app.row + 1,
+        app.col + 1,
+        app.dirty,
+        app.read_only
+    )
+}
+
+fn get(app: &App, range: &str) -> Result<String> {
+    let ((top, left), (bottom, right)) = a1::parse_range(range)?;
+    let cell = |r: usize, c: usize| {
+        json_string(
+            app.data
+                .get(r)
+                .and_then(|row| row.get(c))
+                .map_or("", String::as_str),
+        )
+    };
+    if (top, left) == (bottom, right) {
+        return Ok(cell(top, left));
+    }
+    if bottom >= app.data.len() {
+        return Err(anyhow!("the file has {} rows", app.data.len()));
+    }
+    let rows: Vec<String> = (top..=bottom)
+        .map(|r| {
+            let cells: Vec<String> = (left..=right).map(|c| cell(r, c)).collect();
+            format!("[{}]", cells.join(", "))
+        })
+        .collect();
+    Ok(format!("[{}]", rows.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn answers_status_get_set_and_commands_over_the_socket() -> Result<()> {
+        let mut app = App::new(
+            PathBuf::new(),
+            vec![
+                vec!["id".into(), "name".into()],
+                vec!["1".into(), "bob".into()],
+            ],
+        );
+        assert_eq!(handle(&mut app, "get B2").0, "ok \"bob\"");
+        assert_eq!(
+            handle(&mut app, "get A1:B2").0,
+            "ok [[\"id\", \"name\"], [\"1\", \"bob\"]]"
+        );
+        assert_eq!(handle(&mut app, "set B2 amy lee").0, "ok");
+        assert_eq!(app.data[1][1], "amy lee");
+        assert_eq!(
+            handle(&mut app, "set A999999999 x").0,
+            "err A999999999 is outside the table (2 row(s), 2 column(s))"
+        );
+        assert!(
+            handle(&mut app, "set C1 x")
+                .0
+                .starts_with("err C1 is outside")
+        );
+        assert_eq!(app.data.len(), 2);
+        assert_eq!(handle(&mut app, "goto B2"), ("ok".to_string(), false));
+        assert_eq!(
+            handle(&mut app, "status").0,
+            "ok {\"file\": \"\", \"rows\": 2, \"cols\": 2, \"cell\": \"B2\", \"row\": 2, \"col\": 2, \"dirty\": true, \"read_only\": false}"
+        );
+        assert!(handle(&mut app, "frobnicate").0.starts_with("err "));
+        assert_eq!(handle(&mut app, "q!"), ("ok".to_string(), true));
+
+        // The same over a real socket, answered by a stand-in event loop.
+        let path = env::temp_dir().join(format!("tui_csv_control_{}.sock", std::process::id()));
+        let server = Server::bind(&path)?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        let leftover = format!(".tui-csv-editor-control-{}-", std::process::id());
+        assert!(
+            !fs::read_dir(env::temp_dir())?
+                .flatten()
+                .any(|e| { e.file_name().to_string_lossy().starts_with(&leftover) })
+        );
+        let client = thread::spawn({
+            let path = path.clone();
+            move || -> Result<String> {
+                let mut stream = UnixStream::connect(&path)?;
+                writeln!(stream, "get A1")?;
+                let mut reply = String::new();
+                BufReader::new(stream).read_line(&mut reply)?;
+                Ok(reply)
+            }
+        });
+        let request = loop {
+            if let Some(r) = server.try_next() {
+                break r;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        };
+        let (reply, _) = handle(&mut app, &request.line);
+        request.respond(reply);
+        let reply = client.join().map_err(|_| anyhow!("client panicked"))??;
+        assert_eq!(reply, "ok \"id\"\n");
+        drop(server);
+        assert!(!path.exists());
+
+        // A regular file in the way is left alone.
+        let csv = env::temp_dir().join(format!("tui_csv_control_{}.csv", std::process::id()));
+        fs::write(&csv, "a,b\n")?;
+        assert!(Server::bind(&csv).is_err());
+        assert_eq!(fs::read_to_string(&csv)?, "a,b\n");
+        fs::remove_file(&csv)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,684 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, anyhow};
+use csv::{ByteRecord, ReaderBuilder, Terminator, WriterBuilder};
+
+use crate::compress::Codec;
+use crate::escape;
+
+/// Field separator implied by the file name: tab for `.tsv`, comma otherwise.
+/// A compression suffix is looked through, so `.tsv.gz` is tab-separated.
+pub fn delimiter_for(path: &Path) -> u8 {
+    let inner = if Codec::for_path(path).is_some() {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+    match inner.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("tsv") => b'\t',
+        _ => b',',
+    }
+}
+
+pub fn load_csv(path: &PathBuf) -> Result<Vec<Vec<String>>> {
+    Ok(load_with_spans(path)?.rows)
+}
+
+/// Rows read from a file, and what a later save needs to know about them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loaded {
+    pub rows: Vec<Vec<String>>,
+    /// Where each row sits in a plain (uncompressed) file, read in full.
+    pub spans: Option<Spans>,
+}
+
+/// Byte offsets of the records in a plain file as it was loaded or last
+/// saved, so a save can copy the rows nobody touched instead of writing
+/// them out again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spans {
+    path: PathBuf,
+    /// Where each record starts, then the end of the file. The first is 0,
+    /// so a byte-order mark or blank lines go with the record after them.
+    starts: Vec<u64>,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Spans {
+    fn new(path: &Path, mut starts: Vec<u64>) -> Result<Self> {
+        let meta = fs::metadata(path).with_context(|| format!("stat {path:?}"))?;
+        if let Some(first) = starts.first_mut() {
+            *first = 0;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            starts,
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+
+    /// The file still looks like the one the offsets were taken from: same
+    /// size, same modification time.
+    fn is_current(&self) -> bool {
+        fs::metadata(&self.path)
+            .is_ok_and(|m| m.len() == self.len && m.modified().ok() == self.modified)
+    }
+
+    /// Bytes of record `row`, from its first byte to the next one's.
+    fn range(&self, row: usize) -> Option<(u64, u64)> {
+        Some((*self.starts.get(row)?, *self.starts.get(row + 1)?))
+    }
+}
+
+/// Like [`load_csv`], also noting where each row sits in a plain file.
+pub fn load_with_spans(path: &PathBuf) -> Result<Loaded> {
+    let delimiter = delimiter_for(path);
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let (records, plain) = if let Some(codec) = Codec::for_path(path) {
+        let packed = fs::read(path).with_context(|| format!("read {path:?}"))?;
+        let bytes = codec.decompress(&packed)?;
+        (read_bytes(&bytes, delimiter, threads)?, false)
+    } else {
+        let file = File::open(path).with_context(|| format!("open {path:?}"))?;
+        let big = file
+            .metadata()
+            .is_ok_and(|m| m.len() >= PARALLEL_MIN as u64);
+        let records = if threads < 2 || !big {
+            read_records(file, delimiter)?
+        } else {
+            let bytes = fs::read(path).with_context(|| format!("read {path:?}"))?;
+            read_bytes(&bytes, delimiter, threads)?
+        };
+        (records, true)
+    };
+    let spans = if plain {
+        Some(Spans::new(path, records.starts)?)
+    } else {
+        None
+    };
+    Ok(Loaded {
+        rows: records.rows,
+        spans,
+    })
+}
+
+/// Rows parsed from some bytes, with where each record started in them.
+#[derive(Debug, PartialEq)]
+struct Records {
+    rows: Vec<Vec<String>>,
+    /// One per row, then the end of the input.
+    starts: Vec<u64>,
+}
+
+fn read_bytes(bytes: &[u8], delimiter: u8, threads: usize) -> Result<Records> {
+    if threads > 1 && bytes.len() >= PARALLEL_MIN {
+        return read_parallel(bytes, delimiter, threads);
+    }
+    read_records(bytes, delimiter)
+}
+
+/// Inputs smaller than this parse on one thread; splitting them costs more
+/// than it saves.
+const PARALLEL_MIN: usize = 8 << 20;
+
+/// Offsets that cut `bytes` into about `parts` runs of whole records: each
+/// is just past a newline outside quotes, read the way the csv reader
+/// does, where a quote only opens a field at its start.
+fn split_points(bytes: &[u8], delimiter: u8, parts: usize) -> Vec<usize> {
+    #[derive(PartialEq)]
+    enum State {
+        FieldStart,
+        Unquoted,
+        Quoted,
+        QuoteInQuoted,
+    }
+    let step = bytes.len() / parts.max(1) + 1;
+    let mut points = Vec::new();
+    let mut next = step;
+    let mut state = State::FieldStart;
+    for (i, &b) in bytes.iter().enumerate() {
+        state = match (state, b) {
+            (State::Quoted, b'"') => State::QuoteInQuoted,
+            (State::Quoted, _) => State::Quoted,
+            (State::FieldStart | State::QuoteInQuoted, b'"') => State::Quoted,
+            (_, b'\n') => {
+                if i + 1 >= next && i + 1 < bytes.len() {
+                    points.push(i + 1);
+                    next = i + 1 + step;
+                }
+                State::FieldStart
+            }
+            (_, d) if d == delimiter => State::FieldStart,
+            _ => State::Unquoted,
+        };
+    }
+    points
+}
+
+/// Parse `bytes` in chunks of whole records on `threads` threads and put
+/// the rows back together in order. A chunk that fails to parse, or rows of
+/// unequal length across chunks, go back to one reader, so the error names
+/// the record, line and byte in the whole file.
+fn read_parallel(bytes: &[u8], delimiter: u8, threads: usize) -> Result<Records> {
+    let mut bounds = vec![0];
+    bounds.extend(split_points(bytes, delimiter, threads));
+    bounds.push(bytes.len());
+    let chunks: Vec<&[u8]> = bounds.windows(2).map(|w| &bytes[w[0]..w[1]]).collect();
+    let parsed: Vec<Result<Records>> = thread::scope(|s| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|chunk| s.spawn(move || read_records(*chunk, delimiter)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err(anyhow!("a parser thread panicked")))
+            })
+            .collect()
+    });
+    let mut out = Vec::new();
+    let mut starts = Vec::new();
+    for (part, base) in parsed.into_iter().zip(bounds) {
+        let Ok(part) = part else {
+            return read_records(bytes, delimiter);
+        };
+        let width = out.first().map(Vec::len);
+        if width.is_some_and(|w| part.rows.first().is_some_and(|r| r.len() != w)) {
+            return read_records(bytes, delimiter);
+        }
+        out.extend(part.rows);
+        // Each chunk ends where the next begins.
+        starts.pop();
+        starts.extend(part.starts.iter().map(|s| s + base as u64));
+    }
+    Ok(Records { rows: out, starts })
+}
+
+fn read_records(input: impl std::io::Read, delimiter: u8) -> Result<Records> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .from_reader(input);
+    // Byte records so invalid UTF-8 loads (escaped) instead of failing.
+    let mut out = Vec::new();
+    let mut starts = Vec::new();
+    for rec in rdr.byte_records() {
+        let rec: ByteRecord = rec?;
+        starts.push(rec.position().map_or(0, |p| p.byte()));
+        out.push(rec.iter().map(escape::decode).collect());
+    }
+    starts.push(rdr.position().byte());
+    Ok(Records { rows: out, starts })
+}
+
+/// `data` as CSV text with `delimiter`, the way a save writes it.
+pub fn to_bytes(data: &[Vec<String>], delimiter: u8) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_records(&mut bytes, delimiter, data)?;
+    Ok(bytes)
+}
+
+/// Rows read from CSV text, the way a load reads them.
+pub fn from_bytes(bytes: &[u8], delimiter: u8) -> Result<Vec<Vec<String>>> {
+    Ok(read_records(bytes, delimiter)?.rows)
+}
+
+/// Write `data`, compressed again with the codec its name implies.
+pub fn save_csv(path: &PathBuf, data: &[Vec<String>]) -> Result<()> {
+    if let Some(codec) = Codec::for_path(path) {
+        let packed = codec.compress(&to_bytes(data, delimiter_for(path))?)?;
+        return fs::write(path, packed).with_context(|| format!("write {path:?}"));
+    }
+    let file = File::create(path).with_context(|| format!("create {path:?}"))?;
+    write_records(file, delimiter_for(path), data)
+}
+
+/// Write `data` to the plain file `path`, copying each row that `reuse`
+/// maps to an unchanged record of `source` byte for byte from that file
+/// and serializing only the rest, so saving a few edits to a big file
+/// costs little more than copying it. The result goes to a temporary file
+/// that then replaces `path`. Returns where the rows now sit, for the next
+/// save. Without a usable source (it changed on disk, or uses another
+/// delimiter) every row is written.
+pub fn save_reusing(
+    path: &Path,
+    data: &[Vec<String>],
+    reuse: &[Option<usize>],
+    source: Option<&Spans>,
+) -> Result<Spans> {
+    let delimiter = delimiter_for(path);
+    let source = source.filter(|s| delimiter_for(&s.path) == delimiter && s.is_current());
+    let Some(spans) = source else {
+        let starts = write_replacing(path, |out| {
+            let mut starts = Vec::with_capacity(data.len() + 1);
+            let wtr = record_writer(delimiter, Terminator::Any(b'\n'));
+            for row in data {
+                starts.push(out.written);
+                write_record(&wtr, out, row)?;
+            }
+            starts.push(out.written);
+            Ok(starts)
+        })?;
+        return Spans::new(path, starts);
+    };
+    let mut from = Source {
+        file: BufReader::with_capacity(
+            1 << 20,
+            File::open(&spans.path).with_context(|| format!("open {:?}", spans.path))?,
+        ),
+        at: 0,
+        spans,
+    };
+    // New rows end the way the file's own lines do.
+    let mut buf = Vec::new();
+    from.record(0, &mut buf)?;
+    let (terminator, newline): (_, &[u8]) = if buf.ends_with(b"\r\n") {
+        (Terminator::CRLF, b"\r\n")
+    } else {
+        (Terminator::Any(b'\n'), b"\n")
+    };
+    let starts = write_replacing(path, |out| {
+        let wtr = record_writer(delimiter, terminator);
+        let mut starts = Vec::with_capacity(data.len() + 1);
+        let mut open_line = false;
+        for (r, row) in data.iter().enumerate() {
+            if open_line {
+                out.write_all(newline)?;
+            }
+            starts.push(out.written);
+            // The first record carries any byte-order mark, so it only stays first.
+            let kept = reuse.get(r).copied().flatten().filter(|&o| o > 0 || r == 0);
+            let Some(o) = kept.filter(|&o| o + 1 < spans.starts.len()) else {
+                write_record(&wtr, out, row)?;
+                open_line = false;
+                continue;
+            };
+            from.record(o, &mut buf)?;
+            out.write_all(&buf)?;
+            // The file's last line may have had no newline.
+            open_line = !buf.ends_with(b"\n");
+        }
+        starts.push(out.written);
+        Ok(starts)
+    })?;
+    Spans::new(path, starts)
+}
+
+/// Write `path` by way of a `.saving` file beside it that then replaces
+/// it, so a save that fails part way leaves the old file whole and no
+/// temporary file behind. A link is followed to the file it names, and a
+/// file the rename would detach from its other names or its owner is
+/// overwritten in place instead. Returns what `write` does.
+fn write_replacing(
+    path: &Path,
+    write: impl FnOnce(&mut Counted<BufWriter<File>>) -> Result<Vec<u64>>,
+) -> Result<Vec<u64>> {
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    // Renaming over a file gets round its permissions, so check them first.
+    if path.exists() {
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .with_context(|| format!("open {path:?}"))?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".saving");
+    let temp = PathBuf::from(temp);
+    let result = (|| {
+        let mut out = Counted::new(BufWriter::new(
+            File::create(&temp).with_context(|| format!("create {temp:?}"))?,
+        ));
+        let starts = write(&mut out)?;
+        out.inner.flush()?;
+        drop(out);
+        match fs::metadata(path) {
+            Ok(meta) => {
+                fs::set_permissions(&temp, meta.permissions())?;
+                if can_replace(&temp, &meta) {
+                    fs::rename(&temp, path).with_context(|| format!("replace {path:?}"))?;
+                } else {
+                    fs::copy(&temp, path).with_context(|| format!("write {path:?}"))?;
+                    fs::remove_file(&temp)?;
+                }
+            }
+            Err(_) => fs::rename(&temp, path).with_context(|| format!("create {path:?}"))?,
+        }
+        Ok(starts)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Whether `temp` can take the place of the file `meta` describes: that
+/// file has no other hard links, and `temp` has, or can be given, its
+/// owner.
+#[cfg(unix)]
+fn can_replace(temp: &Path, meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    if meta.nlink() > 1 {
+        return false;
+    }
+    let owner = (meta.uid(), meta.gid());
+    fs::metadata(temp).is_ok_and(|t| (t.uid(), t.gid()) == owner)
+        || std::os::unix::fs::chown(temp, Some(owner.0), Some(owner.1)).is_ok()
+}
+
+#[cfg(not(unix))]
+fn can_replace(_temp: &Path, _meta: &fs::Metadata) -> bool {
+    true
+}
+
+/// The file a save copies unchanged records from.
+struct Source<'a> {
+    file: BufReader<File>,
+    at: u64,
+    spans: &'a Spans,
+}
+
+impl Source<'_> {
+    /// The bytes of record `row` into `buf`. The reader stops a record at
+    /// the `\r` of a `\r\n`, so the `\n` is moved from the start of the
+    /// next record back to the end of this one; reading a byte either side
+    /// shows where that happened.
+    fn record(&mut self, row: usize, buf: &mut Vec<u8>) -> Result<()> {
+        buf.clear();
+        let Some((a, b)) = self.spans.range(row) else {
+            return Ok(());
+        };
+        let (lo, hi) = (a.saturating_sub(1), (b + 1).min(self.spans.len));
+        self.file.seek_relative(lo as i64 - self.at as i64)?;
+        (&mut self.file).take(hi - lo).read_to_end(buf)?;
+        self.at = lo + buf.len() as u64;
+        let crlf = |at: usize| buf.get(at..at + 2) == Some(b"\r\n");
+        let end = if hi > b && !(buf.len() >= 2 && crlf(buf.len() - 2)) {
+            buf.len() - 1
+        } else {
+            buf.len()
+        };
+        let start = match a > lo {
+            true if crlf(0) => 2,
+            true => 1,
+            false => 0,
+        };
+        buf.truncate(end.max(start));
+        buf.drain(..start);
+        Ok(())
+    }
+}
+
+/// A writer that counts the bytes through it.
+struct Counted<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Counted<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn record_writer(delimiter: u8, terminator: Terminator) -> WriterBuilder {
+    let mut builder = WriterBuilder::new();
+    builder
+        .has_headers(false)
+        .delimiter(delimiter)
+        .terminator(terminator)
+        .buffer_capacity(256);
+    builder
+}
+
+fn write_record(builder: &WriterBuilder, out: &mut impl Write, row: &[String]) -> Result<()> {
+    let mut bytes = Vec::new();
+    {
+        let mut wtr = builder.from_writer(&mut bytes);
+        wtr.write_record(row.iter().map(|f| escape::encode(f)))?;
+        wtr.flush()?;
+    }
+    out.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Whether an existing file can be saved in place: its permissions allow it
+/// and it opens for writing (this catches read-only mounts too).
+pub fn is_writable(path: &Path) -> bool {
+    let writable_bits = fs::metadata(path).is_ok_and(|m| !m.permissions().readonly());
+    writable_bits && OpenOptions::new().write(true).open(path).is_ok()
+}
+
+/// Errors that mean "you may not write here" rather than a broken disk.
+pub fn is_permission_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+fn write_records(out: impl std::io::Write, delimiter: u8, data: &[Vec<String>]) -> Result<()> {
+    let mut wtr = WriterBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .from_writer(out);
+    for row in data {
+        wtr.write_record(row.iter().map(|f| escape::encode(f)))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_parallel_parse_matches_one_reader() -> Result<()> {
+        let mut text = String::from("id,note\n");
+        for i in 0..200 {
+            text.push_str(&format!(
+                "{i},\"line\nbreak, \"\"quoted\"\"\"\n{i},5\" screen\n"
+            ));
+        }
+        let bytes = text.as_bytes();
+        let one = read_records(bytes, b',')?;
+        for threads in [2, 3, 7] {
+            assert!(split_points(bytes, b',', threads).len() >= threads - 1);
+            assert_eq!(read_parallel(bytes, b',', threads)?, one);
+        }
+        // A ragged file still fails the way a single reader does.
+        let ragged = "a,b\nc,d\ne\n".repeat(50);
+        assert!(read_parallel(ragged.as_bytes(), b',', 4).is_err());
+        // A short row deep inside a later chunk is reported where it sits in
+        // the file, not where it sits in its chunk.
+        let mut late = "a,b\n".repeat(400);
+        late.push_str("c\n");
+        late.push_str(&"a,b\n".repeat(100));
+        let whole = read_records(late.as_bytes(), b',').unwrap_err();
+        let split = read_parallel(late.as_bytes(), b',', 4).unwrap_err();
+        assert_eq!(format!("{split:#}"), format!("{whole:#}"));
+        assert!(format!("{whole:#}").contains("line: 401"), "{whole:#}");
+        Ok(())
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench_` to compare
+    /// load times on a generated 200 MB file.
+    #[test]
+    #[ignore]
+    fn bench_parallel_load() -> Result<()> {
+        use std::time::Instant;
+        let path = env::temp_dir().join(format!("tui_csv_viewer_bench_{}.csv", std::process::id()));
+        let row = "12345,some text here,\"quoted, with comma\",3.14159,2024-01-31\n";
+        fs::write(&path, row.repeat(200_000_000 / row.len()))?;
+        let bytes = fs::read(&path)?;
+        let start = Instant::now();
+        let one = read_records(bytes.as_slice(), b',')?.rows;
+        let single = start.elapsed();
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        let start = Instant::now();
+        let many = read_parallel(&bytes, b',', threads)?;
+        let parallel = start.elapsed();
+        assert_eq!(one.len(), many.rows.len());
+        println!(
+            "{} rows: 1 thread {single:?}, {threads} threads {parallel:?}",
+            one.len()
+        );
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_copies_unchanged_records() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_reuse_{}.csv", std::process::id()));
+        fs::write(
+            &path,
+            "\u{feff}id,\"name\"\r\n1,\"bob\"\r\n\r\n2,amy\r\n3,\"x\ny\"",
+        )?;
+        let loaded = load_with_spans(&path)?;
+        let mut data = loaded.rows;
+        assert_eq!(data[3], ["3", "x\ny"]);
+        // Edit row 2, drop row 1 and add one at the end.
+        data[2][1] = "amy b".into();
+        data.remove(1);
+        data.push(vec!["4".into(), "new".into()]);
+        let reuse = [Some(0), None, Some(3), None];
+        let spans = save_reusing(&path, &data, &reuse, loaded.spans.as_ref())?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "\u{feff}id,\"name\"\r\n2,amy b\r\n3,\"x\ny\"\r\n4,new\r\n"
+        );
+        assert_eq!(load_csv(&path)?, data);
+        // The returned offsets serve the next save.
+        data.swap(1, 2);
+        save_reusing(
+            &path,
+            &data,
+            &[Some(0), Some(2), Some(1), Some(3)],
+            Some(&spans),
+        )?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "\u{feff}id,\"name\"\r\n3,\"x\ny\"\r\n2,amy b\r\n4,new\r\n"
+        );
+        // Offsets from a file that changed since are not trusted.
+        fs::write(&path, "a,b\n")?;
+        save_reusing(&path, &data[..1], &[Some(0)], Some(&spans))?;
+        assert_eq!(fs::read_to_string(&path)?, "id,name\n");
+        let mut temp = path.clone().into_os_string();
+        temp.push(".saving");
+        assert!(!PathBuf::from(temp).exists());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_symlinks_and_hard_links() -> Result<()> {
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let (target, link, other) = (
+            dir.join(format!("tui_csv_viewer_target_{id}.csv")),
+            dir.join(format!("tui_csv_viewer_link_{id}.csv")),
+            dir.join(format!("tui_csv_viewer_hard_{id}.csv")),
+        );
+        fs::write(&target, "a,b\n")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+        let data = vec![vec!["c".to_string(), "d".to_string()]];
+        save_reusing(&link, &data, &[None], None)?;
+        assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target)?, "c,d\n");
+
+        fs::hard_link(&target, &other)?;
+        let data = vec![vec!["e".to_string()]];
+        save_reusing(&target, &data, &[None], None)?;
+        assert_eq!(fs::read_to_string(&other)?, "e\n");
+        let mut temp = target.clone().into_os_string();
+        temp.push(".saving");
+        assert!(!PathBuf::from(temp).exists());
+        for p in [&link, &other, &target] {
+            fs::remove_file(p)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_and_save_csv_roundtrip() -> Result<()> {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("tui_csv_viewer_test_{}.csv", std::process::id()));
+        fs::write(&path, b"a,b\nc,d\n")?;
+
+        let data = load_csv(&path)?;
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0], vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(data[1], vec!["c".to_string(), "d".to_string()]);
+
+        let mut new_data = data.clone();
+        new_data[1][1] = "dd".into();
+        save_csv(&path, &new_data)?;
+
+        let reread = fs::read_to_string(&path)?;
+        assert!(reread.trim_end().ends_with("c,dd"));
+        let round = load_csv(&path)?;
+        assert_eq!(round[1][1], "dd");
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn tsv_files_use_tabs() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_tsv_{}.tsv", std::process::id()));
+        fs::write(&path, b"a,1\tb\n")?;
+        let data = load_csv(&path)?;
+        assert_eq!(data, vec![vec!["a,1".to_string(), "b".to_string()]]);
+        save_csv(&path, &data)?;
+        assert_eq!(fs::read_to_string(&path)?, "a,1\tb\n");
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_utf8_survives_a_round_trip() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_raw_{}.csv", std::process::id()));
+        let bytes = b"ok,caf\xe9\n\x1b[31m,\"multi\nline\"\n";
+        fs::write(&path, bytes)?;
+        let data = load_csv(&path)?;
+        assert_eq!(data[0][0], "ok");
+        assert!(escape::is_unsafe(&data[1][0]));
+        save_csv(&path, &data)?;
+        assert_eq!(fs::read(&path)?, bytes);
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[test]
+    fn gzip_files_are_recompressed_on_save() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_viewer_gz_{}.tsv.gz", std::process::id()));
+        let data = vec![vec!["a,1".to_string(), "b".to_string()]];
+        save_csv(&path, &data)?;
+        assert_eq!(fs::read(&path)?[..2], [0x1f, 0x8b]);
+        assert_eq!(load_csv(&path)?, data);
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+/// Bytes that aren't valid UTF-8 are kept as `ESCAPE_BASE + byte`, code
+/// points in the last private-use plane that real text doesn't use, so a
+/// file round-trips byte for byte.
+const ESCAPE_BASE: u32 = 0x10_FF00;
+
+fn raw_byte(c: char) -> Option<u8> {
+    (c as u32)
+        .checked_sub(ESCAPE_BASE)
+        .and_then(|b| u8::try_from(b).ok())
+}
+
+/// Decode a field losslessly: valid UTF-8 as is, stray bytes escaped.
+pub fn decode(mut bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(s) => {
+                out.push_str(s);
+                return out;
+            }
+            Err(e) => {
+                let (good, rest) = bytes.split_at(e.valid_up_to());
+                out.push_str(std::str::from_utf8(good).unwrap_or_default());
+                let bad = e.error_len().unwrap_or(rest.len());
+                for &b in &rest[..bad] {
+                    out.push(char::from_u32(ESCAPE_BASE + u32::from(b)).unwrap_or('\u{FFFD}'));
+                }
+                bytes = &rest[bad..];
+            }
+        }
+    }
+}
+
+/// Inverse of [`decode`].
+pub fn encode(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(|c| raw_byte(c).is_some()) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match raw_byte(c) {
+            Some(b) => out.push(b),
+            None => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Whether drawing `s` as is could upset the terminal or hide content.
+pub fn is_unsafe(s: &str) -> bool {
+    s.chars().any(|c| c.is_control() || raw_byte(c).is_some())
+}
+
+/// Whether `s` has bytes that weren't valid UTF-8 in the file.
+pub fn has_raw_bytes(s: &str) -> bool {
+    s.chars().any(|c| raw_byte(c).is_some())
+}
+
+/// `s` with control characters and raw bytes spelled out (`\n`, `\x1b`,
+/// `\xff`), for drawing.
+pub fn display(s: &str) -> Cow<'_, str> {
+    if !is_unsafe(s) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() && (c as u32) < 0x100 => {
+                out.push_str(&format!("\\x{:02x}", c as u32));
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => match raw_byte(c) {
+                Some(b) => out.push_str(&format!("\\x{b:02x}")),
+                None => out.push(c),
+            },
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Like [`display`], but at most `max` bytes of `s`, with a marker saying
+/// how much was cut.
+pub fn clipped(s: &str, max: usize) -> Cow<'_, str> {
+    if s.len() <= max {
+        return display(s);
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut out = display(&s[..end]).into_owned();
+    out.push_str(&format!("…(+{} bytes)", s.len() - end));
+    Cow::Owned(out)
+}
+
+/// `s` exactly as stored, for the formula bar: like [`display`], with
+/// leading and trailing spaces shown as `·` so they can't pass for nothing.
+pub fn raw(s: &str) -> String {
+    let shown = display(s);
+    let body = shown.trim_matches(' ');
+    let lead = shown.len() - shown.trim_start_matches(' ').len();
+    let trail = if body.is_empty() {
+        0
+    } else {
+        shown.len() - shown.trim_end_matches(' ').len()
+    };
+    format!("{}{body}{}", "·".repeat(lead), "·".repeat(trail))
+}
+
+/// Characters that draw as a plain space or as nothing at all.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{a0}' | '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{202f}' | '\u{feff}'
+    )
+}
+
+/// Like [`raw`], with no-break and zero-width spaces spelled out too, for
+/// telling apart two values that look the same.
+pub fn revealed(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in raw(s).chars() {
+        if is_invisible(c) {
+            out.push_str(&format!("\\u{{{:x}}}", c as u32));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Why `a` and `b` differ when they look alike: only in invisible
+/// characters, in spaces at either end, or in case. `None` when they are
+/// equal or plainly different.
+pub fn hidden_difference(a: &str, b: &str) -> Option<&'static str> {
+    if a == b {
+        return None;
+    }
+    let visible = |s: &str| -> String {
+        s.chars()
+            .filter_map(|c| match c {
+                '\u{a0}' | '\u{202f}' => Some(' '),
+                c if is_invisible(c) => None,
+                c => Some(c),
+            })
+            .collect()
+    };
+    if visible(a) == visible(b) {
+        Some("invisible characters")
+    } else if a.trim() == b.trim() {
+        Some("spaces")
+    } else if a.to_lowercase() == b.to_lowercase() {
+        Some("case")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_round_trip_and_display_escaped() {
+        let bytes = b"caf\xe9 \x1b[2J\xff";
+        let text = decode(bytes);
+        assert_eq!(encode(&text), &bytes[..]);
+        assert_eq!(display(&text), "caf\\xe9 \\x1b[2J\\xff");
+        assert!(is_unsafe(&text));
+
+        assert_eq!(decode("héllo".as_bytes()), "héllo");
+        assert!(matches!(display("plain"), Cow::Borrowed("plain")));
+        assert_eq!(display("a\nb\u{85}"), "a\\nb\\x85");
+        assert_eq!(raw("  x y \t "), "··x y \\t·");
+        assert_eq!(raw("   "), "···");
+
+        assert_eq!(clipped("short", 10), "short");
+        assert_eq!(clipped("héllo", 2), "h…(+5 bytes)");
+
+        assert_eq!(
+            hidden_difference("ACME", "ACME\u{200b}"),
+            Some("invisible characters")
+        );
+        assert_eq!(
+            hidden_difference("ACME Inc", "ACME\u{a0}Inc"),
+            Some("invisible characters")
+        );
+        assert_eq!(hidden_difference("ACME ", "ACME"), Some("spaces"));
+        assert_eq!(hidden_difference("Acme", "ACME"), Some("case"));
+        assert_eq!(hidden_difference("ACME", "ACNE"), None);
+        assert_eq!(revealed("a\u{a0}b "), "a\\u{a0}b·");
+    }
+}
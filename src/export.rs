@@ -0,0 +1,674 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use csv::{QuoteStyle, WriterBuilder};
+use unicode_width::UnicodeWidthStr;
+
+use crate::a1;
+use crate::csv_io;
+use crate::escape;
+use crate::report::json_string;
+
+const HTML_STYLE: &str = "\
+body { font-family: sans-serif; margin: 1.5em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 0.2em 0.6em; white-space: pre-wrap; }
+tr:nth-child(even) { background: #f2f2f2; }
+";
+
+/// What `:export` writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Tsv,
+    Json,
+    Markdown,
+    Html,
+    /// Column-aligned CSV in a `.txt` file.
+    Aligned,
+}
+
+impl Format {
+    /// In the order the export dialog offers them.
+    pub const ALL: [Format; 6] = [
+        Format::Csv,
+        Format::Tsv,
+        Format::Json,
+        Format::Markdown,
+        Format::Html,
+        Format::Aligned,
+    ];
+
+    /// The word `:export` and the settings file use.
+    pub fn word(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Json => "json",
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Aligned => "txt",
+        }
+    }
+
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "aligned" => Some(Format::Aligned),
+            "markdown" => Some(Format::Markdown),
+            w => Self::ALL.into_iter().find(|f| f.word() == w),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV",
+            Format::Tsv => "TSV",
+            Format::Json => "JSON",
+            Format::Markdown => "Markdown",
+            Format::Html => "HTML",
+            Format::Aligned => "Aligned text",
+        }
+    }
+
+    /// The extension of the file it goes to; CSV gets `.export.csv` so the
+    /// default can't be the file being edited.
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "export.csv",
+            Format::Tsv => "tsv",
+            Format::Json => "json",
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Aligned => "aligned.txt",
+        }
+    }
+}
+
+/// Which rows the export dialog writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Scope {
+    #[default]
+    All,
+    /// The rows the view's filter lets through, in its order.
+    Shown,
+    /// The block selected with `v`, its columns only.
+    Selection,
+    Tagged,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::All, Scope::Shown, Scope::Selection, Scope::Tagged];
+
+    pub fn word(self) -> &'static str {
+        match self {
+            Scope::All => "all",
+            Scope::Shown => "shown",
+            Scope::Selection => "selection",
+            Scope::Tagged => "tagged",
+        }
+    }
+}
+
+/// Delimiters the dialog offers for CSV.
+pub const DELIMITERS: [u8; 3] = [b',', b';', b'|'];
+
+/// What the export dialog was last set to, kept in `<config dir>/export`
+/// as `key = value` lines so the next export starts there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub format: Format,
+    pub delimiter: u8,   // for CSV; TSV is always a tab
+    pub quote_all: bool, // quote every field, not just the ones that need it
+    pub header: bool,    // write the header row, in header mode
+    pub scope: Scope,
+    pub masked: bool, // write masked columns as they are drawn
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            format: Format::Csv,
+            delimiter: b',',
+            quote_all: false,
+            header: true,
+            scope: Scope::All,
+            masked: false,
+        }
+    }
+}
+
+pub fn settings_file() -> Option<PathBuf> {
+    crate::config::config_dir().map(|d| d.join("export"))
+}
+
+impl Settings {
+    pub fn load(file: &Path) -> Result<Self> {
+        match fs::read_to_string(file) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("{}", file.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read {file:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut s = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| anyhow!("line {}: expected `key = value`", idx + 1))?;
+            let bad = || anyhow!("line {}: bad {key} {value:?}", idx + 1);
+            match key {
+                "format" => s.format = Format::parse(value).ok_or_else(bad)?,
+                "delimiter" => {
+                    s.delimiter = *DELIMITERS
+                        .iter()
+                        .find(|&&d| value == (d as char).to_string())
+                        .ok_or_else(bad)?
+                }
+                "quoting" => s.quote_all = yes_no(value, "all", "needed").ok_or_else(bad)?,
+                "header" => s.header = yes_no(value, "yes", "no").ok_or_else(bad)?,
+                "masks" => s.masked = yes_no(value, "keep", "reveal").ok_or_else(bad)?,
+                "scope" => {
+                    s.scope = Scope::ALL
+                        .into_iter()
+                        .find(|sc| sc.word() == value)
+                        .ok_or_else(bad)?
+                }
+                _ => bail!("line {}: unknown setting {key:?}", idx + 1),
+            }
+        }
+        Ok(s)
+    }
+
+    pub fn to_text(self) -> String {
+        format!(
+            "format = {}\ndelimiter = {}\nquoting = {}\nheader = {}\nscope = {}\nmasks = {}\n",
+            self.format.word(),
+            self.delimiter as char,
+            if self.quote_all { "all" } else { "needed" },
+            if self.header { "yes" } else { "no" },
+            self.scope.word(),
+            if self.masked { "keep" } else { "reveal" }
+        )
+    }
+
+    pub fn save(&self, file: &Path) -> Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create {dir:?}"))?;
+        }
+        fs::write(file, self.to_text()).with_context(|| format!("write {file:?}"))
+    }
+
+    /// Where the export of `csv_path` goes by default.
+    pub fn path_for(&self, csv_path: &Path) -> PathBuf {
+        if csv_path.as_os_str().is_empty() {
+            PathBuf::from(format!("export.{}", self.format.extension()))
+        } else {
+            csv_path.with_extension(self.format.extension())
+        }
+    }
+}
+
+fn yes_no(value: &str, yes: &str, no: &str) -> Option<bool> {
+    match value {
+        v if v == yes => Some(true),
+        v if v == no => Some(false),
+        _ => None,
+    }
+}
+
+/// The dialog's lines, top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Format,
+    Delimiter,
+    Quoting,
+    Header,
+    Scope,
+    Masks,
+    Destination,
+}
+
+pub const FIELDS: [Field; 7] = [
+    Field::Format,
+    Field::Delimiter,
+    Field::Quoting,
+    Field::Header,
+    Field::Scope,
+    Field::Masks,
+    Field::Destination,
+];
+
+/// The export dialog: a line per setting, changed in place, and where the
+/// file goes, typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialog {
+    pub settings: Settings,
+    pub field: usize, // into `FIELDS`
+    pub dest: String,
+    /// Set once the destination is typed into, so picking another format
+    /// no longer renames it.
+    pub dest_typed: bool,
+}
+
+fn cycle<T: Copy + PartialEq>(all: &[T], now: T, forward: bool) -> T {
+    let i = all.iter().position(|&x| x == now).unwrap_or(0);
+    let n = all.len();
+    all[if forward {
+        (i + 1) % n
+    } else {
+        (i + n - 1) % n
+    }]
+}
+
+impl Dialog {
+    pub fn new(settings: Settings, csv_path: &Path) -> Self {
+        Self {
+            settings,
+            field: 0,
+            dest: settings.path_for(csv_path).display().to_string(),
+            dest_typed: false,
+        }
+    }
+
+    pub fn current(&self) -> Field {
+        FIELDS[self.field]
+    }
+
+    pub fn step(&mut self, down: bool) {
+        self.field = if down {
+            (self.field + 1).min(FIELDS.len() - 1)
+        } else {
+            self.field.saturating_sub(1)
+        };
+    }
+
+    /// Move the current setting on to its next (or previous) choice.
+    pub fn change(&mut self, forward: bool, csv_path: &Path) {
+        let field = self.current();
+        let s = &mut self.settings;
+        match field {
+            Field::Format => {
+                s.format = cycle(&Format::ALL, s.format, forward);
+                if !self.dest_typed {
+                    self.dest = s.path_for(csv_path).display().to_string();
+                }
+            }
+            Field::Delimiter => s.delimiter = cycle(&DELIMITERS, s.delimiter, forward),
+            Field::Quoting => s.quote_all = !s.quote_all,
+            Field::Header => s.header = !s.header,
+            Field::Scope => s.scope = cycle(&Scope::ALL, s.scope, forward),
+            Field::Masks => s.masked = !s.masked,
+            Field::Destination => {}
+        }
+    }
+
+    /// The current setting as the dialog shows it.
+    pub fn value(&self, field: Field) -> String {
+        let s = &self.settings;
+        let unused = || format!("(not used for {})", s.format.label());
+        match field {
+            Field::Format => s.format.label().to_string(),
+            Field::Delimiter => match s.format {
+                Format::Tsv => "tab".to_string(),
+                Format::Csv | Format::Aligned => (s.delimiter as char).to_string(),
+                _ => unused(),
+            },
+            Field::Quoting => match s.format {
+                Format::Csv | Format::Tsv if s.quote_all => "every field".to_string(),
+                Format::Csv | Format::Tsv => "where needed".to_string(),
+                _ => unused(),
+            },
+            Field::Header if s.header => "yes".to_string(),
+            Field::Header => "no".to_string(),
+            Field::Scope => match s.scope {
+                Scope::All => "all rows",
+                Scope::Shown => "shown rows (filtered)",
+                Scope::Selection => "selection",
+                Scope::Tagged => "tagged rows",
+            }
+            .to_string(),
+            Field::Masks if s.masked => "keep (write •••)".to_string(),
+            Field::Masks => "reveal (write the values)".to_string(),
+            Field::Destination => self.dest.clone(),
+        }
+    }
+}
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Format => "Format",
+            Field::Delimiter => "Delimiter",
+            Field::Quoting => "Quoting",
+            Field::Header => "Header",
+            Field::Scope => "Rows",
+            Field::Masks => "Masked columns",
+            Field::Destination => "Save to",
+        }
+    }
+}
+
+/// `table` in `settings`' format. With `header`, its first row names the
+/// columns: JSON gets objects, Markdown and HTML a heading row. Without,
+/// JSON gets arrays and Markdown column letters over the rows.
+pub fn render(
+    settings: &Settings,
+    title: &str,
+    table: &[Vec<String>],
+    header: bool,
+) -> Result<Vec<u8>> {
+    let cols = table.iter().map(Vec::len).max().unwrap_or(0);
+    let text = match settings.format {
+        Format::Csv | Format::Tsv => {
+            let delimiter = match settings.format {
+                Format::Tsv => b'\t',
+                _ => settings.delimiter,
+            };
+            let style = if settings.quote_all {
+                QuoteStyle::Always
+            } else {
+                QuoteStyle::Necessary
+            };
+            let mut wtr = WriterBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .delimiter(delimiter)
+                .quote_style(style)
+                .from_writer(Vec::new());
+            for row in table {
+                wtr.write_record(row.iter().map(|f| escape::encode(f)))?;
+            }
+            return wtr.into_inner().map_err(|e| anyhow!("{}", e.error()));
+        }
+        Format::Json => to_json(table, header),
+        Format::Markdown => to_markdown(table, header, cols),
+        Format::Html => html(title, table, header),
+        Format::Aligned => to_aligned(table, settings.delimiter),
+    };
+    Ok(text.into_bytes())
+}
+
+fn to_json(table: &[Vec<String>], header: bool) -> String {
+    let (names, body) = match table.split_first() {
+        Some((first, rest)) if header => (Some(first), rest),
+        _ => (None, table),
+    };
+    let items: Vec<String> = body
+        .iter()
+        .map(|row| {
+            let values = row.iter().map(|v| json_string(v));
+            match names {
+                Some(names) => {
+                    let pairs: Vec<String> = names
+                        .iter()
+                        .zip(values)
+                        .map(|(n, v)| format!("{}: {v}", json_string(n)))
+                        .collect();
+                    format!("  {{{}}}", pairs.join(", "))
+                }
+                None => format!("  [{}]", values.collect::<Vec<_>>().join(", ")),
+            }
+        })
+        .collect();
+    if items.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", items.join(",\n"))
+    }
+}
+
+fn markdown_cell(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+fn to_markdown(table: &[Vec<String>], header: bool, cols: usize) -> String {
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let cells = |row: &[String]| {
+        (0..cols)
+            .map(|c| markdown_cell(row.get(c).map_or("", String::as_str)))
+            .collect::<Vec<_>>()
+    };
+    let (head, body) = match table.split_first() {
+        Some((first, rest)) if header => (cells(first), rest),
+        _ => ((0..cols).map(a1::col_name).collect(), table),
+    };
+    let mut out = line(head);
+    out.push_str(&line(vec!["---".to_string(); cols]));
+    for row in body {
+        out.push_str(&line(cells(row)));
+    }
+    out
+}
+
+/// Default destination for an HTML export: the CSV path with an `.html` extension.
+pub fn html_path_for(csv_path: &Path) -> PathBuf {
+    csv_path.with_extension("html")
+}
+
+/// Escape text for use in HTML element content and attribute values.
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Render rows as a standalone HTML document. Short rows are padded so the
+/// table stays rectangular.
+pub fn to_html(title: &str, data: &[Vec<String>]) -> String {
+    html(title, data, false)
+}
+
+/// [`to_html`], with the first row as `<th>` cells when `header` is set.
+fn html(title: &str, data: &[Vec<String>], header: bool) -> String {
+    let cols = data.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    out.push_str(&format!("<style>\n{HTML_STYLE}</style>\n"));
+    out.push_str("</head>\n<body>\n<table>\n");
+    for (i, row) in data.iter().enumerate() {
+        let tag = if header && i == 0 { "th" } else { "td" };
+        out.push_str("<tr>");
+        for c in 0..cols {
+            let txt = row.get(c).map(String::as_str).unwrap_or("");
+            out.push_str(&format!("<{tag}>{}</{tag}>", escape_html(txt)));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+pub fn export_html(path: &Path, title: &str, data: &[Vec<String>]) -> Result<()> {
+    fs::write(path, to_html(title, data)).with_context(|| format!("write {path:?}"))
+}
+
+/// Destination for an aligned export: `data.csv` -> `data.aligned.txt`.
+pub fn aligned_path_for(csv_path: &Path) -> PathBuf {
+    csv_path.with_extension("aligned.txt")
+}
+
+/// A field quoted as CSV requires. Leading or trailing spaces are quoted
+/// too, so they can't be confused with the alignment padding.
+fn quote(field: &str, delim: u8) -> String {
+    let needs = field.contains([delim as char, '"', '\n', '\r'])
+        || field.starts_with(char::is_whitespace)
+        || field.ends_with(char::is_whitespace);
+    if needs {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV with each field padded to its column's display width, so the file
+/// reads as a table with `cat`. Reading it back with surrounding whitespace
+/// trimmed gives the original fields.
+pub fn to_aligned(data: &[Vec<String>], delim: u8) -> String {
+    let quoted: Vec<Vec<String>> = data
+        .iter()
+        .map(|r| r.iter().map(|f| quote(f, delim)).collect())
+        .collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &quoted {
+        for (c, f) in row.iter().enumerate() {
+            // Only single-line fields take part; a multiline one would widen
+            // the column for no visual gain.
+            let w = if f.contains('\n') { 0 } else { f.width() };
+            match widths.get_mut(c) {
+                Some(max) => *max = (*max).max(w),
+                None => widths.push(w),
+            }
+        }
+    }
+    let mut out = String::new();
+    for row in &quoted {
+        for (c, f) in row.iter().enumerate() {
+            out.push_str(f);
+            if c + 1 < row.len() {
+                let pad = widths[c].saturating_sub(f.width());
+                out.extend(std::iter::repeat_n(' ', pad));
+                // No space after the delimiter: a quote must start the field.
+                out.push(delim as char);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn export_aligned(path: &Path, csv_path: &Path, data: &[Vec<String>]) -> Result<()> {
+    let text = to_aligned(data, csv_io::delimiter_for(csv_path));
+    fs::write(path, text).with_context(|| format!("write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn renders_padded_rows() {
+        let data = vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["<c>".to_string()],
+        ];
+        let html = to_html("t.csv", &data);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>t.csv</title>"));
+        assert!(html.contains("<tr><td>a</td><td>b</td></tr>"));
+        assert!(html.contains("<tr><td>&lt;c&gt;</td><td></td></tr>"));
+    }
+
+    #[test]
+    fn aligned_output_pads_columns_and_reads_back_trimmed() -> Result<()> {
+        let data = vec![
+            vec!["name".to_string(), "note".to_string(), "qty".to_string()],
+            vec!["東京".to_string(), "a, b".to_string(), "12".to_string()],
+            vec!["x".to_string(), "ok".to_string(), "3".to_string()],
+        ];
+        let text = to_aligned(&data, b',');
+        assert_eq!(text, "name,note  ,qty\n東京,\"a, b\",12\nx   ,ok    ,3\n");
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+        let back: Vec<Vec<String>> = rdr
+            .records()
+            .map(|r| Ok(r?.iter().map(str::to_string).collect()))
+            .collect::<Result<_>>()?;
+        assert_eq!(back, data);
+        assert_eq!(to_aligned(&[vec![" x".to_string()]], b','), "\" x\"\n");
+        Ok(())
+    }
+
+    #[test]
+    fn renders_the_dialog_formats_and_round_trips_its_settings() -> Result<()> {
+        let table = vec![
+            vec!["name".to_string(), "note".to_string()],
+            vec!["a|b".to_string(), "x\ny".to_string()],
+        ];
+        let render_as = |format, header| -> Result<String> {
+            let s = Settings {
+                format,
+                ..Settings::default()
+            };
+            Ok(String::from_utf8(render(&s, "t", &table, header)?)?)
+        };
+        assert_eq!(
+            render_as(Format::Json, true)?,
+            "[\n  {\"name\": \"a|b\", \"note\": \"x\\ny\"}\n]\n"
+        );
+        assert_eq!(
+            render_as(Format::Json, false)?,
+            "[\n  [\"name\", \"note\"],\n  [\"a|b\", \"x\\ny\"]\n]\n"
+        );
+        assert_eq!(
+            render_as(Format::Markdown, true)?,
+            "| name | note |\n| --- | --- |\n| a\\|b | x<br>y |\n"
+        );
+        assert!(render_as(Format::Html, true)?.contains("<tr><th>name</th><th>note</th></tr>"));
+        let semi = Settings {
+            delimiter: b';',
+            quote_all: true,
+            ..Settings::default()
+        };
+        let csv = String::from_utf8(render(&semi, "t", &table[..1], true)?)?;
+        assert_eq!(csv, "\"name\";\"note\"\n");
+
+        let saved = Settings {
+            format: Format::Markdown,
+            scope: Scope::Shown,
+            header: false,
+            ..semi
+        };
+        assert_eq!(Settings::parse(&saved.to_text())?, saved);
+        assert!(Settings::parse("format = xls").is_err());
+
+        let csv_path = Path::new("data.csv");
+        let mut d = Dialog::new(Settings::default(), csv_path);
+        assert_eq!(d.dest, "data.export.csv");
+        d.change(true, csv_path);
+        assert_eq!(
+            (d.settings.format, d.dest.as_str()),
+            (Format::Tsv, "data.tsv")
+        );
+        assert_eq!(d.value(Field::Delimiter), "tab");
+        d.dest_typed = true;
+        d.change(true, csv_path);
+        assert_eq!(
+            (d.settings.format, d.dest.as_str()),
+            (Format::Json, "data.tsv")
+        );
+        assert_eq!(d.value(Field::Quoting), "(not used for JSON)");
+        Ok(())
+    }
+}
@@ -0,0 +1,593 @@
+//! Row expressions such as `total == qty * price`: arithmetic over a row's
+//! cells compared with `== != < <= > >=`. Columns are written as header
+//! names (`qty`, or `[unit price]` with spaces), or `$3` by number; text
+//! literals are quoted. `col("status")` is a cell as it is written and
+//! `num("latency")` one that has to be a number.
+//!
+//! Filters go further with [`Condition`]: comparisons and regex matches
+//! (`=~`, `!~`) joined with `&&`, `||` and `!` (or `and`, `or`, `not`).
+
+use std::cmp::Ordering;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::header;
+use crate::nulls::Nulls;
+use crate::regex::Regex;
+use crate::viz::{Decimal, format_number};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn holds(self, o: Ordering) -> bool {
+        match self {
+            Cmp::Eq => o == Ordering::Equal,
+            Cmp::Ne => o != Ordering::Equal,
+            Cmp::Lt => o == Ordering::Less,
+            Cmp::Le => o != Ordering::Greater,
+            Cmp::Gt => o == Ordering::Greater,
+            Cmp::Ge => o != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(f64),
+    Text(String),
+    /// A column by name, until [`Rule::resolve`] turns it into an index.
+    Name(String),
+    Col(usize),
+    /// `col(...)`: the cell's text, never a number.
+    Raw(Box<Expr>),
+    /// `num(...)`: the cell, which must be a number.
+    Number(Box<Expr>),
+    Neg(Box<Expr>),
+    Bin(char, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Text(String),
+}
+
+impl Value {
+    fn show(&self) -> String {
+        match self {
+            Value::Num(n) => format_number(*n),
+            Value::Text(t) => format!("{t:?}"),
+        }
+    }
+}
+
+/// Numbers compare as numbers, with a little slack for float rounding;
+/// anything else compares as text, which suits ISO dates.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Num(x), Value::Num(y)) => {
+            let slack = 1e-9 * x.abs().max(y.abs()).max(1.0);
+            if (x - y).abs() <= slack {
+                Ordering::Equal
+            } else {
+                x.total_cmp(y)
+            }
+        }
+        _ => text(a).cmp(&text(b)),
+    }
+}
+
+fn text(v: &Value) -> String {
+    match v {
+        Value::Num(n) => format_number(*n),
+        Value::Text(t) => t.clone(),
+    }
+}
+
+/// What checking a rule against one row found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Pass,
+    /// A referenced cell is blank or null; `required` covers those.
+    Missing,
+    /// The comparison is false; both sides' values, for the message.
+    Fail(String, String),
+    /// The row couldn't be evaluated, e.g. arithmetic on text.
+    Error(String),
+}
+
+/// A comparison that every row should satisfy.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub text: String,
+    left: Expr,
+    cmp: Cmp,
+    right: Expr,
+}
+
+impl Rule {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut p = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let left = p.sum()?;
+        let cmp = p.cmp()?;
+        let right = p.sum()?;
+        p.skip_space();
+        if p.pos < p.chars.len() {
+            bail!("unexpected {:?} at {}", p.chars[p.pos], p.pos + 1);
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            left,
+            cmp,
+            right,
+        })
+    }
+
+    /// Turn column names into indices against `headers`.
+    pub fn resolve(&mut self, headers: &[String]) -> Result<()> {
+        resolve(&mut self.left, headers)?;
+        resolve(&mut self.right, headers)
+    }
+
+    /// The first column the rule mentions, where its findings point.
+    pub fn first_col(&self) -> Option<usize> {
+        fn walk(e: &Expr) -> Option<usize> {
+            match e {
+                Expr::Col(c) => Some(*c),
+                Expr::Raw(a) | Expr::Number(a) | Expr::Neg(a) => walk(a),
+                Expr::Bin(_, a, b) => walk(a).or_else(|| walk(b)),
+                _ => None,
+            }
+        }
+        walk(&self.left).or_else(|| walk(&self.right))
+    }
+
+    pub fn check(&self, row: &[String], nulls: &Nulls, decimal: Decimal) -> Outcome {
+        let sides = eval(&self.left, row, nulls, decimal)
+            .and_then(|l| Ok((l, eval(&self.right, row, nulls, decimal)?)));
+        match sides {
+            Ok((Some(l), Some(r))) if self.cmp.holds(compare(&l, &r)) => Outcome::Pass,
+            Ok((Some(l), Some(r))) => Outcome::Fail(l.show(), r.show()),
+            Ok(_) => Outcome::Missing,
+            Err(e) => Outcome::Error(format!("{e:#}")),
+        }
+    }
+}
+
+fn resolve(e: &mut Expr, headers: &[String]) -> Result<()> {
+    match e {
+        Expr::Name(n) => *e = Expr::Col(header::resolve_column(headers, n)?),
+        Expr::Raw(a) | Expr::Number(a) | Expr::Neg(a) => resolve(a, headers)?,
+        Expr::Bin(_, a, b) => {
+            resolve(a, headers)?;
+            resolve(b, headers)?;
+        }
+        Expr::Num(_) | Expr::Text(_) | Expr::Col(_) => {}
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    Cmp(Expr, Cmp, Expr),
+    /// `=~` when the flag is set, `!~` when not.
+    Match(Expr, Regex, bool),
+    Not(Box<Cond>),
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+}
+
+/// A filter over whole rows, such as
+/// `col("status") == "FAILED" && num("latency") > 500`. A comparison with
+/// a blank cell, or one that can't be worked out, doesn't hold.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    root: Cond,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut p = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let root = p.or()?;
+        p.skip_space();
+        if p.pos < p.chars.len() {
+            bail!("unexpected {:?} at {}", p.chars[p.pos], p.pos + 1);
+        }
+        Ok(Self { root })
+    }
+
+    /// Turn column names into indices against `headers`.
+    pub fn resolve(&mut self, headers: &[String]) -> Result<()> {
+        fn walk(c: &mut Cond, headers: &[String]) -> Result<()> {
+            match c {
+                Cond::Cmp(a, _, b) => {
+                    resolve(a, headers)?;
+                    resolve(b, headers)
+                }
+                Cond::Match(a, ..) => resolve(a, headers),
+                Cond::Not(a) => walk(a, headers),
+                Cond::And(a, b) | Cond::Or(a, b) => {
+                    walk(a, headers)?;
+                    walk(b, headers)
+                }
+            }
+        }
+        walk(&mut self.root, headers)
+    }
+
+    pub fn matches(&self, row: &[String], nulls: &Nulls, decimal: Decimal) -> bool {
+        fn holds(c: &Cond, row: &[String], nulls: &Nulls, decimal: Decimal) -> bool {
+            let value = |e| eval(e, row, nulls, decimal).ok().flatten();
+            match c {
+                Cond::Cmp(a, cmp, b) => match (value(a), value(b)) {
+                    (Some(a), Some(b)) => cmp.holds(compare(&a, &b)),
+                    _ => false,
+                },
+                Cond::Match(a, re, want) => {
+                    value(a).is_some_and(|v| re.is_match(&text(&v)) == *want)
+                }
+                Cond::Not(a) => !holds(a, row, nulls, decimal),
+                Cond::And(a, b) => holds(a, row, nulls, decimal) && holds(b, row, nulls, decimal),
+                Cond::Or(a, b) => holds(a, row, nulls, decimal) || holds(b, row, nulls, decimal),
+            }
+        }
+        holds(&self.root, row, nulls, decimal)
+    }
+}
+
+/// `None` when a cell it needs is missing.
+fn eval(e: &Expr, row: &[String], nulls: &Nulls, decimal: Decimal) -> Result<Option<Value>> {
+    let num = |v: Value| match v {
+        Value::Num(n) => Ok(n),
+        Value::Text(t) => Err(anyhow!("{t:?} is not a number")),
+    };
+    Ok(Some(match e {
+        Expr::Num(n) => Value::Num(*n),
+        Expr::Text(t) => Value::Text(t.clone()),
+        Expr::Name(n) => bail!("unresolved column {n:?}"),
+        Expr::Col(c) => {
+            let cell = row.get(*c).map_or("", String::as_str);
+            if cell.trim().is_empty() || nulls.is_null(cell) {
+                return Ok(None);
+            }
+            match decimal.parse(cell) {
+                Some(n) => Value::Num(n),
+                None => Value::Text(cell.trim().to_string()),
+            }
+        }
+        Expr::Raw(a) => match &**a {
+            Expr::Col(c) => Value::Text(row.get(*c).cloned().unwrap_or_default()),
+            _ => bail!("col() takes a column"),
+        },
+        Expr::Number(a) => match eval(a, row, nulls, decimal)? {
+            Some(v) => Value::Num(num(v)?),
+            None => return Ok(None),
+        },
+        Expr::Neg(a) => match eval(a, row, nulls, decimal)? {
+            Some(v) => Value::Num(-num(v)?),
+            None => return Ok(None),
+        },
+        Expr::Bin(op, a, b) => {
+            let (Some(a), Some(b)) = (eval(a, row, nulls, decimal)?, eval(b, row, nulls, decimal)?)
+            else {
+                return Ok(None);
+            };
+            let (a, b) = (num(a)?, num(b)?);
+            Value::Num(match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                _ if b == 0.0 => bail!("division by zero"),
+                _ => a / b,
+            })
+        }
+    }))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_space(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        self.skip_space();
+        let n = s.chars().count();
+        if self.chars[self.pos..].iter().take(n).copied().eq(s.chars()) {
+            self.pos += n;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A keyword such as `and`, in any case, that isn't the start of a
+    /// longer name.
+    fn eat_word(&mut self, word: &str) -> bool {
+        self.skip_space();
+        let n = word.chars().count();
+        let here = self.chars[self.pos..].iter().take(n).collect::<String>();
+        let next = self.chars.get(self.pos + n);
+        if here.eq_ignore_ascii_case(word)
+            && !next.is_some_and(|&c| c.is_alphanumeric() || c == '_')
+        {
+            self.pos += n;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Cond> {
+        let mut c = self.and()?;
+        while self.eat("||") || self.eat_word("or") {
+            c = Cond::Or(Box::new(c), Box::new(self.and()?));
+        }
+        Ok(c)
+    }
+
+    fn and(&mut self) -> Result<Cond> {
+        let mut c = self.not()?;
+        while self.eat("&&") || self.eat_word("and") {
+            c = Cond::And(Box::new(c), Box::new(self.not()?));
+        }
+        Ok(c)
+    }
+
+    fn not(&mut self) -> Result<Cond> {
+        if self.eat("!") || self.eat_word("not") {
+            return Ok(Cond::Not(Box::new(self.not()?)));
+        }
+        // `(` opens either a group of conditions or arithmetic such as
+        // `(a + b) > 3`; try the group first.
+        let start = self.pos;
+        if self.eat("(") {
+            if let Ok(c) = self.or()
+                && self.eat(")")
+                && !matches!(
+                    self.peek(),
+                    Some('+' | '-' | '*' | '/' | '=' | '!' | '<' | '>')
+                )
+            {
+                return Ok(c);
+            }
+            self.pos = start;
+        }
+        let left = self.sum()?;
+        for (op, want) in [("=~", true), ("!~", false)] {
+            if self.eat(op) {
+                self.skip_space();
+                let at = self.pos + 1;
+                let Expr::Text(pattern) = self.atom()? else {
+                    bail!("expected a quoted regex after {op} at {at}");
+                };
+                let re = Regex::new(&pattern).map_err(|e| anyhow!("{e:#} at {at}"))?;
+                return Ok(Cond::Match(left, re, want));
+            }
+        }
+        let cmp = self.cmp()?;
+        Ok(Cond::Cmp(left, cmp, self.sum()?))
+    }
+
+    fn cmp(&mut self) -> Result<Cmp> {
+        for (s, c) in [
+            ("==", Cmp::Eq),
+            ("!=", Cmp::Ne),
+            ("<=", Cmp::Le),
+            (">=", Cmp::Ge),
+            ("<", Cmp::Lt),
+            (">", Cmp::Gt),
+        ] {
+            if self.eat(s) {
+                return Ok(c);
+            }
+        }
+        bail!(
+            "expected a comparison (== != < <= > >=) at {}",
+            self.pos + 1
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut e = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            e = Expr::Bin(op, Box::new(e), Box::new(self.term()?));
+        }
+        Ok(e)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut e = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.pos += 1;
+            e = Expr::Bin(op, Box::new(e), Box::new(self.unary()?));
+        }
+        Ok(e)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    /// Characters from here while `f` holds.
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|&c| f(c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Characters up to `close`, which is consumed.
+    fn until(&mut self, close: char) -> Result<String> {
+        let s = self.take_while(|c| c != close);
+        if !self.eat(&close.to_string()) {
+            bail!("missing closing {close}");
+        }
+        Ok(s)
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        let at = self.pos + 1;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let e = self.sum()?;
+                if !self.eat(")") {
+                    bail!("missing closing )");
+                }
+                Ok(e)
+            }
+            Some(q @ ('"' | '\'')) => {
+                self.pos += 1;
+                Ok(Expr::Text(self.until(q)?))
+            }
+            Some('[') => {
+                self.pos += 1;
+                Ok(Expr::Name(self.until(']')?.trim().to_string()))
+            }
+            Some('$') => {
+                self.pos += 1;
+                let n = self.take_while(|c| c.is_ascii_digit());
+                Ok(Expr::Name(n))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let n = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                n.parse()
+                    .map(Expr::Num)
+                    .map_err(|_| anyhow!("bad number {n:?} at {at}"))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                if (name == "col" || name == "num") && self.eat("(") {
+                    let col = match self.atom()? {
+                        Expr::Text(t) => Expr::Name(t),
+                        Expr::Num(n) => Expr::Name(n.to_string()),
+                        e @ Expr::Name(_) => e,
+                        _ => bail!("{name}() takes a column name or number at {at}"),
+                    };
+                    if !self.eat(")") {
+                        bail!("missing closing ) of {name}() at {}", self.pos + 1);
+                    }
+                    return Ok(if name == "col" {
+                        Expr::Raw(Box::new(col))
+                    } else {
+                        Expr::Number(Box::new(col))
+                    });
+                }
+                Ok(Expr::Name(name))
+            }
+            Some(c) => bail!("unexpected {c:?} at {at}"),
+            None => bail!("expression ends early"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn checks_arithmetic_and_date_comparisons() -> Result<()> {
+        let headers = row(&["qty", "unit price", "total", "start", "end"]);
+        let mut total = Rule::parse("total == qty * [unit price]")?;
+        total.resolve(&headers)?;
+        let nulls = Nulls::default();
+        let ok = row(&["3", "0.1", "0.3", "2024-01-02", "2024-02-01"]);
+        assert_eq!(total.check(&ok, &nulls, Decimal::Dot), Outcome::Pass);
+        let bad = row(&["3", "2", "5", "2024-03-01", "2024-02-01"]);
+        assert_eq!(
+            total.check(&bad, &nulls, Decimal::Dot),
+            Outcome::Fail("5".into(), "6".into())
+        );
+        assert_eq!(total.first_col(), Some(2));
+
+        let mut dates = Rule::parse("end >= start")?;
+        dates.resolve(&headers)?;
+        assert_eq!(dates.check(&ok, &nulls, Decimal::Dot), Outcome::Pass);
+        assert!(matches!(
+            dates.check(&bad, &nulls, Decimal::Dot),
+            Outcome::Fail(..)
+        ));
+        let open = row(&["1", "1", "1", "2024-01-01", "NA"]);
+        assert_eq!(dates.check(&open, &nulls, Decimal::Dot), Outcome::Missing);
+
+        let mut text = Rule::parse("$1 * 2 > -(total - 10) / 2")?;
+        text.resolve(&headers)?;
+        let words = row(&["many", "1", "1"]);
+        assert!(
+            matches!(text.check(&words, &nulls, Decimal::Dot), Outcome::Error(e) if e.contains("not a number"))
+        );
+        assert!(Rule::parse("nope").is_err());
+        assert!(Rule::parse("a == (b").is_err());
+        assert!(Rule::parse("total > 1 x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn filters_rows_with_and_or_not_and_regex() -> Result<()> {
+        let headers = row(&["id", "status", "latency", "msg"]);
+        let nulls = Nulls::default();
+        let keeps = |text: &str, cells: &[&str]| -> Result<bool> {
+            let mut cond = Condition::parse(text)?;
+            cond.resolve(&headers)?;
+            Ok(cond.matches(&row(cells), &nulls, Decimal::Dot))
+        };
+        let slow = r#"col("status")=="FAILED" && num("latency")>500"#;
+        assert!(keeps(slow, &["1", "FAILED", "750", ""])?);
+        assert!(!keeps(slow, &["2", "FAILED", "120", ""])?);
+        assert!(!keeps(slow, &["3", "FAILED", "n/a", ""])?);
+        // col() keeps the text as written, so leading zeros count.
+        assert!(keeps(r#"col(1) == "007""#, &["007"])?);
+        assert!(!keeps("id == \"007\"", &["007"])?);
+        let either = r#"not (status == "OK" or msg =~ "^time.?out") && (latency + 1) * 2 > 10"#;
+        assert!(keeps(either, &["4", "FAILED", "9", "refused"])?);
+        assert!(!keeps(either, &["5", "FAILED", "9", "timeout"])?);
+        assert!(!keeps(either, &["6", "OK", "9", ""])?);
+        assert!(keeps(r#"msg !~ "x" || !(id > 0)"#, &["0", "", "", "y"])?);
+
+        for (bad, at) in [
+            (r#"col("status") = "x""#, "at 15"),
+            (r#"status == "x" &&"#, "ends early"),
+            (r#"msg =~ latency"#, "at 8"),
+            (r#"num("latency" > 5"#, "at 15"),
+        ] {
+            let e = Condition::parse(bad).map(|_| ()).unwrap_err();
+            assert!(format!("{e:#}").contains(at), "{bad}: {e:#}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::journal::Edit;
+use crate::scratch;
+
+/// What to hand to the external editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Cell,
+    Row,
+    File,
+}
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`. The value may carry arguments,
+/// e.g. `code --wait`.
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|v| env::var(v).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// A file in this process's private scratch directory, ending in `.ext`.
+pub fn temp_path(ext: &str) -> Result<PathBuf> {
+    scratch::path(&format!("edit.{ext}"))
+}
+
+/// Run the editor on `path` and wait for it to exit.
+pub fn run_editor(path: &Path) -> Result<()> {
+    let cmd = editor();
+    let mut words = cmd.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("run {cmd}"))?;
+    if !status.success() {
+        bail!("{cmd} exited with {status}");
+    }
+    Ok(())
+}
+
+/// `command` run by the shell, so pipes and quoting work as typed.
+pub fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+/// Run `cmd` with `input` on its stdin and return what it wrote to stdout.
+/// A failed exit is an error carrying its stderr; `name` says what ran.
+pub fn pipe(mut cmd: Command, name: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("run {name} (is it installed?)"))?;
+    // Feed stdin from another thread so a full stdout pipe can't deadlock.
+    let mut stdin = child.stdin.take().context("open pipe")?;
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let out = child.wait_with_output()?;
+    let written = writer
+        .join()
+        .map_err(|_| anyhow!("{name} input thread panicked"))?;
+    if !out.status.success() {
+        bail!(
+            "{name} failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    // A filter may stop reading early (`head`); that's not an error.
+    if let Err(e) = written
+        && e.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        return Err(e).with_context(|| format!("write to {name}"));
+    }
+    Ok(out.stdout)
+}
+
+/// The scripts in `dir` (`<config dir>/scripts`), sorted.
+pub fn scripts(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.metadata().is_ok_and(|m| is_script(&m)))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+/// An executable file. Without an execute bit to go by, any file counts.
+#[cfg(unix)]
+fn is_script(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_script(meta: &fs::Metadata) -> bool {
+    meta.is_file()
+}
+
+/// The script called `name` in `dir`; names can't reach outside it.
+pub fn script_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        bail!("bad script name {name:?}");
+    }
+    let path = dir.join(name);
+    if !path.is_file() {
+        bail!("no script {name} in {}", dir.display());
+    }
+    Ok(path)
+}
+
+/// Edit plain text; the newline most editors add at the end is dropped.
+pub fn edit_text(text: &str) -> Result<String> {
+    let path = temp_path("txt")?;
+    fs::write(&path, text).with_context(|| format!("write {}", path.display()))?;
+    let result = run_editor(&path).and_then(|()| {
+        fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))
+    });
+    let _ = fs::remove_file(&path);
+    let mut text = result?;
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    Ok(text)
+}
+
+/// Edits turning `old`, the rows from `first` on, into `new`: changed cells
+/// are set, extra rows inserted after them and missing ones deleted.
+pub fn diff(old: &[Vec<String>], new: &[Vec<String>], first: usize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    for (i, (a, b)) in old.iter().zip(new).enumerate() {
+        for c in 0..a.len().max(b.len()) {
+            let (va, vb) = (
+                a.get(c).map_or("", String::as_str),
+                b.get(c).map_or("", String::as_str),
+            );
+            if va != vb {
+                edits.push(Edit::SetCell {
+                    row: first + i,
+                    col: c,
+                    value: vb.to_string(),
+                });
+            }
+        }
+    }
+    for (i, cells) in new.iter().enumerate().skip(old.len()) {
+        edits.push(Edit::InsertRow {
+            row: first + i,
+            cells: cells.clone(),
+        });
+    }
+    if new.len() < old.len() {
+        edits.push(Edit::DeleteRows {
+            row: first + new.len(),
+            count: old.len() - new.len(),
+        });
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn diff_sets_inserts_and_deletes() {
+        let old = grid(&[&["a", "b"], &["c"]]);
+        let edits = diff(&old, &grid(&[&["a", "B", "x"], &["c"], &["new"]]), 4);
+        assert_eq!(edits.len(), 3);
+        assert!(matches!(&edits[0], Edit::SetCell { row: 4, col: 1, value } if value == "B"));
+        assert!(matches!(&edits[1], Edit::SetCell { row: 4, col: 2, value } if value == "x"));
+        assert!(matches!(&edits[2], Edit::InsertRow { row: 6, cells } if cells == &["new"]));
+
+        let edits = diff(&old, &grid(&[&["a"]]), 0);
+        assert!(matches!(&edits[0], Edit::SetCell { row: 0, col: 1, value } if value.is_empty()));
+        assert!(matches!(edits[1], Edit::DeleteRows { row: 1, count: 1 }));
+        assert!(diff(&old, &old, 0).is_empty());
+    }
+}
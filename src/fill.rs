@@ -0,0 +1,133 @@
+use anyhow::{Result, anyhow, bail};
+
+use crate::nulls::Nulls;
+use crate::viz::{self, Decimal};
+
+/// What `:fill` puts into the missing cells of a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fill {
+    /// `:fill value <text>`
+    Value(String),
+    /// `:fill down` — the nearest non-missing value above.
+    Down,
+    Mean,
+    Median,
+}
+
+pub fn parse(s: &str) -> Result<Fill> {
+    let (how, arg) = s
+        .split_once(char::is_whitespace)
+        .map_or((s, ""), |(h, a)| (h, a.trim()));
+    match (how, arg) {
+        ("value", v) if !v.is_empty() => Ok(Fill::Value(v.to_string())),
+        ("down" | "ffill", "") => Ok(Fill::Down),
+        ("mean", "") => Ok(Fill::Mean),
+        ("median", "") => Ok(Fill::Median),
+        _ => Err(anyhow!("usage: :fill down|mean|median|value <text>")),
+    }
+}
+
+/// The cells of `col` to fill, from row `from` down, as `(row, value)`. A
+/// cell is missing when it is blank, a null token, or past the row's end.
+/// Forward fill leaves missing cells above the first value alone.
+pub fn plan<'a>(
+    data: &'a [Vec<String>],
+    col: usize,
+    from: usize,
+    how: &Fill,
+    nulls: &Nulls,
+    decimal: Decimal,
+) -> Result<Vec<(usize, String)>> {
+    let cell = |r: &'a [String]| r.get(col).map_or("", String::as_str);
+    let missing = |s: &str| s.trim().is_empty() || nulls.is_null(s);
+    let rows = data.iter().enumerate().skip(from);
+    let constant = match how {
+        Fill::Value(v) => Some(v.clone()),
+        Fill::Down => None,
+        Fill::Mean | Fill::Median => {
+            let mut values: Vec<f64> = rows
+                .clone()
+                .map(|(_, r)| cell(r))
+                .filter(|s| !missing(s))
+                .filter_map(|s| decimal.parse(s))
+                .collect();
+            if values.is_empty() {
+                bail!("column {} has no numbers", col + 1);
+            }
+            Some(viz::format_number(if *how == Fill::Mean {
+                values.iter().sum::<f64>() / values.len() as f64
+            } else {
+                values.sort_by(f64::total_cmp);
+                let mid = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[mid - 1] + values[mid]) / 2.0
+                } else {
+                    values[mid]
+                }
+            }))
+        }
+    };
+    let mut last: Option<&str> = None;
+    let mut out = Vec::new();
+    for (i, r) in rows {
+        let s = cell(r);
+        if !missing(s) {
+            last = Some(s);
+        } else if let Some(v) = constant.as_deref().or(last) {
+            out.push((i, v.to_string()));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(cells: &[&str]) -> Vec<Vec<String>> {
+        cells.iter().map(|s| vec![s.to_string()]).collect()
+    }
+
+    #[test]
+    fn fills_down_or_with_a_statistic() -> Result<()> {
+        let nulls = Nulls::default();
+        let data = column(&["n", "", "4", "NA", " ", "1", "7", ""]);
+        assert_eq!(
+            plan(&data, 0, 1, &Fill::Down, &nulls, Decimal::Dot)?,
+            [(3, "4".into()), (4, "4".into()), (7, "7".into())]
+        );
+        let mean = plan(&data, 0, 1, &Fill::Mean, &nulls, Decimal::Dot)?;
+        assert_eq!(mean.len(), 4);
+        assert_eq!(mean[0], (1, "4".to_string()));
+        assert_eq!(
+            plan(&data, 0, 1, &Fill::Median, &nulls, Decimal::Dot)?[0].1,
+            "4"
+        );
+        assert_eq!(
+            plan(
+                &column(&["1", "2"]),
+                0,
+                0,
+                &Fill::Median,
+                &nulls,
+                Decimal::Dot
+            )?,
+            []
+        );
+        assert!(plan(&column(&["", "x"]), 0, 0, &Fill::Mean, &nulls, Decimal::Dot).is_err());
+        // A column past the end of short rows counts as missing too.
+        assert_eq!(
+            plan(&data, 3, 6, &Fill::Value("0".into()), &nulls, Decimal::Dot)?,
+            [(6, "0".into()), (7, "0".into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_fill_methods() {
+        assert!(matches!(parse("value n/a"), Ok(Fill::Value(v)) if v == "n/a"));
+        assert!(matches!(parse("ffill"), Ok(Fill::Down)));
+        assert!(parse("value").is_err());
+        assert!(parse("mode").is_err());
+    }
+}
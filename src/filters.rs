@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::names;
+use crate::view::Rule;
+
+/// Filters saved under a name, kept in `<file>.filters` next to the CSV,
+/// one per line in the form presets use:
+///
+/// ```text
+/// failed = is 3 FAILED
+/// slow = where num("latency") > 500
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedFilters(BTreeMap<String, Rule>);
+
+pub fn filters_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".filters");
+    PathBuf::from(name)
+}
+
+impl SavedFilters {
+    pub fn load_for(csv_path: &Path) -> Result<Self> {
+        let path = filters_path_for(csv_path);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("{}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read {path:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut saved = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, rule) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `name = is 3 FAILED`", idx + 1))?;
+            let name = name.trim();
+            if !names::is_name(name) {
+                bail!("line {}: bad filter name {name:?}", idx + 1);
+            }
+            let rule = Rule::parse(rule).with_context(|| format!("line {}", idx + 1))?;
+            saved.0.insert(name.to_string(), rule);
+        }
+        Ok(saved)
+    }
+
+    /// Write the filters next to `csv_path`, or remove the file when there
+    /// are none left.
+    pub fn save_for(&self, csv_path: &Path) -> Result<()> {
+        let path = filters_path_for(csv_path);
+        if self.0.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("remove {path:?}"))
+                }
+                _ => Ok(()),
+            };
+        }
+        let text: String = self
+            .0
+            .iter()
+            .map(|(name, rule)| format!("{name} = {}\n", rule.to_text()))
+            .collect();
+        fs::write(&path, text).with_context(|| format!("write {path:?}"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, rule: Rule) {
+        self.0.insert(name, rule);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+/// The `F` list of saved filters, where Space switches one on or off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Panel {
+    pub cursor: usize,
+}
+
+impl Panel {
+    pub fn step(&mut self, down: bool, len: usize) {
+        self.cursor = if down {
+            (self.cursor + 1).min(len.saturating_sub(1))
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_saved_filters() -> Result<()> {
+        let text = "failed = is 3 FAILED\nslow = where num(\"latency\") > 500\n";
+        let saved = SavedFilters::parse(text)?;
+        assert_eq!(saved.get("failed"), Some(&Rule::Is(2, "FAILED".into())));
+        assert_eq!(saved.names(), ["failed", "slow"]);
+        let again: String = saved
+            .0
+            .iter()
+            .map(|(n, r)| format!("{n} = {}\n", r.to_text()))
+            .collect();
+        assert_eq!(again, text);
+        assert!(SavedFilters::parse("x = hide 3").is_err());
+        assert!(SavedFilters::parse("A1 = is 1 x").is_err());
+        Ok(())
+    }
+}
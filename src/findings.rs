@@ -0,0 +1,129 @@
+/// One problem found by a check, pointing at a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub row: usize,
+    pub col: usize,
+    /// The check or schema rule that found it, e.g. `email`; the panel can
+    /// be narrowed to one rule.
+    pub rule: String,
+    pub text: String,
+    /// A schema `warn` rule: worth a look, but not an error.
+    pub warning: bool,
+    /// The problem is with the row as a whole, as for a cross-column rule;
+    /// `col` is just where jumping to it lands.
+    pub whole_row: bool,
+}
+
+/// A jumpable list of findings shown in a panel below the table.
+#[derive(Debug, Default)]
+pub struct Findings {
+    pub title: String,
+    pub items: Vec<Finding>,
+    /// Index into the shown items.
+    pub selected: usize,
+    /// Whether Up/Down/Enter go to the panel rather than the table.
+    pub focused: bool,
+    /// Only show findings of this rule.
+    pub filter: Option<String>,
+}
+
+impl Findings {
+    pub fn new(title: String, items: Vec<Finding>) -> Self {
+        Self {
+            title,
+            items,
+            selected: 0,
+            focused: true,
+            filter: None,
+        }
+    }
+
+    fn shows(&self, f: &Finding) -> bool {
+        self.filter.as_ref().is_none_or(|r| *r == f.rule)
+    }
+
+    /// The items the rule filter lets through, in order.
+    pub fn shown(&self) -> impl Iterator<Item = &Finding> {
+        self.items.iter().filter(|f| self.shows(f))
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        self.shown()
+            .any(|f| f.row == row && (f.col == col || f.whole_row))
+    }
+
+    /// Distinct rules, in the order they first appear.
+    pub fn rules(&self) -> Vec<&str> {
+        let mut out: Vec<&str> = Vec::new();
+        for f in &self.items {
+            if !out.contains(&f.rule.as_str()) {
+                out.push(&f.rule);
+            }
+        }
+        out
+    }
+
+    /// Narrow the list to the next rule in turn, and back to all of them
+    /// after the last.
+    pub fn cycle_filter(&mut self) {
+        let rules = self.rules();
+        let next = match &self.filter {
+            None => rules.first(),
+            Some(r) => rules
+                .iter()
+                .position(|x| x == r)
+                .and_then(|i| rules.get(i + 1)),
+        };
+        self.filter = next.map(|r| r.to_string());
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.shown().count();
+        self.selected = (self.selected + 1).min(len.saturating_sub(1));
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn current(&self) -> Option<&Finding> {
+        self.shown().nth(self.selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(row: usize, rule: &str) -> Finding {
+        Finding {
+            row,
+            col: 0,
+            rule: rule.to_string(),
+            text: String::new(),
+            warning: false,
+            whole_row: false,
+        }
+    }
+
+    #[test]
+    fn filters_by_rule_in_turn() {
+        let mut panel = Findings::new(
+            "t".into(),
+            vec![finding(1, "email"), finding(2, "url"), finding(3, "email")],
+        );
+        assert_eq!(panel.rules(), ["email", "url"]);
+        panel.select_next();
+        panel.cycle_filter();
+        assert_eq!(panel.filter.as_deref(), Some("email"));
+        assert_eq!(panel.selected, 0);
+        panel.select_next();
+        assert_eq!(panel.current().map(|f| f.row), Some(3));
+        assert!(!panel.contains(2, 0));
+        panel.cycle_filter();
+        panel.cycle_filter();
+        assert_eq!(panel.filter, None);
+        assert!(panel.contains(2, 0));
+    }
+}
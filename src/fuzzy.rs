@@ -0,0 +1,103 @@
+/// Most hits the fuzzy finder keeps; the list is for picking, not browsing.
+const MAX_HITS: usize = 200;
+
+/// fzf-style subsequence score of `query` in `text`, or `None` when the
+/// query characters don't all appear in order. Consecutive characters and
+/// matches at the start of a word score higher; gaps cost a little.
+/// Matching is case-insensitive.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+    let text: Vec<char> = text.chars().collect();
+    let mut qi = 0;
+    let mut total = 0;
+    let mut prev: Option<usize> = None;
+    for (i, &c) in text.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(query[qi])) {
+            continue;
+        }
+        let word_start = i == 0 || !text[i - 1].is_alphanumeric();
+        total += 16;
+        if word_start {
+            total += 8;
+        }
+        match prev {
+            Some(p) if p + 1 == i => total += 12,
+            Some(p) => total -= (i - p - 1).min(8) as i64,
+            None => total -= i.min(8) as i64,
+        }
+        prev = Some(i);
+        qi += 1;
+    }
+    // Shorter cells are the better match for the same characters.
+    (qi == query.len()).then(|| total - (text.len() / 4) as i64)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub row: usize,
+    pub col: usize,
+    pub score: i64,
+}
+
+/// Best-matching cells, highest score first; ties keep table order.
+pub fn hits(data: &[Vec<String>], query: &str) -> Vec<Hit> {
+    let mut out: Vec<Hit> = data
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, v)| (r, c, v)))
+        .filter_map(|(row, col, v)| {
+            Some(Hit {
+                row,
+                col,
+                score: score(query, v)?,
+            })
+        })
+        .collect();
+    out.sort_by_key(|h| std::cmp::Reverse(h.score));
+    out.truncate(MAX_HITS);
+    out
+}
+
+/// State of the fuzzy-find overlay.
+#[derive(Debug, Default)]
+pub struct FuzzyView {
+    pub query: String,
+    pub hits: Vec<Hit>,
+    pub selected: usize,
+}
+
+impl FuzzyView {
+    pub fn refresh(&mut self, data: &[Vec<String>]) {
+        self.hits = hits(data, &self.query);
+        self.selected = 0;
+    }
+
+    pub fn current(&self) -> Option<&Hit> {
+        self.hits.get(self.selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_tight_and_word_start_matches_first() {
+        assert_eq!(score("xyz", "abc"), None);
+        assert_eq!(score("ba", "abc"), None);
+        assert!(score("nyc", "New York City") > score("nyc", "sunny cat"));
+        assert!(score("york", "York") > score("york", "new york city"));
+        let data = vec![
+            vec!["alpha".to_string(), "al".to_string()],
+            vec!["a_l".to_string(), "nothing".to_string()],
+        ];
+        let found: Vec<_> = hits(&data, "AL").iter().map(|h| (h.row, h.col)).collect();
+        assert_eq!(found, [(0, 1), (0, 0), (1, 0)]);
+    }
+}
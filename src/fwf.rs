@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::escape;
+
+/// How to find the columns of a fixed-width file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Spec {
+    /// Guess from character positions that are blank on every line.
+    Auto,
+    /// Field widths in characters; the last field runs to the end of the line.
+    Widths(Vec<usize>),
+}
+
+/// `auto`, or widths like `5,10,8`.
+pub fn parse_spec(s: &str) -> Result<Spec> {
+    if s.trim() == "auto" {
+        return Ok(Spec::Auto);
+    }
+    let widths = s
+        .split(',')
+        .map(|w| match w.trim().parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(anyhow!("bad field width {w:?}")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Spec::Widths(widths))
+}
+
+/// Start positions of the columns: a column starts wherever text follows a
+/// position that is blank (or past the end) on every non-empty line.
+pub fn detect(lines: &[Vec<char>]) -> Vec<usize> {
+    let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let mut used = vec![false; width];
+    for line in lines {
+        for (i, c) in line.iter().enumerate() {
+            used[i] |= !c.is_whitespace();
+        }
+    }
+    (0..width)
+        .filter(|&i| used[i] && (i == 0 || !used[i - 1]))
+        .collect()
+}
+
+fn starts_from_widths(widths: &[usize]) -> Vec<usize> {
+    widths
+        .iter()
+        .scan(0, |pos, w| {
+            let start = *pos;
+            *pos += w;
+            Some(start)
+        })
+        .collect()
+}
+
+/// Cut `line` at `starts` and trim the padding off each field.
+pub fn split(line: &[char], starts: &[usize]) -> Vec<String> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(line.len());
+            let (start, end) = (start.min(line.len()), end.min(line.len()));
+            line[start..end]
+                .iter()
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Read a fixed-width file into rows. Blank lines are skipped.
+pub fn load(path: &Path, spec: &Spec) -> Result<Vec<Vec<String>>> {
+    let bytes = fs::read(path).with_context(|| format!("read {path:?}"))?;
+    let text = escape::decode(&bytes);
+    let lines: Vec<Vec<char>> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.chars().collect())
+        .collect();
+    let starts = match spec {
+        Spec::Auto => detect(&lines),
+        Spec::Widths(w) => starts_from_widths(w),
+    };
+    if starts.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(lines.iter().map(|l| split(l, &starts)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(lines: &[&str]) -> Vec<Vec<char>> {
+        lines.iter().map(|l| l.chars().collect()).collect()
+    }
+
+    #[test]
+    fn detects_columns_and_splits_by_width() -> Result<()> {
+        let lines = chars(&[
+            "ID   NAME      AMOUNT",
+            "1    Alice       12.5",
+            "22   Bob Smith    3.0",
+        ]);
+        let starts = detect(&lines);
+        assert_eq!(starts, [0, 5, 15]);
+        assert_eq!(split(&lines[2], &starts), ["22", "Bob Smith", "3.0"]);
+
+        assert_eq!(parse_spec("auto")?, Spec::Auto);
+        let Spec::Widths(w) = parse_spec("5, 10,6")? else {
+            panic!("expected widths");
+        };
+        assert_eq!(starts_from_widths(&w), [0, 5, 15]);
+        assert_eq!(
+            split(&lines[1], &starts_from_widths(&w)),
+            ["1", "Alice", "12.5"]
+        );
+        assert!(parse_spec("5,x").is_err());
+        assert!(parse_spec("0").is_err());
+        Ok(())
+    }
+}
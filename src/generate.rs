@@ -0,0 +1,122 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use anyhow::{Result, anyhow};
+
+/// Values for a generated column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequence {
+    Numbers {
+        start: i64,
+        step: i64,
+    },
+    /// Random (version 4) UUIDs.
+    Uuid,
+}
+
+/// `:seq [first|last] [uuid | start [step]]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqSpec {
+    pub seq: Sequence,
+    /// Append as the last column instead of inserting as the first.
+    pub at_end: bool,
+}
+
+pub fn parse(args: &str) -> Result<SeqSpec> {
+    let mut at_end = false;
+    let mut uuid = false;
+    let mut numbers = Vec::new();
+    for word in args.split_whitespace() {
+        match word {
+            "first" => at_end = false,
+            "last" => at_end = true,
+            "uuid" => uuid = true,
+            w => numbers.push(
+                w.parse::<i64>()
+                    .map_err(|_| anyhow!("bad sequence argument {w:?}"))?,
+            ),
+        }
+    }
+    let seq = match (uuid, numbers.as_slice()) {
+        (true, []) => Sequence::Uuid,
+        (true, _) => return Err(anyhow!("uuid takes no start or step")),
+        (false, []) => Sequence::Numbers { start: 1, step: 1 },
+        (false, [start]) => Sequence::Numbers {
+            start: *start,
+            step: 1,
+        },
+        (false, [start, step]) => Sequence::Numbers {
+            start: *start,
+            step: *step,
+        },
+        _ => return Err(anyhow!("expected at most a start and a step")),
+    };
+    Ok(SeqSpec { seq, at_end })
+}
+
+impl Sequence {
+    pub fn values(self, n: usize) -> Vec<String> {
+        match self {
+            Sequence::Numbers { start, step } => (0..n as i64)
+                .map(|i| start.saturating_add(step.saturating_mul(i)).to_string())
+                .collect(),
+            Sequence::Uuid => {
+                // std's hasher keys are randomly seeded per process, which is
+                // enough entropy for identifiers without an extra dependency.
+                let seed = RandomState::new();
+                (0..n as u64).map(|i| uuid_v4(&seed, i)).collect()
+            }
+        }
+    }
+}
+
+fn uuid_v4(seed: &RandomState, i: u64) -> String {
+    let half = |salt: u64| {
+        let mut h = seed.build_hasher();
+        h.write_u64(i);
+        h.write_u64(salt);
+        h.finish()
+    };
+    let (hi, lo) = (half(0), half(1));
+    // Version 4 in the high nibble of byte 6, RFC 4122 variant in byte 8.
+    let hi = (hi & !0xf000) | 0x4000;
+    let lo = (lo & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        hi >> 32,
+        (hi >> 16) & 0xffff,
+        hi & 0xffff,
+        lo >> 48,
+        lo & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_specs_and_generates_values() -> Result<()> {
+        let spec = parse("last 100 -10")?;
+        assert!(spec.at_end);
+        assert_eq!(spec.seq.values(3), ["100", "90", "80"]);
+        assert_eq!(parse("")?.seq.values(2), ["1", "2"]);
+        assert!(parse("uuid 5").is_err());
+        assert!(parse("1 2 3").is_err());
+
+        let ids = parse("uuid")?.seq.values(50);
+        assert!(
+            ids.iter()
+                .all(|id| id.len() == 36 && id.as_bytes()[14] == b'4')
+        );
+        assert!(
+            ids.iter()
+                .all(|id| matches!(id.as_bytes()[19], b'8' | b'9' | b'a' | b'b'))
+        );
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+        Ok(())
+    }
+}
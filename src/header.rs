@@ -0,0 +1,110 @@
+use anyhow::{Result, anyhow};
+
+use crate::command;
+use crate::viz::Decimal;
+
+/// Whether the first row looks like column names: every cell is non-blank,
+/// distinct text, and at least one column holds numbers below it.
+pub fn looks_like_header(data: &[Vec<String>], decimal: Decimal) -> bool {
+    let Some((first, rest)) = data.split_first() else {
+        return false;
+    };
+    if first.is_empty() || rest.is_empty() {
+        return false;
+    }
+    let mut seen = std::collections::HashSet::new();
+    if !first
+        .iter()
+        .all(|h| !h.trim().is_empty() && decimal.parse(h).is_none() && seen.insert(h.trim()))
+    {
+        return false;
+    }
+    (0..first.len()).any(|c| {
+        let (mut filled, mut numbers) = (0, 0);
+        for cell in rest.iter().filter_map(|r| r.get(c)) {
+            if !cell.trim().is_empty() {
+                filled += 1;
+                numbers += usize::from(decimal.parse(cell).is_some());
+            }
+        }
+        numbers > 0 && numbers * 2 >= filled
+    })
+}
+
+/// A column given as a 1-based number or by its header name.
+pub fn resolve_column(headers: &[String], s: &str) -> Result<usize> {
+    if let Some(c) = headers.iter().position(|h| h.trim() == s.trim()) {
+        return Ok(c);
+    }
+    command::parse_column(s).map_err(|_| anyhow!("no column named {s:?}"))
+}
+
+/// Check that `name` can become the header of column `col`: not blank and
+/// not used by another column.
+pub fn check_name(headers: &[String], col: usize, name: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("column name can't be empty"));
+    }
+    match headers
+        .iter()
+        .enumerate()
+        .find(|&(c, h)| c != col && h.trim() == name)
+    {
+        Some((c, _)) => Err(anyhow!("{name:?} is already the name of column {}", c + 1)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn detects_a_text_row_over_numbers() {
+        assert!(looks_like_header(
+            &grid(&[&["name", "qty"], &["a", "3"], &["b", "4"]]),
+            Decimal::Dot
+        ));
+        // All text: nothing to tell a header from data.
+        assert!(!looks_like_header(
+            &grid(&[&["name", "city"], &["a", "x"]]),
+            Decimal::Dot
+        ));
+        assert!(!looks_like_header(
+            &grid(&[&["1", "2"], &["3", "4"]]),
+            Decimal::Dot
+        ));
+        assert!(!looks_like_header(
+            &grid(&[&["a", "a"], &["1", "2"]]),
+            Decimal::Dot
+        ));
+        assert!(!looks_like_header(
+            &grid(&[&["a", ""], &["1", "2"]]),
+            Decimal::Dot
+        ));
+        assert!(!looks_like_header(&grid(&[&["name", "qty"]]), Decimal::Dot));
+    }
+
+    #[test]
+    fn resolves_and_validates_names() -> Result<()> {
+        let headers: Vec<String> = ["id", "name", "3"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(resolve_column(&headers, "name")?, 1);
+        assert_eq!(resolve_column(&headers, "1")?, 0);
+        // A header that looks like a number wins over the position.
+        assert_eq!(resolve_column(&headers, "3")?, 2);
+        assert!(resolve_column(&headers, "email").is_err());
+
+        assert!(check_name(&headers, 1, "full name").is_ok());
+        assert!(check_name(&headers, 1, "name").is_ok());
+        assert!(check_name(&headers, 1, " id ").is_err());
+        assert!(check_name(&headers, 0, "  ").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use crate::input::{Action, Keymap};
+
+/// State of the `?` overlay: a search query and a scroll offset.
+#[derive(Debug, Default)]
+pub struct HelpView {
+    pub query: String,
+    pub scroll: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HelpEntry {
+    pub keys: String,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Actions with their current bindings, filtered by a case-insensitive
+/// substring match on keys, name, or description.
+pub fn entries(keymap: &Keymap, query: &str) -> Vec<HelpEntry> {
+    let q = query.to_lowercase();
+    Action::ALL
+        .iter()
+        .map(|&a| HelpEntry {
+            keys: keymap.keys_for(a).join(" "),
+            name: a.name(),
+            description: a.description(),
+        })
+        .filter(|e| {
+            q.is_empty()
+                || e.keys.to_lowercase().contains(&q)
+                || e.name.contains(&q)
+                || e.description.to_lowercase().contains(&q)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Keyboard;
+
+    #[test]
+    fn lists_every_action_and_filters_by_query() {
+        let km = Keymap::default();
+        assert_eq!(entries(&km, "").len(), Action::ALL.len());
+        let found = entries(&km, "DELETE-ROW");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].keys, "dd");
+    }
+
+    #[test]
+    fn reflects_remapped_keys() -> anyhow::Result<()> {
+        let km = Keymap::for_keyboard(Keyboard::Qwerty, &[("s".into(), "write".into())])?;
+        let write = entries(&km, "write-");
+        assert!(write.is_empty());
+        let write = entries(&km, "write changes");
+        assert_eq!(write[0].keys, "w s");
+        Ok(())
+    }
+}
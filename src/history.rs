@@ -0,0 +1,163 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// How many lines of each kind are kept.
+const MAX_ENTRIES: usize = 500;
+
+/// `<config dir>/history`: past `:` command lines and `/` searches.
+pub fn history_file() -> Option<PathBuf> {
+    crate::config::config_dir().map(|d| d.join("history"))
+}
+
+/// Which prompt a history line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Command,
+    Search,
+}
+
+impl Kind {
+    fn mark(self) -> char {
+        match self {
+            Kind::Command => ':',
+            Kind::Search => '/',
+        }
+    }
+}
+
+/// The lines typed at the `:` and `/` prompts, oldest first, kept in a
+/// file (one per line, starting `:` or `/`) so they outlive the session.
+#[derive(Debug, Default)]
+pub struct PromptHistory {
+    commands: Vec<String>,
+    searches: Vec<String>,
+    /// Where to keep it; `None` keeps it in memory only.
+    file: Option<PathBuf>,
+}
+
+impl PromptHistory {
+    /// The history kept at `file`, empty if there is none yet.
+    pub fn load(file: &Path) -> Result<Self> {
+        let mut history = Self {
+            file: Some(file.to_path_buf()),
+            ..Self::default()
+        };
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("read {file:?}")),
+        };
+        for line in text.lines() {
+            let mut chars = line.chars();
+            let kind = match chars.next() {
+                Some(':') => Kind::Command,
+                Some('/') => Kind::Search,
+                _ => continue,
+            };
+            history.push(kind, chars.as_str());
+        }
+        Ok(history)
+    }
+
+    pub fn entries(&self, kind: Kind) -> &[String] {
+        match kind {
+            Kind::Command => &self.commands,
+            Kind::Search => &self.searches,
+        }
+    }
+
+    /// Remember `line` as the newest entry, moving it up if it was already
+    /// there, and write the file.
+    pub fn add(&mut self, kind: Kind, line: &str) -> Result<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        self.push(kind, line);
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create {dir:?}"))?;
+        }
+        let mut text = String::new();
+        for kind in [Kind::Command, Kind::Search] {
+            for line in self.entries(kind) {
+                text.push(kind.mark());
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        fs::write(file, text).with_context(|| format!("write {file:?}"))
+    }
+
+    fn push(&mut self, kind: Kind, line: &str) {
+        let lines = match kind {
+            Kind::Command => &mut self.commands,
+            Kind::Search => &mut self.searches,
+        };
+        lines.retain(|l| l != line);
+        lines.push(line.to_string());
+        if lines.len() > MAX_ENTRIES {
+            lines.remove(0);
+        }
+    }
+
+    /// The entry before `from` (or the newest, with `None`) that starts
+    /// with `prefix`, for stepping back with Up.
+    pub fn older(&self, kind: Kind, prefix: &str, from: Option<usize>) -> Option<usize> {
+        let lines = self.entries(kind);
+        let end = from.unwrap_or(lines.len());
+        (0..end).rev().find(|&i| lines[i].starts_with(prefix))
+    }
+
+    /// The entry after `from` that starts with `prefix`, for Down; `None`
+    /// past the newest, where the typed text comes back.
+    pub fn newer(&self, kind: Kind, prefix: &str, from: usize) -> Option<usize> {
+        let lines = self.entries(kind);
+        (from + 1..lines.len()).find(|&i| lines[i].starts_with(prefix))
+    }
+
+    /// The `skip`-th most recent entry containing `query`, for Ctrl+R.
+    pub fn find(&self, kind: Kind, query: &str, skip: usize) -> Option<&str> {
+        self.entries(kind)
+            .iter()
+            .rev()
+            .filter(|l| l.contains(query))
+            .nth(skip)
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn keeps_commands_and_searches_across_sessions() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_history_{}", std::process::id()));
+        let file = dir.join("history");
+        let _ = fs::remove_dir_all(&dir);
+        let mut history = PromptHistory::load(&file)?;
+        for line in ["w", "sort 2", "goto B3", "w"] {
+            history.add(Kind::Command, line)?;
+        }
+        history.add(Kind::Search, "bob")?;
+
+        let history = PromptHistory::load(&file)?;
+        assert_eq!(history.entries(Kind::Command), ["sort 2", "goto B3", "w"]);
+        assert_eq!(history.entries(Kind::Search), ["bob"]);
+        assert_eq!(history.older(Kind::Command, "", None), Some(2));
+        assert_eq!(history.older(Kind::Command, "s", Some(2)), Some(0));
+        assert_eq!(history.newer(Kind::Command, "", 1), Some(2));
+        assert_eq!(history.newer(Kind::Command, "", 2), None);
+        assert_eq!(history.find(Kind::Command, "o", 0), Some("goto B3"));
+        assert_eq!(history.find(Kind::Command, "o", 1), Some("sort 2"));
+        assert_eq!(history.find(Kind::Command, "o", 2), None);
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,821 @@
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Everything a key sequence in navigation mode can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Write,
+    Edit,
+    ChangeCell,
+    ExportHtml,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    FirstRow,
+    LastRow,
+    FirstCol,
+    LastCol,
+    DeleteRow,
+    InsertRowBelow,
+    InsertRowAbove,
+    Help,
+    CommandLine,
+    CompareCell,
+    NextModified,
+    PrevModified,
+    CycleViz,
+    ChartColumn,
+    ChartRow,
+    Select,
+    Search,
+    SearchNext,
+    SearchPrev,
+    FuzzyFind,
+    Undo,
+    Redo,
+    NextEmpty,
+    PrevEmpty,
+    NextEmptyAnywhere,
+    PrevEmptyAnywhere,
+    ToggleEmpty,
+    SplitView,
+    SwitchPane,
+    ScrollLock,
+    Yank,
+    Paste,
+    PasteSpecial,
+    ExternalCell,
+    ExternalRow,
+    ExternalFile,
+    ViewCell,
+    RenameColumn,
+    ToggleTag,
+    ColumnManager,
+    NextFinding,
+    PrevFinding,
+    Issues,
+    Repeat,
+    ClearCells,
+    DeleteColumn,
+    ToggleRainbow,
+    ToggleBadges,
+    FilterEqual,
+    FilterNotEqual,
+    SavedFilters,
+    ToggleGroup,
+    RecordCard,
+    CompareRows,
+    ExportDialog,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them.
+    pub const ALL: &'static [Action] = &[
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::FirstRow,
+        Action::LastRow,
+        Action::FirstCol,
+        Action::LastCol,
+        Action::Select,
+        Action::Edit,
+        Action::ChangeCell,
+        Action::DeleteRow,
+        Action::InsertRowBelow,
+        Action::InsertRowAbove,
+        Action::DeleteColumn,
+        Action::ClearCells,
+        Action::RenameColumn,
+        Action::ToggleTag,
+        Action::ColumnManager,
+        Action::Yank,
+        Action::Paste,
+        Action::PasteSpecial,
+        Action::ExternalCell,
+        Action::ExternalRow,
+        Action::ExternalFile,
+        Action::Undo,
+        Action::Redo,
+        Action::Repeat,
+        Action::Search,
+        Action::SearchNext,
+        Action::SearchPrev,
+        Action::FuzzyFind,
+        Action::FilterEqual,
+        Action::FilterNotEqual,
+        Action::SavedFilters,
+        Action::ToggleGroup,
+        Action::NextModified,
+        Action::PrevModified,
+        Action::NextEmpty,
+        Action::PrevEmpty,
+        Action::NextEmptyAnywhere,
+        Action::PrevEmptyAnywhere,
+        Action::NextFinding,
+        Action::PrevFinding,
+        Action::Issues,
+        Action::ToggleEmpty,
+        Action::CompareCell,
+        Action::ViewCell,
+        Action::RecordCard,
+        Action::CompareRows,
+        Action::ExportDialog,
+        Action::CycleViz,
+        Action::ToggleRainbow,
+        Action::ToggleBadges,
+        Action::ChartColumn,
+        Action::ChartRow,
+        Action::SplitView,
+        Action::SwitchPane,
+        Action::ScrollLock,
+        Action::Write,
+        Action::ExportHtml,
+        Action::CommandLine,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// Stable name used in the config file (`bind <keys> = <name>`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Write => "write",
+            Action::Edit => "edit",
+            Action::ChangeCell => "change-cell",
+            Action::ExportHtml => "export-html",
+            Action::MoveLeft => "move-left",
+            Action::MoveRight => "move-right",
+            Action::MoveUp => "move-up",
+            Action::MoveDown => "move-down",
+            Action::FirstRow => "first-row",
+            Action::LastRow => "last-row",
+            Action::FirstCol => "first-col",
+            Action::LastCol => "last-col",
+            Action::DeleteRow => "delete-row",
+            Action::InsertRowBelow => "insert-row-below",
+            Action::InsertRowAbove => "insert-row-above",
+            Action::Help => "help",
+            Action::CommandLine => "command-line",
+            Action::CompareCell => "compare-cell",
+            Action::NextModified => "next-modified",
+            Action::PrevModified => "prev-modified",
+            Action::CycleViz => "cycle-viz",
+            Action::ChartColumn => "chart-column",
+            Action::ChartRow => "chart-row",
+            Action::Select => "select",
+            Action::Search => "search",
+            Action::SearchNext => "search-next",
+            Action::SearchPrev => "search-prev",
+            Action::FuzzyFind => "fuzzy-find",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::NextEmpty => "next-empty",
+            Action::PrevEmpty => "prev-empty",
+            Action::NextEmptyAnywhere => "next-empty-anywhere",
+            Action::PrevEmptyAnywhere => "prev-empty-anywhere",
+            Action::ToggleEmpty => "toggle-empty",
+            Action::SplitView => "split-view",
+            Action::SwitchPane => "switch-pane",
+            Action::ScrollLock => "scroll-lock",
+            Action::Yank => "yank",
+            Action::Paste => "paste",
+            Action::PasteSpecial => "paste-special",
+            Action::ExternalCell => "external-cell",
+            Action::ExternalRow => "external-row",
+            Action::ExternalFile => "external-file",
+            Action::ViewCell => "view-cell",
+            Action::RenameColumn => "rename-column",
+            Action::ToggleTag => "toggle-tag",
+            Action::ColumnManager => "column-manager",
+            Action::NextFinding => "next-finding",
+            Action::PrevFinding => "prev-finding",
+            Action::Issues => "issues",
+            Action::Repeat => "repeat",
+            Action::ClearCells => "clear-cells",
+            Action::DeleteColumn => "delete-column",
+            Action::ToggleRainbow => "toggle-rainbow",
+            Action::ToggleBadges => "toggle-badges",
+            Action::FilterEqual => "filter-equal",
+            Action::FilterNotEqual => "filter-not-equal",
+            Action::SavedFilters => "saved-filters",
+            Action::ToggleGroup => "toggle-group",
+            Action::RecordCard => "record-card",
+            Action::CompareRows => "compare-rows",
+            Action::ExportDialog => "export",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit (saves unsaved changes first)",
+            Action::Write => "Write changes to the file",
+            Action::Edit => "Edit the selected cell",
+            Action::ChangeCell => "Clear the selected cell and edit it",
+            Action::ExportHtml => "Export the table as HTML",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::FirstRow => "Go to the first row (or row N with a count)",
+            Action::LastRow => "Go to the last row (or row N with a count)",
+            Action::FirstCol => "Go to the first column",
+            Action::LastCol => "Go to the last column",
+            Action::DeleteRow => "Delete the current row (N rows with a count)",
+            Action::InsertRowBelow => "Insert a row below, filled with the schema's defaults",
+            Action::InsertRowAbove => "Insert a row above, filled with the schema's defaults",
+            Action::Help => "Show this help",
+            Action::CommandLine => {
+                "Enter a command (:w, :q, :q!, :wq, :s/re/rep/, :sort, :filter, :commit-view, :recent, :unique N, :help)"
+            }
+            Action::CompareCell => "Compare a modified cell with its on-disk value (r reverts)",
+            Action::NextModified => "Jump to the next cell changed since the last save",
+            Action::PrevModified => "Jump to the previous cell changed since the last save",
+            Action::CycleViz => "Cycle numeric column rendering: off, data bars, heatmap",
+            Action::ChartColumn => "Chart the numbers in the current column",
+            Action::ChartRow => "Chart the numbers in the current row",
+            Action::Select => "Start or end a block selection (Esc clears it)",
+            Action::Search => "Search cells as you type (regex, smart case)",
+            Action::SearchNext => "Jump to the next match of the last search",
+            Action::SearchPrev => "Jump to the previous match of the last search",
+            Action::FuzzyFind => "Fuzzy-find a cell by typing parts of its value",
+            Action::Undo => "Undo the last change (edits, row removal, replace, sort)",
+            Action::Redo => "Redo the last undone change",
+            Action::NextEmpty => "Jump to the next empty cell in this column",
+            Action::PrevEmpty => "Jump to the previous empty cell in this column",
+            Action::NextEmptyAnywhere => "Jump to the next empty cell in the file",
+            Action::PrevEmptyAnywhere => "Jump to the previous empty cell in the file",
+            Action::ToggleEmpty => "Highlight all empty cells",
+            Action::SplitView => "Show the table in two side-by-side panes, or close the split",
+            Action::SwitchPane => "Move the cursor to the other pane",
+            Action::ScrollLock => "Lock both panes' scrolling at their current row distance",
+            Action::Yank => "Copy the selection or the current cell",
+            Action::Paste => "Paste copied cells at the cursor",
+            Action::PasteSpecial => "Paste with options: transpose, skip blanks, values only",
+            Action::ExternalCell => "Edit the current cell in $EDITOR",
+            Action::ExternalRow => "Edit the current row as CSV in $EDITOR",
+            Action::ExternalFile => "Edit the whole table as CSV in $EDITOR",
+            Action::ViewCell => "Show the full value of the current cell, wrapped",
+            Action::RenameColumn => "Rename the column (on the header row in header mode)",
+            Action::ToggleTag => "Tag or untag the row and move down (:tagged acts on all of them)",
+            Action::ColumnManager => "Reorder, hide, pin and rename columns in one list",
+            Action::NextFinding => {
+                "Jump to the next cell listed by the last check (:outliers, :unique)"
+            }
+            Action::PrevFinding => "Jump to the previous cell listed by the last check",
+            Action::Issues => "Show every validation problem from the schema, or close the panel",
+            Action::Repeat => "Repeat the last change (edit, paste, dd, :s, :fill) at the cursor",
+            Action::ClearCells => {
+                "Empty the selected cells (or this one), keeping rows and columns"
+            }
+            Action::DeleteColumn => "Delete this column, or every column of the selection",
+            Action::ToggleRainbow => "Tint each column with its own color (rainbow columns)",
+            Action::ToggleBadges => "Mark line breaks, leading zeros and other notable cells",
+            Action::FilterEqual => "Show only rows with this cell's value in this column",
+            Action::FilterNotEqual => "Hide the rows with this cell's value in this column",
+            Action::SavedFilters => "List saved filters to switch them on and off",
+            Action::ToggleGroup => "Collapse or expand the cursor's group (:group)",
+            Action::RecordCard => "Show the row as one field per line, to read and edit",
+            Action::CompareRows => "Compare two tagged rows field by field",
+            Action::ExportDialog => "Export in a chosen format, step by step",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|a| a.name() == name)
+    }
+}
+
+/// A single key press with modifiers normalized so that `G` and
+/// `Shift+g` compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub mods: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        let mut mods = key.modifiers;
+        if matches!(key.code, KeyCode::Char(_)) {
+            // The shift state is already folded into the character.
+            mods.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            mods,
+        }
+    }
+}
+
+/// Parse vim-style key notation: plain characters, `<Left>`, `<Esc>`,
+/// `<C-x>` and friends, e.g. `gg`, `<C-d>`, `d<Down>`.
+pub fn parse_keys(s: &str) -> Result<Vec<KeyPress>> {
+    let mut out = Vec::new();
+    let mut rest = s;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '<'
+            && let Some(end) = rest.find('>')
+            && end > 1
+        {
+            out.push(parse_special(&rest[1..end])?);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        out.push(KeyPress {
+            code: KeyCode::Char(ch),
+            mods: KeyModifiers::NONE,
+        });
+        rest = &rest[ch.len_utf8()..];
+    }
+    if out.is_empty() {
+        return Err(anyhow!("empty key sequence"));
+    }
+    Ok(out)
+}
+
+/// Inverse of [`parse_keys`], for showing bindings to the user.
+pub fn format_keys(keys: &[KeyPress]) -> String {
+    let mut out = String::new();
+    for k in keys {
+        let base = match k.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(c) if k.mods.is_empty() => {
+                out.push(c);
+                continue;
+            }
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Left => "Left".into(),
+            KeyCode::Right => "Right".into(),
+            KeyCode::Up => "Up".into(),
+            KeyCode::Down => "Down".into(),
+            KeyCode::Enter => "Enter".into(),
+            KeyCode::Esc => "Esc".into(),
+            KeyCode::Tab => "Tab".into(),
+            KeyCode::Backspace => "BS".into(),
+            KeyCode::Delete => "Del".into(),
+            KeyCode::Home => "Home".into(),
+            KeyCode::End => "End".into(),
+            KeyCode::PageUp => "PageUp".into(),
+            KeyCode::PageDown => "PageDown".into(),
+            other => format!("{other:?}"),
+        };
+        let prefix = if k.mods.contains(KeyModifiers::CONTROL) {
+            "C-"
+        } else {
+            ""
+        };
+        out.push_str(&format!("<{prefix}{base}>"));
+    }
+    out
+}
+
+fn parse_special(name: &str) -> Result<KeyPress> {
+    let mut mods = KeyModifiers::NONE;
+    let mut base = name;
+    if let Some(r) = base.strip_prefix("C-") {
+        mods |= KeyModifiers::CONTROL;
+        base = r;
+    }
+    let code = match base.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "enter" | "cr" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        _ => {
+            let mut chars = base.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if mods != KeyModifiers::NONE => KeyCode::Char(c),
+                _ => return Err(anyhow!("unknown key <{name}>")),
+            }
+        }
+    };
+    Ok(KeyPress { code, mods })
+}
+
+/// The keyboard the default bindings are laid out for. The terminal only
+/// reports the character a key types, not where the key sits, so rather
+/// than binding physical keys each layout swaps a few characters in the
+/// default map: the movement keys back under the right hand on Dvorak and
+/// Colemak (the letters they displace take the freed keys), and keys
+/// AZERTY and QWERTZ only type with AltGr or Shift for unshifted ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Keyboard {
+    #[default]
+    Qwerty,
+    Azerty,
+    Qwertz,
+    Dvorak,
+    Colemak,
+}
+
+impl Keyboard {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "qwerty" => Ok(Self::Qwerty),
+            "azerty" => Ok(Self::Azerty),
+            "qwertz" => Ok(Self::Qwertz),
+            "dvorak" => Ok(Self::Dvorak),
+            "colemak" => Ok(Self::Colemak),
+            _ => Err(anyhow!(
+                "keyboard must be qwerty, azerty, qwertz, dvorak or colemak"
+            )),
+        }
+    }
+
+    /// Characters traded both ways in the default map.
+    fn swaps(self) -> &'static [(char, char)] {
+        match self {
+            Self::Qwerty => &[],
+            // `[`/`]` need AltGr and `0` needs Shift; `(`, `)` and `à` don't.
+            Self::Azerty => &[('[', '('), (']', ')'), ('0', 'à')],
+            // The keys where QWERTY has `[` and `]`.
+            Self::Qwertz => &[('[', 'ü'), (']', '+')],
+            // Left, down, up, right on h t n s.
+            Self::Dvorak => &[
+                ('j', 't'),
+                ('k', 'n'),
+                ('l', 's'),
+                ('J', 'T'),
+                ('K', 'N'),
+                ('L', 'S'),
+            ],
+            // Left, down, up, right on h n e i.
+            Self::Colemak => &[
+                ('j', 'n'),
+                ('k', 'e'),
+                ('l', 'i'),
+                ('J', 'N'),
+                ('K', 'E'),
+                ('L', 'I'),
+            ],
+        }
+    }
+
+    fn swap(self, c: char) -> char {
+        self.swaps()
+            .iter()
+            .find_map(|&(a, b)| match c {
+                _ if c == a => Some(b),
+                _ if c == b => Some(a),
+                _ => None,
+            })
+            .unwrap_or(c)
+    }
+}
+
+/// Key-sequence bindings for navigation mode.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults: &[(&str, Action)] = &[
+            ("q", Action::Quit),
+            ("w", Action::Write),
+            ("e", Action::Edit),
+            ("cc", Action::ChangeCell),
+            ("H", Action::ExportHtml),
+            ("<Left>", Action::MoveLeft),
+            ("h", Action::MoveLeft),
+            ("<Right>", Action::MoveRight),
+            ("l", Action::MoveRight),
+            ("<Up>", Action::MoveUp),
+            ("k", Action::MoveUp),
+            ("<Down>", Action::MoveDown),
+            ("j", Action::MoveDown),
+            ("gg", Action::FirstRow),
+            ("G", Action::LastRow),
+            ("0", Action::FirstCol),
+            ("$", Action::LastCol),
+            ("dd", Action::DeleteRow),
+            ("o", Action::InsertRowBelow),
+            ("O", Action::InsertRowAbove),
+            ("?", Action::Help),
+            (":", Action::CommandLine),
+            ("K", Action::CompareCell),
+            ("]c", Action::NextModified),
+            ("[c", Action::PrevModified),
+            ("zv", Action::CycleViz),
+            ("zc", Action::ChartColumn),
+            ("zr", Action::ChartRow),
+            ("v", Action::Select),
+            ("/", Action::Search),
+            ("n", Action::SearchNext),
+            ("N", Action::SearchPrev),
+            ("<C-p>", Action::FuzzyFind),
+            ("u", Action::Undo),
+            ("<C-r>", Action::Redo),
+            ("]e", Action::NextEmpty),
+            ("[e", Action::PrevEmpty),
+            ("]E", Action::NextEmptyAnywhere),
+            ("[E", Action::PrevEmptyAnywhere),
+            ("ze", Action::ToggleEmpty),
+            ("<C-w>v", Action::SplitView),
+            ("<C-w>w", Action::SwitchPane),
+            ("<C-w>b", Action::ScrollLock),
+            ("y", Action::Yank),
+            ("p", Action::Paste),
+            ("P", Action::PasteSpecial),
+            ("E", Action::ExternalCell),
+            ("gE", Action::ExternalRow),
+            ("gF", Action::ExternalFile),
+            ("zo", Action::ViewCell),
+            ("r", Action::RenameColumn),
+            ("t", Action::ToggleTag),
+            ("gc", Action::ColumnManager),
+            ("]f", Action::NextFinding),
+            ("[f", Action::PrevFinding),
+            ("gi", Action::Issues),
+            (".", Action::Repeat),
+            ("x", Action::ClearCells),
+            ("dc", Action::DeleteColumn),
+            ("zR", Action::ToggleRainbow),
+            ("zb", Action::ToggleBadges),
+            ("*", Action::FilterEqual),
+            ("#", Action::FilterNotEqual),
+            ("F", Action::SavedFilters),
+            ("za", Action::ToggleGroup),
+            ("gr", Action::RecordCard),
+            ("gd", Action::CompareRows),
+            ("gx", Action::ExportDialog),
+        ];
+        let bindings = defaults
+            .iter()
+            .map(|(keys, action)| (parse_keys(keys).unwrap_or_default(), *action))
+            .collect();
+        Self { bindings }
+    }
+}
+
+enum Lookup {
+    Exact(Action),
+    Prefix,
+    None,
+}
+
+impl Keymap {
+    /// The default bindings for `keyboard` with user overrides applied in
+    /// order. Each override is `(keys, action name)`, in the characters the
+    /// keyboard types; the name `none` removes the binding.
+    pub fn for_keyboard(keyboard: Keyboard, overrides: &[(String, String)]) -> Result<Self> {
+        let mut km = Self::default();
+        for (keys, _) in &mut km.bindings {
+            for k in keys.iter_mut() {
+                if let KeyCode::Char(c) = k.code
+                    && k.mods.is_empty()
+                {
+                    k.code = KeyCode::Char(keyboard.swap(c));
+                }
+            }
+        }
+        for (keys, name) in overrides {
+            let seq = parse_keys(keys)?;
+            let action = match name.as_str() {
+                "none" => None,
+                n => Some(Action::from_name(n).ok_or_else(|| anyhow!("unknown action {n:?}"))?),
+            };
+            km.bind(seq, action);
+        }
+        Ok(km)
+    }
+
+    pub fn bind(&mut self, keys: Vec<KeyPress>, action: Option<Action>) {
+        self.bindings.retain(|(k, _)| *k != keys);
+        if let Some(a) = action {
+            self.bindings.push((keys, a));
+        }
+    }
+
+    /// Current key sequences for an action, in notation form.
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(k, _)| format_keys(k))
+            .collect()
+    }
+
+    fn lookup(&self, seq: &[KeyPress]) -> Lookup {
+        let mut prefix = false;
+        for (keys, action) in &self.bindings {
+            if keys.as_slice() == seq {
+                return Lookup::Exact(*action);
+            }
+            if keys.len() > seq.len() && keys.starts_with(seq) {
+                prefix = true;
+            }
+        }
+        if prefix { Lookup::Prefix } else { Lookup::None }
+    }
+}
+
+/// Outcome of feeding one key into the input state machine.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolved {
+    /// More keys are needed (a count or the start of a chord).
+    Pending,
+    /// A complete binding; `count` is the numeric prefix, if one was typed.
+    Action {
+        action: Action,
+        count: Option<usize>,
+    },
+    /// The sequence matches nothing; state has been reset.
+    Unbound,
+}
+
+/// Accumulates a numeric count prefix and a partial chord between key presses.
+#[derive(Debug, Default)]
+pub struct InputState {
+    count: Option<usize>,
+    pending: Vec<KeyPress>,
+}
+
+impl InputState {
+    pub fn feed(&mut self, keymap: &Keymap, key: KeyEvent) -> Resolved {
+        let press = KeyPress::from(key);
+        if press.code == KeyCode::Esc && self.is_pending() {
+            self.reset();
+            return Resolved::Pending;
+        }
+        if self.pending.is_empty()
+            && press.mods.is_empty()
+            && let KeyCode::Char(c @ '0'..='9') = press.code
+            && (c != '0' || self.count.is_some())
+        {
+            let digit = c as usize - '0' as usize;
+            self.count = Some(
+                self.count
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit),
+            );
+            return Resolved::Pending;
+        }
+        self.pending.push(press);
+        match keymap.lookup(&self.pending) {
+            Lookup::Exact(action) => {
+                let count = self.count.take();
+                self.pending.clear();
+                Resolved::Action { action, count }
+            }
+            Lookup::Prefix => Resolved::Pending,
+            Lookup::None => {
+                self.reset();
+                Resolved::Unbound
+            }
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || !self.pending.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.pending.clear();
+    }
+
+    /// What has been typed so far, for display in the status bar.
+    pub fn display(&self) -> String {
+        let mut s = self.count.map(|c| c.to_string()).unwrap_or_default();
+        for k in &self.pending {
+            if let KeyCode::Char(c) = k.code {
+                s.push(c);
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_str(state: &mut InputState, keymap: &Keymap, s: &str) -> Vec<Resolved> {
+        s.chars()
+            .map(|c| state.feed(keymap, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_chords_and_counts() {
+        let km = Keymap::default();
+        let mut st = InputState::default();
+        let out = feed_str(&mut st, &km, "3dd");
+        assert_eq!(
+            out.last(),
+            Some(&Resolved::Action {
+                action: Action::DeleteRow,
+                count: Some(3)
+            })
+        );
+        assert!(!st.is_pending());
+
+        let out = feed_str(&mut st, &km, "gg");
+        assert_eq!(out[0], Resolved::Pending);
+        assert_eq!(
+            out[1],
+            Resolved::Action {
+                action: Action::FirstRow,
+                count: None
+            }
+        );
+    }
+
+    #[test]
+    fn zero_is_a_motion_unless_a_count_is_pending() {
+        let km = Keymap::default();
+        let mut st = InputState::default();
+        assert_eq!(
+            feed_str(&mut st, &km, "0"),
+            vec![Resolved::Action {
+                action: Action::FirstCol,
+                count: None
+            }]
+        );
+        let out = feed_str(&mut st, &km, "10j");
+        assert_eq!(
+            out[2],
+            Resolved::Action {
+                action: Action::MoveDown,
+                count: Some(10)
+            }
+        );
+    }
+
+    #[test]
+    fn unbound_sequence_resets_state() {
+        let km = Keymap::default();
+        let mut st = InputState::default();
+        assert_eq!(
+            feed_str(&mut st, &km, "2dx").last(),
+            Some(&Resolved::Unbound)
+        );
+        assert!(!st.is_pending());
+    }
+
+    #[test]
+    fn parses_key_notation() -> Result<()> {
+        let keys = parse_keys("d<Down><C-x>")?;
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[1].code, KeyCode::Down);
+        assert_eq!(keys[2].mods, KeyModifiers::CONTROL);
+        assert!(parse_keys("<Nope>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_rebind_and_unbind_keys() -> Result<()> {
+        let km = Keymap::for_keyboard(
+            Keyboard::Qwerty,
+            &[("s".into(), "write".into()), ("w".into(), "none".into())],
+        )?;
+        assert_eq!(km.keys_for(Action::Write), vec!["s".to_string()]);
+        assert!(Keymap::for_keyboard(Keyboard::Qwerty, &[("x".into(), "nope".into())]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keyboard_presets_swap_default_keys() -> Result<()> {
+        let km = Keymap::for_keyboard(Keyboard::Dvorak, &[])?;
+        assert_eq!(km.keys_for(Action::MoveDown), ["<Down>", "t"]);
+        assert_eq!(km.keys_for(Action::ToggleTag), ["j"]);
+        assert_eq!(km.keys_for(Action::SearchNext), ["k"]);
+        let km = Keymap::for_keyboard(Keyboard::Azerty, &[("s".into(), "write".into())])?;
+        assert_eq!(km.keys_for(Action::NextModified), [")c"]);
+        assert_eq!(km.keys_for(Action::FirstCol), ["à"]);
+        assert_eq!(km.keys_for(Action::Write), ["w", "s"]);
+        assert_eq!(
+            Keymap::for_keyboard(Keyboard::Colemak, &[])?.keys_for(Action::Edit),
+            ["k"]
+        );
+        assert!(Keyboard::parse("bepo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn formats_keys_back_to_notation() -> Result<()> {
+        for s in ["gg", "<Left>", "<C-z>", "d<Down>"] {
+            assert_eq!(format_keys(&parse_keys(s)?), s);
+        }
+        Ok(())
+    }
+}
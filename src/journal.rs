@@ -0,0 +1,220 @@
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use csv::{ReaderBuilder, WriterBuilder};
+
+/// One committed change, as recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    SetCell {
+        row: usize,
+        col: usize,
+        value: String,
+    },
+    DeleteRows {
+        row: usize,
+        count: usize,
+    },
+    /// Insert a row before `row` (restores deleted rows on undo).
+    InsertRow {
+        row: usize,
+        cells: Vec<String>,
+    },
+    /// Rearrange rows so that new row `i` is old row `order[i]`.
+    Reorder {
+        order: Vec<usize>,
+    },
+    /// Insert a column before `col`; `cells[r]` goes into row `r`.
+    InsertCol {
+        col: usize,
+        cells: Vec<String>,
+    },
+    DeleteCol {
+        col: usize,
+    },
+    /// Rearrange columns so that new column `i` is old column `order[i]`.
+    ReorderCols {
+        order: Vec<usize>,
+    },
+}
+
+impl Edit {
+    fn to_record(&self) -> Vec<String> {
+        match self {
+            Edit::SetCell { row, col, value } => {
+                vec![
+                    "set".into(),
+                    row.to_string(),
+                    col.to_string(),
+                    value.clone(),
+                ]
+            }
+            Edit::DeleteRows { row, count } => {
+                vec!["delete-rows".into(), row.to_string(), count.to_string()]
+            }
+            Edit::InsertRow { row, cells } => {
+                let mut rec = vec!["insert-row".into(), row.to_string()];
+                rec.extend(cells.iter().cloned());
+                rec
+            }
+            Edit::Reorder { order } => std::iter::once("reorder".to_string())
+                .chain(order.iter().map(usize::to_string))
+                .collect(),
+            Edit::InsertCol { col, cells } => {
+                let mut rec = vec!["insert-col".into(), col.to_string()];
+                rec.extend(cells.iter().cloned());
+                rec
+            }
+            Edit::DeleteCol { col } => vec!["delete-col".into(), col.to_string()],
+            Edit::ReorderCols { order } => std::iter::once("reorder-cols".to_string())
+                .chain(order.iter().map(usize::to_string))
+                .collect(),
+        }
+    }
+
+    fn from_record(rec: &csv::StringRecord) -> Option<Self> {
+        let num = |i: usize| rec.get(i)?.parse().ok();
+        match rec.get(0)? {
+            "set" => Some(Edit::SetCell {
+                row: num(1)?,
+                col: num(2)?,
+                value: rec.get(3)?.to_string(),
+            }),
+            "delete-rows" => Some(Edit::DeleteRows {
+                row: num(1)?,
+                count: num(2)?,
+            }),
+            "insert-row" => Some(Edit::InsertRow {
+                row: num(1)?,
+                cells: rec.iter().skip(2).map(str::to_string).collect(),
+            }),
+            "reorder" => Some(Edit::Reorder {
+                order: (1..rec.len()).map(num).collect::<Option<_>>()?,
+            }),
+            "insert-col" => Some(Edit::InsertCol {
+                col: num(1)?,
+                cells: rec.iter().skip(2).map(str::to_string).collect(),
+            }),
+            "delete-col" => Some(Edit::DeleteCol { col: num(1)? }),
+            "reorder-cols" => Some(Edit::ReorderCols {
+                order: (1..rec.len()).map(num).collect::<Option<_>>()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Append-only journal (`<file>.journal`) of edits made since the last save.
+/// Entries are stored as CSV records (`set,row,col,value`,
+/// `delete-rows,row,count`, `insert-row,row,cells...`, `reorder,old...`,
+/// `insert-col,col,cells...`, `delete-col,col`, `reorder-cols,old...`) so multiline values and
+/// separators survive unchanged.
+pub fn journal_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+/// Append the edits of one step with a single write and sync, however
+/// many cells it touches.
+pub fn append(path: &Path, edits: &[Edit]) -> Result<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {path:?}"))?;
+    let mut wtr = WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .buffer_capacity(1 << 16)
+        .from_writer(file);
+    for edit in edits {
+        wtr.write_record(edit.to_record())?;
+    }
+    let file = wtr.into_inner().map_err(|e| e.into_error())?;
+    // Survive a crash or a dropped SSH session right after the edit.
+    file.sync_data().with_context(|| format!("sync {path:?}"))?;
+    Ok(())
+}
+
+/// Read back recorded edits. A torn final record (crash mid-write) is ignored.
+pub fn read(path: &Path) -> Result<Vec<Edit>> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("open {path:?}")),
+    };
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+    let mut out = Vec::new();
+    for rec in rdr.records() {
+        let Some(edit) = rec.ok().as_ref().and_then(Edit::from_record) else {
+            break;
+        };
+        out.push(edit);
+    }
+    Ok(out)
+}
+
+pub fn clear(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("remove {path:?}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn appends_and_reads_back_edits() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_journal_{}.journal", std::process::id()));
+        clear(&path)?;
+        let edits = [
+            Edit::SetCell {
+                row: 0,
+                col: 1,
+                value: "plain".into(),
+            },
+            Edit::SetCell {
+                row: 3,
+                col: 0,
+                value: "multi\nline, \"quoted\"".into(),
+            },
+            Edit::DeleteRows { row: 1, count: 2 },
+            Edit::InsertRow {
+                row: 1,
+                cells: vec!["a".into(), "".into(), "c,d".into()],
+            },
+            Edit::Reorder {
+                order: vec![2, 0, 1],
+            },
+            Edit::InsertCol {
+                col: 0,
+                cells: vec!["1".into(), "2".into()],
+            },
+            Edit::DeleteCol { col: 3 },
+            Edit::ReorderCols {
+                order: vec![1, 0, 2],
+            },
+        ];
+        append(&path, &edits[..2])?;
+        append(&path, &[])?;
+        append(&path, &edits[2..])?;
+        assert_eq!(read(&path)?, edits);
+        clear(&path)?;
+        assert!(read(&path)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Advisory lock file placed next to the CSV (`<file>.lock`) while a session
+/// has it open for editing. The file holds the owning process id so stale
+/// locks left behind by a crashed session can be taken over.
+pub struct FileLock {
+    path: PathBuf,
+}
+
+pub enum LockOutcome {
+    Acquired(FileLock),
+    /// Another live session holds the lock; carries its pid when readable.
+    HeldBy(Option<u32>),
+    /// The directory doesn't accept new files, so nothing can be saved there.
+    Unwritable,
+}
+
+pub fn lock_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+impl FileLock {
+    pub fn acquire(csv_path: &Path) -> Result<LockOutcome> {
+        let path = lock_path_for(csv_path);
+        // Two attempts: the second one runs after removing a stale lock.
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    writeln!(f, "{}", std::process::id())
+                        .with_context(|| format!("write {path:?}"))?;
+                    return Ok(LockOutcome::Acquired(FileLock { path }));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let owner = read_owner(&path);
+                    match owner {
+                        Some(pid) if !process_alive(pid) => {
+                            let _ = fs::remove_file(&path);
+                        }
+                        _ => return Ok(LockOutcome::HeldBy(owner)),
+                    }
+                }
+                Err(e) if crate::csv_io::is_permission_error(&e) => {
+                    return Ok(LockOutcome::Unwritable);
+                }
+                Err(e) => return Err(e).with_context(|| format!("create {path:?}")),
+            }
+        }
+        Ok(LockOutcome::HeldBy(read_owner(&path)))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Only remove the lock if it is still ours.
+        if read_owner(&self.path) == Some(std::process::id()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn read_owner(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new(&format!("/proc/{pid}")).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> bool {
+    // Without a cheap liveness probe, err on the side of respecting the lock.
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn second_acquire_reports_holder() -> Result<()> {
+        let csv = env::temp_dir().join(format!("tui_csv_lock_{}.csv", std::process::id()));
+        let first = match FileLock::acquire(&csv)? {
+            LockOutcome::Acquired(l) => l,
+            LockOutcome::HeldBy(_) | LockOutcome::Unwritable => panic!("expected to acquire lock"),
+        };
+        match FileLock::acquire(&csv)? {
+            LockOutcome::HeldBy(pid) => assert_eq!(pid, Some(std::process::id())),
+            LockOutcome::Acquired(_) | LockOutcome::Unwritable => panic!("lock acquired twice"),
+        }
+        drop(first);
+        assert!(!lock_path_for(&csv).exists());
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stale_lock_is_taken_over() -> Result<()> {
+        let csv = env::temp_dir().join(format!("tui_csv_stale_{}.csv", std::process::id()));
+        // pid_max on Linux is at most 2^22, so this pid cannot be running.
+        fs::write(lock_path_for(&csv), "4294967\n")?;
+        assert!(matches!(FileLock::acquire(&csv)?, LockOutcome::Acquired(_)));
+        Ok(())
+    }
+}
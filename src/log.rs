@@ -0,0 +1,91 @@
+//! `--log-file <path>`: one line per action, command, file operation and
+//! error, for working out afterwards what happened in a session ("my save
+//! didn't take") without writing anything over the TUI. Lines are
+//! `key=value` pairs after a UTC timestamp. Cell contents are never
+//! logged, only names, paths, counts, timings and error messages.
+
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use crate::picker;
+
+static LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Append to `path` from now on. Only the first call takes effect.
+pub fn init(path: &Path) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open log file {path:?}"))?;
+    let _ = LOG.set(Mutex::new(file));
+    event("start", &[("version", &env!("CARGO_PKG_VERSION"))]);
+    Ok(())
+}
+
+/// `2024-05-01T09:30:12.345Z event=save path="/tmp/a b.csv" rows=120`.
+/// Values with spaces, quotes or `=` are quoted.
+pub fn line(now: SystemTime, what: &str, fields: &[(&str, &dyn Display)]) -> String {
+    let millis = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_millis());
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() % 60);
+    let mut out = format!(
+        "{}:{secs:02}.{millis:03}Z event={what}",
+        picker::format_time(now).replace(' ', "T")
+    );
+    for (key, value) in fields {
+        let value = value.to_string();
+        if value.is_empty() || value.contains([' ', '"', '=', '\n', '\t']) {
+            out.push_str(&format!(" {key}={value:?}"));
+        } else {
+            out.push_str(&format!(" {key}={value}"));
+        }
+    }
+    out
+}
+
+/// Write one line, if logging is on. A failed write is dropped: the log
+/// must never get in the way of editing.
+pub fn event(what: &str, fields: &[(&str, &dyn Display)]) {
+    if let Some(file) = LOG.get()
+        && let Ok(mut file) = file.lock()
+    {
+        let _ = writeln!(file, "{}", line(SystemTime::now(), what, fields));
+    }
+}
+
+/// An error and its causes, as `event=error during=<what> error="..."`.
+pub fn error(during: &str, e: &anyhow::Error) {
+    event(
+        "error",
+        &[("during", &during), ("error", &format!("{e:#}"))],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_timestamped_key_value_lines() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_714_555_812_345);
+        assert_eq!(
+            line(at, "save", &[("path", &"/tmp/a b.csv"), ("rows", &120)]),
+            "2024-05-01T09:30:12.345Z event=save path=\"/tmp/a b.csv\" rows=120"
+        );
+        assert_eq!(
+            line(at, "x", &[("note", &"")]),
+            "2024-05-01T09:30:12.345Z event=x note=\"\""
+        );
+    }
+}
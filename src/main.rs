@@ -1,359 +1,549 @@
+mod a1;
+mod a11y;
+mod anonymize;
+mod app;
+mod badge;
+#[cfg(test)]
+mod bench;
+mod bidi;
+mod check;
+mod cli;
+mod clipboard;
+mod columns;
+mod command;
+mod complete;
+mod compress;
+mod config;
+#[cfg(unix)]
+mod control;
+/// `--control` needs Unix sockets; elsewhere no server can exist.
+#[cfg(not(unix))]
+mod control {
+    pub enum Server {}
+}
+mod csv_io;
+mod escape;
+mod export;
+mod expr;
+mod external;
+mod fill;
+mod filters;
+mod findings;
+mod fuzzy;
+mod fwf;
+mod generate;
+mod header;
+mod help;
+mod history;
+mod input;
+mod journal;
+mod lock;
+mod log;
+mod mask;
+mod money;
+mod names;
+mod nulls;
+mod outliers;
+mod palette;
+mod picker;
+mod presets;
+mod profile;
+mod prompt;
+mod recent;
+mod regex;
+mod remote;
+mod replace;
+mod report;
+mod schema;
+mod sci;
+mod scratch;
+mod search;
+mod session;
+mod sort;
+mod split;
+mod statusline;
+mod tags;
+mod term;
+mod tutorial;
+mod typing;
+mod ui;
+mod undo;
+mod view;
+mod viz;
+
 use std::env;
-use std::fs::File;
 use std::io::{self};
-use std::path::PathBuf;
-use std::time::Duration;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::terminal::{
-    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
-};
-use crossterm::{execute, terminal};
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
-use ratatui::Frame as TuiFrame;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
 
-#[derive(Default)]
-struct App {
-    file_path: PathBuf,
-    data: Vec<Vec<String>>, // rows x cols
-    row: usize,
-    col: usize,
-    editing: bool,
-    editor_buf: String,
-    dirty: bool,
+use app::{App, handle_key, handle_paste};
+use csv_io::save_csv;
+
+/// Where an emergency save after a panic or error goes; never the original file, since
+/// the in-memory data may be mid-update.
+fn rescue_path_for(path: &Path) -> PathBuf {
+    path.with_extension("rescue.csv")
 }
 
-impl App {
-    fn max_cols(&self) -> usize {
-        self.data.iter().map(|r| r.len()).max().unwrap_or(0)
-    }
+fn usage(program: &str) {
+    eprintln!(
+        "Usage: {program} [--tick-rate <ms>] [--fwf <widths|auto>] [--expand-sci] [--decimal-comma] [--log-file <path>] [--control <socket>] [--record <session.json>] [--replay <session.json>] [--validate] [--tutorial] [--new --template <schema>] [path/to/file.csv | directory | https:// or s3:// URL]"
+    );
+}
 
-    fn ensure_cell_exists(&mut self, r: usize, c: usize) {
-        if r >= self.data.len() {
-            self.data.resize(r + 1, Vec::new());
+fn main() -> Result<()> {
+    let _scratch = scratch::Cleanup;
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+    let args = match cli::parse(args) {
+        Ok(a) => a,
+        Err(e) => {
+            usage(&program);
+            return Err(e);
         }
-        if c >= self.data[r].len() {
-            self.data[r].resize(c + 1, String::new());
+    };
+    if let Some(path) = &args.log_file {
+        log::init(path)?;
+    }
+    let mut config = config::Config::load()?;
+    if let Some(t) = args.tick_rate {
+        config.tick_rate = t;
+    }
+    if args.expand_sci {
+        config.expand_sci = true;
+    }
+    if args.decimal_comma {
+        config.decimal = viz::Decimal::Comma;
+    }
+    if args.new {
+        let path = args
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("--new needs the path of the file to create"))?;
+        new_from_template(path, args.template.as_deref().unwrap_or(Path::new("")))?;
+    }
+    if args.validate {
+        let path = args
+            .path
+            .filter(|p| !p.is_dir())
+            .ok_or_else(|| anyhow!("--validate needs a CSV file"))?;
+        let (out, errors) = validate_file(&path, &config)?;
+        print!("{out}");
+        if errors > 0 {
+            std::process::exit(1);
         }
+        return Ok(());
     }
-}
+    let replay = args
+        .replay
+        .as_deref()
+        .map(session::Session::load)
+        .transpose()?;
+    let control: Option<control::Server> = match &args.control {
+        #[cfg(unix)]
+        Some(path) => Some(control::Server::bind(path)?),
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("--control needs Unix domain sockets"),
+        None => None,
+    };
+    let _guard = term::TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.clear()?;
 
-struct TerminalGuard;
+    let mut app = App {
+        keymap: input::Keymap::for_keyboard(config.keyboard, &config.bindings)
+            .context("key bindings")?,
+        caps: config.caps(),
+        config,
+        recent_list: recent::recent_file(),
+        scripts: config::config_dir().map(|d| d.join("scripts")),
+        prompt_history: history::history_file()
+            .and_then(|f| history::PromptHistory::load(&f).ok())
+            .unwrap_or_default(),
+        export_settings: export::settings_file()
+            .and_then(|f| export::Settings::load(&f).ok())
+            .unwrap_or_default(),
+        export_file: export::settings_file(),
+        ..App::default()
+    };
 
-impl TerminalGuard {
-    fn enter() -> Result<Self> {
-        enable_raw_mode().context("enable raw mode")?;
-        execute!(io::stdout(), EnterAlternateScreen).context("enter alt screen")?;
-        Ok(Self)
+    // A directory (or no argument) opens the file picker first; with no
+    // argument, recently edited files are offered at the top.
+    // A replayed session opens the file it was recorded against unless
+    // another one is named.
+    if args.tutorial {
+        app.tutorial = Some(tutorial::Tutorial::default());
     }
-}
+    let path = match args.tutorial {
+        true => Some(tutorial::sample_file()?),
+        false => args.path,
+    };
+    let path = path.or_else(|| replay.as_ref().and_then(|s| s.file.clone()));
+    let path = match path {
+        Some(p) if !p.is_dir() => Some(p),
+        dir => {
+            let recent = match (&dir, &app.recent_list) {
+                (None, Some(list)) => recent::load(list).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            let dir = dir.unwrap_or_else(|| PathBuf::from("."));
+            match pick_file(&mut terminal, &dir, &recent, app.caps)? {
+                Some(picker::Pick::File(p)) => Some(p),
+                Some(picker::Pick::NewFile) => None,
+                None => return Ok(()),
+            }
+        }
+    };
 
-impl Drop for TerminalGuard {
-    fn drop(&mut self) {
-        let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen);
-        let _ = terminal::disable_raw_mode();
+    let mut recorder = args
+        .record
+        .as_ref()
+        .map(|_| session::Recorder::new(path.clone()));
+    // No path ("new file" in the picker) starts an unnamed grid that asks
+    // for a file name on the first save.
+    match path {
+        Some(path) if let Some(remote) = path.to_str().and_then(remote::Remote::parse) => {
+            app.open_remote(remote)?
+        }
+        Some(path) if let Some(spec) = &args.fwf => app.import_fwf(&path, spec)?,
+        Some(path) => app.open(path)?,
+        None => app.prompt = Some(prompt::Prompt::new(prompt::PromptKind::NewDimensions)),
     }
-}
 
-fn usage(program: &str) {
-    eprintln!("Usage: {program} <path/to/file.csv>");
+    term::install_panic_hook();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        run(
+            &mut terminal,
+            &mut app,
+            control.as_ref(),
+            recorder.as_mut(),
+            replay.as_ref(),
+        )
+    }));
+    let result = finish(outcome, &app);
+    // Saved even after a panic: that is the session a bug report needs.
+    if let (Some(out), Some(recorder)) = (&args.record, &recorder) {
+        recorder.session().save(out)?;
+    }
+    result.inspect_err(|e| log::error("exit", e))
 }
 
-fn load_csv(path: &PathBuf) -> Result<Vec<Vec<String>>> {
-    let file = File::open(path).with_context(|| format!("open {path:?}"))?;
-    let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(file);
-    let mut out = Vec::new();
-    for rec in rdr.records() {
-        let rec: StringRecord = rec?;
-        out.push(rec.iter().map(|s| s.to_string()).collect());
+/// How the event loop ended, with unsaved data written to a rescue file
+/// whenever it ended in a panic or an error rather than a quit.
+fn finish(outcome: std::thread::Result<Result<()>>, app: &App) -> Result<()> {
+    let rescue = || -> Result<PathBuf> {
+        let path = if app.has_path() {
+            rescue_path_for(&app.file_path)
+        } else {
+            env::temp_dir().join(format!("tui-csv-editor-{}.rescue.csv", std::process::id()))
+        };
+        save_csv(&path, &app.data)?;
+        Ok(path)
+    };
+    match outcome {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) if app.dirty => {
+            let path = rescue().with_context(|| format!("{e:#}; and the emergency save failed"))?;
+            Err(e.context(format!("unsaved data written to {}", path.display())))
+        }
+        Ok(Err(e)) => Err(e),
+        Err(_) if app.dirty => {
+            let path = rescue().with_context(|| "panicked, and the emergency save failed")?;
+            Err(anyhow!(
+                "panicked; unsaved data written to {}",
+                path.display()
+            ))
+        }
+        Err(_) => Err(anyhow!("panicked; no unsaved changes were lost")),
     }
-    Ok(out)
 }
 
-fn save_csv(path: &PathBuf, data: &[Vec<String>]) -> Result<()> {
-    let file = File::create(path).with_context(|| format!("create {path:?}"))?;
-    let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
-    for row in data {
-        wtr.write_record(row)?;
+/// `--new --template`: write `path` from the template's header and example
+/// rows, and keep the template as the file's schema so `:validate` checks
+/// new rows against the same rules.
+fn new_from_template(path: &PathBuf, template: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    let text = std::fs::read_to_string(template)
+        .with_context(|| format!("read {}", template.display()))?;
+    let schema = schema::Schema::parse(&text).with_context(|| format!("{}", template.display()))?;
+    save_csv(path, &schema.template()?)?;
+    let sidecar = schema::schema_path_for(path);
+    if !sidecar.exists() {
+        std::fs::write(&sidecar, text).with_context(|| format!("write {sidecar:?}"))?;
     }
-    wtr.flush()?;
     Ok(())
 }
 
-fn draw_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &App) -> Result<()> {
-    terminal.draw(|f| {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Min(3),    // table
-                Constraint::Length(3), // status/help
-                Constraint::Length(3), // editor / message line
-            ])
-            .split(f.area());
-
-        draw_table(f, chunks[0], app);
-        draw_status(f, chunks[1], app);
-        draw_editor(f, chunks[2], app);
-    })?;
-    Ok(())
+/// `--validate`: the findings for `path` against its schema, in
+/// `file:row:col:` form, and how many are errors rather than warnings. A
+/// one-line summary goes to stderr so stdout stays machine-readable.
+fn validate_file(path: &Path, config: &config::Config) -> Result<(String, usize)> {
+    let data = csv_io::load_csv(&path.to_path_buf()).context("failed to load CSV")?;
+    let schema = schema::Schema::load_for(path)?
+        .ok_or_else(|| anyhow!("no schema at {}", schema::schema_path_for(path).display()))?;
+    let header = config
+        .header
+        .unwrap_or_else(|| header::looks_like_header(&data, config.decimal));
+    let items = schema.validate(&data, header, &config.nulls, config.decimal)?;
+    let errors = items.iter().filter(|f| !f.warning).count();
+    eprintln!(
+        "{}: {errors} error(s), {} warning(s)",
+        path.display(),
+        items.len() - errors
+    );
+    Ok((report::to_text(&path.display().to_string(), &items), errors))
 }
 
-fn draw_table(f: &mut TuiFrame, area: Rect, app: &App) {
-    let rows_len = app.data.len();
-    let cols_len = app.max_cols();
-    let cols = cols_len.max(1);
-
-    let mut rows = Vec::with_capacity(rows_len.max(1));
-    for (r_idx, row) in app.data.iter().enumerate() {
-        let mut cells = Vec::with_capacity(cols);
-        for c_idx in 0..cols {
-            let txt = row.get(c_idx).map(String::as_str).unwrap_or("");
-            let mut cell = Cell::from(txt.to_string());
-            if r_idx == app.row && c_idx == app.col {
-                cell = cell.style(
-                    Style::default()
-                        .fg(Color::Black)
-                        .bg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
-                );
+/// Browse from `dir` until a file (or a new one) is chosen; `None` on quit.
+fn pick_file<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    dir: &Path,
+    recent: &[PathBuf],
+    caps: palette::Caps,
+) -> Result<Option<picker::Pick>> {
+    let mut picker = picker::Picker::open_with_recent(dir, recent)?;
+    loop {
+        ui::draw_picker(terminal, &picker, caps)?;
+        if let Event::Key(key) = event::read()?
+            && key.kind != KeyEventKind::Release
+        {
+            if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(None);
+            }
+            if let Some(pick) = picker.handle_key(key)? {
+                return Ok(Some(pick));
             }
-            cells.push(cell);
         }
-        rows.push(Row::new(cells));
     }
-
-    // Construct basic constraints: at least 5 chars per column.
-    let constraints: Vec<Constraint> = (0..cols).map(|_| Constraint::Min(5)).collect();
-
-    let table = Table::new(rows, constraints)
-        .block(Block::default().title("CSV Viewer").borders(Borders::ALL))
-        .column_spacing(1);
-    f.render_widget(table, area);
-}
-
-fn draw_status(f: &mut TuiFrame, area: Rect, app: &App) {
-    let status = format!(
-        "File: {} | Pos: (row {}, col {}) | Dirty: {}",
-        app.file_path.display(),
-        app.row + 1,
-        app.col + 1,
-        if app.dirty { "yes" } else { "no" }
-    );
-    let help = "Arrows: move  e: edit  Enter: save cell  Esc: cancel  w: write  q: quit";
-    let text = vec![Line::raw(status), Line::raw(help)];
-    let p = Paragraph::new(text)
-        .block(Block::default().title("Status").borders(Borders::ALL))
-        .wrap(Wrap { trim: true });
-    f.render_widget(p, area);
 }
 
-fn draw_editor(f: &mut TuiFrame, area: Rect, app: &App) {
-    let (title, content) = if app.editing {
-        (
-            "Editor",
-            format!(
-                "Editing (r{}, c{}): {}",
-                app.row + 1,
-                app.col + 1,
-                app.editor_buf
-            ),
-        )
-    } else {
-        ("Info", "Press 'e' to edit selected cell".to_string())
-    };
-    let p = Paragraph::new(Line::from(Span::raw(content)))
-        .block(Block::default().title(title).borders(Borders::ALL));
-    f.render_widget(p, area);
+/// What the terminal was last told about the open file, so the title and
+/// directory are only sent again when they change.
+#[derive(Default)]
+struct Shown {
+    title: Option<String>,
+    dir: Option<PathBuf>,
 }
 
-fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
-    // Returns Ok(true) to request exit
-    if app.editing {
-        match key.code {
-            KeyCode::Enter => {
-                app.ensure_cell_exists(app.row, app.col);
-                app.data[app.row][app.col] = app.editor_buf.clone();
-                app.editor_buf.clear();
-                app.editing = false;
-                app.dirty = true;
-            }
-            KeyCode::Esc => {
-                app.editor_buf.clear();
-                app.editing = false;
-            }
-            KeyCode::Backspace => {
-                app.editor_buf.pop();
+impl Shown {
+    fn update(&mut self, app: &App) -> Result<()> {
+        let name = app
+            .remote
+            .as_ref()
+            .map_or(app.file_path.display().to_string(), |r| r.url().to_string());
+        if app.config.title {
+            let title = term::window_title(&name, app.dirty);
+            if self.title.as_ref() != Some(&title) {
+                term::set_title(&title)?;
+                self.title = Some(title);
             }
-            KeyCode::Char(c) => {
-                if key.modifiers.is_empty() || key.modifiers == KeyModifiers::SHIFT {
-                    app.editor_buf.push(c);
-                }
-            }
-            KeyCode::Left => {}
-            KeyCode::Right => {}
-            KeyCode::Up => {}
-            KeyCode::Down => {}
-            _ => {}
         }
-        return Ok(false);
+        if app.config.osc7
+            && app.remote.is_none()
+            && let Some(dir) = app
+                .file_path
+                .canonicalize()
+                .ok()
+                .and_then(|p| p.parent().map(Path::to_path_buf))
+            && self.dir.as_ref() != Some(&dir)
+        {
+            term::report_cwd(&dir)?;
+            self.dir = Some(dir);
+        }
+        Ok(())
     }
+}
 
-    match key.code {
-        KeyCode::Char('q') => {
-            // Auto-save on quit if dirty
-            if app.dirty {
-                save_csv(&app.file_path, &app.data)?;
+fn run<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    control: Option<&control::Server>,
+    mut recorder: Option<&mut session::Recorder>,
+    replay: Option<&session::Session>,
+) -> Result<()> {
+    let suspend = term::suspend_flag()?;
+    // Only redraw when something could have changed the screen; idle ticks
+    // just go back to waiting so an open editor costs next to no CPU.
+    let mut redraw = true;
+    let mut key_at: Option<Instant> = None;
+    // Requests on the control socket are only seen between polls, so keep
+    // those short enough that a script driving the editor isn't held up.
+    let wait = match control {
+        Some(_) => app.config.tick_rate.min(Duration::from_millis(50)),
+        None => app.config.tick_rate,
+    };
+    let started = Instant::now();
+    let mut replayed = 0;
+    let mut shown = Shown::default();
+    loop {
+        if redraw {
+            shown.update(app)?;
+            let start = Instant::now();
+            ui::draw_ui(terminal, app)?;
+            if let Some(perf) = &mut app.perf {
+                perf.frame = start.elapsed();
+                if let Some(at) = key_at.take() {
+                    perf.latency = at.elapsed();
+                }
             }
-            return Ok(true);
-        }
-        KeyCode::Char('w') => {
-            save_csv(&app.file_path, &app.data)?;
-            app.dirty = false;
+            redraw = false;
         }
-        KeyCode::Char('e') => {
-            app.ensure_cell_exists(app.row, app.col);
-            app.editor_buf = app.data[app.row][app.col].clone();
-            app.editing = true;
-        }
-        KeyCode::Left => {
-            if app.col > 0 {
-                app.col -= 1;
+        // A replay types its keys and pastes at the times they were
+        // recorded; typing is ignored until it has played out, so it runs the same each time.
+        let next = replay.and_then(|s| s.events.get(replayed));
+        let wait = next.map_or(wait, |e| wait.min(e.at.saturating_sub(started.elapsed())));
+        if event::poll(wait)? {
+            match event::read()? {
+                Event::Key(_) | Event::Paste(_) if next.is_some() => {}
+                // Windows reports releases too; only presses type.
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) => {
+                    key_at = Some(Instant::now());
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.key(key);
+                    }
+                    if handle_key(app, key)? {
+                        break;
+                    }
+                    redraw = true;
+                }
+                Event::Paste(text) => {
+                    key_at = Some(Instant::now());
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.paste(&text);
+                    }
+                    handle_paste(app, &text);
+                    redraw = true;
+                }
+                Event::Resize(..) => redraw = true,
+                _ => {}
             }
         }
-        KeyCode::Right => {
-            let cols = app.max_cols();
-            if app.col + 1 < cols {
-                app.col += 1;
+        if let Some(e) = next
+            && started.elapsed() >= e.at
+        {
+            replayed += 1;
+            key_at = Some(Instant::now());
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.push(e.input.clone());
             }
-        }
-        KeyCode::Up => {
-            if app.row > 0 {
-                app.row -= 1;
-                app.col = app.col.min(app.data[app.row].len().saturating_sub(1));
+            if e.input.apply(app)? {
+                break;
+            }
+            if replay.is_some_and(|s| replayed == s.events.len()) {
+                app.message = Some(format!("Replayed {replayed} events"));
             }
+            redraw = true;
         }
-        KeyCode::Down => {
-            if app.row + 1 < app.data.len() {
-                app.row += 1;
-                app.col = app.col.min(app.data[app.row].len().saturating_sub(1));
+        #[cfg(unix)]
+        while let Some(request) = control.and_then(control::Server::try_next) {
+            let (reply, quit) = control::handle(app, &request.line);
+            request.respond(reply);
+            if quit {
+                return Ok(());
             }
+            redraw = true;
         }
-        _ => {}
-    }
-    Ok(false)
-}
-
-fn main() -> Result<()> {
-    let mut args = env::args().collect::<Vec<_>>();
-    let program = args.remove(0);
-    if args.is_empty() {
-        usage(&program);
-        return Err(anyhow!("missing CSV file path"));
-    }
-    let file_path = PathBuf::from(&args[0]);
-    let data = load_csv(&file_path).with_context(|| "failed to load CSV")?;
-
-    let _guard = TerminalGuard::enter()?;
-    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
-    terminal.clear()?;
-
-    let mut app = App {
-        file_path,
-        data,
-        row: 0,
-        col: 0,
-        editing: false,
-        editor_buf: String::new(),
-        dirty: false,
-    };
-
-    loop {
-        draw_ui(&mut terminal, &app)?;
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                let exit = handle_key(&mut app, key)?;
-                if exit {
-                    break;
-                }
+        if let Some(target) = app.external.take() {
+            if let Err(e) = term::with_tui_suspended(|| app.edit_externally(target))? {
+                app.message = Some(format!("External edit failed: {e:#}"));
             }
+            terminal.clear()?;
+            shown = Shown::default();
+            redraw = true;
+        }
+        if std::mem::take(&mut app.suspend_requested) || suspend.load(Ordering::SeqCst) {
+            term::suspend(&suspend)?;
+            terminal.clear()?;
+            shown = Shown::default();
+            redraw = true;
         }
     }
-
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
 
-    fn key(code: KeyCode) -> KeyEvent {
-        KeyEvent::new(code, KeyModifiers::NONE)
+    #[test]
+    fn test_rescue_path_keeps_original_untouched() {
+        let p = PathBuf::from("/tmp/data.csv");
+        assert_eq!(rescue_path_for(&p), PathBuf::from("/tmp/data.rescue.csv"));
     }
 
     #[test]
-    fn test_load_and_save_csv_roundtrip() -> Result<()> {
-        let dir = env::temp_dir();
-        let path = dir.join(format!("tui_csv_viewer_test_{}.csv", std::process::id()));
-        fs::write(&path, b"a,b\nc,d\n")?;
-
-        let data = load_csv(&path)?;
-        assert_eq!(data.len(), 2);
-        assert_eq!(data[0], vec!["a".to_string(), "b".to_string()]);
-        assert_eq!(data[1], vec!["c".to_string(), "d".to_string()]);
-
-        let mut new_data = data.clone();
-        new_data[1][1] = "dd".into();
-        save_csv(&path, &new_data)?;
-
-        let reread = fs::read_to_string(&path)?;
-        assert!(reread.trim_end().ends_with("c,dd"));
-        let round = load_csv(&path)?;
-        assert_eq!(round[1][1], "dd");
-        let _ = fs::remove_file(&path);
+    fn test_an_error_exit_with_unsaved_edits_writes_the_rescue_file() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_rescue_{}.csv", std::process::id()));
+        let mut app = App::new(path.clone(), vec![vec!["edited".into()]]);
+        app.dirty = true;
+        let err = finish(Ok(Err(anyhow!("disk gone"))), &app).err();
+        let rescue = rescue_path_for(&path);
+        assert_eq!(std::fs::read_to_string(&rescue)?, "edited\n");
+        assert!(err.is_some_and(|e| format!("{e:#}").ends_with("disk gone")));
+        std::fs::remove_file(&rescue)?;
+
+        app.dirty = false;
+        assert!(finish(Ok(Err(anyhow!("disk gone"))), &app).is_err());
+        assert!(!rescue.exists());
         Ok(())
     }
 
     #[test]
-    fn test_edit_flow_and_write_key() -> Result<()> {
+    fn test_new_from_template_writes_header_examples_and_schema() -> Result<()> {
         let dir = env::temp_dir();
-        let path = dir.join(format!("tui_csv_viewer_flow_{}.csv", std::process::id()));
-        std::fs::write(&path, b"a,b\nc,d\n")?;
-
-        let data = load_csv(&path)?;
-        let mut app = App {
-            file_path: path.clone(),
-            data,
-            row: 0,
-            col: 0,
-            editing: false,
-            editor_buf: String::new(),
-            dirty: false,
+        let template = dir.join(format!("tui_csv_template_{}.schema", std::process::id()));
+        let path = dir.join(format!("tui_csv_new_{}.csv", std::process::id()));
+        std::fs::write(
+            &template,
+            "id = uuid\nmail = email\nexample mail = a@b.io\n",
+        )?;
+        new_from_template(&path, &template)?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "id,mail\n123e4567-e89b-42d3-a456-426614174000,a@b.io\n"
+        );
+        let config = config::Config {
+            header: Some(true),
+            ..config::Config::default()
         };
+        assert_eq!(validate_file(&path, &config)?.1, 0);
+        assert!(new_from_template(&path, &template).is_err());
+        let _ = std::fs::remove_file(schema::schema_path_for(&path));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&template);
+        Ok(())
+    }
 
-        handle_key(&mut app, key(KeyCode::Char('e')))?;
-        assert!(app.editing);
-        assert_eq!(app.editor_buf, "a");
-
-        handle_key(&mut app, key(KeyCode::Char('X')))?;
-        handle_key(&mut app, key(KeyCode::Enter))?;
-        assert!(!app.editing);
-        assert_eq!(app.data[0][0], "aX");
-        assert!(app.dirty);
-
-        handle_key(&mut app, key(KeyCode::Char('w')))?;
-        assert!(!app.dirty);
-        let reread = std::fs::read_to_string(&app.file_path)?;
-        assert!(reread.contains("aX,b"));
-        let _ = std::fs::remove_file(&app.file_path);
+    #[test]
+    fn test_validate_file_counts_errors_but_not_warnings() -> Result<()> {
+        let path = env::temp_dir().join(format!("tui_csv_validate_{}.csv", std::process::id()));
+        std::fs::write(&path, "mail,site\nx,y\na@b.io,https://b.io\n")?;
+        std::fs::write(
+            schema::schema_path_for(&path),
+            "mail = email\nwarn site = url\n",
+        )?;
+        let (out, errors) = validate_file(
+            &path,
+            &config::Config {
+                header: Some(true),
+                ..config::Config::default()
+            },
+        )?;
+        assert_eq!(errors, 1);
+        assert_eq!(out.lines().count(), 2);
+        assert!(out.starts_with(&format!("{}:2:1: error [email]", path.display())));
+        let _ = std::fs::remove_file(schema::schema_path_for(&path));
+        let _ = std::fs::remove_file(&path);
         Ok(())
     }
 }
@@ -0,0 +1,59 @@
+//! Columns drawn masked, for showing real data on a shared screen: the
+//! values stay as they are in the file and in edits, only what is drawn
+//! (and, if asked, exported) hides them.
+
+use anyhow::{Result, bail};
+
+/// What a masked column shows of its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    /// `•••`, whatever the value, so not even its length shows.
+    Full,
+    /// `•••` and the last four characters, as on a card statement.
+    Last4,
+}
+
+const DOTS: &str = "•••";
+
+impl Mask {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim() {
+            "" | "full" => Ok(Mask::Full),
+            "last4" => Ok(Mask::Last4),
+            other => bail!("unknown mask {other:?}; use full or last4"),
+        }
+    }
+
+    /// `value` as the mask shows it. Blank cells stay blank, and a value of
+    /// four characters or fewer is hidden whole rather than shown whole.
+    pub fn apply(self, value: &str) -> String {
+        if value.is_empty() {
+            return String::new();
+        }
+        let chars: Vec<char> = value.chars().collect();
+        match self {
+            Mask::Last4 if chars.len() > 4 => {
+                let tail: String = chars[chars.len() - 4..].iter().collect();
+                format!("{DOTS}{tail}")
+            }
+            _ => DOTS.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hides_values_whole_or_all_but_the_last_four() -> Result<()> {
+        assert_eq!(Mask::Full.apply("4111111111111111"), "•••");
+        assert_eq!(Mask::Last4.apply("4111111111111111"), "•••1111");
+        assert_eq!(Mask::Last4.apply("東京都港区1-2"), "•••区1-2");
+        assert_eq!(Mask::Last4.apply("1234"), "•••");
+        assert_eq!(Mask::Full.apply(""), "");
+        assert_eq!(Mask::parse("last4")?, Mask::Last4);
+        assert!(Mask::parse("half").is_err());
+        Ok(())
+    }
+}
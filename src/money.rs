@@ -0,0 +1,141 @@
+use crate::findings::Finding;
+use crate::nulls::Nulls;
+use crate::replace::Change;
+use crate::viz::Decimal;
+
+/// Currency signs stripped from amounts.
+const SYMBOLS: &str = "$€£¥¢₹₩₽₺₪₫₱₦₴₸₡₲₵₿฿";
+
+fn is_symbol(c: char) -> bool {
+    SYMBOLS.contains(c)
+}
+
+/// `s` without a currency sign or code on either side: `US$ 5`, `5 EUR`
+/// and `CHF5` all become `5`.
+fn strip_unit(s: &str) -> &str {
+    let unit = |c: char| is_symbol(c) || c.is_whitespace();
+    let mut t = s.trim_matches(unit);
+    let lead = t.bytes().take_while(u8::is_ascii_uppercase).count();
+    if (1..=3).contains(&lead)
+        && t[lead..].starts_with(|c: char| unit(c) || (lead == 3 && c.is_ascii_digit()))
+    {
+        t = &t[lead..];
+    }
+    let trail = t.bytes().rev().take_while(u8::is_ascii_uppercase).count();
+    let rest = &t[..t.len() - trail];
+    if (1..=3).contains(&trail)
+        && rest.ends_with(|c: char| unit(c) || (trail == 3 && c.is_ascii_digit()))
+    {
+        t = rest;
+    }
+    t.trim_matches(unit)
+}
+
+/// `s` as a plain number written the `decimal` way, without currency or
+/// thousands separators: `$1,234.50` becomes `1234.50`; `(12.00)`, the
+/// accounting style, becomes `-12.00`. `None` if what's left isn't a number.
+pub fn clean(s: &str, decimal: Decimal) -> Option<String> {
+    let mut t = s.trim();
+    let mut parens = false;
+    if let Some(inner) = t.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        parens = true;
+        t = inner;
+    }
+    // The minus may come before or after the sign: `-$5` or `$-5`.
+    let mut minus = t.starts_with('-');
+    t = strip_unit(t.strip_prefix('-').unwrap_or(t));
+    if !minus && let Some(rest) = t.strip_prefix('-') {
+        minus = true;
+        t = rest;
+    }
+    let negative = parens != minus;
+    if t.starts_with(['+', '-']) {
+        return None;
+    }
+    let plain = decimal.ungroup(t)?;
+    let sign = if negative { "-" } else { "" };
+    Some(decimal.write(&format!("{sign}{plain}")))
+}
+
+/// What `:strip-currency` does to `col` from row `from` down: the cells it
+/// rewrites, and a finding for each it can't read as a number. Blank and
+/// null cells are left alone.
+pub fn plan(
+    data: &[Vec<String>],
+    col: usize,
+    from: usize,
+    nulls: &Nulls,
+    decimal: Decimal,
+) -> (Vec<Change>, Vec<Finding>) {
+    let mut changes = Vec::new();
+    let mut failed = Vec::new();
+    for (row, cells) in data.iter().enumerate().skip(from) {
+        let Some(before) = cells.get(col) else {
+            continue;
+        };
+        if before.trim().is_empty() || nulls.is_null(before) {
+            continue;
+        }
+        match clean(before, decimal) {
+            Some(after) if after != *before => changes.push(Change {
+                row,
+                col,
+                before: before.clone(),
+                after,
+            }),
+            Some(_) => {}
+            None => failed.push(Finding {
+                row,
+                col,
+                rule: "strip-currency".to_string(),
+                text: format!("{before:?} is not an amount"),
+                warning: true,
+                whole_row: false,
+            }),
+        }
+    }
+    (changes, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_currency_and_grouping() {
+        let dot = Decimal::Dot;
+        for (raw, want) in [
+            ("$1,234.50", "1234.50"),
+            ("US$ 5", "5"),
+            ("-€12", "-12"),
+            ("$-12", "-12"),
+            ("(1,000.00)", "-1000.00"),
+            ("1 200 EUR", "1200"),
+            ("CHF1'000", "1000"),
+            ("¥ 30000", "30000"),
+            ("42", "42"),
+        ] {
+            assert_eq!(clean(raw, dot).as_deref(), Some(want), "{raw}");
+        }
+        assert_eq!(
+            clean("1.234,56 €", Decimal::Comma).as_deref(),
+            Some("1234,56")
+        );
+        for bad in ["n/a", "$", "12,34", "USD", "1.2.3", "--5"] {
+            assert_eq!(clean(bad, dot), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn plans_changes_and_reports_unreadable_cells() {
+        let data: Vec<Vec<String>> = ["price", "$1,000", "12", "", "ask", "NA", "£3"]
+            .iter()
+            .map(|s| vec![s.to_string()])
+            .collect();
+        let (changes, failed) = plan(&data, 0, 1, &Nulls::default(), Decimal::Dot);
+        let after: Vec<_> = changes.iter().map(|c| (c.row, c.after.as_str())).collect();
+        assert_eq!(after, [(1, "1000"), (6, "3")]);
+        assert_eq!(failed.iter().map(|f| f.row).collect::<Vec<_>>(), [4]);
+        assert_eq!(failed[0].text, "\"ask\" is not an amount");
+    }
+}
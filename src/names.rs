@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::a1;
+
+/// A block of cells: top-left and bottom-right corners, 0-based.
+pub type Range = ((usize, usize), (usize, usize));
+
+/// Named blocks of cells, kept in `<file>.names` next to the CSV as
+/// `sales_q1 = B2:D13` lines. Names stay on the cells they were given to;
+/// inserting or deleting rows doesn't move them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Names(BTreeMap<String, Range>);
+
+/// `:name range <name>`, `:name delete <name>`, `:name export <name> <file>`
+/// or `:name` to list them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    List,
+    Define(String),
+    Delete(String),
+    Export(String, PathBuf),
+}
+
+pub fn parse_op(s: &str) -> Result<Op> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let name = |n: &str| {
+        if is_name(n) {
+            Ok(n.to_string())
+        } else {
+            Err(anyhow!(
+                "bad name {n:?}; use letters, digits and _, not a cell like B3"
+            ))
+        }
+    };
+    match words[..] {
+        [] => Ok(Op::List),
+        ["range", n] => Ok(Op::Define(name(n)?)),
+        ["delete", n] => Ok(Op::Delete(name(n)?)),
+        ["export", n, _, ..] => {
+            let rest = s.trim()["export".len()..].trim_start();
+            let (_, path) = rest.split_once(char::is_whitespace).unwrap_or_default();
+            Ok(Op::Export(name(n)?, PathBuf::from(path.trim())))
+        }
+        _ => bail!("usage: :name [range <name> | delete <name> | export <name> <file>]"),
+    }
+}
+
+/// Letters, digits and `_`, starting with a letter or `_`, and not
+/// readable as a cell reference.
+pub fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && a1::parse_cell(s).is_err()
+}
+
+pub fn names_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".names");
+    PathBuf::from(name)
+}
+
+/// `B2:D13`, or just `E14` for one cell.
+pub fn range_text(&(from, to): &Range) -> String {
+    if from == to {
+        a1::cell_name(from.0, from.1)
+    } else {
+        format!(
+            "{}:{}",
+            a1::cell_name(from.0, from.1),
+            a1::cell_name(to.0, to.1)
+        )
+    }
+}
+
+impl Names {
+    pub fn load_for(csv_path: &Path) -> Result<Self> {
+        let path = names_path_for(csv_path);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("{}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read {path:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut names = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, range) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `name = A1:C5`", idx + 1))?;
+            let name = name.trim();
+            if !is_name(name) {
+                bail!("line {}: bad name {name:?}", idx + 1);
+            }
+            let range = a1::parse_range(range).with_context(|| format!("line {}", idx + 1))?;
+            names.0.insert(name.to_string(), range);
+        }
+        Ok(names)
+    }
+
+    /// Write the names next to `csv_path`, or remove the file when there
+    /// are none left.
+    pub fn save_for(&self, csv_path: &Path) -> Result<()> {
+        let path = names_path_for(csv_path);
+        if self.0.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("remove {path:?}"))
+                }
+                _ => Ok(()),
+            };
+        }
+        let text: String = self
+            .0
+            .iter()
+            .map(|(name, range)| format!("{name} = {}\n", range_text(range)))
+            .collect();
+        fs::write(&path, text).with_context(|| format!("write {path:?}"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Range> {
+        self.0.get(name).copied()
+    }
+
+    pub fn insert(&mut self, name: String, range: Range) {
+        self.0.insert(name, range);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    /// `name A1:C5` entries, for listing.
+    pub fn describe(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|(name, range)| format!("{name} {}", range_text(range)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ops_and_round_trips_the_sidecar() -> Result<()> {
+        assert_eq!(parse_op("range sales_q1")?, Op::Define("sales_q1".into()));
+        assert_eq!(
+            parse_op("export first out/q 1.csv")?,
+            Op::Export("first".into(), PathBuf::from("out/q 1.csv"))
+        );
+        // Letters then digits would read as a cell.
+        assert!(parse_op("range q1").is_err());
+        assert!(parse_op("range a-b").is_err());
+        let names = Names::parse("# ranges\nsales_q1 = B2:D13\ntotal = E14\n")?;
+        assert_eq!(names.get("sales_q1"), Some(((1, 1), (12, 3))));
+        assert_eq!(names.describe(), ["sales_q1 B2:D13", "total E14"]);
+        assert!(Names::parse("total E14").is_err());
+        assert!(Names::parse("B2 = B2").is_err());
+        Ok(())
+    }
+}
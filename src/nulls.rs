@@ -0,0 +1,60 @@
+/// Cell values that stand for "no value", from the `null_tokens` setting.
+/// They are drawn dimmed, left out of numeric summaries, and can be written
+/// in one chosen form on save (`null_as`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nulls {
+    tokens: Vec<String>,
+}
+
+impl Default for Nulls {
+    fn default() -> Self {
+        Self::parse(r#""", NA, NULL, \N"#)
+    }
+}
+
+/// A token as written in the config: `""` is the empty cell.
+pub fn token(t: &str) -> String {
+    if t == "\"\"" {
+        String::new()
+    } else {
+        t.to_string()
+    }
+}
+
+impl Nulls {
+    /// A comma-separated list; `""` stands for the empty cell.
+    pub fn parse(list: &str) -> Self {
+        let tokens = list
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(token)
+            .collect();
+        Self { tokens }
+    }
+
+    /// Surrounding spaces don't count, so ` NA ` is null too.
+    pub fn is_null(&self, cell: &str) -> bool {
+        let cell = cell.trim();
+        self.tokens.iter().any(|t| t == cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_configured_tokens_and_the_empty_cell() {
+        let nulls = Nulls::default();
+        for cell in ["", "  ", "NA", " NULL", r"\N"] {
+            assert!(nulls.is_null(cell), "{cell:?}");
+        }
+        assert!(!nulls.is_null("na"));
+        assert!(!nulls.is_null("0"));
+
+        let custom = Nulls::parse("n/a, -999");
+        assert!(custom.is_null("-999"));
+        assert!(!custom.is_null(""));
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Result, anyhow, bail};
+
+use crate::findings::Finding;
+use crate::nulls::Nulls;
+use crate::viz::{Decimal, format_number};
+
+/// How far from the rest of the column a number has to be to count as an
+/// outlier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// More than this many standard deviations from the mean.
+    Sigma(f64),
+    /// Beyond this many interquartile ranges outside the middle half.
+    Iqr(f64),
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::Sigma(3.0)
+    }
+}
+
+impl Rule {
+    pub fn label(self) -> String {
+        match self {
+            Rule::Sigma(n) => format!("{} sd", format_number(n)),
+            Rule::Iqr(k) => format!("{}×IQR", format_number(k)),
+        }
+    }
+}
+
+/// `:outliers`, `:outliers sd 2.5` or `:outliers iqr [k]` (k defaults to 1.5).
+pub fn parse(s: &str) -> Result<Rule> {
+    let (kind, arg) = s
+        .split_once(char::is_whitespace)
+        .map_or((s, ""), |(k, a)| (k, a.trim()));
+    let number = |v: &str| {
+        v.parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n > 0.0)
+            .ok_or_else(|| anyhow!("bad multiple {v:?}; use a positive number"))
+    };
+    match (kind, arg) {
+        ("", "") => Ok(Rule::default()),
+        ("sd", n) if !n.is_empty() => Ok(Rule::Sigma(number(n)?)),
+        ("iqr", "") => Ok(Rule::Iqr(1.5)),
+        ("iqr", k) => Ok(Rule::Iqr(number(k)?)),
+        _ => Err(anyhow!("usage: :outliers [sd <n> | iqr [k]]")),
+    }
+}
+
+/// Value at fraction `q` of sorted `v`, interpolating between neighbours.
+fn quantile(v: &[f64], q: f64) -> f64 {
+    let pos = q * (v.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    v[lo] + (v[hi] - v[lo]) * (pos - lo as f64)
+}
+
+/// Numeric cells of `col`, from row `from` down, that `rule` calls outliers.
+/// Text, blank and null cells are ignored; a column needs a few numbers
+/// before anything can stand out.
+pub fn find(
+    data: &[Vec<String>],
+    col: usize,
+    from: usize,
+    rule: Rule,
+    nulls: &Nulls,
+    decimal: Decimal,
+) -> Result<Vec<Finding>> {
+    let cells: Vec<(usize, f64)> = data
+        .iter()
+        .enumerate()
+        .skip(from)
+        .filter_map(|(r, row)| {
+            let s = row.get(col)?;
+            decimal
+                .parse(s)
+                .filter(|_| !nulls.is_null(s))
+                .map(|v| (r, v))
+        })
+        .collect();
+    if cells.len() < 3 {
+        bail!("column {} needs at least 3 numbers", col + 1);
+    }
+    let values: Vec<f64> = cells.iter().map(|&(_, v)| v).collect();
+    let (lo, hi, why): (f64, f64, Box<dyn Fn(f64) -> String>) = match rule {
+        Rule::Sigma(n) => {
+            let len = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / len;
+            let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (len - 1.0);
+            let sd = var.sqrt();
+            let why = move |v: f64| {
+                format!(
+                    "{:.1} sd from the mean {}",
+                    (v - mean).abs() / sd,
+                    format_number(mean)
+                )
+            };
+            (mean - n * sd, mean + n * sd, Box::new(why))
+        }
+        Rule::Iqr(k) => {
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            let (q1, q3) = (quantile(&sorted, 0.25), quantile(&sorted, 0.75));
+            let (lo, hi) = (q1 - k * (q3 - q1), q3 + k * (q3 - q1));
+            let why = move |_| {
+                format!(
+                    "outside [{}, {}] ({}×IQR)",
+                    format_number(lo),
+                    format_number(hi),
+                    format_number(k)
+                )
+            };
+            (lo, hi, Box::new(why))
+        }
+    };
+    Ok(cells
+        .into_iter()
+        .filter(|&(_, v)| v < lo || v > hi)
+        .map(|(row, v)| Finding {
+            row,
+            col,
+            rule: format!("outlier ({})", rule.label()),
+            text: format!("{}: {}", format_number(v), why(v)),
+            warning: true,
+            whole_row: false,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(cells: &[&str]) -> Vec<Vec<String>> {
+        cells.iter().map(|s| vec![s.to_string()]).collect()
+    }
+
+    #[test]
+    fn flags_values_far_from_the_rest() -> Result<()> {
+        let nulls = Nulls::default();
+        let data = column(&[
+            "temp", "20", "21", "19", "20", "22", "NA", "21", "20", "19", "250",
+        ]);
+        let sd = find(&data, 0, 1, Rule::Sigma(2.0), &nulls, Decimal::Dot)?;
+        assert_eq!(sd.iter().map(|f| f.row).collect::<Vec<_>>(), [10]);
+        assert_eq!(sd[0].text, "250: 2.7 sd from the mean 45.7778");
+        let iqr = find(&data, 0, 1, Rule::Iqr(1.5), &nulls, Decimal::Dot)?;
+        assert_eq!(iqr.len(), 1);
+        assert_eq!(iqr[0].text, "250: outside [18.5, 22.5] (1.5×IQR)");
+        assert!(
+            find(
+                &column(&["1", "x"]),
+                0,
+                0,
+                Rule::default(),
+                &nulls,
+                Decimal::Dot
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_rules() -> Result<()> {
+        assert_eq!(parse("")?, Rule::Sigma(3.0));
+        assert_eq!(parse("sd 2.5")?, Rule::Sigma(2.5));
+        assert_eq!(parse("iqr")?, Rule::Iqr(1.5));
+        assert!(parse("iqr -1").is_err());
+        assert!(parse("mad").is_err());
+        Ok(())
+    }
+}
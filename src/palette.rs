@@ -0,0 +1,243 @@
+use anyhow::{Result, anyhow};
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+/// How many colors the terminal can show, best first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Depth {
+    /// No color at all (`NO_COLOR`, `TERM=dumb`): highlights become reverse
+    /// video instead.
+    Mono,
+    /// The 16 ANSI colors.
+    Ansi,
+    /// xterm's 256-color palette.
+    Indexed,
+    #[default]
+    TrueColor,
+}
+
+impl Depth {
+    /// `colors = auto | truecolor | 256 | 16 | off`; `None` for auto.
+    pub fn parse(s: &str) -> Result<Option<Self>> {
+        match s {
+            "auto" => Ok(None),
+            "truecolor" | "24bit" => Ok(Some(Depth::TrueColor)),
+            "256" => Ok(Some(Depth::Indexed)),
+            "16" => Ok(Some(Depth::Ansi)),
+            "off" | "none" => Ok(Some(Depth::Mono)),
+            _ => Err(anyhow!("colors must be auto, truecolor, 256, 16 or off")),
+        }
+    }
+}
+
+/// What the terminal can draw; the screen is toned down to fit after each
+/// frame is laid out, so the drawing code can use any color and border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caps {
+    pub depth: Depth,
+    /// Box-drawing characters; without them borders are drawn `+-|`.
+    pub unicode: bool,
+}
+
+impl Default for Caps {
+    fn default() -> Self {
+        Self {
+            depth: Depth::TrueColor,
+            unicode: true,
+        }
+    }
+}
+
+impl Caps {
+    /// Guess from `NO_COLOR`, `COLORTERM`, `TERM` and the locale, with
+    /// `var` looking up an environment variable.
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let depth = if var("NO_COLOR").is_some_and(|v| !v.is_empty()) || term == "dumb" {
+            Depth::Mono
+        } else if var("COLORTERM").is_some_and(|v| v == "truecolor" || v == "24bit") {
+            Depth::TrueColor
+        } else if term.contains("256color") {
+            Depth::Indexed
+        } else {
+            Depth::Ansi
+        };
+        // The first of these that is set decides, as for the C library.
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .find_map(|k| var(k).filter(|v| !v.is_empty()))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Self {
+            depth,
+            unicode: locale.contains("utf-8") || locale.contains("utf8"),
+        }
+    }
+
+    /// Rewrite the drawn screen for this terminal.
+    pub fn adapt(&self, buf: &mut Buffer) {
+        if *self == Caps::default() {
+            return;
+        }
+        for cell in buf.content.iter_mut() {
+            if !self.unicode
+                && let Some(ascii) = ascii_border(cell.symbol())
+            {
+                cell.set_symbol(ascii);
+            }
+            if self.depth == Depth::Mono {
+                // Highlights were backgrounds; keep them visible.
+                if !matches!(cell.bg, Color::Reset) {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            } else {
+                cell.fg = reduce(cell.fg, self.depth);
+                cell.bg = reduce(cell.bg, self.depth);
+            }
+        }
+    }
+}
+
+fn ascii_border(symbol: &str) -> Option<&'static str> {
+    Some(match symbol {
+        "─" | "━" | "═" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "├" | "┤" | "┬" | "┴" | "┼" | "╭" | "╮" | "╰" | "╯" => {
+            "+"
+        }
+        _ => return None,
+    })
+}
+
+/// The 16 ANSI colors with their usual xterm values.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The nearest color `depth` can show.
+pub fn reduce(color: Color, depth: Depth) -> Color {
+    match (color, depth) {
+        (Color::Rgb(r, g, b), Depth::Indexed) => Color::Indexed(cube_index(r, g, b)),
+        (Color::Rgb(r, g, b), Depth::Ansi) => nearest_ansi((r, g, b)),
+        (Color::Indexed(i), Depth::Ansi) => nearest_ansi(indexed_rgb(i)),
+        _ => color,
+    }
+}
+
+/// The levels of each channel in the 256-color palette's 6×6×6 cube.
+const STEPS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The closest entry of the cube or the gray ramp after it.
+fn cube_index(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| (0..6).min_by_key(|&i| STEPS[i].abs_diff(v)).unwrap_or(0) as u8;
+    let (lr, lg, lb) = (level(r), level(g), level(b));
+    let cube = (STEPS[lr as usize], STEPS[lg as usize], STEPS[lb as usize]);
+    let avg = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3) as u8;
+    let gray = (avg.saturating_sub(8) / 10).min(23);
+    let gray_value = 8 + 10 * gray;
+    let dist = |c: (u8, u8, u8)| distance((r, g, b), c);
+    if dist((gray_value, gray_value, gray_value)) < dist(cube) {
+        232 + gray
+    } else {
+        16 + 36 * lr + 6 * lg + lb
+    }
+}
+
+/// What 256-color palette entry `i` looks like.
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI[usize::from(i)].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                STEPS[usize::from(i / 36)],
+                STEPS[usize::from(i / 6 % 6)],
+                STEPS[usize::from(i % 6)],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+    }
+}
+
+fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI.iter()
+        .min_by_key(|(_, c)| distance(rgb, *c))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::widgets::{Block, Borders, Widget};
+
+    #[test]
+    fn detects_capabilities_and_tones_the_screen_down() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |k: &str| {
+                vars.iter()
+                    .find(|(n, _)| *n == k)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let caps = Caps::detect(env(&[("COLORTERM", "truecolor"), ("LANG", "ja_JP.UTF-8")]));
+        assert_eq!(caps, Caps::default());
+        let caps = Caps::detect(env(&[("TERM", "xterm-256color"), ("LANG", "C")]));
+        assert_eq!((caps.depth, caps.unicode), (Depth::Indexed, false));
+        assert_eq!(Caps::detect(env(&[("TERM", "dumb")])).depth, Depth::Mono);
+        assert_eq!(Caps::detect(env(&[("TERM", "xterm")])).depth, Depth::Ansi);
+
+        assert_eq!(
+            reduce(Color::Rgb(255, 0, 0), Depth::Indexed),
+            Color::Indexed(196)
+        );
+        assert_eq!(
+            reduce(Color::Rgb(128, 128, 128), Depth::Indexed),
+            Color::Indexed(244)
+        );
+        assert_eq!(reduce(Color::Rgb(255, 255, 240), Depth::Ansi), Color::White);
+        assert_eq!(reduce(Color::Indexed(196), Depth::Ansi), Color::LightRed);
+        assert_eq!(reduce(Color::Yellow, Depth::Ansi), Color::Yellow);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 3));
+        Block::default()
+            .borders(Borders::ALL)
+            .render(buf.area, &mut buf);
+        buf[(1, 1)].set_bg(Color::Yellow);
+        Caps {
+            depth: Depth::Mono,
+            unicode: false,
+        }
+        .adapt(&mut buf);
+        assert_eq!(buf, {
+            let mut want = Buffer::with_lines(["+--+", "|  |", "+--+"]);
+            want[(1, 1)].modifier = Modifier::REVERSED;
+            want
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// File extensions the picker lists.
+const EXTENSIONS: &[&str] = &["csv", "tsv"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Start an empty grid instead of opening a file.
+    NewFile,
+    Parent,
+    Dir,
+    File,
+    /// A previously edited file; `name` is the full path.
+    Recent,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub kind: EntryKind,
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// What the user chose in the picker.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Pick {
+    File(PathBuf),
+    NewFile,
+}
+
+/// Entries for `dir`: a "new file" item, `..`, subdirectories, then
+/// CSV/TSV files, each group sorted by name. Hidden entries are skipped.
+pub fn list_entries(dir: &Path) -> Result<Vec<Entry>> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("read {dir:?}"))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        let item = Entry {
+            kind: EntryKind::File,
+            name,
+            path: entry.path(),
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        };
+        if meta.is_dir() {
+            dirs.push(Entry {
+                kind: EntryKind::Dir,
+                ..item
+            });
+        } else if has_listed_extension(&item.path) {
+            files.push(item);
+        }
+    }
+    dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = vec![Entry {
+        kind: EntryKind::NewFile,
+        name: "[new empty file]".to_string(),
+        path: PathBuf::new(),
+        size: 0,
+        modified: None,
+    }];
+    if let Some(parent) = dir.parent() {
+        out.push(Entry {
+            kind: EntryKind::Parent,
+            name: "..".to_string(),
+            path: parent.to_path_buf(),
+            size: 0,
+            modified: None,
+        });
+    }
+    out.extend(dirs);
+    out.extend(files);
+    Ok(out)
+}
+
+fn has_listed_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+fn recent_entry(path: &Path) -> Entry {
+    let meta = fs::metadata(path).ok();
+    Entry {
+        kind: EntryKind::Recent,
+        name: path.display().to_string(),
+        path: path.to_path_buf(),
+        size: meta.as_ref().map_or(0, |m| m.len()),
+        modified: meta.and_then(|m| m.modified().ok()),
+    }
+}
+
+/// Navigation state of the picker screen.
+pub struct Picker {
+    pub title: String,
+    pub dir: PathBuf,
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+}
+
+impl Picker {
+    pub fn open(dir: &Path) -> Result<Self> {
+        Self::open_with_recent(dir, &[])
+    }
+
+    /// Startup screen: recently edited files (that still exist) listed right
+    /// after the "new file" item, followed by the contents of `dir`.
+    pub fn open_with_recent(dir: &Path, recent: &[PathBuf]) -> Result<Self> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut entries = list_entries(&dir)?;
+        let recent = recent
+            .iter()
+            .filter(|p| p.is_file())
+            .map(|p| recent_entry(p));
+        entries.splice(1..1, recent);
+        // Preselect the most recent file, else the first file in `dir`.
+        let selected = entries
+            .iter()
+            .position(|e| e.kind == EntryKind::Recent)
+            .or_else(|| entries.iter().position(|e| e.kind == EntryKind::File))
+            .unwrap_or(0);
+        Ok(Self {
+            title: format!("Open CSV: {}", dir.display()),
+            dir,
+            entries,
+            selected,
+        })
+    }
+
+    /// Just the recent-files list, for the `:recent` overlay.
+    pub fn recent(paths: &[PathBuf]) -> Self {
+        Self {
+            title: "Recent files".to_string(),
+            dir: PathBuf::new(),
+            entries: paths.iter().map(|p| recent_entry(p)).collect(),
+            selected: 0,
+        }
+    }
+
+    /// `Ok(Some(pick))` once a choice is made; `Ok(None)` while browsing.
+    /// `Err` is only returned for unreadable directories.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Result<Option<Pick>> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                if let Some(parent) = self.dir.parent().map(Path::to_path_buf)
+                    && !parent.as_os_str().is_empty()
+                {
+                    *self = Self::open(&parent)?;
+                }
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                let Some(entry) = self.entries.get(self.selected) else {
+                    return Ok(None);
+                };
+                match entry.kind {
+                    EntryKind::NewFile => return Ok(Some(Pick::NewFile)),
+                    EntryKind::File | EntryKind::Recent => {
+                        return Ok(Some(Pick::File(entry.path.clone())));
+                    }
+                    EntryKind::Parent | EntryKind::Dir => {
+                        let path = entry.path.clone();
+                        *self = Self::open(&path)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_time(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use std::env;
+    use std::time::Duration;
+
+    #[test]
+    fn lists_only_csv_like_files_after_dirs() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_picker_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub"))?;
+        fs::write(dir.join("b.csv"), "x")?;
+        fs::write(dir.join("a.TSV"), "x")?;
+        fs::write(dir.join("notes.txt"), "x")?;
+
+        let names: Vec<_> = list_entries(&dir)?.into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["[new empty file]", "..", "sub", "a.TSV", "b.csv"]);
+
+        let mut picker = Picker::open(&dir)?;
+        let pick = picker.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))?;
+        assert!(matches!(pick, Some(Pick::File(p)) if p.ends_with("a.TSV")));
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn formats_sizes_and_times() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        let t = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(format_time(t), "2023-11-14 22:13");
+    }
+
+    #[test]
+    fn startup_screen_lists_existing_recent_files_first() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_picker_recent_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let kept = dir.join("kept.csv");
+        fs::write(&kept, "x")?;
+        let gone = dir.join("gone.csv");
+
+        let picker = Picker::open_with_recent(&dir, &[gone, kept.clone()])?;
+        assert_eq!(picker.entries[1].kind, EntryKind::Recent);
+        assert_eq!(picker.entries[1].path, kept);
+        assert_eq!(picker.selected, 1);
+        assert!(picker.entries.iter().all(|e| !e.name.ends_with("gone.csv")));
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::columns::Layout;
+use crate::names;
+use crate::sort::{self, Collation, SortKey};
+use crate::tags::Tags;
+use crate::view::Rule;
+
+/// How a file was being looked at, under a name: which columns are hidden,
+/// pinned and right-to-left, and the view's sort and filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preset {
+    pub columns: Layout,
+    pub sort: Vec<SortKey>,
+    pub filter: Option<Rule>,
+}
+
+/// Named presets, kept in `<file>.views` next to the CSV, one per line:
+///
+/// ```text
+/// qa = hide 4 5; pin 1; sort 3 desc, 1; filter 2 ^FAIL
+/// open = is 3 OPEN
+/// ```
+///
+/// Columns are numbered from 1 as in `:sort`. The filter comes last, as
+/// `filter` with a column and query, `is` or `isnot` with a column and
+/// value, or `where` with an expression, and runs to the end of the line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Presets(BTreeMap<String, Preset>);
+
+/// `:view <name>`, `:view save <name>`, `:view delete <name>`, or `:view`
+/// to list them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    List,
+    Apply(String),
+    Save(String),
+    Delete(String),
+}
+
+/// The argument of `:view`, or of `command` taking the same form.
+pub fn parse_op(command: &str, s: &str) -> Result<Op> {
+    let name = |n: &str| {
+        if names::is_name(n) {
+            Ok(n.to_string())
+        } else {
+            Err(anyhow!("bad name {n:?}; use letters, digits and _"))
+        }
+    };
+    let words: Vec<&str> = s.split_whitespace().collect();
+    match words[..] {
+        [] => Ok(Op::List),
+        ["save", n] => Ok(Op::Save(name(n)?)),
+        ["delete", n] => Ok(Op::Delete(name(n)?)),
+        [n] if n != "save" && n != "delete" => Ok(Op::Apply(name(n)?)),
+        _ => bail!("usage: :{command} [<name> | save <name> | delete <name>]"),
+    }
+}
+
+pub fn presets_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".views");
+    PathBuf::from(name)
+}
+
+fn numbers(tags: &Tags) -> String {
+    let cols: Vec<String> = tags.rows().map(|c| (c + 1).to_string()).collect();
+    cols.join(" ")
+}
+
+fn parse_numbers(s: &str) -> Result<Tags> {
+    let mut tags = Tags::default();
+    for word in s.split_whitespace() {
+        tags.toggle(crate::command::parse_column(word)?);
+    }
+    Ok(tags)
+}
+
+impl Preset {
+    /// The part after `name = `.
+    pub fn to_line(&self) -> String {
+        let mut parts = Vec::new();
+        for (word, tags) in [
+            ("hide", &self.columns.hidden),
+            ("pin", &self.columns.pinned),
+            ("rtl", &self.columns.rtl),
+        ] {
+            if !tags.is_empty() {
+                parts.push(format!("{word} {}", numbers(tags)));
+            }
+        }
+        if !self.sort.is_empty() {
+            let keys: Vec<String> = self
+                .sort
+                .iter()
+                .map(|k| {
+                    let mut key = (k.col + 1).to_string();
+                    if k.desc {
+                        key.push_str(" desc");
+                    }
+                    match k.collation {
+                        Collation::Plain => {}
+                        Collation::Natural => key.push_str(" nat"),
+                        Collation::Locale => key.push_str(" locale"),
+                    }
+                    key
+                })
+                .collect();
+            parts.push(format!("sort {}", keys.join(", ")));
+        }
+        if let Some(rule) = &self.filter {
+            parts.push(rule.to_text());
+        }
+        parts.join("; ")
+    }
+
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut preset = Self::default();
+        let mut rest = line.trim();
+        while !rest.is_empty() {
+            let (word, after) = rest.split_once(' ').unwrap_or((rest, ""));
+            if let "filter" | "is" | "isnot" | "where" = word {
+                preset.filter = Some(Rule::parse(rest)?);
+                break;
+            }
+            let (part, next) = after.split_once(';').unwrap_or((after, ""));
+            match word {
+                "hide" => preset.columns.hidden = parse_numbers(part)?,
+                "pin" => preset.columns.pinned = parse_numbers(part)?,
+                "rtl" => preset.columns.rtl = parse_numbers(part)?,
+                "sort" => preset.sort = sort::parse_keys(part)?,
+                _ => bail!("unknown view setting {word:?}"),
+            }
+            rest = next.trim_start();
+        }
+        Ok(preset)
+    }
+}
+
+impl Presets {
+    pub fn load_for(csv_path: &Path) -> Result<Self> {
+        let path = presets_path_for(csv_path);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("{}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read {path:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut presets = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, preset) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `name = hide 2; sort 1`", idx + 1))?;
+            let name = name.trim();
+            if !names::is_name(name) {
+                bail!("line {}: bad view name {name:?}", idx + 1);
+            }
+            let preset = Preset::parse(preset).with_context(|| format!("line {}", idx + 1))?;
+            presets.0.insert(name.to_string(), preset);
+        }
+        Ok(presets)
+    }
+
+    /// Write the presets next to `csv_path`, or remove the file when there
+    /// are none left.
+    pub fn save_for(&self, csv_path: &Path) -> Result<()> {
+        let path = presets_path_for(csv_path);
+        if self.0.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("remove {path:?}"))
+                }
+                _ => Ok(()),
+            };
+        }
+        let text: String = self
+            .0
+            .iter()
+            .map(|(name, preset)| format!("{name} = {}\n", preset.to_line()))
+            .collect();
+        fs::write(&path, text).with_context(|| format!("write {path:?}"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, preset: Preset) {
+        self.0.insert(name, preset);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ops_and_round_trips_presets() -> Result<()> {
+        assert_eq!(parse_op("view", "")?, Op::List);
+        assert_eq!(parse_op("view", "qa")?, Op::Apply("qa".into()));
+        assert_eq!(
+            parse_op("view", "save financial")?,
+            Op::Save("financial".into())
+        );
+        assert_eq!(parse_op("view", "delete qa")?, Op::Delete("qa".into()));
+        assert!(parse_op("view", "save").is_err());
+        assert!(parse_op("view", "B3").is_err());
+
+        let text = "financial = hide 4 5; pin 1; sort 3 desc nat, 1\nopen = isnot 3 DONE\nqa = filter 2 ^FAIL; or not\nslow = where num(\"ms\") > 500\n";
+        let presets = Presets::parse(text)?;
+        let qa = presets.get("qa").cloned().unwrap_or_default();
+        assert_eq!(qa.filter, Some(Rule::Matches(1, "^FAIL; or not".into())));
+        let fin = presets.get("financial").cloned().unwrap_or_default();
+        assert!(fin.columns.hidden.contains(4) && fin.columns.pinned.contains(0));
+        assert_eq!(fin.sort.len(), 2);
+        let open = presets.get("open").cloned().unwrap_or_default();
+        assert_eq!(open.filter, Some(Rule::IsNot(2, "DONE".into())));
+        let again: String = ["financial", "open", "qa", "slow"]
+            .iter()
+            .map(|n| {
+                format!(
+                    "{n} = {}\n",
+                    presets.get(n).map(Preset::to_line).unwrap_or_default()
+                )
+            })
+            .collect();
+        assert_eq!(again, text);
+        assert!(Presets::parse("x = widths 3").is_err());
+        Ok(())
+    }
+}
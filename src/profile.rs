@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use csv::WriterBuilder;
+
+use crate::nulls::Nulls;
+use crate::report::json_string;
+use crate::viz::{Decimal, format_number};
+
+/// What one column holds, for documenting a dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    /// The header name, or blank without a header row.
+    pub name: String,
+    /// `number`, `text`, `mixed` (some of each) or `empty`.
+    pub kind: &'static str,
+    /// Cells with a value.
+    pub count: usize,
+    /// Blank or null cells, counting those missing from short rows.
+    pub nulls: usize,
+    pub distinct: usize,
+    /// Numeric for a number column, otherwise by text; blank when empty.
+    pub min: String,
+    pub max: String,
+}
+
+impl ColumnProfile {
+    /// Share of the rows with no value, from 0 to 1.
+    pub fn null_rate(&self) -> f64 {
+        let rows = self.count + self.nulls;
+        if rows == 0 {
+            0.0
+        } else {
+            self.nulls as f64 / rows as f64
+        }
+    }
+}
+
+/// Profile every column, skipping the header row when there is one.
+pub fn profile(
+    data: &[Vec<String>],
+    header: bool,
+    nulls: &Nulls,
+    decimal: Decimal,
+) -> Vec<ColumnProfile> {
+    let cols = data.iter().map(Vec::len).max().unwrap_or(0);
+    let from = usize::from(header);
+    (0..cols)
+        .map(|c| {
+            let name = match data.first() {
+                Some(row) if header => row.get(c).cloned().unwrap_or_default(),
+                _ => String::new(),
+            };
+            let cells: Vec<&str> = data
+                .iter()
+                .skip(from)
+                .map(|row| row.get(c).map_or("", |s| s.trim()))
+                .collect();
+            let values: Vec<&str> = cells
+                .iter()
+                .copied()
+                .filter(|s| !s.is_empty() && !nulls.is_null(s))
+                .collect();
+            let numbers: Vec<f64> = values.iter().filter_map(|s| decimal.parse(s)).collect();
+            let kind = match (values.len(), numbers.len()) {
+                (0, _) => "empty",
+                (v, n) if v == n => "number",
+                (_, 0) => "text",
+                _ => "mixed",
+            };
+            let (min, max) = if kind == "number" {
+                let lo = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+                let hi = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                (format_number(lo), format_number(hi))
+            } else {
+                let text = |s: Option<&&str>| s.map_or(String::new(), |s| s.to_string());
+                (text(values.iter().min()), text(values.iter().max()))
+            };
+            ColumnProfile {
+                name,
+                kind,
+                count: values.len(),
+                nulls: cells.len() - values.len(),
+                distinct: values.iter().collect::<HashSet<_>>().len(),
+                min,
+                max,
+            }
+        })
+        .collect()
+}
+
+const FIELDS: [&str; 9] = [
+    "col",
+    "column",
+    "type",
+    "count",
+    "nulls",
+    "null_rate",
+    "distinct",
+    "min",
+    "max",
+];
+
+/// The profile as CSV, one column per line; columns are 1-based.
+pub fn to_csv(profiles: &[ColumnProfile]) -> Result<String> {
+    let mut wtr = WriterBuilder::new().from_writer(Vec::new());
+    wtr.write_record(FIELDS)?;
+    for (i, p) in profiles.iter().enumerate() {
+        wtr.write_record([
+            &(i + 1).to_string(),
+            &p.name,
+            p.kind,
+            &p.count.to_string(),
+            &p.nulls.to_string(),
+            &format_number(p.null_rate()),
+            &p.distinct.to_string(),
+            &p.min,
+            &p.max,
+        ])?;
+    }
+    let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The profile as a JSON array of objects with the CSV's fields.
+pub fn to_json(profiles: &[ColumnProfile]) -> String {
+    let objects: Vec<String> = profiles
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            format!(
+                "  {{\"col\": {}, \"column\": {}, \"type\": \"{}\", \"count\": {}, \"nulls\": {}, \"null_rate\": {}, \"distinct\": {}, \"min\": {}, \"max\": {}}}",
+                i + 1,
+                json_string(&p.name),
+                p.kind,
+                p.count,
+                p.nulls,
+                format_number(p.null_rate()),
+                p.distinct,
+                json_string(&p.min),
+                json_string(&p.max)
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", objects.join(",\n"))
+    }
+}
+
+/// Write the profile to `path`: JSON for a `.json` file, CSV otherwise.
+pub fn write(path: &Path, profiles: &[ColumnProfile]) -> Result<()> {
+    let json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let text = if json {
+        to_json(profiles)
+    } else {
+        to_csv(profiles)?
+    };
+    fs::write(path, text).with_context(|| format!("write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_types_nulls_and_ranges() -> Result<()> {
+        let data: Vec<Vec<String>> = [
+            &["id", "name", "score", "note"][..],
+            &["1", "bob", "10", ""],
+            &["2", "amy", "x", ""],
+            &["3", "bob", "NA"],
+        ]
+        .iter()
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect();
+        let p = profile(&data, true, &Nulls::default(), Decimal::Dot);
+        assert_eq!(
+            p.iter().map(|c| c.kind).collect::<Vec<_>>(),
+            ["number", "text", "mixed", "empty"]
+        );
+        assert_eq!((p[0].min.as_str(), p[0].max.as_str()), ("1", "3"));
+        assert_eq!((p[1].distinct, p[1].min.as_str()), (2, "amy"));
+        assert_eq!((p[2].count, p[2].nulls), (2, 1));
+        assert_eq!(p[3].nulls, 3);
+        assert_eq!(
+            to_csv(&p[2..3])?,
+            "col,column,type,count,nulls,null_rate,distinct,min,max\n1,score,mixed,2,1,0.3333,2,10,x\n"
+        );
+        assert_eq!(
+            to_json(&p[..1]),
+            "[\n  {\"col\": 1, \"column\": \"id\", \"type\": \"number\", \"count\": 3, \"nulls\": 0, \"null_rate\": 0, \"distinct\": 3, \"min\": \"1\", \"max\": \"3\"}\n]\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::history::{self, PromptHistory};
+use crate::search::Area;
+use crate::typing;
+
+/// Upper bound on cells created from a dimensions prompt, so a typo like
+/// `10000x10000` cannot exhaust memory.
+const MAX_NEW_CELLS: usize = 1_000_000;
+
+/// What a single-line prompt in the info area is asking for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    /// Size of a new grid, `ROWSxCOLS`; empty means 1x1.
+    NewDimensions,
+    /// Path for a buffer that has never been saved.
+    SavePath { then_quit: bool },
+    /// A `:` command line.
+    Command,
+    /// An incremental `/` search; Esc returns the cursor to `origin`.
+    Search { origin: (usize, usize), area: Area },
+    /// A new header name for column `col`.
+    Rename { col: usize },
+}
+
+#[derive(Debug)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub buf: String,
+    /// Up/Down stepping through the history.
+    pub recall: Option<Recall>,
+    /// Ctrl+R searching back through the history.
+    pub reverse: Option<Reverse>,
+}
+
+/// The history entry shown, and what had been typed before Up.
+#[derive(Debug)]
+pub struct Recall {
+    pub at: usize,
+    pub typed: String,
+}
+
+#[derive(Debug)]
+pub struct Reverse {
+    pub query: String,
+    /// How many newer matches Ctrl+R has gone past.
+    pub skip: usize,
+    pub found: bool,
+    /// What had been typed before Ctrl+R, for Esc to bring back.
+    pub typed: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            buf: String::new(),
+            recall: None,
+            reverse: None,
+        }
+    }
+
+    /// Which history the prompt recalls from, if it keeps one.
+    pub fn history_kind(&self) -> Option<history::Kind> {
+        match self.kind {
+            PromptKind::Command => Some(history::Kind::Command),
+            PromptKind::Search { .. } => Some(history::Kind::Search),
+            _ => None,
+        }
+    }
+
+    /// Up/Down and Ctrl+R: step or search back through `history`, putting
+    /// the entry found in the buffer. `false` for keys left to the prompt
+    /// (Enter runs whatever a search found).
+    pub fn recall_key(&mut self, history: &PromptHistory, key: KeyEvent) -> bool {
+        let Some(kind) = self.history_kind() else {
+            return false;
+        };
+        let ctrl_r = key.code == KeyCode::Char('r') && key.modifiers == KeyModifiers::CONTROL;
+        if let Some(rev) = self.reverse.as_mut() {
+            match key.code {
+                _ if ctrl_r => rev.skip += 1,
+                KeyCode::Backspace => {
+                    typing::pop_grapheme(&mut rev.query);
+                    rev.skip = 0;
+                }
+                KeyCode::Char(c) if typing::is_typed(&key) => {
+                    rev.query.push(c);
+                    rev.skip = 0;
+                }
+                KeyCode::Esc => {
+                    self.buf = std::mem::take(&mut rev.typed);
+                    self.reverse = None;
+                    return true;
+                }
+                KeyCode::Enter => {
+                    self.reverse = None;
+                    return false;
+                }
+                // Anything else keeps the match to edit.
+                _ => {
+                    self.reverse = None;
+                    return true;
+                }
+            }
+            match history.find(kind, &rev.query, rev.skip) {
+                Some(line) => {
+                    self.buf = line.to_string();
+                    rev.found = true;
+                }
+                // Keep showing the last match, as a shell does.
+                None => {
+                    rev.found = false;
+                    if ctrl_r {
+                        rev.skip -= 1;
+                    }
+                }
+            }
+            return true;
+        }
+        match key.code {
+            _ if ctrl_r => {
+                let found = history.find(kind, "", 0).map(str::to_string);
+                self.reverse = Some(Reverse {
+                    query: String::new(),
+                    skip: 0,
+                    found: found.is_some(),
+                    typed: self.buf.clone(),
+                });
+                if let Some(line) = found {
+                    self.buf = line;
+                }
+                self.recall = None;
+            }
+            KeyCode::Up => {
+                let (typed, from) = match self.recall.take() {
+                    Some(r) => (r.typed, Some(r.at)),
+                    None => (self.buf.clone(), None),
+                };
+                let at = history.older(kind, &typed, from).or(from);
+                if let Some(at) = at {
+                    self.buf = history.entries(kind)[at].clone();
+                    self.recall = Some(Recall { at, typed });
+                }
+            }
+            KeyCode::Down => {
+                if let Some(r) = self.recall.take() {
+                    match history.newer(kind, &r.typed, r.at) {
+                        Some(at) => {
+                            self.buf = history.entries(kind)[at].clone();
+                            self.recall = Some(Recall { at, ..r });
+                        }
+                        None => self.buf = r.typed,
+                    }
+                }
+            }
+            _ => {
+                self.recall = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn label(&self) -> String {
+        if let Some(rev) = &self.reverse {
+            let failed = if rev.found { "" } else { "failed " };
+            return format!("({failed}reverse-i-search)`{}': ", rev.query);
+        }
+        match self.kind {
+            PromptKind::NewDimensions => "New grid size ROWSxCOLS (Enter for 1x1): ".to_string(),
+            PromptKind::SavePath { .. } => "Save as: ".to_string(),
+            PromptKind::Command => ":".to_string(),
+            PromptKind::Search { area, .. } => format!("{}/", area.tag()),
+            PromptKind::Rename { col } => format!("Rename column {}: ", col + 1),
+        }
+    }
+}
+
+pub fn parse_dimensions(s: &str) -> Result<(usize, usize)> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok((1, 1));
+    }
+    let (r, c) = s
+        .split_once(['x', 'X', '*'])
+        .ok_or_else(|| anyhow!("expected ROWSxCOLS, e.g. 10x4"))?;
+    let rows: usize = r
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("bad row count {r:?}"))?;
+    let cols: usize = c
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("bad column count {c:?}"))?;
+    if rows == 0 || cols == 0 {
+        return Err(anyhow!("dimensions must be at least 1x1"));
+    }
+    if rows.saturating_mul(cols) > MAX_NEW_CELLS {
+        return Err(anyhow!("at most {MAX_NEW_CELLS} cells"));
+    }
+    Ok((rows, cols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dimensions() -> Result<()> {
+        assert_eq!(parse_dimensions("")?, (1, 1));
+        assert_eq!(parse_dimensions(" 10 x 4 ")?, (10, 4));
+        assert!(parse_dimensions("0x3").is_err());
+        assert!(parse_dimensions("3").is_err());
+        assert!(parse_dimensions("5000x5000").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// How many files the list remembers.
+const MAX_RECENT: usize = 20;
+
+/// `<config dir>/recent`: one absolute path per line, most recent first.
+pub fn recent_file() -> Option<PathBuf> {
+    crate::config::config_dir().map(|d| d.join("recent"))
+}
+
+pub fn load(list: &Path) -> Result<Vec<PathBuf>> {
+    match fs::read_to_string(list) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(PathBuf::from)
+            .collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("read {list:?}")),
+    }
+}
+
+/// Move `opened` to the front of the list, dropping duplicates and the oldest
+/// entries beyond the cap.
+pub fn record(list: &Path, opened: &Path) -> Result<()> {
+    let opened = opened
+        .canonicalize()
+        .unwrap_or_else(|_| opened.to_path_buf());
+    let mut paths = load(list)?;
+    paths.retain(|p| *p != opened);
+    paths.insert(0, opened);
+    paths.truncate(MAX_RECENT);
+    if let Some(dir) = list.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {dir:?}"))?;
+    }
+    let mut text = String::new();
+    for p in &paths {
+        text.push_str(&p.to_string_lossy());
+        text.push('\n');
+    }
+    fs::write(list, text).with_context(|| format!("write {list:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn records_most_recent_first_without_duplicates() -> Result<()> {
+        let dir = env::temp_dir().join(format!("tui_csv_recent_{}", std::process::id()));
+        let list = dir.join("recent");
+        let _ = fs::remove_dir_all(&dir);
+        for name in ["/data/a.csv", "/data/b.csv", "/data/a.csv"] {
+            record(&list, Path::new(name))?;
+        }
+        assert_eq!(
+            load(&list)?,
+            vec![PathBuf::from("/data/a.csv"), PathBuf::from("/data/b.csv")]
+        );
+        for i in 0..30 {
+            record(&list, Path::new(&format!("/data/{i}.csv")))?;
+        }
+        assert_eq!(load(&list)?.len(), MAX_RECENT);
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
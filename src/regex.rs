@@ -0,0 +1,724 @@
+//! A small regular expression engine for find and replace.
+//!
+//! Supported syntax: literals, `.`, `[...]` / `[^...]` classes with ranges,
+//! `\d \w \s` (and negations), `\b`, `^`, `$`, groups `(...)` and `(?:...)`,
+//! `|`, the quantifiers `* + ? {n} {n,} {n,m}` (append `?` for lazy), and a
+//! leading `(?i)` for case-insensitive matching. Patterns compile to a
+//! Thompson NFA run as a Pike VM, so matching takes time linear in the cell
+//! and no stack, however long the cell or however the pattern nests.
+
+use anyhow::{Result, anyhow, bail};
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    WordBoundary,
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+/// Capture spans as char indices; index 0 is the whole match.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    groups: usize,
+    ignore_case: bool,
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+    depth: usize,
+    pattern: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, what: &str) -> anyhow::Error {
+        anyhow!(
+            "bad regex {:?}: {what} at offset {}",
+            self.pattern,
+            self.pos
+        )
+    }
+
+    fn alternation(&mut self) -> Result<Node> {
+        let mut branches = vec![self.concat()?];
+        while self.eat('|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            items.push(self.quantified(atom)?);
+        }
+        Ok(Node::Concat(items))
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.braces()? {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary) {
+            return Err(self.error("nothing to repeat"));
+        }
+        let greedy = !self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}`, leaving `pos` on the closing brace. A brace
+    /// that doesn't form a valid bound is a literal, as in most engines.
+    fn braces(&mut self) -> Result<Option<(usize, Option<usize>)>> {
+        let rest: String = self.chars[self.pos + 1..].iter().collect();
+        let Some(end) = rest.find('}') else {
+            return Ok(None);
+        };
+        let body = &rest[..end];
+        let number = |s: &str| s.parse::<usize>().ok();
+        let bounds = match body.split_once(',') {
+            None => number(body).map(|n| (n, Some(n))),
+            Some((lo, "")) => number(lo).map(|n| (n, None)),
+            Some((lo, hi)) => number(lo).zip(number(hi)).map(|(a, b)| (a, Some(b))),
+        };
+        let Some((min, max)) = bounds else {
+            return Ok(None);
+        };
+        if max.is_some_and(|m| m < min) {
+            return Err(self.error("bad repetition bounds"));
+        }
+        self.pos += 1 + body.chars().count();
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(self.error("groups nested too deeply"));
+                }
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        return Err(self.error("unsupported group flag"));
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                self.depth += 1;
+                let inner = self.alternation()?;
+                self.depth -= 1;
+                if !self.eat(')') {
+                    return Err(self.error("missing )"));
+                }
+                Node::Group(Box::new(inner), index)
+            }
+            ')' => return Err(self.error("unmatched )")),
+            '[' => self.class()?,
+            '*' | '+' | '?' => return Err(self.error("nothing to repeat")),
+            '\\' => match self.escape()? {
+                Escape::Char(c) => Node::Char(c),
+                Escape::Class(item) => Node::Class {
+                    items: vec![item],
+                    negated: false,
+                },
+                Escape::WordBoundary => Node::WordBoundary,
+            },
+            c => Node::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Escape> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        self.pos += 1;
+        Ok(match c {
+            'd' => Escape::Class(ClassItem::Digit(true)),
+            'D' => Escape::Class(ClassItem::Digit(false)),
+            'w' => Escape::Class(ClassItem::Word(true)),
+            'W' => Escape::Class(ClassItem::Word(false)),
+            's' => Escape::Class(ClassItem::Space(true)),
+            'S' => Escape::Class(ClassItem::Space(false)),
+            'b' => Escape::WordBoundary,
+            't' => Escape::Char('\t'),
+            'n' => Escape::Char('\n'),
+            'r' => Escape::Char('\r'),
+            c if c.is_ascii_alphanumeric() => return Err(self.error("unknown escape")),
+            c => Escape::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("missing ]"))?;
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Class(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::WordBoundary => '\u{8}',
+                },
+                c => c,
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&n| n != ']') {
+                self.pos += 1;
+                let mut hi = self.peek().ok_or_else(|| self.error("missing ]"))?;
+                self.pos += 1;
+                if hi == '\\' {
+                    match self.escape()? {
+                        Escape::Char(c) => hi = c,
+                        _ => return Err(self.error("bad class range")),
+                    }
+                }
+                if hi < lo {
+                    return Err(self.error("bad class range"));
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+        Ok(Node::Class { items, negated })
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(ClassItem),
+    WordBoundary,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl ClassItem {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => {
+                (lo..=hi).contains(&c)
+                    || ignore_case
+                        && (c.to_lowercase().any(|l| (lo..=hi).contains(&l))
+                            || c.to_uppercase().any(|u| (lo..=hi).contains(&u)))
+            }
+            ClassItem::Digit(yes) => c.is_ascii_digit() == yes,
+            ClassItem::Word(yes) => is_word(c) == yes,
+            ClassItem::Space(yes) => c.is_whitespace() == yes,
+        }
+    }
+}
+
+/// One step of the compiled program. `Split` tries its first target
+/// before its second, which is what makes greedy and lazy differ.
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    WordBoundary,
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+/// Counted repetition is compiled by copying, so `a{100000}` would build a
+/// huge program; past this many steps the pattern is refused instead.
+const MAX_PROGRAM: usize = 20_000;
+
+/// How deeply groups may nest, keeping the parser's recursion bounded.
+const MAX_DEPTH: usize = 100;
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize> {
+        if self.prog.len() >= MAX_PROGRAM {
+            bail!("regex too large");
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        match &mut self.prog[at] {
+            Inst::Jmp(t) => *t = target,
+            Inst::Split(_, b) => *b = target,
+            _ => {}
+        }
+    }
+
+    fn node(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::Char(c) => _ = self.emit(Inst::Char(*c))?,
+            Node::Any => _ = self.emit(Inst::Any)?,
+            Node::Class { items, negated } => {
+                _ = self.emit(Inst::Class(items.clone(), *negated))?;
+            }
+            Node::Start => _ = self.emit(Inst::Start)?,
+            Node::End => _ = self.emit(Inst::End)?,
+            Node::WordBoundary => _ = self.emit(Inst::WordBoundary)?,
+            Node::Group(inner, None) => self.node(inner)?,
+            Node::Group(inner, Some(i)) => {
+                self.emit(Inst::Save(2 * i))?;
+                self.node(inner)?;
+                self.emit(Inst::Save(2 * i + 1))?;
+            }
+            Node::Concat(items) => {
+                for item in items {
+                    self.node(item)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (n, branch) in branches.iter().enumerate() {
+                    let split = (n + 1 < branches.len())
+                        .then(|| {
+                            let at = self.prog.len();
+                            self.emit(Inst::Split(at + 1, 0))
+                        })
+                        .transpose()?;
+                    self.node(branch)?;
+                    if let Some(split) = split {
+                        jumps.push(self.emit(Inst::Jmp(0))?);
+                        let next = self.prog.len();
+                        self.patch(split, next);
+                    }
+                }
+                let end = self.prog.len();
+                for j in jumps {
+                    self.patch(j, end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.node(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.node(node)?;
+                        self.emit(Inst::Jmp(split))?;
+                        let out = self.prog.len();
+                        self.prog[split] = self.split(split + 1, out, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.node(node)?;
+                        }
+                        let out = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = self.split(split + 1, out, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn split(&self, body: usize, out: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, out)
+        } else {
+            Inst::Split(out, body)
+        }
+    }
+}
+
+/// The live threads of one step, in priority order: a sparse set of
+/// program counters, each with its own capture slots.
+struct Threads {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+    slots: usize,
+    caps: Vec<Option<usize>>,
+}
+
+impl Threads {
+    fn new(len: usize, slots: usize) -> Self {
+        Self {
+            dense: Vec::with_capacity(len),
+            sparse: vec![0; len],
+            slots,
+            caps: vec![None; len * slots],
+        }
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        self.dense.get(self.sparse[pc]) == Some(&pc)
+    }
+
+    fn insert(&mut self, pc: usize) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+    }
+
+    fn caps(&self, pc: usize) -> &[Option<usize>] {
+        &self.caps[pc * self.slots..(pc + 1) * self.slots]
+    }
+}
+
+/// Work left while following a thread's empty steps: a program counter to
+/// visit, or a capture slot to put back once a branch has been explored.
+enum Job {
+    Visit(usize),
+    Restore(usize, Option<usize>),
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let (ignore_case, body) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut p = Parser {
+            chars: body.chars().collect(),
+            pos: 0,
+            groups: 0,
+            depth: 0,
+            pattern,
+        };
+        let root = p.alternation()?;
+        if p.pos < p.chars.len() {
+            bail!(p.error("unmatched )"));
+        }
+        let mut c = Compiler { prog: Vec::new() };
+        c.emit(Inst::Save(0))?;
+        c.node(&root)
+            .map_err(|e| anyhow!("bad regex {pattern:?}: {e}"))?;
+        c.emit(Inst::Save(1))?;
+        c.emit(Inst::Match)?;
+        Ok(Self {
+            prog: c.prog,
+            groups: p.groups,
+            ignore_case,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        self.find_at(&chars, 0).is_some()
+    }
+
+    /// The leftmost match starting at or after char index `from`. All
+    /// threads advance together one character at a time (a Pike VM), so
+    /// the work is bounded by the text length times the program size and
+    /// nothing recurses per character.
+    fn find_at(&self, text: &[char], from: usize) -> Option<Captures> {
+        let slots = 2 * (self.groups + 1);
+        let mut clist = Threads::new(self.prog.len(), slots);
+        let mut nlist = Threads::new(self.prog.len(), slots);
+        let mut scratch = vec![None; slots];
+        let mut stack = Vec::new();
+        let mut found: Option<Vec<Option<usize>>> = None;
+        for pos in from..=text.len() {
+            if found.is_none() {
+                scratch.fill(None);
+                self.add(&mut clist, &mut stack, 0, text, pos, &mut scratch);
+            } else if clist.dense.is_empty() {
+                break;
+            }
+            for i in 0..clist.dense.len() {
+                let pc = clist.dense[i];
+                let step = match &self.prog[pc] {
+                    Inst::Match => {
+                        found = Some(clist.caps(pc).to_vec());
+                        // Every thread after this one has lower priority.
+                        break;
+                    }
+                    Inst::Char(c) => text.get(pos).is_some_and(|&t| self.eq(t, *c)),
+                    Inst::Any => text.get(pos).is_some_and(|&t| t != '\n'),
+                    Inst::Class(items, negated) => text.get(pos).is_some_and(|&t| {
+                        items.iter().any(|i| i.matches(t, self.ignore_case)) != *negated
+                    }),
+                    _ => false,
+                };
+                if step {
+                    scratch.copy_from_slice(clist.caps(pc));
+                    self.add(&mut nlist, &mut stack, pc + 1, text, pos + 1, &mut scratch);
+                }
+            }
+            std::mem::swap(&mut clist, &mut nlist);
+            nlist.dense.clear();
+        }
+        let found = found?;
+        Some(
+            (0..=self.groups)
+                .map(|g| found[2 * g].zip(found[2 * g + 1]))
+                .collect(),
+        )
+    }
+
+    /// Add the thread at `pc` to `list`, following jumps, splits, saves
+    /// and assertions until it reaches a step that reads a character.
+    fn add(
+        &self,
+        list: &mut Threads,
+        stack: &mut Vec<Job>,
+        pc: usize,
+        text: &[char],
+        pos: usize,
+        caps: &mut [Option<usize>],
+    ) {
+        stack.push(Job::Visit(pc));
+        while let Some(job) = stack.pop() {
+            let pc = match job {
+                Job::Visit(pc) => pc,
+                Job::Restore(slot, old) => {
+                    caps[slot] = old;
+                    continue;
+                }
+            };
+            if list.contains(pc) {
+                continue;
+            }
+            list.insert(pc);
+            let next = match &self.prog[pc] {
+                Inst::Jmp(target) => Some(*target),
+                Inst::Split(a, b) => {
+                    stack.push(Job::Visit(*b));
+                    Some(*a)
+                }
+                Inst::Save(slot) => {
+                    stack.push(Job::Restore(*slot, caps[*slot]));
+                    caps[*slot] = Some(pos);
+                    Some(pc + 1)
+                }
+                Inst::Start => (pos == 0).then_some(pc + 1),
+                Inst::End => (pos == text.len()).then_some(pc + 1),
+                Inst::WordBoundary => {
+                    let before = pos > 0 && is_word(text[pos - 1]);
+                    let after = text.get(pos).is_some_and(|&c| is_word(c));
+                    (before != after).then_some(pc + 1)
+                }
+                _ => {
+                    let slots = list.slots;
+                    list.caps[pc * slots..(pc + 1) * slots].copy_from_slice(caps);
+                    None
+                }
+            };
+            if let Some(next) = next {
+                stack.push(Job::Visit(next));
+            }
+        }
+    }
+
+    fn eq(&self, a: char, b: char) -> bool {
+        a == b || self.ignore_case && a.to_lowercase().eq(b.to_lowercase())
+    }
+
+    /// Replace the first match (or every match with `all`), expanding `$1`,
+    /// `${1}` and `$0` in `replacement`; `$$` is a literal dollar sign.
+    /// Returns `None` when nothing matched.
+    pub fn replace(&self, text: &str, replacement: &str, all: bool) -> Option<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut last = 0;
+        let mut from = 0;
+        let mut replaced = false;
+        while from <= chars.len() {
+            let Some(caps) = self.find_at(&chars, from) else {
+                break;
+            };
+            let (start, end) = caps[0].unwrap_or_default();
+            out.extend(&chars[last..start]);
+            expand(replacement, &caps, &chars, &mut out);
+            last = end;
+            replaced = true;
+            // Step past empty matches so the loop always advances.
+            from = if end == start { end + 1 } else { end };
+            if !all {
+                break;
+            }
+            if end == start && start < chars.len() {
+                out.push(chars[start]);
+                last = start + 1;
+            }
+        }
+        if !replaced {
+            return None;
+        }
+        if last < chars.len() {
+            out.extend(&chars[last..]);
+        }
+        Some(out)
+    }
+}
+
+fn expand(replacement: &str, caps: &Captures, text: &[char], out: &mut String) {
+    let mut rest = replacement;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let (digits, skip) = if let Some(body) = rest.strip_prefix('{')
+            && let Some(end) = body.find('}')
+        {
+            (&body[..end], end + 2)
+        } else {
+            let n = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            (&rest[..n], n)
+        };
+        match digits.parse::<usize>() {
+            Ok(g) => {
+                if let Some(Some((s, e))) = caps.get(g) {
+                    out.extend(&text[*s..*e]);
+                }
+                rest = &rest[skip..];
+            }
+            Err(_) if rest.starts_with('$') => {
+                out.push('$');
+                rest = &rest[1..];
+            }
+            Err(_) => out.push('$'),
+        }
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(pattern: &str, text: &str, replacement: &str) -> Option<String> {
+        Regex::new(pattern).ok()?.replace(text, replacement, true)
+    }
+
+    #[test]
+    fn matches_common_syntax() -> Result<()> {
+        assert!(Regex::new(r"^\d{3}-\d{4}$")?.is_match("555-1234"));
+        assert!(!Regex::new(r"^\d{3}-\d{4}$")?.is_match("555-12345"));
+        assert!(Regex::new(r"colou?r")?.is_match("color"));
+        assert!(Regex::new(r"(?i)^[a-c]+$")?.is_match("AbC"));
+        assert!(Regex::new(r"\bcat\b")?.is_match("a cat sat"));
+        assert!(!Regex::new(r"\bcat\b")?.is_match("concatenate"));
+        assert!(Regex::new(r"^(?:ab|cd)+$")?.is_match("abcdab"));
+        assert!(Regex::new(r"[^,]+,[\w.]+")?.is_match("x,y.z"));
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new(r"\q").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn long_cells_and_nested_repeats_stay_linear() -> Result<()> {
+        let long = "a".repeat(50_000);
+        assert!(!Regex::new("a*b")?.is_match(&long));
+        assert!(Regex::new("a*$")?.is_match(&long));
+        assert!(!Regex::new("(a|a)*b")?.is_match(&"a".repeat(5_000)));
+        assert!(Regex::new("(a*)*b")?.is_match("aaab"));
+        assert!(Regex::new("a{100000}").is_err());
+        assert!(Regex::new(&"(".repeat(500)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_with_capture_groups() {
+        assert_eq!(
+            sub(r"(\w+)@(\w+)", "bob@example", "$2 at $1").as_deref(),
+            Some("example at bob")
+        );
+        assert_eq!(
+            sub(r"(\d)", "a1b2", "<${1}0>").as_deref(),
+            Some("a<10>b<20>")
+        );
+        assert_eq!(sub("a", "cost", "$$").as_deref(), None);
+        assert_eq!(sub("o", "foo", "$$").as_deref(), Some("f$$"));
+        assert_eq!(sub("x*", "ab", "-").as_deref(), Some("-a-b-"));
+        assert_eq!(sub("<.+?>", "<a><b>", "T").as_deref(), Some("TT"));
+        assert_eq!(
+            Regex::new("o")
+                .ok()
+                .and_then(|r| r.replace("foo", "0", false)),
+            Some("f0o".to_string())
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+use crate::scratch;
+
+/// A file argument that names a remote object. Transfers go through `curl`
+/// and the AWS CLI, which already handle TLS, proxies and credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remote {
+    /// `http://` or `https://`; read-only, saved locally with save-as.
+    Http(String),
+    /// `s3://bucket/key`; saving uploads the file back.
+    S3(String),
+}
+
+impl Remote {
+    pub fn parse(arg: &str) -> Option<Self> {
+        if arg.starts_with("https://") || arg.starts_with("http://") {
+            Some(Remote::Http(arg.to_string()))
+        } else if arg.starts_with("s3://") {
+            Some(Remote::S3(arg.to_string()))
+        } else {
+            None
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        match self {
+            Remote::Http(u) | Remote::S3(u) => u,
+        }
+    }
+
+    /// The object's file name, without a query string or fragment; its
+    /// extension still decides the delimiter and compression.
+    pub fn file_name(&self) -> &str {
+        let path = self.url().split(['?', '#']).next().unwrap_or_default();
+        match path.rsplit('/').next() {
+            Some(name) if !name.is_empty() => name,
+            _ => "download.csv",
+        }
+    }
+
+    /// Where the downloaded copy is kept while editing: the private
+    /// scratch directory, removed on exit.
+    pub fn local_path(&self) -> Result<PathBuf> {
+        scratch::path(self.file_name())
+    }
+
+    pub fn download(&self, dest: &Path) -> Result<()> {
+        let mut cmd = match self {
+            Remote::Http(url) => {
+                let mut c = Command::new("curl");
+                c.args(["-fsSL", "-o"]).arg(dest).arg(url);
+                c
+            }
+            Remote::S3(url) => {
+                let mut c = Command::new("aws");
+                c.args(["s3", "cp", "--only-show-errors", url]).arg(dest);
+                c
+            }
+        };
+        run(&mut cmd).with_context(|| format!("download {}", self.url()))
+    }
+
+    pub fn upload(&self, src: &Path) -> Result<()> {
+        match self {
+            Remote::Http(url) => bail!("can't write back to {url}; save a local copy instead"),
+            Remote::S3(url) => {
+                let mut c = Command::new("aws");
+                c.args(["s3", "cp", "--only-show-errors"]).arg(src).arg(url);
+                run(&mut c).with_context(|| format!("upload to {url}"))
+            }
+        }
+    }
+}
+
+fn run(cmd: &mut Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let out = cmd
+        .output()
+        .with_context(|| format!("run {program} (is it installed?)"))?;
+    if !out.status.success() {
+        bail!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls_and_names_the_local_copy() -> Result<()> {
+        let r = Remote::parse("https://example.com/data/sales.csv.gz?token=x");
+        assert!(matches!(r, Some(Remote::Http(_))));
+        assert_eq!(r.as_ref().map(Remote::file_name), Some("sales.csv.gz"));
+        let s3 = Remote::parse("s3://bucket/logs/");
+        assert_eq!(s3.as_ref().map(Remote::file_name), Some("download.csv"));
+        assert!(matches!(s3, Some(Remote::S3(_))));
+        assert_eq!(Remote::parse("data.csv"), None);
+        let local = Remote::S3("s3://b/k.csv".into()).local_path()?;
+        assert_eq!(local, scratch::path("k.csv")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{Result, anyhow};
+
+use crate::regex::Regex;
+
+/// Which cells a substitution touches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Cell,
+    Column,
+    Selection,
+    /// Every cell of the rows tagged with `t`.
+    Tagged,
+    File,
+}
+
+impl Scope {
+    pub fn label(self) -> &'static str {
+        match self {
+            Scope::Cell => "cell",
+            Scope::Column => "column",
+            Scope::Selection => "selection",
+            Scope::Tagged => "tagged rows",
+            Scope::File => "file",
+        }
+    }
+}
+
+/// `:[range]s/pattern/replacement/[gc]`, where the range is empty (current
+/// cell), `c` (current column), `'<,'>` (selection), `t` (tagged rows) or
+/// `%` (whole file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitute {
+    pub scope: Scope,
+    pub pattern: String,
+    pub replacement: String,
+    /// Replace every match in a cell rather than only the first.
+    pub global: bool,
+    /// Ask about each cell before changing it, like vim's `c` flag.
+    pub confirm: bool,
+}
+
+/// Split a command line into a substitution, if it has the shape of one.
+/// A bad pattern or flag is an error; anything else is `Ok(None)`.
+pub fn parse(line: &str) -> Result<Option<Substitute>> {
+    let line = line.trim_start();
+    let (scope, rest) = if let Some(r) = line.strip_prefix('%') {
+        (Scope::File, r)
+    } else if let Some(r) = line.strip_prefix("'<,'>") {
+        (Scope::Selection, r)
+    } else if let Some(r) = line.strip_prefix('c').filter(|r| r.starts_with('s')) {
+        (Scope::Column, r)
+    } else if let Some(r) = line.strip_prefix('t').filter(|r| r.starts_with('s')) {
+        (Scope::Tagged, r)
+    } else {
+        (Scope::Cell, line)
+    };
+    let Some(rest) = rest.strip_prefix('s') else {
+        return Ok(None);
+    };
+    // Any punctuation works as the delimiter, as in sed: `s|a/b|c|`.
+    let Some(delim) = rest.chars().next().filter(|c| c.is_ascii_punctuation()) else {
+        return Ok(None);
+    };
+    let parts = split_unescaped(&rest[delim.len_utf8()..], delim);
+    let (pattern, replacement, flags) = match parts.as_slice() {
+        [p] => (p.clone(), String::new(), String::new()),
+        [p, r] => (p.clone(), r.clone(), String::new()),
+        [p, r, f] => (p.clone(), r.clone(), f.clone()),
+        _ => return Err(anyhow!("too many {delim:?} in substitution")),
+    };
+    if pattern.is_empty() {
+        return Err(anyhow!("empty pattern"));
+    }
+    if let Some(bad) = flags.trim().chars().find(|&c| c != 'g' && c != 'c') {
+        return Err(anyhow!("unknown flag {bad:?}; only g and c are supported"));
+    }
+    Regex::new(&pattern)?;
+    Ok(Some(Substitute {
+        scope,
+        pattern,
+        replacement,
+        global: flags.contains('g'),
+        confirm: flags.contains('c'),
+    }))
+}
+
+/// Split on `delim`, turning `\<delim>` into a literal delimiter and leaving
+/// other escapes for the regex.
+fn split_unescaped(s: &str, delim: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("never empty");
+        if c == '\\' && chars.peek() == Some(&delim) {
+            part.push(delim);
+            chars.next();
+        } else if c == '\\' {
+            part.push(c);
+            if let Some(n) = chars.next() {
+                part.push(n);
+            }
+        } else if c == delim {
+            parts.push(String::new());
+        } else {
+            part.push(c);
+        }
+    }
+    parts
+}
+
+/// One cell a substitution would change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub row: usize,
+    pub col: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The changes `sub` makes to `cells` of `data`, in order, stopping after
+/// `limit` of them.
+pub fn changes(
+    data: &[Vec<String>],
+    cells: impl Iterator<Item = (usize, usize)>,
+    sub: &Substitute,
+    limit: usize,
+) -> Result<Vec<Change>> {
+    let re = Regex::new(&sub.pattern)?;
+    let mut out = Vec::new();
+    for (row, col) in cells {
+        if out.len() >= limit {
+            break;
+        }
+        let before = data
+            .get(row)
+            .and_then(|r| r.get(col))
+            .map_or("", String::as_str);
+        if let Some(after) = re.replace(before, &sub.replacement, sub.global)
+            && after != before
+        {
+            out.push(Change {
+                row,
+                col,
+                before: before.to_string(),
+                after,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Split a change into the head and tail both sides share and the parts in
+/// between that differ, so only what changed needs highlighting.
+pub fn diff<'a>(before: &'a str, after: &'a str) -> (&'a str, &'a str, &'a str, &'a str) {
+    let head = before
+        .char_indices()
+        .zip(after.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(before.len().min(after.len()), |((i, _), _)| i);
+    let (b_rest, a_rest) = (&before[head..], &after[head..]);
+    let tail = b_rest
+        .chars()
+        .rev()
+        .zip(a_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+    (
+        &before[..head],
+        &b_rest[..b_rest.len() - tail],
+        &a_rest[..a_rest.len() - tail],
+        &b_rest[b_rest.len() - tail..],
+    )
+}
+
+/// A `c`-flagged substitution being stepped through one cell at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+    pub changes: Vec<Change>,
+    /// The change being asked about; `changes.len()` once all are decided.
+    pub index: usize,
+    accepted: Vec<bool>,
+}
+
+impl Review {
+    pub fn new(changes: Vec<Change>) -> Self {
+        let accepted = vec![false; changes.len()];
+        Self {
+            changes,
+            index: 0,
+            accepted,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Change> {
+        self.changes.get(self.index)
+    }
+
+    /// Take or skip the current change and move to the next.
+    pub fn decide(&mut self, accept: bool) {
+        if let Some(a) = self.accepted.get_mut(self.index) {
+            *a = accept;
+            self.index += 1;
+        }
+    }
+
+    /// Take the current change and all the rest.
+    pub fn accept_rest(&mut self) {
+        while self.current().is_some() {
+            self.decide(true);
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.index >= self.changes.len()
+    }
+
+    /// The changes that were taken.
+    pub fn accepted(&self) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .zip(&self.accepted)
+            .filter(|(_, a)| **a)
+            .map(|(c, _)| c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges_delimiters_and_flags() -> Result<()> {
+        let sub = parse(r"%s/(\d+)\/(\d+)/$2-$1/g")?.expect("substitution");
+        assert_eq!(sub.scope, Scope::File);
+        assert_eq!(sub.pattern, r"(\d+)/(\d+)");
+        assert_eq!(sub.replacement, "$2-$1");
+        assert!(sub.global);
+        assert_eq!(parse("cs|a|b")?.map(|s| s.scope), Some(Scope::Column));
+        assert_eq!(parse("ts/a/b/")?.map(|s| s.scope), Some(Scope::Tagged));
+        assert_eq!(parse("'<,'>s/a/")?.map(|s| s.scope), Some(Scope::Selection));
+        assert_eq!(parse("s/a")?.map(|s| s.replacement), Some(String::new()));
+        assert_eq!(parse("sort")?, None);
+        assert_eq!(parse("w")?, None);
+        assert!(parse("s/(/x/").is_err());
+        assert!(parse("s/a/b/i").is_err());
+        let sub = parse("%s/a/b/gc")?.expect("substitution");
+        assert!(sub.global && sub.confirm);
+        Ok(())
+    }
+
+    #[test]
+    fn diffs_and_reviews_changes() {
+        assert_eq!(
+            diff("2024-01-05", "2024/01-05"),
+            ("2024", "-", "/", "01-05")
+        );
+        assert_eq!(diff("café", "cafés"), ("café", "", "s", ""));
+        assert_eq!(diff("aXa", "aa"), ("a", "X", "", "a"));
+
+        let change = |row| Change {
+            row,
+            col: 0,
+            before: "a".into(),
+            after: "b".into(),
+        };
+        let mut review = Review::new(vec![change(0), change(1), change(2)]);
+        review.decide(false);
+        review.decide(true);
+        assert!(!review.is_done());
+        review.accept_rest();
+        assert!(review.is_done());
+        let rows: Vec<_> = review.accepted().map(|c| c.row).collect();
+        assert_eq!(rows, [1, 2]);
+    }
+
+    #[test]
+    fn lists_changed_cells_up_to_the_limit() -> Result<()> {
+        let data = vec![
+            vec!["a-1".to_string(), "b-2".to_string()],
+            vec!["c".to_string(), "d-4".to_string()],
+        ];
+        let sub = parse(r"%s/(\w)-(\d)/$2$1/")?.expect("substitution");
+        let cells = (0..2).flat_map(|r| (0..2).map(move |c| (r, c)));
+        let found = changes(&data, cells.clone(), &sub, 10)?;
+        let after: Vec<_> = found.iter().map(|c| c.after.as_str()).collect();
+        assert_eq!(after, ["1a", "2b", "4d"]);
+        assert_eq!(changes(&data, cells, &sub, 2)?.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use csv::WriterBuilder;
+
+use crate::findings::Finding;
+
+/// One finding as a report line, with the cell's current value and column
+/// name filled in.
+struct Line<'a> {
+    finding: &'a Finding,
+    column: &'a str,
+    value: &'a str,
+}
+
+const FIELDS: [&str; 7] = [
+    "row", "col", "column", "severity", "rule", "value", "message",
+];
+
+fn lines<'a>(items: &'a [Finding], data: &'a [Vec<String>], header: bool) -> Vec<Line<'a>> {
+    let cell = |r: usize, c: usize| {
+        data.get(r)
+            .and_then(|row| row.get(c))
+            .map_or("", String::as_str)
+    };
+    items
+        .iter()
+        .map(|f| Line {
+            finding: f,
+            column: if header { cell(0, f.col) } else { "" },
+            value: cell(f.row, f.col),
+        })
+        .collect()
+}
+
+fn severity(f: &Finding) -> &'static str {
+    if f.warning { "warning" } else { "error" }
+}
+
+/// Findings as CSV, one per line under a header row; rows and columns are
+/// 1-based as on screen.
+pub fn to_csv(items: &[Finding], data: &[Vec<String>], header: bool) -> Result<String> {
+    let mut wtr = WriterBuilder::new().from_writer(Vec::new());
+    wtr.write_record(FIELDS)?;
+    for l in lines(items, data, header) {
+        let f = l.finding;
+        wtr.write_record([
+            &(f.row + 1).to_string(),
+            &(f.col + 1).to_string(),
+            l.column,
+            severity(f),
+            &f.rule,
+            l.value,
+            &f.text,
+        ])?;
+    }
+    let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Findings as a JSON array of objects with the CSV report's fields.
+pub fn to_json(items: &[Finding], data: &[Vec<String>], header: bool) -> String {
+    let objects: Vec<String> = lines(items, data, header)
+        .iter()
+        .map(|l| {
+            let f = l.finding;
+            format!(
+                "  {{\"row\": {}, \"col\": {}, \"column\": {}, \"severity\": \"{}\", \"rule\": {}, \"value\": {}, \"message\": {}}}",
+                f.row + 1,
+                f.col + 1,
+                json_string(l.column),
+                severity(f),
+                json_string(&f.rule),
+                json_string(l.value),
+                json_string(&f.text)
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", objects.join(",\n"))
+    }
+}
+
+/// Findings as `file:row:col: severity [rule] message` lines, the way
+/// compilers report, for `--validate`.
+pub fn to_text(file: &str, items: &[Finding]) -> String {
+    items
+        .iter()
+        .map(|f| {
+            format!(
+                "{file}:{}:{}: {} [{}] {}\n",
+                f.row + 1,
+                f.col + 1,
+                severity(f),
+                f.rule,
+                f.text
+            )
+        })
+        .collect()
+}
+
+/// Write a report to `path`: JSON for a `.json` file, CSV otherwise.
+pub fn write(path: &Path, items: &[Finding], data: &[Vec<String>], header: bool) -> Result<()> {
+    let json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let text = if json {
+        to_json(items, data, header)
+    } else {
+        to_csv(items, data, header)?
+    };
+    fs::write(path, text).with_context(|| format!("write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_findings_as_csv_and_json() -> Result<()> {
+        let data: Vec<Vec<String>> = [["mail", "n"], ["x\"y", "1"]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let items = [Finding {
+            row: 1,
+            col: 0,
+            rule: "email".into(),
+            text: "not a valid email".into(),
+            warning: false,
+            whole_row: false,
+        }];
+        assert_eq!(
+            to_csv(&items, &data, true)?,
+            "row,col,column,severity,rule,value,message\n2,1,mail,error,email,\"x\"\"y\",not a valid email\n"
+        );
+        assert_eq!(
+            to_json(&items, &data, false),
+            "[\n  {\"row\": 2, \"col\": 1, \"column\": \"\", \"severity\": \"error\", \"rule\": \"email\", \"value\": \"x\\\"y\", \"message\": \"not a valid email\"}\n]\n"
+        );
+        assert_eq!(to_json(&[], &data, true), "[]\n");
+        assert_eq!(
+            to_text("a.csv", &items),
+            "a.csv:2:1: error [email] not a valid email\n"
+        );
+        assert_eq!(json_string("a\u{1}\n"), "\"a\\u0001\\n\"");
+        Ok(())
+    }
+}
@@ -0,0 +1,545 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::expr::{self, Outcome};
+use crate::findings::Finding;
+use crate::header;
+use crate::mask::Mask;
+use crate::nulls::Nulls;
+use crate::regex::Regex;
+use crate::viz::Decimal;
+
+/// What a column's values must look like.
+#[derive(Debug, Clone)]
+pub enum Format {
+    /// Not blank (and not a null token).
+    Required,
+    Email,
+    Url,
+    Uuid,
+    Ipv4,
+    Ipv6,
+    /// Either IP version.
+    Ip,
+    /// An E.164 phone number: `+`, a country code and up to 15 digits.
+    Phone,
+    /// `/regex/`, searched anywhere in the value unless anchored.
+    Pattern(String, Regex),
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(re) = s.strip_prefix('/').and_then(|r| r.strip_suffix('/')) {
+            return Ok(Format::Pattern(re.to_string(), Regex::new(re)?));
+        }
+        Ok(match s {
+            "required" => Format::Required,
+            "email" => Format::Email,
+            "url" => Format::Url,
+            "uuid" => Format::Uuid,
+            "ipv4" => Format::Ipv4,
+            "ipv6" => Format::Ipv6,
+            "ip" => Format::Ip,
+            "phone" | "e164" => Format::Phone,
+            _ => bail!(
+                "unknown format {s:?}; use required, email, url, uuid, ipv4, ipv6, ip, phone or /regex/"
+            ),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Format::Required => "required",
+            Format::Email => "email",
+            Format::Url => "url",
+            Format::Uuid => "uuid",
+            Format::Ipv4 => "ipv4",
+            Format::Ipv6 => "ipv6",
+            Format::Ip => "ip",
+            Format::Phone => "phone",
+            Format::Pattern(re, _) => re,
+        }
+    }
+
+    /// A value that passes, for filling in a template's example row; blank
+    /// for a pattern, which can't be guessed.
+    pub fn example(&self) -> &str {
+        match self {
+            Format::Required => "example",
+            Format::Email => "user@example.com",
+            Format::Url => "https://example.com/",
+            Format::Uuid => "123e4567-e89b-42d3-a456-426614174000",
+            Format::Ipv4 | Format::Ip => "192.0.2.1",
+            Format::Ipv6 => "2001:db8::1",
+            Format::Phone => "+14155552671",
+            Format::Pattern(..) => "",
+        }
+    }
+
+    /// Whether a non-missing value passes.
+    pub fn check(&self, v: &str) -> bool {
+        match self {
+            Format::Required => true,
+            Format::Email => is_email(v),
+            Format::Url => is_url(v),
+            Format::Uuid => is_uuid(v),
+            Format::Ipv4 => v.parse::<Ipv4Addr>().is_ok(),
+            Format::Ipv6 => v.parse::<Ipv6Addr>().is_ok(),
+            Format::Ip => v.parse::<Ipv4Addr>().is_ok() || v.parse::<Ipv6Addr>().is_ok(),
+            Format::Phone => is_phone(v),
+            Format::Pattern(_, re) => re.is_match(v),
+        }
+    }
+}
+
+fn is_email(v: &str) -> bool {
+    const LOCAL: &str = ".!#$%&'*+/=?^_`{|}~-";
+    let Some((local, domain)) = v.rsplit_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || LOCAL.contains(c))
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..");
+    local_ok && is_domain(domain)
+}
+
+/// At least two dot-separated labels of letters, digits and inner hyphens,
+/// ending in a top-level domain of two or more letters.
+fn is_domain(d: &str) -> bool {
+    let labels: Vec<&str> = d.split('.').collect();
+    let label_ok = |l: &&str| {
+        !l.is_empty()
+            && l.len() <= 63
+            && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !l.starts_with('-')
+            && !l.ends_with('-')
+    };
+    labels.len() >= 2
+        && labels.iter().all(label_ok)
+        && labels
+            .last()
+            .is_some_and(|t| t.len() >= 2 && t.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// `scheme://host...` with no whitespace; the host is a domain, `localhost`
+/// or an IP address.
+fn is_url(v: &str) -> bool {
+    let Some((scheme, rest)) = v.split_once("://") else {
+        return false;
+    };
+    let scheme_ok = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c));
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split_once(']').map(|(h, _)| h),
+        None => Some(host.split(':').next().unwrap_or_default()),
+    };
+    let host_ok = host.is_some_and(|h| {
+        h == "localhost"
+            || is_domain(h)
+            || h.parse::<Ipv4Addr>().is_ok()
+            || h.parse::<Ipv6Addr>().is_ok()
+    });
+    scheme_ok && host_ok && !v.chars().any(char::is_whitespace)
+}
+
+fn is_uuid(v: &str) -> bool {
+    let groups: Vec<&str> = v.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(g, n)| g.len() == n && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_phone(v: &str) -> bool {
+    v.strip_prefix('+').is_some_and(|d| {
+        (2..=15).contains(&d.len()) && d.chars().all(|c| c.is_ascii_digit()) && !d.starts_with('0')
+    })
+}
+
+/// One `column = format` line of a schema.
+#[derive(Debug, Clone)]
+pub struct ColumnRule {
+    /// A header name or 1-based column number, resolved when validating.
+    pub column: String,
+    pub format: Format,
+    /// From a `warn` line: reported as a warning rather than an error.
+    pub warning: bool,
+}
+
+/// One `check <expression>` line of a schema.
+#[derive(Debug, Clone)]
+pub struct RowRule {
+    pub rule: expr::Rule,
+    pub warning: bool,
+}
+
+/// Checks for a CSV file, read from `<file>.schema` next to it: one
+/// `column = format` or `check <expression>` per line, `#` starting a
+/// comment and a leading `warn` making the rule a warning, e.g.
+///
+/// ```text
+/// email = email
+/// 3 = /^[A-Z]{3}$/
+/// check end_date >= start_date
+/// warn site = url
+/// example name = Alice
+/// default status = open
+/// mask card = last4
+/// ```
+///
+/// `example` lines don't check anything; they give [`Schema::template`] the
+/// values for its example rows, one row per repeat of a column. `default`
+/// lines fill in the rows inserted with `o`/`O`; see [`Schema::new_rows`].
+/// `mask` lines draw a column masked from the moment the file opens.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    pub columns: Vec<ColumnRule>,
+    /// Rules across the columns of each row.
+    pub rows: Vec<RowRule>,
+    /// `example column = value` lines, in order.
+    pub examples: Vec<(String, String)>,
+    /// `default column = value` lines, in order.
+    pub defaults: Vec<(String, String)>,
+    /// `mask column = full|last4` lines.
+    pub masks: Vec<(String, Mask)>,
+}
+
+pub fn schema_path_for(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.as_os_str().to_owned();
+    name.push(".schema");
+    PathBuf::from(name)
+}
+
+impl Schema {
+    /// The schema next to `csv_path`, or `None` when there is none.
+    pub fn load_for(csv_path: &Path) -> Result<Option<Self>> {
+        let path = schema_path_for(csv_path);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text)
+                .with_context(|| format!("{}", path.display()))
+                .map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("read {path:?}")),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut schema = Self::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.split(" #").next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (warning, line) = match line.strip_prefix("warn ") {
+                Some(rest) => (true, rest.trim_start()),
+                None => (false, line),
+            };
+            if let Some(example) = line.strip_prefix("example ") {
+                let (column, value) = example.split_once('=').ok_or_else(|| {
+                    anyhow!("line {}: expected `example column = value`", idx + 1)
+                })?;
+                let example = (column.trim().to_string(), value.trim().to_string());
+                schema.examples.push(example);
+                continue;
+            }
+            if let Some(default) = line.strip_prefix("default ") {
+                let (column, value) = default.split_once('=').ok_or_else(|| {
+                    anyhow!("line {}: expected `default column = value`", idx + 1)
+                })?;
+                let default = (column.trim().to_string(), value.trim().to_string());
+                schema.defaults.push(default);
+                continue;
+            }
+            if let Some(mask) = line.strip_prefix("mask ") {
+                let (column, how) = mask.split_once('=').ok_or_else(|| {
+                    anyhow!("line {}: expected `mask column = full|last4`", idx + 1)
+                })?;
+                let how = Mask::parse(how).with_context(|| format!("line {}", idx + 1))?;
+                schema.masks.push((column.trim().to_string(), how));
+                continue;
+            }
+            if let Some(rule) = line.strip_prefix("check ") {
+                let rule = expr::Rule::parse(rule).with_context(|| format!("line {}", idx + 1))?;
+                schema.rows.push(RowRule { rule, warning });
+                continue;
+            }
+            let (column, format) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `column = format`", idx + 1))?;
+            schema.columns.push(ColumnRule {
+                column: column.trim().to_string(),
+                format: Format::parse(format).with_context(|| format!("line {}", idx + 1))?,
+                warning,
+            });
+        }
+        Ok(schema)
+    }
+
+    /// A new file's rows for `--new --template`: a header of the named
+    /// columns, in the order the schema first mentions them, then the
+    /// `example` rows, or one row of values that pass each column's format.
+    /// Columns given by number have no name to put in the header.
+    pub fn template(&self) -> Result<Vec<Vec<String>>> {
+        let named = |c: &String| c.parse::<usize>().is_err();
+        let mut headers: Vec<String> = Vec::new();
+        let mentioned = self
+            .columns
+            .iter()
+            .map(|r| &r.column)
+            .chain(self.examples.iter().map(|(c, _)| c));
+        for c in mentioned.filter(|c| named(c)) {
+            if !headers.contains(c) {
+                headers.push(c.clone());
+            }
+        }
+        if headers.is_empty() {
+            bail!("the template names no columns");
+        }
+        let given: Vec<Vec<&String>> = headers
+            .iter()
+            .map(|h| {
+                let examples = self.examples.iter().filter(|(c, _)| c == h);
+                examples.map(|(_, v)| v).collect()
+            })
+            .collect();
+        let rows = given.iter().map(Vec::len).max().unwrap_or(0);
+        let mut out = vec![headers.clone()];
+        for i in 0..rows.max(1) {
+            let row = headers.iter().zip(&given).map(|(h, given)| {
+                let mut formats = self
+                    .columns
+                    .iter()
+                    .filter(|r| r.column == *h)
+                    .map(|r| &r.format);
+                // `required` says less about a value than any other format.
+                let format = formats
+                    .clone()
+                    .find(|f| !matches!(f, Format::Required))
+                    .or_else(|| formats.next());
+                match given.get(i) {
+                    Some(v) => v.to_string(),
+                    None => format.map_or("", Format::example).to_string(),
+                }
+            });
+            out.push(row.collect());
+        }
+        Ok(out)
+    }
+
+    /// `count` rows `width` cells wide to insert into `data`, blank but for
+    /// the `default` columns. `{today}` stands for `today`, `{next}` for the
+    /// whole numbers after the column's largest.
+    pub fn new_rows(
+        &self,
+        data: &[Vec<String>],
+        header: bool,
+        width: usize,
+        today: &str,
+        count: usize,
+    ) -> Result<Vec<Vec<String>>> {
+        let headers = if header {
+            data.first().map_or(&[][..], Vec::as_slice)
+        } else {
+            &[]
+        };
+        let mut rows = vec![vec![String::new(); width]; count];
+        for (column, value) in &self.defaults {
+            let col = header::resolve_column(headers, column)?;
+            let next = data
+                .iter()
+                .skip(usize::from(header))
+                .filter_map(|r| r.get(col)?.trim().parse::<i64>().ok())
+                .max()
+                .map_or(1, |n| n + 1);
+            for (i, row) in rows.iter_mut().enumerate() {
+                if col >= row.len() {
+                    row.resize(col + 1, String::new());
+                }
+                row[col] = match value.as_str() {
+                    "{today}" => today.to_string(),
+                    "{next}" => (next + i as i64).to_string(),
+                    v => v.to_string(),
+                };
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Every cell below the header rows that breaks a rule, top to bottom.
+    /// Blank and null cells only fail `required`.
+    pub fn validate(
+        &self,
+        data: &[Vec<String>],
+        header: bool,
+        nulls: &Nulls,
+        decimal: Decimal,
+    ) -> Result<Vec<Finding>> {
+        let headers = if header {
+            data.first().map_or(&[][..], Vec::as_slice)
+        } else {
+            &[]
+        };
+        let rules = self
+            .columns
+            .iter()
+            .map(|r| Ok((header::resolve_column(headers, &r.column)?, r)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut checks = self.rows.clone();
+        for r in &mut checks {
+            r.rule.resolve(headers)?;
+        }
+        let mut out = Vec::new();
+        for (row, cells) in data.iter().enumerate().skip(usize::from(header)) {
+            for &(col, r) in &rules {
+                let format = &r.format;
+                let v = cells.get(col).map_or("", String::as_str);
+                let missing = v.trim().is_empty() || nulls.is_null(v);
+                let text = match format {
+                    Format::Required if missing => "required value is missing".to_string(),
+                    _ if missing || format.check(v) => continue,
+                    Format::Pattern(re, _) => format!("{v:?} doesn't match /{re}/"),
+                    _ => format!("{v:?} is not a valid {}", format.name()),
+                };
+                let rule = match format {
+                    Format::Pattern(re, _) => format!("/{re}/"),
+                    _ => format.name().to_string(),
+                };
+                out.push(Finding {
+                    row,
+                    col,
+                    rule,
+                    text,
+                    warning: r.warning,
+                    whole_row: false,
+                });
+            }
+            for r in &checks {
+                let text = match r.rule.check(cells, nulls, decimal) {
+                    Outcome::Pass | Outcome::Missing => continue,
+                    Outcome::Fail(a, b) => format!("fails: {a} vs {b}"),
+                    Outcome::Error(e) => e,
+                };
+                out.push(Finding {
+                    row,
+                    col: r.rule.first_col().unwrap_or(0),
+                    rule: r.rule.text.clone(),
+                    text,
+                    warning: r.warning,
+                    whole_row: true,
+                });
+            }
+        }
+        out.sort_by_key(|f| (f.row, f.col));
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes(format: &str, v: &str) -> bool {
+        Format::parse(format).is_ok_and(|f| f.check(v))
+    }
+
+    #[test]
+    fn built_in_formats() {
+        assert!(passes("email", "a.b+tag@mail.example.co"));
+        for bad in ["a@b", "@x.com", "a..b@x.com", "a b@x.com", "a@-x.com"] {
+            assert!(!passes("email", bad), "{bad}");
+        }
+        assert!(passes("url", "https://example.com/a?b=1"));
+        assert!(passes("url", "http://user@[::1]:8080/"));
+        assert!(!passes("url", "example.com"));
+        assert!(!passes("url", "https://exa mple.com"));
+        assert!(passes("uuid", "123e4567-e89b-12d3-a456-426614174000"));
+        assert!(!passes("uuid", "123e4567-e89b-12d3-a456-42661417400"));
+        assert!(passes("ipv4", "192.168.0.1") && !passes("ipv4", "256.1.1.1"));
+        assert!(passes("ip", "2001:db8::1") && !passes("ipv6", "10.0.0.1"));
+        assert!(passes("phone", "+14155552671"));
+        assert!(!passes("e164", "4155552671") && !passes("phone", "+0123"));
+        assert!(passes("/^[A-Z]{3}$/", "JPY"));
+        assert!(Format::parse("zip").is_err());
+        for f in [
+            "required", "email", "url", "uuid", "ipv4", "ipv6", "ip", "phone",
+        ] {
+            let format = Format::parse(f).unwrap();
+            assert!(format.check(format.example()), "{f}");
+        }
+    }
+
+    #[test]
+    fn validates_columns_by_name_or_number() -> Result<()> {
+        let schema = Schema::parse("# contacts\nmail = email\n1 = required # the id\n")?;
+        let data: Vec<Vec<String>> = [["id", "mail"], ["1", "a@x.io"], ["", "nope"], ["3", ""]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let found = schema.validate(&data, true, &Nulls::default(), Decimal::Dot)?;
+        let cells: Vec<_> = found.iter().map(|f| (f.row, f.col)).collect();
+        assert_eq!(cells, [(2, 0), (2, 1)]);
+        assert_eq!(found[1].text, "\"nope\" is not a valid email");
+        assert!(
+            schema
+                .validate(&data, false, &Nulls::default(), Decimal::Dot)
+                .is_err()
+        );
+        assert!(Schema::parse("mail email").is_err());
+
+        let rows = Schema::parse("warn check id < 3\ncheck mail != \"a@x.io\"")?;
+        let found = rows.validate(&data, true, &Nulls::default(), Decimal::Dot)?;
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].rule, "mail != \"a@x.io\"");
+        assert_eq!(found[0].text, "fails: \"a@x.io\" vs \"a@x.io\"");
+        assert!(found[0].whole_row);
+        assert_eq!((found[1].row, found[1].text.as_str()), (3, "fails: 3 vs 3"));
+        assert!(found[1].warning && !found[0].warning);
+        Ok(())
+    }
+
+    #[test]
+    fn templates_name_columns_and_fill_example_rows() -> Result<()> {
+        let schema = Schema::parse(
+            "id = required\nid = uuid\n2 = email\ncode = /^[A-Z]+$/\nexample name = Ann\nexample name = Bo\n",
+        )?;
+        let rows = schema.template()?;
+        assert_eq!(rows[0], ["id", "code", "name"]);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1][0], "123e4567-e89b-42d3-a456-426614174000");
+        assert_eq!((rows[1][1].as_str(), rows[2][2].as_str()), ("", "Bo"));
+        assert!(Schema::parse("2 = email")?.template().is_err());
+        assert!(Schema::parse("example name Ann").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn new_rows_get_the_defaults() -> Result<()> {
+        let schema =
+            Schema::parse("default id = {next}\ndefault status = open\ndefault 4 = {today}\n")?;
+        let data: Vec<Vec<String>> = [["id", "status", "note"], ["7", "done", ""], ["x", "", ""]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let rows = schema.new_rows(&data, true, 3, "2026-10-14", 2)?;
+        assert_eq!(rows[0], ["8", "open", "", "2026-10-14"]);
+        assert_eq!(rows[1][0], "9");
+        assert_eq!(schema.new_rows(&data[..1], true, 3, "", 1)?[0][0], "1");
+        let ghost = Schema::parse("default ghost = 1")?;
+        assert!(ghost.new_rows(&data, true, 3, "", 1).is_err());
+        Ok(())
+    }
+}
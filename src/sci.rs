@@ -0,0 +1,84 @@
+/// Smallest exponent taken for a mangled ID; below a million, scientific
+/// notation is more likely meant.
+const MIN_EXPONENT: usize = 6;
+
+/// Split `1.23E+11` into its sign, mantissa digits and the zeros to append.
+/// Only the shape spreadsheets write is accepted: one non-zero integer digit
+/// and a positive exponent that covers the whole fraction, so the number is
+/// a whole one whose low digits were rounded away.
+fn split(s: &str) -> Option<(&str, String, usize)> {
+    let s = s.trim();
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s),
+    };
+    let (mantissa, exp) = s.split_once(['e', 'E'])?;
+    let exp = exp.strip_prefix('+').unwrap_or(exp);
+    if exp.is_empty() || !exp.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let exp: usize = exp.parse().ok()?;
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits_ok = |d: &str| d.bytes().all(|b| b.is_ascii_digit());
+    if int.len() != 1 || int == "0" || !digits_ok(int) || !digits_ok(frac) {
+        return None;
+    }
+    if exp < MIN_EXPONENT || exp < frac.len() {
+        return None;
+    }
+    Some((sign, format!("{int}{frac}"), exp - frac.len()))
+}
+
+/// Whether `s` looks like a large whole number that a spreadsheet turned
+/// into scientific notation, e.g. an ID shown as `1.23457E+11`.
+pub fn looks_mangled(s: &str) -> bool {
+    split(s).is_some()
+}
+
+/// The plain digits for a mangled value: `1.23457E+11` becomes
+/// `123457000000`. Digits the spreadsheet already dropped stay zeros.
+pub fn expand(s: &str) -> Option<String> {
+    let (sign, digits, zeros) = split(s)?;
+    Some(format!("{sign}{digits}{}", "0".repeat(zeros)))
+}
+
+/// Cells that look mangled, as `(row, col)`, top to bottom.
+pub fn find(data: &[Vec<String>]) -> Vec<(usize, usize)> {
+    data.iter()
+        .enumerate()
+        .flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, c)| looks_mangled(c))
+                .map(move |(c, _)| (r, c))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spots_and_expands_mangled_ids_only() {
+        assert_eq!(expand("1.23457E+11").as_deref(), Some("123457000000"));
+        assert_eq!(expand("-4E6").as_deref(), Some("-4000000"));
+        assert_eq!(expand("9.9e+07").as_deref(), Some("99000000"));
+        for s in [
+            "1.5E-3",
+            "12.3E+11",
+            "1.2345678E+6",
+            "2E+3",
+            "E+11",
+            "1.2E+",
+            "abc",
+        ] {
+            assert!(!looks_mangled(s), "{s}");
+        }
+        let data = vec![
+            vec!["id".to_string(), "n".to_string()],
+            vec!["1.2E+11".to_string(), "3".to_string()],
+        ];
+        assert_eq!(find(&data), [(1, 0)]);
+    }
+}
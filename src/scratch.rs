@@ -0,0 +1,100 @@
+//! A private scratch directory for this process: the downloaded copy of a
+//! remote file and the files handed to `$EDITOR` live there. It is made
+//! fresh under a random name, readable by this user only, and removed when
+//! the editor exits, so nothing lingers in a shared temp dir.
+
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, bail};
+
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// `name` inside the scratch directory, which is created on first use.
+pub fn path(name: &str) -> Result<PathBuf> {
+    let mut dir = DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if dir.is_none() {
+        *dir = Some(create()?);
+    }
+    Ok(dir.as_ref().map(|d| d.join(name)).unwrap_or_default())
+}
+
+/// Delete the scratch directory and everything in it, if it was made.
+pub fn remove() {
+    let mut dir = DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(d) = dir.take() {
+        let _ = fs::remove_dir_all(d);
+    }
+}
+
+/// Removes the scratch directory when dropped, however `main` returns.
+pub struct Cleanup;
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        remove();
+    }
+}
+
+fn create() -> Result<PathBuf> {
+    private_dir(&std::env::temp_dir(), "tui-csv-editor")
+}
+
+/// A new directory in `parent` that only this user can enter, named
+/// `<prefix>-<pid>-<random>`.
+pub fn private_dir(parent: &Path, prefix: &str) -> Result<PathBuf> {
+    let seed = RandomState::new();
+    for attempt in 0..8u64 {
+        let mut h = seed.build_hasher();
+        h.write_u64(attempt);
+        let dir = parent.join(format!(
+            "{prefix}-{}-{:016x}",
+            std::process::id(),
+            h.finish()
+        ));
+        // `create_dir` fails rather than reuse anything already there, so
+        // a directory or link planted under the name can't be taken over.
+        match builder().create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("create {}", dir.display())),
+        }
+    }
+    bail!("could not make a scratch directory")
+}
+
+#[cfg(unix)]
+fn builder() -> fs::DirBuilder {
+    use std::os::unix::fs::DirBuilderExt;
+    let mut b = fs::DirBuilder::new();
+    b.mode(0o700);
+    b
+}
+
+#[cfg(not(unix))]
+fn builder() -> fs::DirBuilder {
+    fs::DirBuilder::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_one_private_directory_per_process() -> Result<()> {
+        let a = path("a.csv")?;
+        let dir = a.parent().map(PathBuf::from).unwrap_or_default();
+        assert_eq!(path("b.txt")?.parent(), Some(dir.as_path()));
+        assert!(dir.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&dir)?.permissions().mode() & 0o777, 0o700);
+        }
+        Ok(())
+    }
+}
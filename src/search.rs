@@ -0,0 +1,165 @@
+use anyhow::Result;
+
+use crate::regex::Regex;
+
+/// The part of the table a search looks in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Area {
+    #[default]
+    File,
+    Column(usize),
+    /// A block selection, inclusive: `(top, left, bottom, right)`.
+    Block(usize, usize, usize, usize),
+}
+
+impl Area {
+    pub fn contains(self, r: usize, c: usize) -> bool {
+        match self {
+            Area::File => true,
+            Area::Column(col) => c == col,
+            Area::Block(top, left, bottom, right) => {
+                (top..=bottom).contains(&r) && (left..=right).contains(&c)
+            }
+        }
+    }
+
+    /// Prompt tag, e.g. `[col 3] `; empty for the whole file.
+    pub fn tag(self) -> String {
+        match self {
+            Area::File => String::new(),
+            Area::Column(c) => format!("[col {}] ", c + 1),
+            Area::Block(..) => "[selection] ".to_string(),
+        }
+    }
+}
+
+/// A `/` search. The query is a regex; it is case-insensitive unless it
+/// contains an uppercase letter ("smart case").
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub query: String,
+    pub area: Area,
+    re: Regex,
+}
+
+impl Search {
+    pub fn new(query: &str, area: Area) -> Result<Self> {
+        let re = if query.chars().any(char::is_uppercase) {
+            Regex::new(query)?
+        } else {
+            Regex::new(&format!("(?i){query}"))?
+        };
+        Ok(Self {
+            query: query.to_string(),
+            area,
+            re,
+        })
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.re.is_match(text)
+    }
+}
+
+fn cell(data: &[Vec<String>], (r, c): (usize, usize)) -> &str {
+    data.get(r)
+        .and_then(|row| row.get(c))
+        .map_or("", String::as_str)
+}
+
+/// The next cell in `cells` (row-major order) matching `search` after
+/// `from`, or at `from` itself with `inclusive`, wrapping around the end.
+/// The second value says whether the search wrapped.
+pub fn next_match(
+    data: &[Vec<String>],
+    cells: &[(usize, usize)],
+    search: &Search,
+    from: (usize, usize),
+    forward: bool,
+    inclusive: bool,
+) -> Option<((usize, usize), bool)> {
+    next_cell(data, cells, |t| search.matches(t), from, forward, inclusive)
+}
+
+/// Like [`next_match`], for any predicate on the cell text.
+pub fn next_cell(
+    data: &[Vec<String>],
+    cells: &[(usize, usize)],
+    matches: impl Fn(&str) -> bool,
+    from: (usize, usize),
+    forward: bool,
+    inclusive: bool,
+) -> Option<((usize, usize), bool)> {
+    let n = cells.len();
+    // Index of the first cell at or after `from`.
+    let start = cells.partition_point(|&p| p < from);
+    let at_from = cells.get(start) == Some(&from);
+    (0..n)
+        .map(|i| {
+            if forward {
+                let first = if at_from && !inclusive {
+                    start + 1
+                } else {
+                    start
+                };
+                let idx = first + i;
+                (idx % n, idx >= n)
+            } else {
+                let first = if at_from && inclusive {
+                    start + 1
+                } else {
+                    start
+                };
+                // Walk backwards from just before `first`.
+                let idx = (first + n - 1 - i) % n;
+                (idx, i + 1 > first)
+            }
+        })
+        .find(|&(idx, _)| matches(cell(data, cells[idx])))
+        .map(|(idx, wrapped)| (cells[idx], wrapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn smart_case_and_wrapping() -> Result<()> {
+        let data = grid(&[&["Apple", "pear"], &["fig", "apple pie"]]);
+        let cells: Vec<_> = (0..2).flat_map(|r| (0..2).map(move |c| (r, c))).collect();
+        let s = Search::new("apple", Area::File)?;
+        assert_eq!(
+            next_match(&data, &cells, &s, (0, 0), true, true),
+            Some(((0, 0), false))
+        );
+        assert_eq!(
+            next_match(&data, &cells, &s, (0, 0), true, false),
+            Some(((1, 1), false))
+        );
+        assert_eq!(
+            next_match(&data, &cells, &s, (1, 1), true, false),
+            Some(((0, 0), true))
+        );
+        assert_eq!(
+            next_match(&data, &cells, &s, (1, 1), false, false),
+            Some(((0, 0), false))
+        );
+        assert_eq!(
+            next_match(&data, &cells, &s, (0, 0), false, false),
+            Some(((1, 1), true))
+        );
+        let s = Search::new("Apple", Area::File)?;
+        assert_eq!(
+            next_match(&data, &cells, &s, (0, 1), true, true),
+            Some(((0, 0), true))
+        );
+        assert!(Search::new("(", Area::File).is_err());
+        Ok(())
+    }
+}
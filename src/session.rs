@@ -0,0 +1,379 @@
+//! `--record <session.json>` and `--replay <session.json>`: the keys and
+//! pastes of a session with the time each came in, for reproducing a bug report or
+//! checking an interactive flow end to end. What the editor does depends
+//! on the keys, not on their timing or the terminal size, so replaying the
+//! same keys against the same file and config ends in the same state
+//! (short of inserting today's date).
+//!
+//! The file is JSON, one event per line:
+//!
+//! ```text
+//! {"file": "data.csv", "events": [
+//!   {"ms": 0, "key": "j"},
+//!   {"ms": 850, "key": "C-d"},
+//!   {"ms": 1200, "paste": "a\tb\nc\td"}
+//! ]}
+//! ```
+//!
+//! Keys are a character or a name (`Enter`, `Esc`, `Up`, `F5`, ...) with
+//! `C-`, `A-` and `S-` in front for Ctrl, Alt and Shift. A paste is the
+//! text the terminal handed over in one piece.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::{App, handle_key, handle_paste};
+use crate::report::json_string;
+
+/// One key or paste and when it came, counted from the start of the
+/// session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub at: Duration,
+    pub input: Input,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    Key(KeyEvent),
+    Paste(String),
+}
+
+impl Input {
+    /// Hand the input to `app` the way the terminal would; `true` if it
+    /// quits the editor.
+    pub fn apply(&self, app: &mut App) -> Result<bool> {
+        match self {
+            Input::Key(key) => handle_key(app, *key),
+            Input::Paste(text) => {
+                handle_paste(app, text);
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    /// The file the session was recorded against, if it had one.
+    pub file: Option<PathBuf>,
+    pub events: Vec<Event>,
+}
+
+const NAMES: [(KeyCode, &str); 15] = [
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "BackTab"),
+    (KeyCode::Backspace, "BS"),
+    (KeyCode::Delete, "Del"),
+    (KeyCode::Insert, "Ins"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+];
+
+const MODS: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "C-"),
+    (KeyModifiers::ALT, "A-"),
+    (KeyModifiers::SHIFT, "S-"),
+];
+
+/// `C-d`, `S-Up`, `G`; `None` for keys the editor has no use for.
+pub fn key_name(key: &KeyEvent) -> Option<String> {
+    let base = match key.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        code => NAMES.iter().find(|(c, _)| *c == code)?.1.to_string(),
+    };
+    let mut out: String = MODS
+        .iter()
+        .filter(|(m, _)| key.modifiers.contains(*m))
+        .map(|(_, p)| *p)
+        .collect();
+    out.push_str(&base);
+    Some(out)
+}
+
+/// Inverse of [`key_name`].
+pub fn parse_key(name: &str) -> Result<KeyEvent> {
+    let mut mods = KeyModifiers::NONE;
+    let mut rest = name;
+    'prefixes: while rest.chars().count() > 1 {
+        for (m, p) in MODS {
+            if let Some(r) = rest.strip_prefix(p)
+                && !r.is_empty()
+            {
+                mods |= m;
+                rest = r;
+                continue 'prefixes;
+            }
+        }
+        break;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match NAMES.iter().find(|(_, n)| *n == rest) {
+            Some((code, _)) => *code,
+            None => match rest.strip_prefix('F').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => bail!("unknown key {name:?}"),
+            },
+        },
+    };
+    Ok(KeyEvent::new(code, mods))
+}
+
+impl Session {
+    pub fn to_json(&self) -> String {
+        let file = self.file.as_ref().map_or("null".to_string(), |f| {
+            json_string(&f.display().to_string())
+        });
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .filter_map(|e| {
+                let (name, value) = match &e.input {
+                    Input::Key(key) => ("key", key_name(key)?),
+                    Input::Paste(text) => ("paste", text.clone()),
+                };
+                Some(format!(
+                    "  {{\"ms\": {}, \"{name}\": {}}}",
+                    e.at.as_millis(),
+                    json_string(&value)
+                ))
+            })
+            .collect();
+        format!(
+            "{{\"file\": {file}, \"events\": [\n{}\n]}}\n",
+            events.join(",\n")
+        )
+    }
+
+    /// Read a session in the layout [`Session::to_json`] writes: `"file"`
+    /// first, then one `{"ms": .., "key": ..}` or `{"ms": .., "paste": ..}`
+    /// object per line.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut session = Session::default();
+        let head = text.trim_start().strip_prefix('{').unwrap_or_default();
+        if let Some(file) = head.trim_start().strip_prefix("\"file\":") {
+            let file = file.trim_start();
+            if !file.starts_with("null") {
+                session.file = Some(PathBuf::from(string(file)?));
+            }
+        }
+        for (n, line) in text.lines().enumerate().skip(1) {
+            let line = line.trim().trim_end_matches(',');
+            if !line.starts_with('{') {
+                continue;
+            }
+            let event = (|| -> Result<Event> {
+                let ms = field(line, "ms")?;
+                let end = ms.find(|c: char| !c.is_ascii_digit()).unwrap_or(ms.len());
+                let at = Duration::from_millis(ms[..end].parse()?);
+                // A pasted `"key":` is escaped, so it can't be taken for
+                // the field; `"paste":` never turns up in a key's line.
+                let input = match field(line, "paste") {
+                    Ok(text) => Input::Paste(string(text)?),
+                    Err(_) => Input::Key(parse_key(&string(field(line, "key")?)?)?),
+                };
+                Ok(Event { at, input })
+            })();
+            session
+                .events
+                .push(event.with_context(|| format!("line {}", n + 1))?);
+        }
+        Ok(session)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("read {path:?}"))?;
+        Self::parse(&text).with_context(|| format!("{}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()).with_context(|| format!("write {path:?}"))
+    }
+}
+
+/// What follows `"name":` in a one-line object.
+fn field<'a>(line: &'a str, name: &str) -> Result<&'a str> {
+    let key = format!("\"{name}\":");
+    let at = line
+        .find(&key)
+        .ok_or_else(|| anyhow!("no \"{name}\" in {line}"))?;
+    Ok(line[at + key.len()..].trim_start())
+}
+
+/// The JSON string `s` starts with, unescaped.
+fn string(s: &str) -> Result<String> {
+    let mut chars = s
+        .strip_prefix('"')
+        .ok_or_else(|| anyhow!("expected a string"))?
+        .chars();
+    let mut out = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(out),
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| anyhow!("bad escape \\u{hex}"))?;
+                    out.push(c);
+                }
+                Some(c) => out.push(c),
+                None => break,
+            },
+            c => out.push(c),
+        }
+    }
+    bail!("unterminated string")
+}
+
+/// Collects the keys and pastes of a session as they come in.
+pub struct Recorder {
+    start: Instant,
+    session: Session,
+}
+
+impl Recorder {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        Self {
+            start: Instant::now(),
+            session: Session {
+                file,
+                events: Vec::new(),
+            },
+        }
+    }
+
+    pub fn key(&mut self, key: KeyEvent) {
+        self.push(Input::Key(key));
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.push(Input::Paste(text.to_string()));
+    }
+
+    pub fn push(&mut self, input: Input) {
+        self.session.events.push(Event {
+            at: self.start.elapsed(),
+            input,
+        });
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed every event of `session` to `app` at once, without a terminal;
+    /// `true` if they quit the editor.
+    fn replay(app: &mut App, session: &Session) -> Result<bool> {
+        for e in &session.events {
+            if e.input.apply(app)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    #[test]
+    fn round_trips_key_names_and_sessions() -> Result<()> {
+        for name in [
+            "j", "G", "C-d", "S-Up", "C-A-x", "BackTab", "F5", "-", "C--", " ", "\"",
+        ] {
+            assert_eq!(key_name(&parse_key(name)?).as_deref(), Some(name));
+        }
+        assert!(parse_key("Hyper").is_err());
+        let session = Session {
+            file: Some(PathBuf::from("my \"data\".csv")),
+            events: vec![
+                Event {
+                    at: Duration::from_millis(0),
+                    input: Input::Key(parse_key("e")?),
+                },
+                Event {
+                    at: Duration::from_millis(1250),
+                    input: Input::Key(parse_key("\\")?),
+                },
+            ],
+        };
+        let json = session.to_json();
+        assert_eq!(
+            json,
+            "{\"file\": \"my \\\"data\\\".csv\", \"events\": [\n  {\"ms\": 0, \"key\": \"e\"},\n  {\"ms\": 1250, \"key\": \"\\\\\"}\n]}\n"
+        );
+        assert_eq!(Session::parse(&json)?, session);
+        Ok(())
+    }
+
+    #[test]
+    fn replays_keys_to_the_same_state() -> Result<()> {
+        let data = vec![vec!["a".to_string(), "b".to_string()]];
+        let mut session = Session::default();
+        for k in ["l", "e", "!", "Enter", "q"] {
+            session.events.push(Event {
+                at: Duration::ZERO,
+                input: Input::Key(parse_key(k)?),
+            });
+        }
+        let session = Session::parse(&session.to_json())?;
+        let mut app = App::new(PathBuf::new(), data);
+        // Unsaved, so `q` does not quit.
+        assert!(!replay(&mut app, &session)?);
+        assert_eq!(app.data[0][1], "b!");
+        assert_eq!(app.col, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn records_and_replays_pastes() -> Result<()> {
+        let data = vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["c".to_string(), "d".to_string()],
+        ];
+        let mut recorder = Recorder::new(None);
+        recorder.key(parse_key("j")?);
+        recorder.paste("x\t\"key\": 1\ny\tz");
+        recorder.key(parse_key("y")?);
+        let json = recorder.session().to_json();
+        assert!(json.contains(r#""paste": "x\t\"key\": 1\ny\tz"}"#));
+        let session = Session::parse(&json)?;
+        let inputs = |s: &Session| s.events.iter().map(|e| e.input.clone()).collect::<Vec<_>>();
+        assert_eq!(inputs(&session), inputs(recorder.session()));
+
+        let mut live = App::new(PathBuf::new(), data.clone());
+        handle_key(&mut live, parse_key("j")?)?;
+        handle_paste(&mut live, "x\t\"key\": 1\ny\tz");
+        handle_key(&mut live, parse_key("y")?)?;
+        let mut app = App::new(PathBuf::new(), data);
+        assert!(!replay(&mut app, &session)?);
+        assert_eq!(app.data, live.data);
+        assert_eq!(
+            (app.data[1][0].as_str(), &app.data[2]),
+            ("x", &vec!["y".to_string(), "z".to_string()])
+        );
+        Ok(())
+    }
+}
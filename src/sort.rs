@@ -0,0 +1,263 @@
+use std::cmp::Ordering;
+
+use anyhow::{Result, anyhow};
+
+use crate::command;
+use crate::viz::Decimal;
+
+/// How text values are compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// Byte-wise, so `B` < `a` and `file10` < `file2`.
+    #[default]
+    Plain,
+    /// Digit runs compare by value: `file2` < `file10`.
+    Natural,
+    /// Dictionary order: case and accents are ignored first, so `apple`,
+    /// `Äpfel` and `Banana` sort together as a reader expects. This
+    /// approximates locale collation without per-language rules.
+    Locale,
+}
+
+/// One column of a `:sort` specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub col: usize,
+    pub desc: bool,
+    pub collation: Collation,
+}
+
+/// Parse `2 asc, 5 desc nat` (1-based columns). Each key may be followed by
+/// a direction (`asc`, the default, or `desc`) and a collation (`natural` /
+/// `nat`, or `locale`), in any order.
+pub fn parse_keys(spec: &str) -> Result<Vec<SortKey>> {
+    let keys = spec
+        .split(',')
+        .map(|part| {
+            let mut words = part.split_whitespace();
+            let col = command::parse_column(words.next().unwrap_or(""))?;
+            let mut key = SortKey {
+                col,
+                desc: false,
+                collation: Collation::Plain,
+            };
+            for word in words {
+                match word.to_ascii_lowercase().as_str() {
+                    "asc" => key.desc = false,
+                    "desc" => key.desc = true,
+                    "nat" | "natural" => key.collation = Collation::Natural,
+                    "locale" => key.collation = Collation::Locale,
+                    other => return Err(anyhow!("unknown sort option {other:?}")),
+                }
+            }
+            Ok(key)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(keys)
+}
+
+/// Compare digit runs by value and everything else character by character.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ord = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (da, ra) = split_digits(a);
+            let (db, rb) = split_digits(b);
+            (a, b) = (ra, rb);
+            let (za, zb) = (da.trim_start_matches('0'), db.trim_start_matches('0'));
+            // Equal values: fewer leading zeros first, so the order is total.
+            za.len()
+                .cmp(&zb.len())
+                .then_with(|| za.cmp(zb))
+                .then_with(|| da.len().cmp(&db.len()))
+        } else {
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+            ca.cmp(&cb)
+        };
+        if ord.is_ne() {
+            return ord;
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// Lowercase with common Latin diacritics removed.
+fn fold(s: &str) -> String {
+    s.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+            'ç' | 'ć' | 'č' => 'c',
+            'ď' | 'đ' => 'd',
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => 'i',
+            'ł' => 'l',
+            'ñ' | 'ń' | 'ň' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+            'ŕ' | 'ř' => 'r',
+            'ś' | 'š' | 'ş' => 's',
+            'ť' | 'ţ' => 't',
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ź' | 'ż' | 'ž' => 'z',
+            c => c,
+        })
+        .collect()
+}
+
+pub fn collate(a: &str, b: &str, collation: Collation) -> Ordering {
+    match collation {
+        Collation::Plain => a.cmp(b),
+        Collation::Natural => natural_cmp(a, b),
+        // Ties on the folded form fall back to the exact text.
+        Collation::Locale => fold(a).cmp(&fold(b)).then_with(|| a.cmp(b)),
+    }
+}
+
+/// Numbers sort before text, which sorts before blanks.
+#[derive(Debug, PartialEq)]
+enum Value<'a> {
+    Number(f64),
+    Text(&'a str),
+    Blank,
+}
+
+fn value<'a>(cell: &'a str, numeric: bool, decimal: Decimal) -> Value<'a> {
+    if cell.trim().is_empty() {
+        Value::Blank
+    } else if numeric && let Some(n) = decimal.parse(cell) {
+        Value::Number(n)
+    } else {
+        Value::Text(cell)
+    }
+}
+
+fn compare(a: &Value, b: &Value, collation: Collation) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Number(_) => 0,
+        Value::Text(_) => 1,
+        Value::Blank => 2,
+    };
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+        (Value::Text(x), Value::Text(y)) => collate(x, y, collation),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Whether `col` should compare as numbers: at least half of its non-blank
+/// cells are numeric, so a header row doesn't turn it into text.
+fn is_numeric(data: &[Vec<String>], col: usize, decimal: Decimal) -> bool {
+    let (mut filled, mut numbers) = (0, 0);
+    for cell in data.iter().filter_map(|r| r.get(col)) {
+        if !cell.trim().is_empty() {
+            filled += 1;
+            numbers += usize::from(decimal.parse(cell).is_some());
+        }
+    }
+    numbers > 0 && numbers * 2 >= filled
+}
+
+/// The new row order (old indices) for sorting `data` by `keys`. The sort is
+/// stable: rows equal on every key keep their current relative order.
+/// Blanks stay last whatever the direction.
+pub fn order(data: &[Vec<String>], keys: &[SortKey], decimal: Decimal) -> Vec<usize> {
+    let numeric: Vec<bool> = keys
+        .iter()
+        .map(|k| is_numeric(data, k.col, decimal))
+        .collect();
+    let cell = |r: usize, c: usize| data[r].get(c).map_or("", String::as_str);
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .zip(&numeric)
+            .map(|(k, &num)| {
+                let (va, vb) = (
+                    value(cell(a, k.col), num, decimal),
+                    value(cell(b, k.col), num, decimal),
+                );
+                let ord = compare(&va, &vb, k.collation);
+                let blank = va == Value::Blank || vb == Value::Blank;
+                if k.desc && !blank { ord.reverse() } else { ord }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    fn key(col: usize, desc: bool) -> SortKey {
+        SortKey {
+            col,
+            desc,
+            collation: Collation::Plain,
+        }
+    }
+
+    #[test]
+    fn parses_multi_key_specs() -> Result<()> {
+        assert_eq!(
+            parse_keys("2 asc, 5 DESC,1")?,
+            [key(1, false), key(4, true), key(0, false)]
+        );
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("2 up").is_err());
+        assert_eq!(
+            parse_keys("3 nat desc")?,
+            [SortKey {
+                collation: Collation::Natural,
+                ..key(2, true)
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn natural_and_locale_collation() {
+        let mut names = ["file10", "file2", "File1", "file02", "file1b"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["File1", "file1b", "file2", "file02", "file10"]);
+
+        let mut words = ["banana", "Éclair", "apple", "eclair", "Apple"];
+        words.sort_by(|a, b| collate(a, b, Collation::Locale));
+        assert_eq!(words, ["Apple", "apple", "banana", "eclair", "Éclair"]);
+    }
+
+    #[test]
+    fn sorts_by_type_and_keeps_ties_in_order() {
+        let data = grid(&[
+            &["b", "10"],
+            &["a", "9"],
+            &["b", ""],
+            &["a", "10"],
+            &["b", "9"],
+        ]);
+        // Numeric: 9 < 10, and the blank goes last even descending.
+        assert_eq!(order(&data, &[key(1, true)], Decimal::Dot), [0, 3, 1, 4, 2]);
+        assert_eq!(
+            order(&data, &[key(0, false), key(1, false)], Decimal::Dot),
+            [1, 3, 4, 0, 2]
+        );
+        // Stable: equal keys keep their relative order.
+        assert_eq!(
+            order(&data, &[key(0, false)], Decimal::Dot),
+            [1, 3, 0, 2, 4]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+/// A second, side-by-side view of the same table. The app's cursor belongs
+/// to the focused pane; this keeps the other one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    /// Cursor of the unfocused pane.
+    pub other: (usize, usize),
+    /// Whether the focused pane is the right one.
+    pub right_focused: bool,
+    /// Scroll lock: the unfocused pane's row stays this far from the
+    /// focused one.
+    pub lock: Option<isize>,
+}
+
+impl Split {
+    pub fn new(cursor: (usize, usize)) -> Self {
+        Self {
+            other: cursor,
+            right_focused: false,
+            lock: None,
+        }
+    }
+
+    /// Where the unfocused pane's cursor is, given the focused one's.
+    pub fn other_cursor(&self, active: (usize, usize), rows: usize) -> (usize, usize) {
+        let last = rows.saturating_sub(1);
+        let row = match self.lock {
+            Some(delta) => (active.0 as isize + delta).clamp(0, last as isize) as usize,
+            None => self.other.0.min(last),
+        };
+        (row, self.other.1)
+    }
+
+    /// Lock scrolling at the panes' current distance, or unlock it where
+    /// they are now. Returns whether the lock is on.
+    pub fn toggle_lock(&mut self, active: (usize, usize), rows: usize) -> bool {
+        if self.lock.is_some() {
+            self.other = self.other_cursor(active, rows);
+            self.lock = None;
+        } else {
+            self.lock = Some(self.other.0.min(rows.saturating_sub(1)) as isize - active.0 as isize);
+        }
+        self.lock.is_some()
+    }
+
+    /// Move focus to the other pane; `active` becomes its cursor.
+    pub fn switch(&mut self, active: &mut (usize, usize), rows: usize) {
+        let other = self.other_cursor(*active, rows);
+        self.other = *active;
+        *active = other;
+        self.right_focused = !self.right_focused;
+        if let Some(delta) = &mut self.lock {
+            *delta = -*delta;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_panes_keep_their_distance() {
+        let mut split = Split::new((0, 0));
+        split.other = (40, 1);
+        assert!(split.toggle_lock((10, 0), 100));
+        assert_eq!(split.other_cursor((11, 0), 100), (41, 1));
+        // Clamped at the end, but the distance is kept for later.
+        assert_eq!(split.other_cursor((90, 0), 100), (99, 1));
+        assert_eq!(split.other_cursor((20, 0), 100), (50, 1));
+
+        let mut active = (20, 0);
+        split.switch(&mut active, 100);
+        assert_eq!(active, (50, 1));
+        assert!(split.right_focused);
+        assert_eq!(split.other_cursor((49, 1), 100), (19, 0));
+
+        assert!(!split.toggle_lock((60, 1), 100));
+        assert_eq!(split.other_cursor((0, 1), 100), (30, 0));
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{Result, bail};
+
+/// What the status line says by default; `status_format` in the config
+/// replaces it.
+pub const DEFAULT: &str = "File: {file}{flags} | Pos: {cell} (row {row}, col {col}) | Dirty: {dirty}\
+    [ | Sel: {selection}][ | Cols: {columns}][ | Tagged: {tagged}][ | View: {filter}]\
+    [ | Viz: {viz}][ | Keys: {keys}]";
+
+/// A value the status line can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    File,
+    /// `[read-only]`, `[escaped]` and the data-entry direction, when set.
+    Flags,
+    /// `ROWSxCOLS`.
+    Dims,
+    Rows,
+    Cols,
+    /// The cursor in A1 form.
+    Cell,
+    Row,
+    Col,
+    /// `yes` or `no`.
+    Dirty,
+    /// `modified` with unsaved changes, otherwise blank.
+    Modified,
+    /// `utf-8`, `utf-8 (escaped)`, plus the compression.
+    Encoding,
+    /// The view's sort and filter.
+    Filter,
+    /// `normal`, `edit`, `select`, `command` or `search`.
+    Mode,
+    /// The selection's size and the sum, average and so on of its numbers.
+    Selection,
+    /// How many columns are hidden and pinned.
+    Columns,
+    Tagged,
+    Viz,
+    /// A key sequence typed so far.
+    Keys,
+}
+
+const FIELDS: [(&str, Field); 18] = [
+    ("file", Field::File),
+    ("flags", Field::Flags),
+    ("dims", Field::Dims),
+    ("rows", Field::Rows),
+    ("cols", Field::Cols),
+    ("cell", Field::Cell),
+    ("row", Field::Row),
+    ("col", Field::Col),
+    ("dirty", Field::Dirty),
+    ("modified", Field::Modified),
+    ("encoding", Field::Encoding),
+    ("filter", Field::Filter),
+    ("mode", Field::Mode),
+    ("selection", Field::Selection),
+    ("columns", Field::Columns),
+    ("tagged", Field::Tagged),
+    ("viz", Field::Viz),
+    ("keys", Field::Keys),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Field),
+    /// `[...]`: shown only when none of its fields are blank.
+    Group(Vec<Part>),
+}
+
+/// A parsed `status_format`: text with `{field}` placeholders, and
+/// `[...]` around the parts to leave out when their fields are blank.
+/// `{{`, `}}`, `[[` and `]]` are literal braces and brackets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template(Vec<Part>);
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::parse(DEFAULT).unwrap_or(Self(Vec::new()))
+    }
+}
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut top = Vec::new();
+        let mut group: Option<Vec<Part>> = None;
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' | '[' | ']' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let Some(&(_, field)) = FIELDS.iter().find(|(n, _)| *n == name.trim()) else {
+                        let names: Vec<&str> = FIELDS.iter().map(|(n, _)| *n).collect();
+                        bail!("unknown field {{{name}}}; use {}", names.join(", "));
+                    };
+                    let parts = group.as_mut().unwrap_or(&mut top);
+                    flush(parts, &mut text);
+                    parts.push(Part::Field(field));
+                }
+                '[' if group.is_some() => bail!("[...] groups cannot nest"),
+                '[' => {
+                    flush(&mut top, &mut text);
+                    group = Some(Vec::new());
+                }
+                ']' => {
+                    let Some(mut parts) = group.take() else {
+                        bail!("unmatched ]");
+                    };
+                    flush(&mut parts, &mut text);
+                    top.push(Part::Group(parts));
+                }
+                '}' => bail!("unmatched }}"),
+                c => text.push(c),
+            }
+        }
+        if group.is_some() {
+            bail!("unclosed [");
+        }
+        flush(&mut top, &mut text);
+        Ok(Self(top))
+    }
+
+    /// The status line, with `value` giving each field's text.
+    pub fn render(&self, value: impl Fn(Field) -> String) -> String {
+        let mut out = String::new();
+        for part in &self.0 {
+            match part {
+                Part::Group(parts) => {
+                    let mut shown = String::new();
+                    for part in parts {
+                        match part {
+                            Part::Field(f) => match value(*f) {
+                                v if v.is_empty() => {
+                                    shown.clear();
+                                    break;
+                                }
+                                v => shown.push_str(&v),
+                            },
+                            Part::Text(t) => shown.push_str(t),
+                            Part::Group(_) => {}
+                        }
+                    }
+                    out.push_str(&shown);
+                }
+                Part::Field(f) => out.push_str(&value(*f)),
+                Part::Text(t) => out.push_str(t),
+            }
+        }
+        out
+    }
+}
+
+fn flush(parts: &mut Vec<Part>, text: &mut String) {
+    if !text.is_empty() {
+        parts.push(Part::Text(std::mem::take(text)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_fields_and_drops_groups_with_blanks() -> Result<()> {
+        let value = |f| match f {
+            Field::File => "a.csv".to_string(),
+            Field::Dims => "3x2".to_string(),
+            Field::Cell => "B2".to_string(),
+            _ => String::new(),
+        };
+        let t = Template::parse("{file} {{{dims}}}[ [[{cell}]]][ {mode}] ]]")?;
+        assert_eq!(t.render(value), "a.csv {3x2} [B2] ]");
+        assert!(Template::parse("{nope}").is_err());
+        assert!(Template::parse("[a [b]]").is_err());
+        assert!(Template::parse("[{file}").is_err());
+        assert_eq!(
+            Template::default().render(value),
+            "File: a.csv | Pos: B2 (row , col ) | Dirty: "
+        );
+        Ok(())
+    }
+}
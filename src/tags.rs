@@ -0,0 +1,134 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+
+/// What `:tagged <op>` does with the tagged rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bulk {
+    Delete,
+    Yank,
+    /// Write the rows (under the header row, in header mode) to a new file.
+    Export(PathBuf),
+    Clear,
+    /// Empty the rows' cells but keep the rows.
+    Empty,
+}
+
+pub fn parse_bulk(s: &str) -> Result<Bulk> {
+    let (op, arg) = s
+        .split_once(char::is_whitespace)
+        .map_or((s, ""), |(o, a)| (o, a.trim()));
+    match (op, arg) {
+        ("d" | "delete", "") => Ok(Bulk::Delete),
+        ("y" | "yank", "") => Ok(Bulk::Yank),
+        ("clear", "") => Ok(Bulk::Clear),
+        ("empty", "") => Ok(Bulk::Empty),
+        ("export", path) if !path.is_empty() => Ok(Bulk::Export(PathBuf::from(path))),
+        _ => Err(anyhow!(
+            "usage: :tagged delete|empty|yank|clear|export <file>"
+        )),
+    }
+}
+
+/// Rows marked with `t`, by data index. Bulk commands act on all of them at
+/// once, however far apart they are; row edits shift the marks along. The
+/// column layout uses the same set to mark hidden and pinned columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags(BTreeSet<usize>);
+
+impl Tags {
+    /// Tag `row`, or untag it if it was tagged. Returns whether it is now.
+    pub fn toggle(&mut self, row: usize) -> bool {
+        if self.0.remove(&row) {
+            false
+        } else {
+            self.0.insert(row)
+        }
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.0.contains(&row)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Tagged rows, top to bottom.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Follow the deletion of `count` indices from `row`.
+    pub fn delete(&mut self, row: usize, count: usize) {
+        let end = row.saturating_add(count);
+        self.0 = self
+            .0
+            .iter()
+            .filter(|&&r| !(row..end).contains(&r))
+            .map(|&r| if r >= end { r - count } else { r })
+            .collect();
+    }
+
+    /// Follow an insertion before `row`.
+    pub fn insert(&mut self, row: usize) {
+        self.0 = self
+            .0
+            .iter()
+            .map(|&r| if r >= row { r + 1 } else { r })
+            .collect();
+    }
+
+    /// Follow a reorder where new row `i` is old row `order[i]`.
+    pub fn reorder(&mut self, order: &[usize]) {
+        self.0 = order
+            .iter()
+            .enumerate()
+            .filter(|&(_, old)| self.0.contains(old))
+            .map(|(new, _)| new)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_follow_row_edits() {
+        let mut tags = Tags::default();
+        assert!(tags.toggle(1));
+        assert!(tags.toggle(4));
+        assert!(tags.toggle(6));
+        assert!(!tags.toggle(6));
+
+        tags.insert(2);
+        assert_eq!(tags.rows().collect::<Vec<_>>(), [1, 5]);
+        tags.delete(0, 2);
+        assert_eq!(tags.rows().collect::<Vec<_>>(), [3]);
+        tags.reorder(&[3, 0, 1, 2]);
+        assert_eq!(tags.rows().collect::<Vec<_>>(), [0]);
+        assert_eq!(tags.len(), 1);
+    }
+
+    #[test]
+    fn parses_bulk_ops() -> Result<()> {
+        assert_eq!(parse_bulk("d")?, Bulk::Delete);
+        assert_eq!(
+            parse_bulk("export out/picked.csv")?,
+            Bulk::Export(PathBuf::from("out/picked.csv"))
+        );
+        assert!(parse_bulk("export").is_err());
+        assert!(parse_bulk("delete now").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::{self, Write};
+use std::panic;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::execute;
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode,
+};
+
+/// xterm's title stack: save the title the shell had, and bring it back.
+/// Terminals without one ignore both.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Raw mode + alternate screen for the lifetime of the guard.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        setup_terminal()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn setup_terminal() -> Result<()> {
+    enable_raw_mode().context("enable raw mode")?;
+    execute!(io::stdout(), EnterAlternateScreen).context("enter alt screen")?;
+    // Pasted text then comes as one event instead of keys that would run
+    // as commands.
+    let _ = execute!(io::stdout(), EnableBracketedPaste);
+    print!("{PUSH_TITLE}");
+    Ok(())
+}
+
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    print!("{POP_TITLE}");
+    let _ = execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen);
+}
+
+/// `data.csv [+] - tui-csv-editor`: the file name, and a mark while there
+/// are unsaved changes, so the tab running the editor can be told apart.
+pub fn window_title(name: &str, dirty: bool) -> String {
+    let name = Path::new(name)
+        .file_name()
+        .map_or(name.to_string(), |n| n.to_string_lossy().into_owned());
+    let name = if name.is_empty() { "[No Name]" } else { &name };
+    let mark = if dirty { " [+]" } else { "" };
+    format!("{name}{mark} - {}", env!("CARGO_PKG_NAME"))
+}
+
+pub fn set_title(title: &str) -> Result<()> {
+    execute!(io::stdout(), SetTitle(title)).context("set window title")
+}
+
+/// OSC 7, telling the terminal the working directory is `dir`, so a new
+/// tab or split opens next to the file. The path is percent-encoded.
+pub fn osc7(dir: &Path, host: &str) -> String {
+    let mut path = String::new();
+    for b in dir.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            path.push(char::from(b));
+        } else {
+            path.push_str(&format!("%{b:02X}"));
+        }
+    }
+    format!("\x1b]7;file://{host}{path}\x1b\\")
+}
+
+/// Report `dir` as the working directory with [`osc7`].
+pub fn report_cwd(dir: &Path) -> Result<()> {
+    let host = fs::read_to_string("/etc/hostname").unwrap_or_default();
+    let mut out = io::stdout();
+    write!(out, "{}", osc7(dir, host.trim()))?;
+    out.flush().context("report working directory")
+}
+
+/// Give the terminal to `f` (e.g. a child editor) and take it back after.
+pub fn with_tui_suspended<T>(f: impl FnOnce() -> T) -> Result<T> {
+    restore_terminal();
+    let out = f();
+    setup_terminal()?;
+    Ok(out)
+}
+
+/// Restore the terminal before the default hook prints, so the panic message
+/// lands on the normal screen instead of a raw-mode alternate screen.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+}
+
+/// Set when a SIGTSTP arrives from outside (e.g. `kill -TSTP`). Ctrl+Z itself
+/// reaches us as a key event because raw mode disables ISIG.
+pub fn suspend_flag() -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    signal_hook::flag::register(signal_hook::consts::SIGTSTP, Arc::clone(&flag))
+        .context("register SIGTSTP handler")?;
+    Ok(flag)
+}
+
+/// Leave the TUI, stop the process like a default SIGTSTP would, and set the
+/// terminal back up once the shell resumes us with SIGCONT (`fg`).
+pub fn suspend(flag: &AtomicBool) -> Result<()> {
+    flag.store(false, Ordering::SeqCst);
+    restore_terminal();
+    #[cfg(unix)]
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)
+        .context("suspend process")?;
+    setup_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_and_reports_the_directory() {
+        assert_eq!(
+            window_title("/data/q1 sales.csv", true),
+            "q1 sales.csv [+] - tui-csv-editor"
+        );
+        assert_eq!(window_title("", false), "[No Name] - tui-csv-editor");
+        assert_eq!(
+            osc7(Path::new("/data/q1 sales"), "box"),
+            "\x1b]7;file://box/data/q1%20sales\x1b\\"
+        );
+    }
+}
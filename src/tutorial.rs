@@ -0,0 +1,184 @@
+//! `--tutorial`: a sample file and a box of instructions that moves on as
+//! each one is carried out, through moving, editing, saving, searching and
+//! undoing. The keys named are the ones bound in the current key map.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::app::App;
+use crate::input::Action;
+
+const SAMPLE: &str =
+    "name,city,age\nAlice,Tokyo,34\nBob,Osaka,27\nCarol,Kyoto,41\nDave,Nagoya,19\n";
+
+/// Bob's age, the cell the tutorial edits and then brings back.
+const TARGET: (usize, usize) = (2, 2);
+const TARGET_VALUE: &str = "27";
+/// Where the search for Carol lands.
+const CAROL: (usize, usize) = (3, 0);
+
+/// Write a fresh copy of the sample to a directory of its own under the
+/// temp dir, so a journal or lock left by an earlier run can't get in the way.
+pub fn sample_file() -> Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("{}-tutorial", env!("CARGO_PKG_NAME")));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).with_context(|| format!("create {dir:?}"))?;
+    let path = dir.join("people.csv");
+    fs::write(&path, SAMPLE).with_context(|| format!("write {path:?}"))?;
+    Ok(path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Move,
+    Edit,
+    Save,
+    Search,
+    Undo,
+    Done,
+}
+
+const STEPS: [Step; 5] = [Step::Move, Step::Edit, Step::Save, Step::Search, Step::Undo];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tutorial {
+    step: Step,
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self { step: Step::Move }
+    }
+}
+
+/// The key bound to `action`, preferring a letter to an arrow.
+fn key(app: &App, action: Action) -> String {
+    let keys = app.keymap.keys_for(action);
+    keys.iter()
+        .find(|k| !k.starts_with('<'))
+        .or(keys.first())
+        .cloned()
+        .unwrap_or_else(|| format!(":{}", action.name()))
+}
+
+impl Tutorial {
+    /// `Tutorial 2/5`, or just `Tutorial` at the end.
+    pub fn title(&self) -> String {
+        match STEPS.iter().position(|&s| s == self.step) {
+            Some(i) => format!("Tutorial {}/{}", i + 1, STEPS.len()),
+            None => "Tutorial".to_string(),
+        }
+    }
+
+    pub fn text(&self, app: &App) -> String {
+        match self.step {
+            Step::Move => format!(
+                "Move to Bob's age (C3) with {} {} {} {} or the arrow keys.",
+                key(app, Action::MoveLeft),
+                key(app, Action::MoveDown),
+                key(app, Action::MoveUp),
+                key(app, Action::MoveRight)
+            ),
+            Step::Edit => format!(
+                "Press {} to edit the cell, type a new age and press Enter (Esc cancels).",
+                key(app, Action::Edit)
+            ),
+            Step::Save => format!(
+                "The cell is marked as changed. Press {} to save the file.",
+                key(app, Action::Write)
+            ),
+            Step::Search => format!(
+                "Press {}, type carol and press Enter to jump to her row.",
+                key(app, Action::Search)
+            ),
+            Step::Undo => format!(
+                "Press {} to undo the change to Bob's age.",
+                key(app, Action::Undo)
+            ),
+            Step::Done => format!(
+                "That's the basics. {} lists every key and :q quits. Press any key to close this.",
+                key(app, Action::Help)
+            ),
+        }
+    }
+
+    /// Move on once the current step shows in `app`, after a key; `false`
+    /// once the last box has been seen and should go.
+    pub fn update(&mut self, app: &App) -> bool {
+        let at = (app.row, app.col);
+        let target = app
+            .data
+            .get(TARGET.0)
+            .and_then(|r| r.get(TARGET.1))
+            .map(String::as_str);
+        let done = match self.step {
+            Step::Move => at == TARGET,
+            Step::Edit => !app.editing && target != Some(TARGET_VALUE),
+            Step::Save => !app.dirty,
+            Step::Search => app.prompt.is_none() && at == CAROL,
+            Step::Undo => target == Some(TARGET_VALUE),
+            Step::Done => return false,
+        };
+        if done {
+            self.step = match self.step {
+                Step::Move => Step::Edit,
+                Step::Edit => Step::Save,
+                Step::Save => Step::Search,
+                Step::Search => Step::Undo,
+                Step::Undo | Step::Done => Step::Done,
+            };
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::handle_key;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn moves_on_as_each_step_is_done() -> Result<()> {
+        let mut app = App {
+            tutorial: Some(Tutorial::default()),
+            ..App::default()
+        };
+        app.open(sample_file()?)?;
+        let type_keys = |app: &mut App, keys: &str| -> Result<()> {
+            for c in keys.chars() {
+                let code = match c {
+                    '\n' => KeyCode::Enter,
+                    c => KeyCode::Char(c),
+                };
+                handle_key(app, KeyEvent::new(code, KeyModifiers::NONE))?;
+            }
+            Ok(())
+        };
+        let title = |app: &App| app.tutorial.as_ref().map(Tutorial::title);
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 1/5"));
+        type_keys(&mut app, "j")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 1/5"));
+        type_keys(&mut app, "jll")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 2/5"));
+        type_keys(&mut app, "cc30\n")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 3/5"));
+        type_keys(&mut app, "w")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 4/5"));
+        type_keys(&mut app, "/carol\n")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial 5/5"));
+        type_keys(&mut app, "u")?;
+        assert_eq!(title(&app).as_deref(), Some("Tutorial"));
+        assert!(
+            app.tutorial
+                .as_ref()
+                .is_some_and(|t| t.text(&app).contains("? lists"))
+        );
+        type_keys(&mut app, "k")?;
+        assert_eq!(app.tutorial, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Text typed into the editor and the prompts. Input methods hand over a
+//! composed word as a run of characters or a paste, Windows layouts type
+//! some letters with AltGr (Ctrl+Alt), and one character on screen can be
+//! several code points: a letter and its accents, an emoji with a skin tone,
+//! a family joined with zero-width joiners, a flag.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Whether `key` types its character: plain and shifted letters, and
+/// AltGr ones, but not Ctrl or Alt shortcuts.
+pub fn is_typed(key: &KeyEvent) -> bool {
+    let ctrl_alt = KeyModifiers::CONTROL | KeyModifiers::ALT;
+    let mods = key.modifiers - KeyModifiers::SHIFT;
+    matches!(key.code, KeyCode::Char(_)) && (mods.is_empty() || mods == ctrl_alt)
+}
+
+/// Pasted text for a one-line buffer: line breaks become spaces.
+pub fn one_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(['\r', '\n'], " ")
+}
+
+/// Remove the last character as it is seen, with its marks, for Backspace.
+pub fn pop_grapheme(s: &mut String) {
+    // Walk forwards: the reverse cursor merges Indic conjuncts with the
+    // letters before them.
+    if let Some(i) = s.grapheme_indices(true).map(|(i, _)| i).max() {
+        s.truncate(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_altgr_letters_and_deletes_whole_graphemes() {
+        let key = |c, m| KeyEvent::new(KeyCode::Char(c), m);
+        assert!(is_typed(&key('é', KeyModifiers::NONE)));
+        assert!(is_typed(&key('E', KeyModifiers::SHIFT)));
+        assert!(is_typed(&key(
+            '€',
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )));
+        assert!(!is_typed(&key('s', KeyModifiers::CONTROL)));
+        assert!(!is_typed(&key('x', KeyModifiers::ALT)));
+
+        for (before, after) in [
+            ("cafe\u{301}", "caf"),
+            ("ok👍🏽", "ok"),
+            ("a👨\u{200D}👩\u{200D}👧", "a"),
+            ("🇯🇵🇫🇷", "🇯🇵"),
+            ("が", ""),
+            ("か\u{3099}", ""),
+            ("x\r\n", "x"),
+            ("नमस्ते", "नम"),
+            ("தமிழ்", "தமி"),
+            ("\u{1100}\u{1161}\u{11A8}", ""),
+            ("ক\u{09CE}", "ক"),
+            ("", ""),
+        ] {
+            let mut s = before.to_string();
+            pop_grapheme(&mut s);
+            assert_eq!(s, after, "{before:?}");
+        }
+        assert_eq!(one_line("a\r\nb\nc"), "a b c");
+    }
+}
@@ -0,0 +1,1777 @@
+use anyhow::Result;
+use ratatui::Frame as TuiFrame;
+use ratatui::Terminal;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, Paragraph, Row, Sparkline, Table, TableState, Wrap,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::a1;
+use crate::a11y;
+use crate::app::{App, Perf, RowCompare};
+use crate::badge;
+use crate::bidi;
+use crate::clipboard::PasteOptions;
+use crate::columns::Manager;
+use crate::command::{self, Command};
+use crate::complete;
+use crate::compress::Codec;
+use crate::config::{Advance, Theme};
+use crate::escape;
+use crate::export;
+use crate::filters;
+use crate::findings::Findings;
+use crate::fuzzy::FuzzyView;
+use crate::help;
+use crate::money;
+use crate::palette::Caps;
+use crate::picker::{self, EntryKind, Picker};
+use crate::prompt::PromptKind;
+use crate::replace;
+use crate::statusline::Field;
+use crate::tutorial::Tutorial;
+use crate::view::{self, Item};
+use crate::viz::{self, Series, Summary, VizMode};
+
+pub fn draw_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &App) -> Result<()> {
+    terminal.draw(|f| {
+        let panel_h = if app.findings.is_some() { 8 } else { 0 };
+        let bar_h = u16::from(app.config.formula_bar);
+        let outer = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(bar_h), Constraint::Min(3)])
+            .split(f.area());
+        if app.config.formula_bar {
+            draw_formula_bar(f, outer[0], app);
+        }
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),          // table
+                Constraint::Length(panel_h), // findings panel
+                Constraint::Length(3),       // status/help
+                Constraint::Length(3),       // editor / message line
+            ])
+            .split(outer[1]);
+
+        match &app.split {
+            None => draw_table(f, chunks[0], app, (app.row, app.col), true, "CSV Viewer"),
+            Some(split) => {
+                let panes = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(chunks[0]);
+                let title = if split.lock.is_some() {
+                    "CSV Viewer [scroll lock]"
+                } else {
+                    "CSV Viewer"
+                };
+                let (focused, other) = if split.right_focused {
+                    (panes[1], panes[0])
+                } else {
+                    (panes[0], panes[1])
+                };
+                let cursor = split.other_cursor((app.row, app.col), app.data.len());
+                draw_table(f, focused, app, (app.row, app.col), true, title);
+                draw_table(f, other, app, cursor, false, title);
+            }
+        }
+        if let Some(panel) = &app.findings {
+            draw_findings(f, chunks[1], panel);
+        }
+        draw_status(f, chunks[2], app);
+        draw_editor(f, chunks[3], app);
+        if let Some(p) = &app.prompt
+            && p.kind == PromptKind::Command
+        {
+            draw_replace_preview(f, chunks[0], app, &p.buf);
+            draw_currency_preview(f, chunks[0], app, &p.buf);
+            draw_filter_preview(f, chunks[0], app, &p.buf);
+        }
+        if let Some(review) = &app.review {
+            draw_review(f, chunks[0], app, review);
+        }
+        if let Some(view) = &app.help {
+            draw_help(f, f.area(), app, view);
+        }
+        if app.compare {
+            draw_compare(f, f.area(), app);
+        }
+        if let Some(scroll) = app.cell_view {
+            draw_cell_view(f, f.area(), app, scroll);
+        }
+        if app.record_card {
+            draw_record_card(f, f.area(), app);
+        }
+        if let Some(cmp) = &app.row_compare {
+            draw_row_compare(f, f.area(), app, cmp);
+        }
+        if let Some(d) = &app.export_dialog {
+            draw_export_dialog(f, f.area(), app, d);
+        }
+        if let Some(series) = &app.chart {
+            draw_chart(f, f.area(), series);
+        }
+        if let Some(view) = &app.fuzzy {
+            draw_fuzzy(f, f.area(), app, view);
+        }
+        if let Some(opts) = &app.paste_special {
+            draw_paste_special(f, f.area(), opts);
+        }
+        if let Some(m) = &app.column_manager {
+            draw_column_manager(f, f.area(), m);
+        }
+        if let Some(panel) = &app.filter_panel {
+            draw_filter_panel(f, f.area(), app, panel);
+        }
+        if let Some(picker) = &app.recent {
+            let title = format!("{} (Enter: open  Esc: close)", picker.title);
+            render_picker(f, centered(f.area(), 80, 60), picker, &title);
+        }
+        if let Some(perf) = &app.perf {
+            draw_perf(f, f.area(), perf);
+        }
+        if let Some(t) = &app.tutorial {
+            draw_tutorial(f, chunks[0], app, t);
+        }
+        app.caps.adapt(f.buffer_mut());
+    })?;
+    Ok(())
+}
+
+/// One view of the table with its cursor at `cursor`; only the focused
+/// pane's cursor is highlighted brightly.
+fn draw_table(
+    f: &mut TuiFrame,
+    area: Rect,
+    app: &App,
+    cursor: (usize, usize),
+    focused: bool,
+    title: &str,
+) {
+    let rows_len = app.data.len();
+    let cols_len = app.max_cols();
+    let max_len = app.config.max_cell_len;
+    let cols = cols_len.max(1);
+    let col_order = app.column_order();
+    let ranges = if app.viz == VizMode::Off {
+        Vec::new()
+    } else {
+        app.column_ranges()
+    };
+
+    // A view shows some rows in its own order; positions below are on screen.
+    let total = app.view.as_ref().map_or(rows_len, |v| v.rows.len());
+    let row_at = |pos: usize| app.view.as_ref().map_or(pos, |v| v.rows[pos]);
+    let cursor_pos = app
+        .view
+        .as_ref()
+        .map_or(Some(cursor.0), |v| v.position(cursor.0));
+    // Only the rows that fit are built: the header, if pinned, and the
+    // window that scrolls the cursor row just into view from the top, the
+    // way a table without a kept offset would.
+    let pinned = usize::from(app.header && total > 0);
+    // Grouped, each run of rows has a header line above it, so lines and
+    // row positions part ways.
+    let group = app.view.as_ref().and_then(|v| v.group.as_ref());
+    let lines = total.saturating_sub(pinned) + group.map_or(0, |g| g.runs.len());
+    let item_at = |line: usize| match group {
+        Some(g) => g.item_at(line, pinned),
+        None => Item::Row(line + pinned),
+    };
+    let selected = cursor_pos
+        .filter(|&p| p >= pinned)
+        .map(|p| group.map_or(p - pinned, |g| g.line_of(p, pinned)));
+    let height = usize::from(area.height).saturating_sub(2 + pinned);
+    let offset = selected.map_or(0, |s| (s + 1).saturating_sub(height));
+    let body = offset.min(lines)..(offset + height).min(lines);
+    let mut rows = Vec::with_capacity(pinned + body.len());
+    for item in (0..pinned).map(Item::Row).chain(body.map(item_at)) {
+        let pos = match (item, group) {
+            (Item::Row(pos), _) => pos,
+            (Item::Run(k), Some(g)) => {
+                rows.push(group_header(app, &g.runs[k], g, col_order.len()));
+                continue;
+            }
+            (Item::Run(_), None) => continue,
+        };
+        let r_idx = row_at(pos);
+        let row = &app.data[r_idx];
+        let mut cells = Vec::with_capacity(cols);
+        for &c_idx in &col_order {
+            // A masked column is drawn, and checked for nulls and matches,
+            // as its mask.
+            let shown = app.masked(c_idx, row.get(c_idx).map_or("", String::as_str));
+            let txt = shown.as_ref();
+            // Cut before reordering, so a long cell keeps its logical start.
+            let mut label = reorder(app, escape::clipped(txt, max_len).into_owned()).0;
+            if app.config.screen_reader {
+                label = a11y::decorate(
+                    &label,
+                    focused && (r_idx, c_idx) == cursor,
+                    app.is_modified(r_idx, c_idx),
+                    app.tags.contains(r_idx) && col_order.first() == Some(&c_idx),
+                );
+            }
+            let scaled = ranges
+                .get(c_idx)
+                .copied()
+                .flatten()
+                .zip(app.config.decimal.parse(txt))
+                .map(|(range, v)| viz::scale(v, range));
+            let mut spans = match (app.viz, scaled) {
+                (VizMode::Bars, Some(t)) => vec![
+                    Span::styled(
+                        viz::bar(t, viz::BAR_WIDTH),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(format!(" {label}")),
+                ],
+                _ => vec![Span::raw(label)],
+            };
+            if app.config.badges {
+                let marks = badge::marks(txt);
+                if !marks.is_empty() {
+                    spans.push(Span::styled(
+                        format!(" {marks}"),
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::DIM),
+                    ));
+                }
+            }
+            let mut line = Line::from(spans);
+            if app.columns.rtl.contains(c_idx) {
+                line = line.alignment(Alignment::Right);
+            }
+            let mut cell = Cell::from(line);
+            if let (VizMode::Heatmap, Some(t)) = (app.viz, scaled) {
+                cell = cell.style(Style::default().fg(Color::Black).bg(viz::heat_color(t)));
+            }
+            if app.config.rainbow && !(app.viz == VizMode::Heatmap && scaled.is_some()) {
+                let fg = match app.config.theme {
+                    Theme::Dark => Color::White,
+                    Theme::Light => Color::Black,
+                };
+                let bg = viz::rainbow_color(c_idx, app.config.theme);
+                cell = cell.style(Style::default().fg(fg).bg(bg));
+            }
+            if app.tags.contains(r_idx) {
+                cell = cell.style(
+                    Style::default()
+                        .fg(Color::LightGreen)
+                        .add_modifier(Modifier::BOLD),
+                );
+            }
+            if !txt.is_empty() && app.config.nulls.is_null(txt) {
+                cell = cell.style(
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                );
+            }
+            if app.is_modified(r_idx, c_idx) {
+                cell = cell.style(Style::default().bg(Color::DarkGray));
+            }
+            if app.show_empty && txt.trim().is_empty() {
+                cell = cell.style(Style::default().bg(Color::Cyan));
+            }
+            if app
+                .live_search
+                .as_ref()
+                .is_some_and(|s| s.area.contains(r_idx, c_idx) && s.matches(txt))
+            {
+                cell = cell.style(Style::default().fg(Color::Black).bg(Color::LightMagenta));
+            }
+            if app.is_selected(r_idx, c_idx) {
+                cell = cell.style(Style::default().fg(Color::White).bg(Color::Blue));
+            }
+            if app
+                .findings
+                .as_ref()
+                .is_some_and(|p| p.contains(r_idx, c_idx))
+            {
+                cell = cell.style(Style::default().fg(Color::White).bg(Color::Red));
+            }
+            if (r_idx, c_idx) == cursor {
+                cell = cell.style(if focused {
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Black).bg(Color::Gray)
+                });
+            }
+            cells.push(cell);
+        }
+        rows.push(Row::new(cells));
+    }
+
+    // Construct basic constraints: at least 5 chars per column, more when a
+    // data bar has to fit in front of the value.
+    let constraints: Vec<Constraint> = col_order
+        .iter()
+        .map(|&c| match ranges.get(c) {
+            Some(Some(_)) if app.viz == VizMode::Bars => {
+                Constraint::Min(5 + viz::BAR_WIDTH as u16 + 1)
+            }
+            _ => Constraint::Min(5),
+        })
+        .collect();
+
+    // In header mode the first row stays pinned above the scrolling data.
+    let header = (pinned > 0).then(|| {
+        rows.remove(0)
+            .style(Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
+    });
+    let selected = selected.map(|s| s - offset);
+    let mut table = Table::new(rows, constraints)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .column_spacing(1);
+    if let Some(header) = header {
+        table = table.header(header);
+    }
+    // Selecting the cursor row makes the table scroll it into view.
+    let mut state = TableState::default().with_selected(selected);
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// A grouped run's header line: `▾ FAILED (12)`, or `▸` once collapsed, in
+/// the first column.
+fn group_header(app: &App, run: &view::Run, group: &view::Grouping, cols: usize) -> Row<'static> {
+    let mark = if group.collapsed.contains(&run.value) {
+        '▸'
+    } else {
+        '▾'
+    };
+    let value = escape::clipped(&app.masked(group.col, &run.value), 40).into_owned();
+    let mut cells = vec![Cell::from(format!("{mark} {value} ({})", run.len))];
+    cells.resize(cols.max(1), Cell::from(""));
+    Row::new(cells).style(
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+/// `B3 │ value` with the cell's stored text, spaces and all, as much of it
+/// as fits before its badges and a note when the table shows it cut short.
+fn draw_formula_bar(f: &mut TuiFrame, area: Rect, app: &App) {
+    let stored = app
+        .data
+        .get(app.row)
+        .and_then(|row| row.get(app.col))
+        .map_or("", String::as_str);
+    let shown = app.masked(app.col, stored);
+    let txt = shown.as_ref();
+    let label = format!("{} │ ", a1::cell_name(app.row, app.col));
+    // Only what the bar can show is escaped, reordered and checked for
+    // badges, so a cell of megabytes costs no more than a short one.
+    let width = usize::from(area.width);
+    let probe = chars_prefix(txt, width.max(badge::LONG + 1));
+    let mut suffix = Vec::new();
+    let badges: Vec<&str> = badge::of(probe).into_iter().map(|b| b.label()).collect();
+    if !badges.is_empty() {
+        suffix.push(Span::styled(
+            format!("  [{}]", badges.join(", ")),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if txt.len() > app.config.max_cell_len {
+        suffix.push(Span::styled(
+            format!("  ({} bytes; clipped in the table)", txt.len()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let taken = label.width() + suffix.iter().map(Span::width).sum::<usize>();
+    let room = width.saturating_sub(taken);
+    let head = chars_prefix(probe, room);
+    let mut value = reorder(app, escape::raw(head)).0;
+    if value.width() > room || head.len() < txt.len() {
+        value = value
+            .chars()
+            .scan(0, |w, c| {
+                *w += c.width().unwrap_or(0);
+                (*w < room).then_some(c)
+            })
+            .collect();
+        value.push('…');
+    }
+    let mut spans = vec![
+        Span::styled(label, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(value),
+    ];
+    spans.extend(suffix);
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// The first `n` characters of `s`.
+fn chars_prefix(s: &str, n: usize) -> &str {
+    s.char_indices().nth(n).map_or(s, |(i, _)| &s[..i])
+}
+
+fn draw_status(f: &mut TuiFrame, area: Rect, app: &App) {
+    let status = app
+        .config
+        .status_format
+        .render(|field| status_field(app, field));
+    let help = "Arrows/hjkl: move  gg/G: first/last row  e: edit  cc: change  dd: delete row  \
+                w: write  H: export HTML  ?: help  q: quit  (counts: 10j, 3dd)";
+    let text = vec![Line::raw(status), Line::raw(help)];
+    let p = Paragraph::new(text)
+        .block(Block::default().title("Status").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(p, area);
+}
+
+/// One value for the status line template.
+fn status_field(app: &App, field: Field) -> String {
+    match field {
+        Field::File => app
+            .remote
+            .as_ref()
+            .map_or(app.file_path.display().to_string(), |r| r.url().to_string()),
+        Field::Flags => format!(
+            "{}{}{}",
+            if app.read_only { " [read-only]" } else { "" },
+            if app.escaped { " [escaped]" } else { "" },
+            match app.config.entry {
+                Advance::Off => "",
+                Advance::Right => " [entry →]",
+                Advance::Down => " [entry ↓]",
+            }
+        ),
+        Field::Dims => format!("{}x{}", app.data.len(), app.max_cols()),
+        Field::Rows => app.data.len().to_string(),
+        Field::Cols => app.max_cols().to_string(),
+        Field::Cell => a1::cell_name(app.row, app.col),
+        Field::Row => (app.row + 1).to_string(),
+        Field::Col => (app.col + 1).to_string(),
+        Field::Dirty => if app.dirty { "yes" } else { "no" }.to_string(),
+        Field::Modified => if app.dirty { "modified" } else { "" }.to_string(),
+        Field::Encoding => {
+            let mut out = if app.escaped {
+                "utf-8 (escaped)"
+            } else {
+                "utf-8"
+            }
+            .to_string();
+            if let Some(codec) = Codec::for_path(&app.file_path) {
+                out.push_str(&format!(", {}", codec.program()));
+            }
+            out
+        }
+        Field::Filter => app
+            .view
+            .as_ref()
+            .map_or(String::new(), |v| v.describe(app.data.len())),
+        Field::Mode => match &app.prompt {
+            _ if app.editing => "edit",
+            Some(p) if p.kind == PromptKind::Command => "command",
+            Some(p) if matches!(p.kind, PromptKind::Search { .. }) => "search",
+            _ if app.selection().is_some() => "select",
+            _ => "normal",
+        }
+        .to_string(),
+        Field::Selection => {
+            let Some((top, left, bottom, right)) = app.selection() else {
+                return String::new();
+            };
+            let cells = (top..=bottom).flat_map(|r| {
+                (left..=right).map(move |c| {
+                    app.data
+                        .get(r)
+                        .and_then(|row| row.get(c))
+                        .map_or("", String::as_str)
+                })
+            });
+            let mut out = format!("{}x{}", bottom - top + 1, right - left + 1);
+            let stats = Summary::of(cells, &app.config.nulls, app.config.decimal);
+            if stats.count > 0 {
+                out.push_str(&format!(
+                    " | Sum {}  Avg {}  Min {}  Max {}  Count {}",
+                    viz::format_number(stats.sum),
+                    viz::format_number(stats.mean()),
+                    viz::format_number(stats.min),
+                    viz::format_number(stats.max),
+                    stats.count
+                ));
+            }
+            if stats.text > 0 {
+                out.push_str(&format!(" (+{} non-numeric)", stats.text));
+            }
+            out
+        }
+        Field::Columns if app.columns.is_default() => String::new(),
+        Field::Columns => format!(
+            "{} hidden, {} pinned",
+            app.columns.hidden.len(),
+            app.columns.pinned.len()
+        ),
+        Field::Tagged if app.tags.is_empty() => String::new(),
+        Field::Tagged => app.tags.len().to_string(),
+        Field::Viz if app.viz == VizMode::Off => String::new(),
+        Field::Viz => app.viz.label().to_string(),
+        Field::Keys if app.input.is_pending() => app.input.display(),
+        Field::Keys => String::new(),
+    }
+}
+
+fn draw_editor(f: &mut TuiFrame, area: Rect, app: &App) {
+    let (title, content) = if app.editing {
+        (
+            editor_title(app),
+            format!(
+                "Editing (r{}, c{}): {}",
+                app.row + 1,
+                app.col + 1,
+                escape::display(&app.editor_buf)
+            ),
+        )
+    } else if let Some(p) = &app.prompt {
+        ("Prompt".into(), format!("{}{}", p.label(), p.buf))
+    } else if !app.pending_replay.is_empty() {
+        (
+            "Recover".into(),
+            format!(
+                "Found {} unsaved edit(s) from a previous session. Replay? (y/n)",
+                app.pending_replay.len()
+            ),
+        )
+    } else if let Some(block) = &app.pending_block {
+        let rows = block.len();
+        let cols = block.iter().map(Vec::len).max().unwrap_or(0);
+        let end = a1::cell_name(
+            app.row + rows.saturating_sub(1),
+            app.col + cols.saturating_sub(1),
+        );
+        (
+            "Confirm".into(),
+            format!(
+                "Paste {rows}x{cols} cell(s) into {}:{end}? (y/n)",
+                a1::cell_name(app.row, app.col)
+            ),
+        )
+    } else if let Some((_, n)) = app.pending_paste {
+        (
+            "Confirm".into(),
+            format!("Paste over {n} non-empty cell(s)? (y/n)"),
+        )
+    } else if let Some(msg) = &app.message {
+        ("Info".into(), msg.clone())
+    } else {
+        ("Info".into(), "Press 'e' to edit selected cell".to_string())
+    };
+    // Typing goes at the end of the text, so the cursor sits there, counted
+    // in terminal cells so wide characters don't put it short; in
+    // right-to-left text that end is drawn on the left of the run.
+    let typing = app.editing || app.prompt.is_some();
+    let (content, at) = reorder(app, content);
+    let before: String = content.chars().take(at).collect();
+    let end = (area.x + 1).saturating_add(before.width().min(usize::from(u16::MAX)) as u16);
+    let p = Paragraph::new(Line::from(Span::raw(content)))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(p, area);
+    // Screen readers follow the terminal cursor; park it on the message.
+    if app.config.screen_reader {
+        f.set_cursor_position((area.x + 1, area.y + 1));
+    } else if typing {
+        f.set_cursor_position((end.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+}
+
+/// "Editor", plus the column values Ctrl+N would complete to.
+fn editor_title(app: &App) -> String {
+    const SHOWN: usize = 5;
+    let found;
+    let candidates = match &app.completion {
+        Some(c) => &c.candidates,
+        None if app.editor_buf.is_empty() => return "Editor".into(),
+        None => {
+            found = complete::candidates(&app.data, app.row, app.col, &app.editor_buf);
+            &found
+        }
+    };
+    if candidates.is_empty() {
+        return "Editor".into();
+    }
+    let masked: Vec<_> = candidates[..candidates.len().min(SHOWN)]
+        .iter()
+        .map(|v| app.masked(app.col, v))
+        .collect();
+    let mut shown = masked.join(" | ");
+    if candidates.len() > SHOWN {
+        shown.push_str(&format!(" | +{}", candidates.len() - SHOWN));
+    }
+    format!("Editor (Ctrl+N: {shown})")
+}
+
+fn draw_findings(f: &mut TuiFrame, area: Rect, panel: &Findings) {
+    let body = area.height.saturating_sub(2) as usize;
+    let offset = panel.selected.saturating_sub(body.saturating_sub(1));
+    let lines: Vec<Line> = panel
+        .shown()
+        .enumerate()
+        .skip(offset)
+        .take(body)
+        .map(|(i, item)| {
+            let (level, color) = if item.warning {
+                ("warning", Color::Yellow)
+            } else {
+                ("error", Color::Red)
+            };
+            let place = format!("r{}, c{} ", item.row + 1, item.col + 1);
+            let text = format!(" [{}] {}", item.rule, item.text);
+            if i == panel.selected {
+                let style = Style::default().fg(Color::Black).bg(Color::Yellow);
+                Line::styled(format!("{place}{level}{text}"), style)
+            } else {
+                Line::from(vec![
+                    Span::raw(place),
+                    Span::styled(level, Style::default().fg(color)),
+                    Span::raw(text),
+                ])
+            }
+        })
+        .collect();
+    let hint = if panel.focused {
+        "Enter: jump  f: filter by rule  Tab: to table  Esc: close"
+    } else {
+        "Tab: to panel  Esc: close"
+    };
+    let title = match &panel.filter {
+        Some(rule) => format!("{} | only [{rule}]: {}", panel.title, panel.shown().count()),
+        None => panel.title.clone(),
+    };
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("{title} ({hint})"))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(p, area);
+}
+
+/// How many substitutions the live preview lists.
+const PREVIEW_LEN: usize = 5;
+
+/// Live preview of a `:s` command, drawn over the bottom of the table while
+/// it is being typed.
+fn draw_replace_preview(f: &mut TuiFrame, area: Rect, app: &App, line: &str) {
+    let lines = match replace::parse(line) {
+        Ok(None) => return,
+        Err(e) => vec![Line::styled(
+            format!("{e:#}"),
+            Style::default().fg(Color::Red),
+        )],
+        Ok(Some(sub)) => {
+            let changes = app.cells_in(sub.scope).and_then(|cells| {
+                replace::changes(&app.data, cells.into_iter(), &sub, PREVIEW_LEN)
+            });
+            match changes {
+                Err(e) => vec![Line::styled(
+                    format!("{e:#}"),
+                    Style::default().fg(Color::Red),
+                )],
+                Ok(changes) if changes.is_empty() => vec![Line::raw("No matches")],
+                Ok(changes) => changes
+                    .iter()
+                    .map(|c| {
+                        let mut spans =
+                            vec![Span::raw(format!("r{}, c{}: ", c.row + 1, c.col + 1))];
+                        spans.extend(change_spans(app, c));
+                        Line::from(spans)
+                    })
+                    .collect(),
+            }
+        }
+    };
+    draw_preview(f, area, lines);
+}
+
+/// While `:strip-currency` is typed, a sample of what it makes of the
+/// cursor column and how many cells it would leave alone.
+fn draw_currency_preview(f: &mut TuiFrame, area: Rect, app: &App, line: &str) {
+    if !matches!(command::parse(line), Ok(Command::StripCurrency)) {
+        return;
+    }
+    let (changes, failed) = money::plan(
+        &app.data,
+        app.col,
+        usize::from(app.header),
+        &app.config.nulls,
+        app.config.decimal,
+    );
+    let mut lines: Vec<Line> = changes
+        .iter()
+        .take(PREVIEW_LEN)
+        .map(|c| {
+            let mut spans = vec![Span::raw(format!("r{}: ", c.row + 1))];
+            spans.extend(change_spans(app, c));
+            Line::from(spans)
+        })
+        .collect();
+    if changes.len() > PREVIEW_LEN {
+        lines.push(Line::raw(format!("… {} more", changes.len() - PREVIEW_LEN)));
+    }
+    if changes.is_empty() {
+        lines.push(Line::raw("Nothing to clean"));
+    }
+    if let Some(first) = failed.first() {
+        lines.push(Line::styled(
+            format!(
+                "{} cell(s) can't be read and stay as they are, e.g. r{}: {}",
+                failed.len(),
+                first.row + 1,
+                first.text
+            ),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    draw_preview(f, area, lines);
+}
+
+/// While a `:filter` expression is typed, what is wrong with it so far, or
+/// how many rows it keeps.
+fn draw_filter_preview(f: &mut TuiFrame, area: Rect, app: &App, line: &str) {
+    let Some(text) = command::filter_expression(line) else {
+        return;
+    };
+    let line = match view::Filter::with_rule(view::Rule::Expr(text.to_string()), app.headers()) {
+        Err(e) => Line::styled(format!("{e:#}"), Style::default().fg(Color::Red)),
+        Ok(filter) => {
+            let body = app.data.get(usize::from(app.header)..).unwrap_or_default();
+            let shown = body
+                .iter()
+                .filter(|r| filter.matches(r, &app.config.nulls, app.config.decimal))
+                .count();
+            Line::raw(format!("{shown} of {} row(s) match", body.len()))
+        }
+    };
+    draw_preview(f, area, vec![line]);
+}
+
+/// A bordered "Preview" box along the bottom of `area`.
+fn draw_preview(f: &mut TuiFrame, area: Rect, lines: Vec<Line>) {
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let rect = Rect {
+        y: area.y + area.height - height,
+        height,
+        ..area
+    };
+    let p = Paragraph::new(lines).block(Block::default().title("Preview").borders(Borders::ALL));
+    f.render_widget(Clear, rect);
+    f.render_widget(p, rect);
+}
+
+/// `before → after` with only the part that changes highlighted on each side.
+fn change_spans(app: &App, c: &replace::Change) -> Vec<Span<'static>> {
+    let plain = |s: &str| Span::raw(escape::display(s).into_owned());
+    // Where the change falls would give a masked value away.
+    if app.columns.mask(c.col).is_some() {
+        return vec![
+            plain(&app.masked(c.col, &c.before)),
+            Span::raw(" → "),
+            plain(&app.masked(c.col, &c.after)),
+        ];
+    }
+    let (head, removed, added, tail) = replace::diff(&c.before, &c.after);
+    let marked = |s: &str, color: Color| {
+        Span::styled(
+            escape::display(s).into_owned(),
+            Style::default()
+                .fg(Color::Black)
+                .bg(color)
+                .add_modifier(Modifier::BOLD),
+        )
+    };
+    vec![
+        plain(head),
+        marked(removed, Color::LightRed),
+        plain(tail),
+        Span::raw(" → "),
+        plain(head),
+        marked(added, Color::LightGreen),
+        plain(tail),
+    ]
+}
+
+fn draw_review(f: &mut TuiFrame, area: Rect, app: &App, review: &replace::Review) {
+    let Some(c) = review.current() else {
+        return;
+    };
+    let mut spans = vec![Span::raw(format!("r{}, c{}: ", c.row + 1, c.col + 1))];
+    spans.extend(change_spans(app, c));
+    let height = 3.min(area.height);
+    let rect = Rect {
+        y: area.y + area.height - height,
+        height,
+        ..area
+    };
+    let title = format!(
+        "Replace {}/{}? (y: yes  n: no  a: all the rest  q: stop)",
+        review.index + 1,
+        review.changes.len()
+    );
+    let p = Paragraph::new(Line::from(spans))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, rect);
+    f.render_widget(p, rect);
+}
+
+/// `:perf` timings in the top-right corner, from the frame before this one.
+fn draw_perf(f: &mut TuiFrame, area: Rect, perf: &Perf) {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    let text = format!(
+        "frame {:.1} ms  key→screen {:.1} ms",
+        ms(perf.frame),
+        ms(perf.latency)
+    );
+    let width = (text.width() as u16 + 2).min(area.width);
+    let rect = Rect {
+        x: area.x + area.width - width,
+        width,
+        height: 3.min(area.height),
+        ..area
+    };
+    let p = Paragraph::new(text).block(Block::default().title("perf").borders(Borders::ALL));
+    f.render_widget(Clear, rect);
+    f.render_widget(p, rect);
+}
+
+/// The tutorial's current instruction along the bottom of the table.
+fn draw_tutorial(f: &mut TuiFrame, area: Rect, app: &App, t: &Tutorial) {
+    let height = 4.min(area.height);
+    let rect = Rect {
+        x: area.x + 2.min(area.width / 4),
+        y: area.bottom().saturating_sub(height + 1).max(area.y),
+        width: area.width.saturating_sub(4.min(area.width / 2)),
+        height,
+    };
+    let p = Paragraph::new(t.text(app))
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Black).bg(Color::LightYellow))
+        .block(Block::default().title(t.title()).borders(Borders::ALL));
+    f.render_widget(Clear, rect);
+    f.render_widget(p, rect);
+}
+
+/// `s` in the order the terminal should get it, and how many characters in
+/// its logical end falls; see [`bidi::visual`].
+fn reorder(app: &App, s: String) -> (String, usize) {
+    if app.config.bidi && bidi::has_rtl(&s) {
+        bidi::visual(&s)
+    } else {
+        let n = s.chars().count();
+        (s, n)
+    }
+}
+
+/// `s` and enough spaces to fill `width` terminal cells; `format!`'s own
+/// padding counts characters, which leaves wide ones sticking out.
+fn pad(s: &str, width: usize) -> String {
+    format!("{s}{}", " ".repeat(width.saturating_sub(s.width())))
+}
+
+/// A rectangle of the given percentage size centered in `area`.
+fn centered(area: Rect, pct_x: u16, pct_y: u16) -> Rect {
+    let w = area.width * pct_x / 100;
+    let h = area.height * pct_y / 100;
+    Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    }
+}
+
+fn draw_compare(f: &mut TuiFrame, area: Rect, app: &App) {
+    let area = centered(area, 60, 40);
+    let stored = app
+        .data
+        .get(app.row)
+        .and_then(|r| r.get(app.col))
+        .map_or("", String::as_str);
+    let current = app.masked(app.col, stored);
+    let label = Style::default().add_modifier(Modifier::BOLD);
+    let lines = vec![
+        Line::styled("On disk:", label),
+        Line::styled(
+            app.original_value(app.row, app.col)
+                .map_or("(row added since the last save)".to_string(), |v| {
+                    escape::display(&app.masked(app.col, v)).into_owned()
+                }),
+            Style::default().fg(Color::Red),
+        ),
+        Line::raw(""),
+        Line::styled("Current:", label),
+        Line::styled(
+            escape::display(&current).into_owned(),
+            Style::default().fg(Color::Green),
+        ),
+    ];
+    let title = format!(
+        "Cell (r{}, c{}) — r: revert  any key: close",
+        app.row + 1,
+        app.col + 1
+    );
+    let p = Paragraph::new(lines)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+/// The whole current cell, wrapped, with only the visible lines built so a
+/// huge value stays cheap to draw.
+fn draw_cell_view(f: &mut TuiFrame, area: Rect, app: &App, scroll: usize) {
+    let area = centered(area, 80, 70);
+    let stored = app
+        .data
+        .get(app.row)
+        .and_then(|r| r.get(app.col))
+        .map_or("", String::as_str);
+    let value = app.masked(app.col, stored);
+    let width = area.width.saturating_sub(2).max(1) as usize;
+    let body = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = value
+        .split('\n')
+        .flat_map(|line| wrap_chars(line, width))
+        .skip(scroll)
+        .take(body)
+        .map(|chunk| Line::raw(escape::display(chunk).into_owned()))
+        .collect();
+    let title = format!(
+        "Cell (r{}, c{}) — {} bytes (j/k scroll, any other key: close)",
+        app.row + 1,
+        app.col + 1,
+        value.len()
+    );
+    let p = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+/// The cursor's row down the screen, one `header: value` line per shown
+/// column, with the field being edited showing the editor's text.
+fn draw_record_card(f: &mut TuiFrame, area: Rect, app: &App) {
+    let area = centered(area, 80, 80);
+    let row = app.data.get(app.row).map_or(&[][..], Vec::as_slice);
+    let order = app.column_order();
+    let label = |c: usize| match app.headers().get(c) {
+        Some(h) if app.header => escape::clipped(h, 24).into_owned(),
+        _ => a1::col_name(c),
+    };
+    let width = order.iter().map(|&c| label(c).width()).max().unwrap_or(0);
+    let body = area.height.saturating_sub(2) as usize;
+    let selected = app.col_pos();
+    let offset = selected.saturating_sub(body.saturating_sub(1));
+    let lines: Vec<Line> = order
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(body)
+        .map(|(i, &c)| {
+            let name = label(c);
+            let pad = " ".repeat(width.saturating_sub(name.width()));
+            let value = if i == selected && app.editing {
+                format!("{}_", escape::display(&app.editor_buf))
+            } else {
+                let txt = app.masked(c, row.get(c).map_or("", String::as_str));
+                escape::clipped(&txt, app.config.max_cell_len).into_owned()
+            };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else if app.is_modified(app.row, c) {
+                Style::default().bg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{pad}{name}: "),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(value, style),
+            ])
+        })
+        .collect();
+    let title = format!(
+        "Row {} (j/k: field  J/K: row  Enter: edit  Esc: close)",
+        app.row + 1
+    );
+    let p = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+/// `line` in pieces of `width` characters; an empty line stays one piece.
+/// Two rows side by side, one line per column, fields that differ in
+/// yellow with their values spelled out and a note when they only look alike.
+fn draw_row_compare(f: &mut TuiFrame, area: Rect, app: &App, cmp: &RowCompare) {
+    let area = centered(area, 90, 80);
+    let (a, b) = cmp.rows;
+    let differ = app.row_differences(a, b);
+    let cols: Vec<usize> = if cmp.only_diff {
+        differ.clone()
+    } else {
+        (0..app.max_cols()).collect()
+    };
+    let cell = |r: usize, c: usize| {
+        app.data
+            .get(r)
+            .and_then(|row| row.get(c))
+            .map_or("", String::as_str)
+    };
+    let label = |c: usize| match app.headers().get(c) {
+        Some(h) if app.header => escape::clipped(h, 24).into_owned(),
+        _ => a1::col_name(c),
+    };
+    let width = cols.iter().map(|&c| label(c).width()).max().unwrap_or(0);
+    let max = app.config.max_cell_len;
+    let rows: Vec<Row> = cols
+        .iter()
+        .skip(cmp.scroll.min(cols.len().saturating_sub(1)))
+        .map(|&c| {
+            let (x, y) = (app.masked(c, cell(a, c)), app.masked(c, cell(b, c)));
+            let (x, y) = (x.as_ref(), y.as_ref());
+            if !differ.contains(&c) {
+                return Row::new(vec![
+                    label(c),
+                    escape::clipped(x, max).into_owned(),
+                    escape::clipped(y, max).into_owned(),
+                    String::new(),
+                ]);
+            }
+            let note = escape::hidden_difference(x, y).unwrap_or_default();
+            Row::new(vec![
+                label(c),
+                escape::revealed(x),
+                escape::revealed(y),
+                note.to_string(),
+            ])
+            .style(Style::default().fg(Color::Yellow))
+        })
+        .collect();
+    let header = Row::new(vec![
+        String::new(),
+        format!("Row {}", a + 1),
+        format!("Row {}", b + 1),
+        String::new(),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let title = format!(
+        "{} of {} fields differ (d: only differences  j/k scroll  Esc: close)",
+        differ.len(),
+        app.max_cols()
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(width as u16),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(20),
+        ],
+    )
+    .header(header)
+    .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+/// A line per export setting, the current one highlighted, and how much
+/// the export would write, or why it can't.
+fn draw_export_dialog(f: &mut TuiFrame, area: Rect, app: &App, d: &export::Dialog) {
+    let area = centered(area, 70, 60);
+    let width = export::FIELDS
+        .iter()
+        .map(|f| f.label().len())
+        .max()
+        .unwrap_or(0);
+    let mut lines: Vec<Line> = export::FIELDS
+        .iter()
+        .enumerate()
+        .map(|(i, &field)| {
+            let mut value = d.value(field);
+            if i == d.field && field == export::Field::Destination {
+                value.push('_');
+            }
+            let style = if i == d.field {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>width$}: ", field.label()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(value, style),
+            ])
+        })
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(match app.export_table(&d.settings) {
+        Ok((table, header)) => Line::raw(format!(
+            "{} row(s) × {} column(s)",
+            table.len() - usize::from(header),
+            table.first().map_or(0, Vec::len)
+        )),
+        Err(e) => Line::styled(format!("{e:#}"), Style::default().fg(Color::Red)),
+    });
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Export (j/k: setting  h/l: change  Enter: export  Esc: cancel)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+fn wrap_chars(line: &str, width: usize) -> impl Iterator<Item = &str> {
+    let mut rest = Some(line);
+    std::iter::from_fn(move || {
+        let s = rest?;
+        let cut = s.char_indices().nth(width).map_or(s.len(), |(i, _)| i);
+        let (piece, tail) = s.split_at(cut);
+        rest = (!tail.is_empty()).then_some(tail);
+        Some(piece)
+    })
+}
+
+fn draw_chart(f: &mut TuiFrame, area: Rect, series: &Series) {
+    let area = centered(area, 80, 50);
+    let (lo, hi) = series.range().unwrap_or_default();
+    let sum: f64 = series.values.iter().sum();
+    let title = format!(
+        "{}: {} value(s)  min {lo}  max {hi}  mean {:.2} (any key: close)",
+        series.title,
+        series.values.len(),
+        sum / series.values.len().max(1) as f64
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area);
+    // Thin out long series so the whole trend fits the popup's width.
+    let data = series.scaled();
+    let step = data.len().div_ceil(inner.width.max(1) as usize).max(1);
+    let data: Vec<u64> = data
+        .chunks(step)
+        .map(|c| c.iter().sum::<u64>() / c.len() as u64)
+        .collect();
+    let sparkline = Sparkline::default()
+        .data(&data)
+        .max(100)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    f.render_widget(sparkline, inner);
+}
+
+fn draw_paste_special(f: &mut TuiFrame, area: Rect, opts: &PasteOptions) {
+    let area = centered(area, 50, 40);
+    let check = |on: bool| if on { "[x]" } else { "[ ]" };
+    let lines = vec![
+        Line::raw(format!(
+            "{} t  Transpose rows and columns",
+            check(opts.transpose)
+        )),
+        Line::raw(format!(
+            "{} b  Skip blank cells (keep what is there)",
+            check(opts.skip_blanks)
+        )),
+        Line::raw(format!(
+            "{} v  Values only (paste formulas as blanks)",
+            check(opts.values_only)
+        )),
+    ];
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Paste special (Enter: paste  Esc: cancel)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+fn draw_column_manager(f: &mut TuiFrame, area: Rect, m: &Manager) {
+    let area = centered(area, 60, 70);
+    let body = area.height.saturating_sub(2) as usize;
+    let offset = m.cursor.saturating_sub(body.saturating_sub(1));
+    let lines: Vec<Line> = m
+        .entries
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(body)
+        .map(|(i, e)| {
+            let name = match &m.renaming {
+                Some(buf) if i == m.cursor => format!("{buf}_"),
+                _ => escape::display(&e.name).into_owned(),
+            };
+            let text = format!(
+                "{} {} {:>3}  {name}",
+                if e.hidden { "[ ]" } else { "[x]" },
+                if e.pinned { "pin" } else { "   " },
+                e.col + 1
+            );
+            let style = if i == m.cursor {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Line::styled(text, style)
+        })
+        .collect();
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Columns (J/K: move  Space: show  p: pin  r: rename  Enter: apply  Esc: cancel)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+fn draw_filter_panel(f: &mut TuiFrame, area: Rect, app: &App, panel: &filters::Panel) {
+    let area = centered(area, 60, 50);
+    let body = area.height.saturating_sub(2) as usize;
+    let offset = panel.cursor.saturating_sub(body.saturating_sub(1));
+    let active: Vec<&str> = app
+        .view
+        .iter()
+        .flat_map(|v| v.saved.iter().map(|(n, _)| n.as_str()))
+        .collect();
+    let lines: Vec<Line> = app
+        .saved_filters
+        .names()
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .take(body)
+        .map(|(i, name)| {
+            let rule = app
+                .saved_filters
+                .get(name)
+                .map(|r| r.to_text())
+                .unwrap_or_default();
+            let text = format!(
+                "{} {name}  {}",
+                if active.contains(&name) { "[x]" } else { "[ ]" },
+                escape::display(&rule)
+            );
+            let style = if i == panel.cursor {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Line::styled(text, style)
+        })
+        .collect();
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Filters (Space: on/off  d: delete  Esc: close)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+fn draw_fuzzy(f: &mut TuiFrame, area: Rect, app: &App, view: &FuzzyView) {
+    let area = centered(area, 80, 70);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Find: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(view.query.clone()),
+        ]),
+        Line::raw(""),
+    ];
+    let body = area.height.saturating_sub(4) as usize;
+    let offset = view.selected.saturating_sub(body.saturating_sub(1));
+    for (i, hit) in view.hits.iter().enumerate().skip(offset).take(body) {
+        let row = &app.data[hit.row];
+        let cell = |c: usize, v: &str| escape::display(&app.masked(c, v)).into_owned();
+        // The rest of the row gives context for which record this is.
+        let context: Vec<_> = row
+            .iter()
+            .take(4)
+            .enumerate()
+            .map(|(c, v)| cell(c, v))
+            .collect();
+        let style = if i == view.selected {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("r{:<5} c{:<3} ", hit.row + 1, hit.col + 1),
+                style.fg(if i == view.selected {
+                    Color::Black
+                } else {
+                    Color::Cyan
+                }),
+            ),
+            Span::styled(
+                cell(hit.col, &row[hit.col]),
+                style.add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("  │ {}", context.join(" | ")),
+                style.fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    if !view.query.is_empty() && view.hits.is_empty() {
+        lines.push(Line::raw("No matching cells"));
+    }
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Fuzzy find (Up/Down select, Enter jump, Esc close)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+fn draw_help(f: &mut TuiFrame, area: Rect, app: &App, view: &help::HelpView) {
+    let area = centered(area, 80, 80);
+    let entries = help::entries(&app.keymap, &view.query);
+    let key_w = entries
+        .iter()
+        .map(|e| e.keys.width())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let name_w = entries.iter().map(|e| e.name.width()).max().unwrap_or(0);
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Search: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(view.query.clone()),
+        ]),
+        Line::raw(""),
+    ];
+    let body = area.height.saturating_sub(4) as usize;
+    let scroll = view.scroll.min(entries.len().saturating_sub(body));
+    for e in entries.iter().skip(scroll).take(body) {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}  ", pad(&e.keys, key_w)),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                format!("{}  ", pad(e.name, name_w)),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(e.description),
+        ]));
+    }
+    if entries.is_empty() {
+        lines.push(Line::raw("No matching actions"));
+    }
+
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title("Help (type to search, Up/Down scroll, Esc close)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+pub fn draw_picker<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    picker: &Picker,
+    caps: Caps,
+) -> Result<()> {
+    terminal.draw(|f| {
+        let title = format!("{} (Enter: open  Backspace: up  q: quit)", picker.title);
+        render_picker(f, f.area(), picker, &title);
+        caps.adapt(f.buffer_mut());
+    })?;
+    Ok(())
+}
+
+fn render_picker(f: &mut TuiFrame, area: Rect, picker: &Picker, title: &str) {
+    let body = area.height.saturating_sub(2) as usize;
+    // Keep the selection visible by scrolling a page at a time.
+    let offset = picker.selected / body.max(1) * body.max(1);
+    let name_w = picker
+        .entries
+        .iter()
+        .map(|e| e.name.width() + 1)
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Line> = picker
+        .entries
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(body)
+        .map(|(i, e)| {
+            let (name, details) = match e.kind {
+                EntryKind::Dir | EntryKind::Parent => (format!("{}/", e.name), String::new()),
+                EntryKind::NewFile => (e.name.clone(), String::new()),
+                EntryKind::File | EntryKind::Recent => (
+                    e.name.clone(),
+                    format!(
+                        "{:>10}  {}",
+                        picker::format_size(e.size),
+                        e.modified.map(picker::format_time).unwrap_or_default()
+                    ),
+                ),
+            };
+            let style = if i == picker.selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                match e.kind {
+                    EntryKind::File => Style::default(),
+                    EntryKind::Recent => Style::default().fg(Color::Green),
+                    _ => Style::default().fg(Color::Cyan),
+                }
+            };
+            Line::styled(format!("{} {details}", pad(&name, name_w)), style)
+        })
+        .collect();
+    let p = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(p, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use std::{env, fs};
+
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+
+    fn app(rows: &[&[&str]]) -> App {
+        let data = rows
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        App::new(PathBuf::new(), data)
+    }
+
+    fn render(app: &App, width: u16, height: u16) -> Result<Buffer> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+        draw_ui(&mut terminal, app)?;
+        Ok(terminal.backend().buffer().clone())
+    }
+
+    /// The screen's text, one line per row with trailing blanks dropped. A
+    /// wide character stands for the cells it covers.
+    fn text(buf: &Buffer) -> String {
+        let area = buf.area;
+        let lines: Vec<String> = (area.y..area.bottom())
+            .map(|y| {
+                let mut line = String::new();
+                let mut x = area.x;
+                while x < area.right() {
+                    let symbol = buf[(x, y)].symbol();
+                    line.push_str(symbol);
+                    x += symbol.width().max(1) as u16;
+                }
+                line.trim_end().to_string()
+            })
+            .collect();
+        lines.join("\n") + "\n"
+    }
+
+    /// The style of the screen cell where `needle` first starts in the table.
+    fn style_at(buf: &Buffer, needle: &str) -> Style {
+        let screen = text(buf);
+        let (y, line) = screen
+            .lines()
+            .enumerate()
+            .skip_while(|(_, l)| !l.contains("CSV Viewer"))
+            .find(|(_, l)| l.contains(needle))
+            .unwrap_or_else(|| panic!("{needle:?} is not on the screen:\n{screen}"));
+        let x = line[..line.find(needle).unwrap_or(0)].width();
+        buf[(x as u16, y as u16)].style()
+    }
+
+    /// Compare the screen with `tests/snapshots/<name>.txt`. Run with
+    /// `UPDATE_SNAPSHOTS=1` to write the snapshot after a deliberate change
+    /// to the layout, then review it in the diff.
+    fn assert_snapshot(name: &str, buf: &Buffer) -> Result<()> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("{name}.txt"));
+        let screen = text(buf);
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap_or(&path))?;
+            fs::write(&path, &screen)?;
+            return Ok(());
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            screen == expected,
+            "screen differs from {path:?} (UPDATE_SNAPSHOTS=1 rewrites it)\n--- expected\n{expected}--- drawn\n{screen}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn draws_table_with_pinned_header_and_highlights() -> Result<()> {
+        let mut app = app(&[
+            &["id", "name", "score"],
+            &["1", "bob", "10"],
+            &["2", "amy", "12"],
+            &["3", "eve", "9"],
+        ]);
+        app.header = true;
+        app.row = 2;
+        app.col = 1;
+        app.set_cell(3, 2, "90".into());
+        let buf = render(&app, 64, 14)?;
+        assert_snapshot("table", &buf)?;
+        let cursor = style_at(&buf, "amy");
+        assert_eq!(
+            (cursor.fg, cursor.bg),
+            (Some(Color::Black), Some(Color::Yellow))
+        );
+        assert!(cursor.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(style_at(&buf, "90").bg, Some(Color::DarkGray));
+        assert!(
+            style_at(&buf, "name")
+                .add_modifier
+                .contains(Modifier::UNDERLINED)
+        );
+        assert_eq!(style_at(&buf, "bob").bg, Some(Color::Reset));
+        Ok(())
+    }
+
+    #[test]
+    fn scrolls_to_the_cursor_below_the_pinned_header() -> Result<()> {
+        let mut app = app(&[&["n"]]);
+        app.data.extend((1..=60).map(|i| vec![format!("row {i}")]));
+        app.header = true;
+        app.row = 45;
+        let buf = render(&app, 40, 16)?;
+        assert_snapshot("scrolled", &buf)?;
+        let screen = text(&buf);
+        assert!(screen.contains("│row 45") && !screen.contains("│row 46"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_overlays_on_top_of_the_table() -> Result<()> {
+        let mut app = app(&[&["note"], &["a long note that the table cuts short"]]);
+        app.config.max_cell_len = 10;
+        app.row = 1;
+        app.cell_view = Some(0);
+        app.perf = Some(Perf {
+            frame: Duration::from_micros(1500),
+            latency: Duration::from_millis(4),
+        });
+        assert_snapshot("overlays", &render(&app, 64, 16)?)
+    }
+
+    #[test]
+    fn lines_up_wide_characters_and_the_editor_cursor() -> Result<()> {
+        let mut app = app(&[&["名前", "x"], &["😀😀", "y"]]);
+        app.editing = true;
+        app.editor_buf = "日本".into();
+        let mut terminal = Terminal::new(TestBackend::new(40, 12))?;
+        draw_ui(&mut terminal, &app)?;
+        let screen = text(terminal.backend().buffer());
+        let column_of = |needle: &str| {
+            screen
+                .lines()
+                .find_map(|l| l.find(needle).map(|at| l[..at].width()))
+        };
+        assert_eq!(column_of("x"), column_of("y"));
+        // "│Editing (r1, c1): 日本" ends 23 cells in.
+        assert_eq!(terminal.get_cursor_position()?, (23, 10).into());
+        Ok(())
+    }
+
+    #[test]
+    fn draws_right_to_left_cells_reordered_and_right_aligned() -> Result<()> {
+        let mut app = app(&[&["name", "x"], &["שלום 123", "y"]]);
+        crate::app::run_command(&mut app, command::parse("rtl")?)?;
+        app.row = 1;
+        app.editing = true;
+        app.editor_buf = "שלום".into();
+        let mut terminal = Terminal::new(TestBackend::new(40, 12))?;
+        draw_ui(&mut terminal, &app)?;
+        let screen = text(terminal.backend().buffer());
+        let end_of = |needle: &str| {
+            screen
+                .lines()
+                .filter(|l| l.starts_with('│'))
+                .find_map(|l| l.find(needle).map(|at| l[..at].width() + needle.width()))
+        };
+        assert!(end_of("123 םולש").is_some());
+        assert_eq!(end_of("name"), end_of("123 םולש"));
+        assert!(screen.contains("Editing (r2, c1): םולש"));
+        // The logical end of the Hebrew word is drawn on its left.
+        assert_eq!(terminal.get_cursor_position()?, (19, 10).into());
+        Ok(())
+    }
+
+    #[test]
+    fn points_out_filter_expression_mistakes_as_they_are_typed() -> Result<()> {
+        let mut app = app(&[&["id", "ms"], &["1", "900"], &["2", "80"]]);
+        app.header = true;
+        let typed = |app: &mut App, buf: &str| -> Result<String> {
+            let mut prompt = crate::prompt::Prompt::new(PromptKind::Command);
+            prompt.buf = buf.to_string();
+            app.prompt = Some(prompt);
+            Ok(text(&render(app, 60, 12)?))
+        };
+        assert!(typed(&mut app, "filter num(ms) >")?.contains("expression ends early"));
+        let unknown = typed(&mut app, "filter num(ms) > 500 && col(nope) == 1")?;
+        assert!(unknown.contains("no column named \"nope\""));
+        assert!(typed(&mut app, "filter num(ms) > 500")?.contains("1 of 2 row(s) match"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_group_headers_with_counts() -> Result<()> {
+        let mut app = app(&[
+            &["level", "msg"],
+            &["info", "started"],
+            &["info", "ready"],
+            &["warn", "slow disk"],
+        ]);
+        app.header = true;
+        let mut group = view::Grouping::new(0);
+        group.collapsed.insert("info".into());
+        let mut v = view::View {
+            group: Some(group),
+            ..view::View::default()
+        };
+        v.rebuild(&app.data, 1, None, &app.config.nulls, app.config.decimal);
+        app.view = Some(v);
+        app.row = 1;
+        let screen = text(&render(&app, 40, 16)?);
+        let table: Vec<&str> = screen.lines().filter(|l| l.starts_with('│')).collect();
+        assert_eq!(table[1].trim_end_matches('│').trim_end(), "│▸ info (2)");
+        assert!(table[2].contains("started") && table[3].contains("▾ warn (1)"));
+        assert!(!screen.contains("ready"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_the_record_card_with_the_field_being_edited() -> Result<()> {
+        let mut app = app(&[&["id", "full name"], &["1", "Alice"]]);
+        app.header = true;
+        (app.row, app.col) = (1, 1);
+        app.record_card = true;
+        app.editing = true;
+        app.editor_buf = "Alice B".to_string();
+        let screen = text(&render(&app, 60, 16)?);
+        assert!(screen.contains("Row 2 (j/k: field"));
+        assert!(screen.contains("│       id: 1"));
+        assert!(screen.contains("│full name: Alice B_"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_two_rows_side_by_side_with_hidden_differences_named() -> Result<()> {
+        let mut app = app(&[
+            &["id", "vendor", "amount"],
+            &["1", "ACME", "10"],
+            &["2", "ACME ", "10"],
+        ]);
+        app.header = true;
+        app.row_compare = Some(RowCompare {
+            rows: (1, 2),
+            ..RowCompare::default()
+        });
+        let screen = text(&render(&app, 80, 16)?);
+        assert!(screen.contains("2 of 3 fields differ"));
+        assert!(screen.contains("ACME·"));
+        assert!(screen.contains("spaces"));
+        if let Some(cmp) = app.row_compare.as_mut() {
+            cmp.only_diff = true;
+        }
+        let screen = text(&render(&app, 80, 16)?);
+        assert!(!screen.contains("amount"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_the_export_dialog_with_what_it_would_write() -> Result<()> {
+        let mut app = app(&[&["id", "name"], &["1", "Alice"], &["2", "Bob"]]);
+        app.header = true;
+        let mut d = export::Dialog::new(export::Settings::default(), Path::new("people.csv"));
+        d.field = 4;
+        d.change(true, Path::new("people.csv"));
+        d.change(true, Path::new("people.csv"));
+        app.export_dialog = Some(d.clone());
+        let screen = text(&render(&app, 80, 20)?);
+        assert!(screen.contains("Format: CSV"));
+        assert!(screen.contains("Save to: people.export.csv"));
+        assert!(screen.contains("No selection; v starts one"));
+        d.change(false, Path::new("people.csv"));
+        app.export_dialog = Some(d);
+        let screen = text(&render(&app, 80, 20)?);
+        assert!(screen.contains("2 row(s) × 2 column(s)"));
+        Ok(())
+    }
+
+    #[test]
+    fn draws_masked_columns_in_the_table_and_the_formula_bar() -> Result<()> {
+        let mut app = app(&[&["name", "card"], &["Alice", "4111111111111111"]]);
+        app.header = true;
+        (app.row, app.col) = (1, 1);
+        app.columns.set_mask(1, Some(crate::mask::Mask::Last4));
+        let screen = text(&render(&app, 60, 16)?);
+        assert!(screen.contains("•••1111"));
+        assert!(screen.contains("B2 │ •••1111"));
+        assert!(!screen.contains("4111111111111111"));
+        Ok(())
+    }
+
+    #[test]
+    fn clips_a_huge_cell_to_the_formula_bar_and_keeps_its_badges() -> Result<()> {
+        let mut app = app(&[&["id", "blob"], &["1", &"ab ".repeat(1 << 20)]]);
+        app.header = true;
+        (app.row, app.col) = (1, 1);
+        let screen = text(&render(&app, 80, 16)?);
+        let bar = screen.lines().next().unwrap_or_default();
+        assert!(bar.starts_with("B2 │ ab ab"));
+        assert!(bar.contains("…  [very long]  (3145728 bytes; clipped in the table)"));
+        assert!(bar.width() <= 80);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_masked_values_out_of_fuzzy_results_and_completions() -> Result<()> {
+        let mut app = app(&[
+            &["name", "card"],
+            &["Alice", "4111111111111111"],
+            &["Bob", "4111222233334444"],
+        ]);
+        app.header = true;
+        app.columns.set_mask(1, Some(crate::mask::Mask::Full));
+        let mut view = FuzzyView {
+            query: "4111".into(),
+            ..FuzzyView::default()
+        };
+        view.refresh(&app.data);
+        app.fuzzy = Some(view);
+        let screen = text(&render(&app, 70, 16)?);
+        assert!(screen.contains("Alice | •••"));
+        assert!(!screen.contains("41111") && !screen.contains("41112"));
+
+        app.fuzzy = None;
+        (app.row, app.col) = (2, 1);
+        app.editing = true;
+        app.editor_buf = "41".into();
+        assert_eq!(editor_title(&app), "Editor (Ctrl+N: •••)");
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::journal::Edit;
+
+/// Oldest steps are dropped beyond this, so a long session stays bounded.
+const MAX_STEPS: usize = 1000;
+
+/// What it takes to revert one user-level change.
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Edits that restore the previous data, in the order to apply them.
+    pub edits: Vec<Edit>,
+    /// Row identities before a structural change, which re-inserted rows
+    /// can't recover on their own.
+    pub origin: Option<Vec<Option<usize>>>,
+    /// Column identities before a column was inserted or deleted.
+    pub col_origin: Option<Vec<Option<usize>>>,
+    /// Cursor position before the change.
+    pub cursor: (usize, usize),
+}
+
+/// Undo and redo stacks.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+impl History {
+    /// A new change: it becomes undoable and invalidates the redo stack.
+    pub fn push(&mut self, step: Step) {
+        self.redo.clear();
+        self.push_undo(step);
+    }
+
+    pub fn push_undo(&mut self, step: Step) {
+        if self.undo.len() == MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.undo.push(step);
+    }
+
+    pub fn push_redo(&mut self, step: Step) {
+        self.redo.push(step);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Step> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Step> {
+        self.redo.pop()
+    }
+}
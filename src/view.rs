@@ -0,0 +1,438 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Result, anyhow};
+
+use crate::expr;
+use crate::journal::Edit;
+use crate::nulls::Nulls;
+use crate::search::{Area, Search};
+use crate::sort::{self, SortKey};
+use crate::viz::Decimal;
+
+/// What a filter asks of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// A `/`-style query on one column: a regex, smart case.
+    Matches(usize, String),
+    /// Exactly this value in the column (`*` on a cell).
+    Is(usize, String),
+    /// Anything but this value in the column (`#` on a cell).
+    IsNot(usize, String),
+    /// An [`expr::Condition`] over the whole row.
+    Expr(String),
+}
+
+impl Rule {
+    /// How presets and saved filters write the rule: `filter 2 ^FAIL`,
+    /// `is 3 OPEN`, `isnot 3 DONE` or `where num("ms") > 500`, with the
+    /// column numbered from 1.
+    pub fn to_text(&self) -> String {
+        match self {
+            Rule::Matches(col, query) => format!("filter {} {query}", col + 1),
+            Rule::Is(col, value) => format!("is {} {value}", col + 1),
+            Rule::IsNot(col, value) => format!("isnot {} {value}", col + 1),
+            Rule::Expr(text) => format!("where {text}"),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let (word, after) = s.trim().split_once(' ').unwrap_or((s.trim(), ""));
+        if word == "where" {
+            return Ok(Rule::Expr(after.trim().to_string()));
+        }
+        let (col, text) = after.trim_start().split_once(' ').unwrap_or((after, ""));
+        let (col, text) = (crate::command::parse_column(col)?, text.to_string());
+        match word {
+            "filter" => Ok(Rule::Matches(col, text)),
+            "is" => Ok(Rule::Is(col, text)),
+            "isnot" => Ok(Rule::IsNot(col, text)),
+            _ => Err(anyhow!("expected filter, is, isnot or where, not {word:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Search(Search),
+    Value,
+    Condition(expr::Condition),
+}
+
+/// Keep rows that pass `rule`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub rule: Rule,
+    test: Test,
+}
+
+impl Filter {
+    pub fn new(col: usize, query: &str) -> Result<Self> {
+        Self::with_rule(Rule::Matches(col, query.to_string()), &[])
+    }
+
+    /// Column names in an expression are looked up in `headers`.
+    pub fn with_rule(rule: Rule, headers: &[String]) -> Result<Self> {
+        let test = match &rule {
+            Rule::Matches(col, query) => Test::Search(Search::new(query, Area::Column(*col))?),
+            Rule::Is(..) | Rule::IsNot(..) => Test::Value,
+            Rule::Expr(text) => {
+                let mut cond = expr::Condition::parse(text)?;
+                cond.resolve(headers)?;
+                Test::Condition(cond)
+            }
+        };
+        Ok(Self { rule, test })
+    }
+
+    /// `col 2 ~ ^FAIL`, `col 2 == FAILED`, or the expression, for the
+    /// status line.
+    pub fn describe(&self) -> String {
+        match &self.rule {
+            Rule::Matches(col, query) => format!("col {} ~ {query}", col + 1),
+            Rule::Is(col, value) => format!("col {} == {value}", col + 1),
+            Rule::IsNot(col, value) => format!("col {} != {value}", col + 1),
+            Rule::Expr(text) => text.clone(),
+        }
+    }
+
+    pub fn matches(&self, row: &[String], nulls: &Nulls, decimal: Decimal) -> bool {
+        let cell = |col: usize| row.get(col).map_or("", String::as_str);
+        match (&self.rule, &self.test) {
+            (Rule::Is(col, value), _) => cell(*col) == value,
+            (Rule::IsNot(col, value), _) => cell(*col) != value,
+            (Rule::Matches(col, _), Test::Search(search)) => search.matches(cell(*col)),
+            (_, Test::Condition(cond)) => cond.matches(row, nulls, decimal),
+            _ => true,
+        }
+    }
+}
+
+/// One run of shown rows with the same value in the grouped column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    /// Where its first row is in [`View::rows`].
+    pub pos: usize,
+    /// How many rows it has, collapsed or not.
+    pub len: usize,
+    pub value: String,
+}
+
+/// What a line of a grouped table shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    /// The header line of run `n`.
+    Run(usize),
+    /// The row at this position in [`View::rows`].
+    Row(usize),
+}
+
+/// `:group`: rows that follow one another with the same value in `col` are
+/// drawn under a header line with their count. A collapsed value keeps
+/// only the first row of each of its runs.
+#[derive(Debug, Clone, Default)]
+pub struct Grouping {
+    pub col: usize,
+    pub collapsed: BTreeSet<String>,
+    /// Worked out by [`View::rebuild`].
+    pub runs: Vec<Run>,
+}
+
+impl Grouping {
+    pub fn new(col: usize) -> Self {
+        Self {
+            col,
+            ..Self::default()
+        }
+    }
+
+    /// The run the row at `pos` belongs to.
+    pub fn run_at(&self, pos: usize) -> Option<usize> {
+        self.runs.partition_point(|r| r.pos <= pos).checked_sub(1)
+    }
+
+    /// Table line of the row at `pos`, counting from the first row below
+    /// the `pinned` ones, with a header line before each run.
+    pub fn line_of(&self, pos: usize, pinned: usize) -> usize {
+        pos - pinned + self.runs.partition_point(|r| r.pos <= pos)
+    }
+
+    /// What table `line` shows; the inverse of [`Grouping::line_of`].
+    pub fn item_at(&self, line: usize, pinned: usize) -> Item {
+        // Runs whose header is at or above `line`.
+        let n = self
+            .runs
+            .partition_point(|r| self.line_of(r.pos, pinned) <= line + 1);
+        match n.checked_sub(1) {
+            Some(k) if self.line_of(self.runs[k].pos, pinned) == line + 1 => Item::Run(k),
+            _ => Item::Row(line + pinned - n),
+        }
+    }
+}
+
+/// A sort and filter applied to the display only: the data keeps file order
+/// until `:commit-view` bakes the view in.
+#[derive(Debug, Clone, Default)]
+pub struct View {
+    pub sort: Vec<SortKey>,
+    pub filter: Option<Filter>,
+    /// Saved filters switched on, by name; rows have to pass these too.
+    pub saved: Vec<(String, Filter)>,
+    pub group: Option<Grouping>,
+    /// Data rows shown, in display order.
+    pub rows: Vec<usize>,
+}
+
+impl View {
+    /// Neither sorted nor filtered, so there's nothing to show differently.
+    pub fn is_empty(&self) -> bool {
+        self.sort.is_empty()
+            && self.filter.is_none()
+            && self.saved.is_empty()
+            && self.group.is_none()
+    }
+
+    /// Recompute `rows`. The first `pinned` rows (the header) stay on top and
+    /// are never filtered out; `keep` stays visible too, so a row just added
+    /// or edited doesn't vanish from under the cursor.
+    pub fn rebuild(
+        &mut self,
+        data: &[Vec<String>],
+        pinned: usize,
+        keep: Option<usize>,
+        nulls: &Nulls,
+        decimal: Decimal,
+    ) {
+        let pinned = pinned.min(data.len());
+        let body = &data[pinned..];
+        let order = if self.sort.is_empty() {
+            (0..body.len()).collect()
+        } else {
+            sort::order(body, &self.sort, decimal)
+        };
+        self.rows = (0..pinned)
+            .chain(order.into_iter().map(|o| o + pinned).filter(|&r| {
+                keep == Some(r)
+                    || self
+                        .filter
+                        .iter()
+                        .chain(self.saved.iter().map(|(_, f)| f))
+                        .all(|f| f.matches(&data[r], nulls, decimal))
+            }))
+            .collect();
+        if let Some(g) = &mut self.group {
+            let cell = |r: usize| data[r].get(g.col).map_or("", String::as_str);
+            let mut rows = self.rows[..pinned].to_vec();
+            g.runs.clear();
+            let mut i = pinned;
+            while i < self.rows.len() {
+                let value = cell(self.rows[i]);
+                let len = self.rows[i..]
+                    .iter()
+                    .take_while(|&&r| cell(r) == value)
+                    .count();
+                let collapsed = g.collapsed.contains(value);
+                g.runs.push(Run {
+                    pos: rows.len(),
+                    len,
+                    value: value.to_string(),
+                });
+                rows.extend(
+                    self.rows[i..i + len]
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, &r)| j == 0 || !collapsed || keep == Some(r))
+                        .map(|(_, &r)| r),
+                );
+                i += len;
+            }
+            self.rows = rows;
+        }
+    }
+
+    /// Display position of data row `row`, if it is shown.
+    pub fn position(&self, row: usize) -> Option<usize> {
+        self.rows.iter().position(|&r| r == row)
+    }
+
+    /// Status-line summary, e.g. `sorted, col 2 ~ x & failed: 12/40 rows`,
+    /// with saved filters by name.
+    pub fn describe(&self, total: usize) -> String {
+        let mut parts = Vec::new();
+        if !self.sort.is_empty() {
+            parts.push("sorted".to_string());
+        }
+        if let Some(g) = &self.group {
+            let collapsed = g
+                .runs
+                .iter()
+                .filter(|r| g.collapsed.contains(&r.value))
+                .count();
+            parts.push(match collapsed {
+                0 => format!("grouped by col {}", g.col + 1),
+                n => format!("grouped by col {}, {n} collapsed", g.col + 1),
+            });
+        }
+        let filters: Vec<String> = self
+            .filter
+            .iter()
+            .map(Filter::describe)
+            .chain(self.saved.iter().map(|(name, _)| name.clone()))
+            .collect();
+        if !filters.is_empty() {
+            parts.push(format!(
+                "{}: {}/{} rows",
+                filters.join(" & "),
+                self.rows.len(),
+                total
+            ));
+        }
+        parts.join(", ")
+    }
+
+    /// The edits that make the data look like the view: reorder rows into
+    /// display order, then delete the hidden ones, which end up last.
+    pub fn commit_edits(&self, len: usize) -> Vec<Edit> {
+        let mut shown = vec![false; len];
+        for &r in &self.rows {
+            shown[r] = true;
+        }
+        let order: Vec<usize> = self
+            .rows
+            .iter()
+            .copied()
+            .chain((0..len).filter(|&r| !shown[r]))
+            .collect();
+        let mut edits = Vec::new();
+        if order.iter().enumerate().any(|(i, &o)| i != o) {
+            edits.push(Edit::Reorder { order });
+        }
+        if self.rows.len() < len {
+            edits.push(Edit::DeleteRows {
+                row: self.rows.len(),
+                count: len - self.rows.len(),
+            });
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::Collation;
+
+    fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_and_filters_below_the_header_and_commits() -> Result<()> {
+        let data = grid(&[
+            &["name", "n"],
+            &["b", "2"],
+            &["a", "3"],
+            &["c", "1"],
+            &["ab", "9"],
+        ]);
+        let nulls = Nulls::default();
+        let mut view = View {
+            sort: vec![SortKey {
+                col: 1,
+                desc: false,
+                collation: Collation::Plain,
+            }],
+            ..View::default()
+        };
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 3, 1, 2, 4]);
+
+        view.filter = Some(Filter::new(0, "^a")?);
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 2, 4]);
+        assert_eq!(view.describe(data.len()), "sorted, col 1 ~ ^a: 3/5 rows");
+        // The cursor's row stays even though it doesn't match.
+        view.rebuild(&data, 1, Some(3), &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 3, 2, 4]);
+        assert_eq!(view.position(2), Some(2));
+
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        let edits = view.commit_edits(data.len());
+        assert!(matches!(&edits[0], Edit::Reorder { order } if order == &[0, 2, 4, 1, 3]));
+        assert!(matches!(edits[1], Edit::DeleteRows { row: 3, count: 2 }));
+
+        view.filter = Some(Filter::with_rule(Rule::IsNot(1, "3".into()), &[])?);
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        assert_eq!(view.describe(data.len()), "sorted, col 2 != 3: 4/5 rows");
+        view.filter = Some(Filter::with_rule(Rule::Is(0, "a".into()), &[])?);
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 2]);
+
+        // Saved filters switched on narrow it down along with the filter.
+        view.filter = Some(Filter::new(0, "a")?);
+        let big = Rule::parse(r#"where num("n") > 5"#)?;
+        view.saved
+            .push(("big".into(), Filter::with_rule(big, &data[0])?));
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 4]);
+        assert_eq!(
+            view.describe(data.len()),
+            "sorted, col 1 ~ a & big: 2/5 rows"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn groups_runs_and_collapses_them() {
+        let data = grid(&[
+            &["level"],
+            &["info"],
+            &["info"],
+            &["warn"],
+            &["info"],
+            &["info"],
+        ]);
+        let nulls = Nulls::default();
+        let mut view = View {
+            group: Some(Grouping::new(0)),
+            ..View::default()
+        };
+        view.rebuild(&data, 1, None, &nulls, Decimal::Dot);
+        let runs = |v: &View| {
+            v.group
+                .as_ref()
+                .map(|g| g.runs.iter().map(|r| (r.pos, r.len)).collect::<Vec<_>>())
+        };
+        assert_eq!(runs(&view), Some(vec![(1, 2), (3, 1), (4, 2)]));
+        let g = view.group.clone().unwrap_or_default();
+        // Header at line 0, rows 1-2, header, row 3, header, rows 4-5.
+        let items: Vec<Item> = (0..8).map(|l| g.item_at(l, 1)).collect();
+        use Item::{Row, Run};
+        assert_eq!(
+            items,
+            [
+                Run(0),
+                Row(1),
+                Row(2),
+                Run(1),
+                Row(3),
+                Run(2),
+                Row(4),
+                Row(5)
+            ]
+        );
+        assert!((1..6).all(|p| items[g.line_of(p, 1)] == Row(p)));
+        assert_eq!(g.run_at(5), Some(2));
+        assert_eq!(g.run_at(0), None);
+
+        // Both runs of `info` fold to their first row; the kept row stays.
+        if let Some(g) = &mut view.group {
+            g.collapsed.insert("info".into());
+        }
+        view.rebuild(&data, 1, Some(5), &nulls, Decimal::Dot);
+        assert_eq!(view.rows, [0, 1, 3, 4, 5]);
+        assert_eq!(runs(&view), Some(vec![(1, 2), (2, 1), (3, 2)]));
+        assert_eq!(view.describe(6), "grouped by col 1, 2 collapsed");
+    }
+}
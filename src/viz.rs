@@ -0,0 +1,396 @@
+use ratatui::style::Color;
+
+use crate::config::Theme;
+use crate::nulls::Nulls;
+
+/// How numeric columns are drawn in the table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VizMode {
+    #[default]
+    Off,
+    /// An inline bar in front of each value, scaled to the column's range.
+    Bars,
+    /// The cell background colored from blue (min) to red (max).
+    Heatmap,
+}
+
+impl VizMode {
+    pub fn next(self) -> Self {
+        match self {
+            VizMode::Off => VizMode::Bars,
+            VizMode::Bars => VizMode::Heatmap,
+            VizMode::Heatmap => VizMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VizMode::Off => "off",
+            VizMode::Bars => "data bars",
+            VizMode::Heatmap => "heatmap",
+        }
+    }
+}
+
+/// Width of an inline data bar, in terminal cells.
+pub const BAR_WIDTH: usize = 6;
+
+pub fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// How the file writes the decimal point of its numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Decimal {
+    #[default]
+    Dot,
+    /// `1.234,56`: a comma before the fraction, dots or spaces grouping
+    /// the thousands, as much of Europe writes numbers.
+    Comma,
+}
+
+impl Decimal {
+    pub fn parse(self, s: &str) -> Option<f64> {
+        match self {
+            Decimal::Dot => parse_number(s),
+            Decimal::Comma => parse_number(&self.normalize(s)?),
+        }
+    }
+
+    /// `s` as a dot-decimal number without grouping, `1.234,56` becoming
+    /// `1234.56`; `None` unless it reads as a number this way. The digits
+    /// are kept as written.
+    pub fn normalize(self, s: &str) -> Option<String> {
+        match self {
+            Decimal::Dot => parse_number(s).map(|_| s.trim().to_string()),
+            Decimal::Comma => self.ungroup(s),
+        }
+    }
+
+    /// Like [`normalize`](Self::normalize), but taking thousands separators
+    /// in either style: `1,234.5` as well as `1.234,5` with `Comma`. Groups
+    /// after the first must be three digits.
+    pub fn ungroup(self, s: &str) -> Option<String> {
+        let (point, seps): (char, &[char]) = match self {
+            Decimal::Dot => ('.', &[',', ' ', '\'', '\u{a0}', '\u{202f}']),
+            Decimal::Comma => (',', &['.', ' ', '\u{a0}', '\u{202f}']),
+        };
+        let t = s.trim();
+        let (int, frac) = t.split_once(point).map_or((t, None), |(i, f)| (i, Some(f)));
+        let groups: Vec<&str> = int.split(seps).collect();
+        let grouped = groups[1..]
+            .iter()
+            .all(|g| g.len() == 3 && g.bytes().all(|b| b.is_ascii_digit()));
+        if !grouped {
+            return None;
+        }
+        let plain = match frac {
+            Some(f) => format!("{}.{f}", groups.concat()),
+            None => groups.concat(),
+        };
+        parse_number(&plain).map(|_| plain)
+    }
+
+    /// A dot-decimal number written back this way: `1234.5`, or `1234,5`
+    /// with `Comma`.
+    pub fn write(self, plain: &str) -> String {
+        match self {
+            Decimal::Dot => plain.to_string(),
+            Decimal::Comma => plain.replace('.', ","),
+        }
+    }
+}
+
+/// `(min, max)` of each numeric column; `None` for text columns. A column
+/// counts as numeric when at least half of its non-blank cells are numbers,
+/// so a header row or the odd "n/a" doesn't switch it off. Null cells
+/// don't count either way.
+pub fn column_ranges(
+    data: &[Vec<String>],
+    cols: usize,
+    nulls: &Nulls,
+    decimal: Decimal,
+) -> Vec<Option<(f64, f64)>> {
+    (0..cols)
+        .map(|c| {
+            let mut filled = 0;
+            let mut range: Option<(f64, f64)> = None;
+            let mut numbers = 0;
+            for cell in data.iter().filter_map(|r| r.get(c)) {
+                if cell.trim().is_empty() || nulls.is_null(cell) {
+                    continue;
+                }
+                filled += 1;
+                if let Some(v) = decimal.parse(cell) {
+                    numbers += 1;
+                    range = Some(range.map_or((v, v), |(lo, hi)| (lo.min(v), hi.max(v))));
+                }
+            }
+            range.filter(|_| numbers * 2 >= filled)
+        })
+        .collect()
+}
+
+/// Position of `v` within `range`, from 0.0 to 1.0. A constant column maps
+/// to a full bar.
+pub fn scale(v: f64, (lo, hi): (f64, f64)) -> f64 {
+    if hi > lo {
+        ((v - lo) / (hi - lo)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// A left-aligned bar of `width` cells filled to `t`, using eighth blocks
+/// for the partial cell.
+pub fn bar(t: f64, width: usize) -> String {
+    const PARTS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (t.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+    let mut out = "█".repeat(eighths / 8);
+    let rem = eighths % 8;
+    if rem > 0 {
+        out.push(PARTS[rem]);
+    }
+    let len = out.chars().count();
+    out.extend(std::iter::repeat_n(' ', width - len));
+    out
+}
+
+/// Diverging blue → pale yellow → red color for `t` in 0.0..=1.0.
+pub fn heat_color(t: f64) -> Color {
+    const LOW: (f64, f64, f64) = (69.0, 117.0, 180.0);
+    const MID: (f64, f64, f64) = (255.0, 255.0, 191.0);
+    const HIGH: (f64, f64, f64) = (215.0, 48.0, 39.0);
+    let t = t.clamp(0.0, 1.0);
+    let (a, b, u) = if t < 0.5 {
+        (LOW, MID, t * 2.0)
+    } else {
+        (MID, HIGH, t * 2.0 - 1.0)
+    };
+    let mix = |x: f64, y: f64| (x + (y - x) * u).round() as u8;
+    Color::Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// Background tint of column `col` in rainbow mode: dark shades behind
+/// light text, or pastels behind dark text, cycling through the hues.
+pub fn rainbow_color(col: usize, theme: Theme) -> Color {
+    const DARK: [(u8, u8, u8); 8] = [
+        (64, 28, 28),
+        (64, 48, 20),
+        (50, 60, 20),
+        (22, 58, 30),
+        (20, 54, 60),
+        (26, 36, 70),
+        (48, 28, 68),
+        (64, 24, 50),
+    ];
+    const LIGHT: [(u8, u8, u8); 8] = [
+        (255, 218, 218),
+        (255, 234, 204),
+        (246, 248, 200),
+        (214, 244, 214),
+        (206, 240, 246),
+        (214, 224, 255),
+        (232, 216, 255),
+        (255, 214, 240),
+    ];
+    let palette = match theme {
+        Theme::Dark => &DARK,
+        Theme::Light => &LIGHT,
+    };
+    let (r, g, b) = palette[col % palette.len()];
+    Color::Rgb(r, g, b)
+}
+
+/// The numbers of one row or column, for the chart popup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub title: String,
+    /// Numeric cells in order; text, blank and null cells are skipped.
+    pub values: Vec<f64>,
+}
+
+/// The cell as a number, unless it is one of the null tokens.
+fn value(s: &str, nulls: &Nulls, decimal: Decimal) -> Option<f64> {
+    decimal.parse(s).filter(|_| !nulls.is_null(s))
+}
+
+impl Series {
+    pub fn column(data: &[Vec<String>], col: usize, nulls: &Nulls, decimal: Decimal) -> Self {
+        Self {
+            title: format!("Column {}", col + 1),
+            values: data
+                .iter()
+                .filter_map(|r| r.get(col).and_then(|s| value(s, nulls, decimal)))
+                .collect(),
+        }
+    }
+
+    pub fn row(data: &[Vec<String>], row: usize, nulls: &Nulls, decimal: Decimal) -> Self {
+        Self {
+            title: format!("Row {}", row + 1),
+            values: data
+                .get(row)
+                .map(|r| r.iter().filter_map(|s| value(s, nulls, decimal)).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.values.iter().fold(None, |acc, &v| {
+            Some(acc.map_or((v, v), |(lo, hi): (f64, f64)| (lo.min(v), hi.max(v))))
+        })
+    }
+
+    /// Values mapped onto 1..=100 for the sparkline widget, which only takes
+    /// unsigned integers; the minimum keeps a sliver so it stays visible.
+    pub fn scaled(&self) -> Vec<u64> {
+        let Some(range) = self.range() else {
+            return Vec::new();
+        };
+        self.values
+            .iter()
+            .map(|&v| 1 + (scale(v, range) * 99.0).round() as u64)
+            .collect()
+    }
+}
+
+/// Spreadsheet-style totals of a block of cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Non-blank cells that aren't numbers; blanks and nulls are skipped
+    /// silently.
+    pub text: usize,
+}
+
+impl Summary {
+    pub fn of<'a>(
+        cells: impl IntoIterator<Item = &'a str>,
+        nulls: &Nulls,
+        decimal: Decimal,
+    ) -> Self {
+        let mut out = Summary::default();
+        for cell in cells {
+            match value(cell, nulls, decimal) {
+                Some(v) => {
+                    (out.min, out.max) = if out.count == 0 {
+                        (v, v)
+                    } else {
+                        (out.min.min(v), out.max.max(v))
+                    };
+                    out.count += 1;
+                    out.sum += v;
+                }
+                None if cell.trim().is_empty() || nulls.is_null(cell) => {}
+                None => out.text += 1,
+            }
+        }
+        out
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count.max(1) as f64
+    }
+}
+
+/// A number for the status line: at most four decimals, without trailing
+/// zeros, so float noise like `0.30000000000000004` reads as `0.3`.
+pub fn format_number(v: f64) -> String {
+    let s = format!("{v:.4}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_columns_tolerate_a_header_row() {
+        let data: Vec<Vec<String>> = [["name", "qty"], ["a", "3"], ["b", "-1.5"], ["c", ""]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        assert_eq!(
+            column_ranges(&data, 3, &Nulls::default(), Decimal::Dot),
+            [None, Some((-1.5, 3.0)), None]
+        );
+    }
+
+    #[test]
+    fn rainbow_neighbours_differ_and_follow_the_theme() {
+        for c in 0..16 {
+            let here = rainbow_color(c, Theme::Dark);
+            assert_ne!(here, rainbow_color(c + 1, Theme::Dark));
+            assert_ne!(here, rainbow_color(c, Theme::Light));
+        }
+        assert_eq!(
+            rainbow_color(0, Theme::Light),
+            rainbow_color(8, Theme::Light)
+        );
+    }
+
+    #[test]
+    fn bars_fill_proportionally() {
+        assert_eq!(bar(0.0, 4), "    ");
+        assert_eq!(bar(1.0, 4), "████");
+        assert_eq!(bar(scale(5.0, (0.0, 10.0)), 4), "██  ");
+        assert_eq!(bar(1.0 / 16.0, 2), "▏ ");
+        assert_eq!(heat_color(0.5), Color::Rgb(255, 255, 191));
+    }
+
+    #[test]
+    fn series_skip_text_and_scale_to_the_range() {
+        let data: Vec<Vec<String>> = [["x", "1", "3"], ["y", "n/a", "5"], ["z", "2", ""]]
+            .iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect();
+        let col = Series::column(&data, 1, &Nulls::default(), Decimal::Dot);
+        assert_eq!(col.values, [1.0, 2.0]);
+        assert_eq!(col.scaled(), [1, 100]);
+        assert_eq!(
+            Series::row(&data, 0, &Nulls::default(), Decimal::Dot).values,
+            [1.0, 3.0]
+        );
+        assert!(
+            Series::column(&data, 0, &Nulls::default(), Decimal::Dot)
+                .scaled()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn summary_counts_text_separately() {
+        let s = Summary::of(
+            ["1", "2.5", "", "n/a", " 3 ", "x", "NA"],
+            &Nulls::default(),
+            Decimal::Dot,
+        );
+        assert_eq!((s.count, s.text), (3, 2));
+        let t = Summary::of(["1", "-999", "3"], &Nulls::parse("-999"), Decimal::Dot);
+        assert_eq!((t.count, t.sum), (2, 4.0));
+        assert_eq!((s.min, s.max), (1.0, 3.0));
+        assert_eq!(format_number(s.sum), "6.5");
+        assert_eq!(format_number(s.mean()), "2.1667");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(-0.00001), "0");
+        assert_eq!(Summary::of(["a"], &Nulls::default(), Decimal::Dot).count, 0);
+    }
+
+    #[test]
+    fn decimal_comma_reads_european_numbers() {
+        let c = Decimal::Comma;
+        assert_eq!(c.parse("1.234,56"), Some(1234.56));
+        assert_eq!(c.parse("-1 234 567"), Some(-1234567.0));
+        assert_eq!(c.parse("0,5"), Some(0.5));
+        assert_eq!(c.normalize(" 12.000,50 ").as_deref(), Some("12000.50"));
+        for bad in ["1.5", "1,2,3", "12.34.5", "abc"] {
+            assert_eq!(c.parse(bad), None, "{bad}");
+        }
+        assert_eq!(Decimal::Dot.parse("1,5"), None);
+    }
+}